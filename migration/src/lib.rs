@@ -7,6 +7,30 @@ mod m20231220_000001_agents;
 mod m20231220_000002_tasks;
 mod m20231220_000003_memory;
 mod m20231220_000004_knowledge;
+mod m20241021_000001_learning_model_versions;
+mod m20241022_000001_model_evaluations;
+mod m20241023_000001_conversations_user_id;
+mod m20241023_000002_training_feedback;
+mod m20241024_000001_tool_approvals;
+mod m20241025_000001_task_delegation;
+mod m20241026_000001_bus_messages;
+mod m20241027_000001_agent_versions;
+mod m20241028_000001_task_schedules;
+mod m20241029_000001_usage_records;
+mod m20241030_000001_user_plans;
+mod m20241031_000001_organizations;
+mod m20241101_000001_sessions;
+mod m20241102_000001_api_keys;
+mod m20241103_000001_two_factor;
+mod m20241104_000001_login_throttles;
+mod m20241105_000001_previous_passwords;
+mod m20241106_000001_email_change;
+mod m20241107_000001_magic_links;
+mod m20241108_000001_identities;
+mod m20241109_000001_account_deletion;
+mod m20241110_000001_data_exports;
+mod m20241111_000001_message_agent_versions;
+mod m20241112_000001_clear_user_api_keys;
 
 pub struct Migrator;
 
@@ -19,6 +43,30 @@ impl MigratorTrait for Migrator {
             Box::new(m20231220_000002_tasks::Migration),
             Box::new(m20231220_000003_memory::Migration),
             Box::new(m20231220_000004_knowledge::Migration),
+            Box::new(m20241021_000001_learning_model_versions::Migration),
+            Box::new(m20241022_000001_model_evaluations::Migration),
+            Box::new(m20241023_000001_conversations_user_id::Migration),
+            Box::new(m20241023_000002_training_feedback::Migration),
+            Box::new(m20241024_000001_tool_approvals::Migration),
+            Box::new(m20241025_000001_task_delegation::Migration),
+            Box::new(m20241026_000001_bus_messages::Migration),
+            Box::new(m20241027_000001_agent_versions::Migration),
+            Box::new(m20241028_000001_task_schedules::Migration),
+            Box::new(m20241029_000001_usage_records::Migration),
+            Box::new(m20241030_000001_user_plans::Migration),
+            Box::new(m20241031_000001_organizations::Migration),
+            Box::new(m20241101_000001_sessions::Migration),
+            Box::new(m20241102_000001_api_keys::Migration),
+            Box::new(m20241103_000001_two_factor::Migration),
+            Box::new(m20241104_000001_login_throttles::Migration),
+            Box::new(m20241105_000001_previous_passwords::Migration),
+            Box::new(m20241106_000001_email_change::Migration),
+            Box::new(m20241107_000001_magic_links::Migration),
+            Box::new(m20241108_000001_identities::Migration),
+            Box::new(m20241109_000001_account_deletion::Migration),
+            Box::new(m20241110_000001_data_exports::Migration),
+            Box::new(m20241111_000001_message_agent_versions::Migration),
+            Box::new(m20241112_000001_clear_user_api_keys::Migration),
         ]
     }
 }
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Track which version a learning model was derived from
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .add_column(ColumnDef::new(LearningModels::ParentId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .drop_column(LearningModels::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum LearningModels {
+    Table,
+    ParentId,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the active version of a model is served; promotion is gated on
+        // evaluation results
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .add_column(
+                        ColumnDef::new(LearningModels::Status)
+                            .string()
+                            .not_null()
+                            .default("draft"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create model_evaluations table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ModelEvaluations::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ModelEvaluations::Id).uuid().primary_key())
+                    .col(ColumnDef::new(ModelEvaluations::ModelId).uuid().not_null())
+                    .col(ColumnDef::new(ModelEvaluations::Split).string().not_null())
+                    .col(
+                        ColumnDef::new(ModelEvaluations::ExampleCount)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ModelEvaluations::Scores).json().not_null())
+                    .col(ColumnDef::new(ModelEvaluations::Results).json().not_null())
+                    .col(
+                        ColumnDef::new(ModelEvaluations::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_model_evaluations_model")
+                            .from(ModelEvaluations::Table, ModelEvaluations::ModelId)
+                            .to(LearningModels::Table, LearningModels::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ModelEvaluations::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .drop_column(LearningModels::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum LearningModels {
+    Table,
+    Id,
+    Status,
+}
+
+#[derive(Iden)]
+pub enum ModelEvaluations {
+    Table,
+    Id,
+    ModelId,
+    Split,
+    ExampleCount,
+    Scores,
+    Results,
+    CreatedAt,
+}
@@ -0,0 +1,108 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{
+    m20220101_000001_users::Users,
+    m20231220_000001_agents::Agents,
+    m20231220_000003_memory::{Conversations, Messages},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// `conversations.user_id` was created as a uuid referencing the integer
+// `users.id`, so no conversation could ever be stored. Both tables are
+// necessarily empty and are recreated with the right column type.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_tables(manager).await?;
+        create_tables(
+            manager,
+            ColumnDef::new(Conversations::UserId).integer().not_null(),
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_tables(manager).await?;
+        create_tables(
+            manager,
+            ColumnDef::new(Conversations::UserId).uuid().not_null(),
+        )
+        .await
+    }
+}
+
+async fn drop_tables(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    manager
+        .drop_table(Table::drop().table(Messages::Table).to_owned())
+        .await?;
+    manager
+        .drop_table(Table::drop().table(Conversations::Table).to_owned())
+        .await?;
+
+    Ok(())
+}
+
+async fn create_tables(manager: &SchemaManager<'_>, user_id: &mut ColumnDef) -> Result<(), DbErr> {
+    manager
+        .create_table(
+            Table::create()
+                .table(Conversations::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(Conversations::Id).uuid().primary_key())
+                .col(ColumnDef::new(Conversations::AgentId).uuid().not_null())
+                .col(user_id)
+                .col(ColumnDef::new(Conversations::Title).string())
+                .col(ColumnDef::new(Conversations::Status).string().not_null())
+                .col(ColumnDef::new(Conversations::Metadata).json())
+                .col(
+                    ColumnDef::new(Conversations::CreatedAt)
+                        .timestamp()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(Conversations::UpdatedAt)
+                        .timestamp()
+                        .not_null(),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_conversations_agent")
+                        .from(Conversations::Table, Conversations::AgentId)
+                        .to(Agents::Table, Agents::Id),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_conversations_user")
+                        .from(Conversations::Table, Conversations::UserId)
+                        .to(Users::Table, Users::Id),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+    manager
+        .create_table(
+            Table::create()
+                .table(Messages::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(Messages::Id).uuid().primary_key())
+                .col(ColumnDef::new(Messages::ConversationId).uuid().not_null())
+                .col(ColumnDef::new(Messages::Role).string().not_null())
+                .col(ColumnDef::new(Messages::Content).text().not_null())
+                .col(ColumnDef::new(Messages::Metadata).json())
+                .col(ColumnDef::new(Messages::CreatedAt).timestamp().not_null())
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk_messages_conversation")
+                        .from(Messages::Table, Messages::ConversationId)
+                        .to(Conversations::Table, Conversations::Id)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+    Ok(())
+}
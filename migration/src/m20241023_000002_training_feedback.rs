@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The agent whose conversations feed this model
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .add_column(ColumnDef::new(LearningModels::AgentId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Samples captured from conversations wait for review before they are
+        // used for training; existing rows are considered reviewed
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModelTrainingData::Table)
+                    .add_column(
+                        ColumnDef::new(ModelTrainingData::Status)
+                            .string()
+                            .not_null()
+                            .default("approved"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModelTrainingData::Table)
+                    .drop_column(ModelTrainingData::Status)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LearningModels::Table)
+                    .drop_column(LearningModels::AgentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum LearningModels {
+    Table,
+    AgentId,
+}
+
+#[derive(Iden)]
+pub enum ModelTrainingData {
+    Table,
+    Status,
+}
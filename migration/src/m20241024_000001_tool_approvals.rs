@@ -0,0 +1,166 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Calls to these capabilities wait for the conversation owner to
+        // approve them
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AgentCapabilities::Table)
+                    .add_column(
+                        ColumnDef::new(AgentCapabilities::RequiresApproval)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create tool_approvals table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ToolApprovals::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ToolApprovals::Id).uuid().primary_key())
+                    .col(
+                        ColumnDef::new(ToolApprovals::ConversationId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ToolApprovals::MessageId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ToolApprovals::CapabilityId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ToolApprovals::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(ToolApprovals::ToolCallId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ToolApprovals::Name).string().not_null())
+                    .col(ColumnDef::new(ToolApprovals::Arguments).json().not_null())
+                    .col(
+                        ColumnDef::new(ToolApprovals::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(ToolApprovals::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ToolApprovals::DecidedAt).timestamp())
+                    .col(
+                        ColumnDef::new(ToolApprovals::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ToolApprovals::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tool_approvals_conversation")
+                            .from(ToolApprovals::Table, ToolApprovals::ConversationId)
+                            .to(Conversations::Table, Conversations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tool_approvals_message")
+                            .from(ToolApprovals::Table, ToolApprovals::MessageId)
+                            .to(Messages::Table, Messages::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tool_approvals_capability")
+                            .from(ToolApprovals::Table, ToolApprovals::CapabilityId)
+                            .to(AgentCapabilities::Table, AgentCapabilities::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tool_approvals_user")
+                            .from(ToolApprovals::Table, ToolApprovals::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ToolApprovals::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AgentCapabilities::Table)
+                    .drop_column(AgentCapabilities::RequiresApproval)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum AgentCapabilities {
+    Table,
+    Id,
+    RequiresApproval,
+}
+
+#[derive(Iden)]
+pub enum Conversations {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Messages {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum ToolApprovals {
+    Table,
+    Id,
+    ConversationId,
+    MessageId,
+    CapabilityId,
+    UserId,
+    ToolCallId,
+    Name,
+    Arguments,
+    Status,
+    ExpiresAt,
+    DecidedAt,
+    CreatedAt,
+    UpdatedAt,
+}
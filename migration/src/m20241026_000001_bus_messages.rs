@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Messages sent between agents, kept until they are delivered so a
+        // restart does not lose them
+        manager
+            .create_table(
+                Table::create()
+                    .table(BusMessages::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(BusMessages::Id).uuid().primary_key())
+                    .col(ColumnDef::new(BusMessages::SenderId).uuid())
+                    .col(ColumnDef::new(BusMessages::RecipientId).uuid())
+                    .col(ColumnDef::new(BusMessages::Topic).string())
+                    .col(ColumnDef::new(BusMessages::Kind).string().not_null())
+                    .col(ColumnDef::new(BusMessages::Payload).json().not_null())
+                    .col(
+                        ColumnDef::new(BusMessages::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(BusMessages::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(BusMessages::DeliveredAt).timestamp())
+                    .col(
+                        ColumnDef::new(BusMessages::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BusMessages::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bus_messages_sender")
+                            .from(BusMessages::Table, BusMessages::SenderId)
+                            .to(Agents::Table, Agents::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bus_messages_recipient")
+                            .from(BusMessages::Table, BusMessages::RecipientId)
+                            .to(Agents::Table, Agents::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_bus_messages_status")
+                    .table(BusMessages::Table)
+                    .col(BusMessages::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BusMessages::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum BusMessages {
+    Table,
+    Id,
+    SenderId,
+    RecipientId,
+    Topic,
+    Kind,
+    Payload,
+    Status,
+    Attempts,
+    DeliveredAt,
+    CreatedAt,
+    UpdatedAt,
+}
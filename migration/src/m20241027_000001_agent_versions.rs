@@ -0,0 +1,127 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Immutable snapshots of what defines an agent's behavior
+        manager
+            .create_table(
+                Table::create()
+                    .table(AgentVersions::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AgentVersions::Id).uuid().primary_key())
+                    .col(ColumnDef::new(AgentVersions::AgentId).uuid().not_null())
+                    .col(ColumnDef::new(AgentVersions::Version).integer().not_null())
+                    .col(ColumnDef::new(AgentVersions::Configuration).json())
+                    .col(
+                        ColumnDef::new(AgentVersions::Capabilities)
+                            .json()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AgentVersions::LearningModelId).uuid())
+                    .col(
+                        ColumnDef::new(AgentVersions::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_agent_versions_agent")
+                            .from(AgentVersions::Table, AgentVersions::AgentId)
+                            .to(Agents::Table, Agents::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_agent_versions_agent_version")
+                    .table(AgentVersions::Table)
+                    .col(AgentVersions::AgentId)
+                    .col(AgentVersions::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The agent version that handled the conversation or task
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversations::Table)
+                    .add_column(ColumnDef::new(Conversations::AgentVersionId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .add_column(ColumnDef::new(Tasks::AgentVersionId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .drop_column(Tasks::AgentVersionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversations::Table)
+                    .drop_column(Conversations::AgentVersionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(AgentVersions::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Conversations {
+    Table,
+    AgentVersionId,
+}
+
+#[derive(Iden)]
+pub enum Tasks {
+    Table,
+    AgentVersionId,
+}
+
+#[derive(Iden)]
+pub enum AgentVersions {
+    Table,
+    Id,
+    AgentId,
+    Version,
+    Configuration,
+    Capabilities,
+    LearningModelId,
+    CreatedAt,
+}
@@ -0,0 +1,146 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Templates that create a task whenever their cron expression fires
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskSchedules::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TaskSchedules::Id).uuid().primary_key())
+                    .col(ColumnDef::new(TaskSchedules::AgentId).uuid().not_null())
+                    .col(ColumnDef::new(TaskSchedules::UserId).integer().not_null())
+                    .col(ColumnDef::new(TaskSchedules::Name).string().not_null())
+                    .col(ColumnDef::new(TaskSchedules::Description).text())
+                    .col(ColumnDef::new(TaskSchedules::Input).json())
+                    .col(ColumnDef::new(TaskSchedules::Cron).string().not_null())
+                    .col(
+                        ColumnDef::new(TaskSchedules::Timezone)
+                            .string()
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::CatchUp)
+                            .string()
+                            .not_null()
+                            .default("latest"),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::Status)
+                            .string()
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(ColumnDef::new(TaskSchedules::NextRunAt).timestamp())
+                    .col(ColumnDef::new(TaskSchedules::LastRunAt).timestamp())
+                    .col(
+                        ColumnDef::new(TaskSchedules::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TaskSchedules::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_task_schedules_agent")
+                            .from(TaskSchedules::Table, TaskSchedules::AgentId)
+                            .to(Agents::Table, Agents::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_task_schedules_user")
+                            .from(TaskSchedules::Table, TaskSchedules::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_schedules_next_run_at")
+                    .table(TaskSchedules::Table)
+                    .col(TaskSchedules::Status)
+                    .col(TaskSchedules::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The schedule that created the task
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .add_column(ColumnDef::new(Tasks::ScheduleId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .drop_column(Tasks::ScheduleId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TaskSchedules::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Tasks {
+    Table,
+    ScheduleId,
+}
+
+#[derive(Iden)]
+pub enum TaskSchedules {
+    Table,
+    Id,
+    AgentId,
+    UserId,
+    Name,
+    Description,
+    Input,
+    Cron,
+    Timezone,
+    CatchUp,
+    Status,
+    NextRunAt,
+    LastRunAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,136 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per provider call, with the tokens it used and what they
+        // cost
+        manager
+            .create_table(
+                Table::create()
+                    .table(UsageRecords::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UsageRecords::Id).uuid().primary_key())
+                    .col(ColumnDef::new(UsageRecords::UserId).integer())
+                    .col(ColumnDef::new(UsageRecords::AgentId).uuid())
+                    .col(ColumnDef::new(UsageRecords::ConversationId).uuid())
+                    .col(ColumnDef::new(UsageRecords::TaskId).uuid())
+                    .col(ColumnDef::new(UsageRecords::Provider).string().not_null())
+                    .col(ColumnDef::new(UsageRecords::Model).string().not_null())
+                    .col(
+                        ColumnDef::new(UsageRecords::PromptTokens)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(UsageRecords::CompletionTokens)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(UsageRecords::Cost)
+                            .double()
+                            .not_null()
+                            .default(0.0),
+                    )
+                    .col(
+                        ColumnDef::new(UsageRecords::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_usage_records_user")
+                            .from(UsageRecords::Table, UsageRecords::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_usage_records_agent")
+                            .from(UsageRecords::Table, UsageRecords::AgentId)
+                            .to(Agents::Table, Agents::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_usage_records_conversation")
+                            .from(UsageRecords::Table, UsageRecords::ConversationId)
+                            .to(Conversations::Table, Conversations::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_usage_records_user_created_at")
+                    .table(UsageRecords::Table)
+                    .col(UsageRecords::UserId)
+                    .col(UsageRecords::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_usage_records_created_at")
+                    .table(UsageRecords::Table)
+                    .col(UsageRecords::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UsageRecords::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Conversations {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum UsageRecords {
+    Table,
+    Id,
+    UserId,
+    AgentId,
+    ConversationId,
+    TaskId,
+    Provider,
+    Model,
+    PromptTokens,
+    CompletionTokens,
+    Cost,
+    CreatedAt,
+}
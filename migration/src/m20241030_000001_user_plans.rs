@@ -0,0 +1,126 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The subscription plan that sets the user's quotas
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::Plan)
+                            .string()
+                            .not_null()
+                            .default("free"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Who created the agent and the knowledge base, counted against
+        // their plan
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Agents::Table)
+                    .add_column(ColumnDef::new(Agents::UserId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_agents_user_id")
+                    .table(Agents::Table)
+                    .col(Agents::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(KnowledgeBase::Table)
+                    .add_column(ColumnDef::new(KnowledgeBase::UserId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_knowledge_base_user_id")
+                    .table(KnowledgeBase::Table)
+                    .col(KnowledgeBase::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_knowledge_base_user_id")
+                    .table(KnowledgeBase::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(KnowledgeBase::Table)
+                    .drop_column(KnowledgeBase::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_agents_user_id")
+                    .table(Agents::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Agents::Table)
+                    .drop_column(Agents::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::Plan)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Plan,
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    UserId,
+}
+
+#[derive(Iden)]
+pub enum KnowledgeBase {
+    Table,
+    UserId,
+}
@@ -0,0 +1,275 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Organizations::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Organizations::Id).uuid().primary_key())
+                    .col(ColumnDef::new(Organizations::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(Organizations::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Organizations::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Who belongs to an organization and with which role
+        manager
+            .create_table(
+                Table::create()
+                    .table(Memberships::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Memberships::Id).uuid().primary_key())
+                    .col(
+                        ColumnDef::new(Memberships::OrganizationId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Memberships::UserId).integer().not_null())
+                    .col(ColumnDef::new(Memberships::Role).string().not_null())
+                    .col(
+                        ColumnDef::new(Memberships::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Memberships::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_memberships_organization")
+                            .from(Memberships::Table, Memberships::OrganizationId)
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_memberships_user")
+                            .from(Memberships::Table, Memberships::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_memberships_organization_user")
+                    .table(Memberships::Table)
+                    .col(Memberships::OrganizationId)
+                    .col(Memberships::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Pending invitations, accepted by following the emailed token
+        manager
+            .create_table(
+                Table::create()
+                    .table(Invitations::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Invitations::Id).uuid().primary_key())
+                    .col(
+                        ColumnDef::new(Invitations::OrganizationId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Invitations::Email).string().not_null())
+                    .col(ColumnDef::new(Invitations::Role).string().not_null())
+                    .col(
+                        ColumnDef::new(Invitations::Token)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Invitations::InvitedBy).integer())
+                    .col(
+                        ColumnDef::new(Invitations::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Invitations::AcceptedAt).timestamp())
+                    .col(
+                        ColumnDef::new(Invitations::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Invitations::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invitations_organization")
+                            .from(Invitations::Table, Invitations::OrganizationId)
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invitations_invited_by")
+                            .from(Invitations::Table, Invitations::InvitedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The organization sharing the agent, knowledge base or
+        // conversation. Rows from before organizations have none.
+        for (table, column, index) in [
+            (
+                Agents::Table.into_iden(),
+                Agents::OrganizationId.into_iden(),
+                "idx_agents_organization_id",
+            ),
+            (
+                KnowledgeBase::Table.into_iden(),
+                KnowledgeBase::OrganizationId.into_iden(),
+                "idx_knowledge_base_organization_id",
+            ),
+            (
+                Conversations::Table.into_iden(),
+                Conversations::OrganizationId.into_iden(),
+                "idx_conversations_organization_id",
+            ),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table.clone())
+                        .add_column(ColumnDef::new(column.clone()).uuid())
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index)
+                        .table(table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, column, index) in [
+            (
+                Conversations::Table.into_iden(),
+                Conversations::OrganizationId.into_iden(),
+                "idx_conversations_organization_id",
+            ),
+            (
+                KnowledgeBase::Table.into_iden(),
+                KnowledgeBase::OrganizationId.into_iden(),
+                "idx_knowledge_base_organization_id",
+            ),
+            (
+                Agents::Table.into_iden(),
+                Agents::OrganizationId.into_iden(),
+                "idx_agents_organization_id",
+            ),
+        ] {
+            manager
+                .drop_index(Index::drop().name(index).table(table.clone()).to_owned())
+                .await?;
+            manager
+                .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                .await?;
+        }
+        manager
+            .drop_table(Table::drop().table(Invitations::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Memberships::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Organizations::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Agents {
+    Table,
+    OrganizationId,
+}
+
+#[derive(Iden)]
+pub enum KnowledgeBase {
+    Table,
+    OrganizationId,
+}
+
+#[derive(Iden)]
+pub enum Conversations {
+    Table,
+    OrganizationId,
+}
+
+#[derive(Iden)]
+pub enum Organizations {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum Memberships {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum Invitations {
+    Table,
+    Id,
+    OrganizationId,
+    Email,
+    Role,
+    Token,
+    InvitedBy,
+    ExpiresAt,
+    AcceptedAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per refresh token. Rotating a token adds a row to the same
+        // family, so a whole login can be revoked at once.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sessions::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Sessions::Id).uuid().primary_key())
+                    .col(ColumnDef::new(Sessions::FamilyId).uuid().not_null())
+                    .col(ColumnDef::new(Sessions::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Sessions::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Sessions::UserAgent).string())
+                    .col(ColumnDef::new(Sessions::IpAddress).string())
+                    .col(ColumnDef::new(Sessions::SignedInAt).timestamp().not_null())
+                    .col(ColumnDef::new(Sessions::ExpiresAt).timestamp().not_null())
+                    .col(ColumnDef::new(Sessions::RotatedAt).timestamp())
+                    .col(ColumnDef::new(Sessions::RevokedAt).timestamp())
+                    .col(ColumnDef::new(Sessions::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(Sessions::UpdatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sessions_user")
+                            .from(Sessions::Table, Sessions::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sessions_family_id")
+                    .table(Sessions::Table)
+                    .col(Sessions::FamilyId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sessions_user_id")
+                    .table(Sessions::Table)
+                    .col(Sessions::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sessions::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Sessions {
+    Table,
+    Id,
+    FamilyId,
+    UserId,
+    TokenHash,
+    UserAgent,
+    IpAddress,
+    SignedInAt,
+    ExpiresAt,
+    RotatedAt,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
+}
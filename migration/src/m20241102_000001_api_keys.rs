@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKeys::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ApiKeys::Id).uuid().primary_key())
+                    .col(ColumnDef::new(ApiKeys::UserId).integer().not_null())
+                    .col(ColumnDef::new(ApiKeys::Name).string().not_null())
+                    // the start of the key, to tell keys apart by
+                    .col(ColumnDef::new(ApiKeys::Prefix).string().not_null())
+                    .col(
+                        ColumnDef::new(ApiKeys::KeyHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // space separated, e.g. `agents:read tasks:run`
+                    .col(ColumnDef::new(ApiKeys::Scopes).string().not_null())
+                    .col(ColumnDef::new(ApiKeys::LastUsedAt).timestamp())
+                    .col(ColumnDef::new(ApiKeys::ExpiresAt).timestamp())
+                    .col(ColumnDef::new(ApiKeys::RevokedAt).timestamp())
+                    .col(ColumnDef::new(ApiKeys::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(ApiKeys::UpdatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_api_keys_user")
+                            .from(ApiKeys::Table, ApiKeys::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_keys_user_id")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum ApiKeys {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    LastUsedAt,
+    ExpiresAt,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
+}
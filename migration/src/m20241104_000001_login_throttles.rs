@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Failed logins counted per account (by email, known or not) and per
+        // client address
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginThrottles::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(LoginThrottles::Id).uuid().primary_key())
+                    .col(ColumnDef::new(LoginThrottles::Scope).string().not_null())
+                    .col(ColumnDef::new(LoginThrottles::Key).string().not_null())
+                    .col(
+                        ColumnDef::new(LoginThrottles::Failures)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(LoginThrottles::LastFailureAt).timestamp())
+                    .col(ColumnDef::new(LoginThrottles::LockedUntil).timestamp())
+                    .col(
+                        ColumnDef::new(LoginThrottles::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginThrottles::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_login_throttles_scope_key")
+                    .table(LoginThrottles::Table)
+                    .col(LoginThrottles::Scope)
+                    .col(LoginThrottles::Key)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginThrottles::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum LoginThrottles {
+    Table,
+    Id,
+    Scope,
+    Key,
+    Failures,
+    LastFailureAt,
+    LockedUntil,
+    CreatedAt,
+    UpdatedAt,
+}
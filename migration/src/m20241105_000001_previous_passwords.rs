@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Hashes of passwords a user had before, so recent ones are not
+        // taken again
+        manager
+            .create_table(
+                Table::create()
+                    .table(PreviousPasswords::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(PreviousPasswords::Id).uuid().primary_key())
+                    .col(
+                        ColumnDef::new(PreviousPasswords::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PreviousPasswords::PasswordHash)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PreviousPasswords::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PreviousPasswords::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_previous_passwords_user")
+                            .from(PreviousPasswords::Table, PreviousPasswords::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_previous_passwords_user_id")
+                    .table(PreviousPasswords::Table)
+                    .col(PreviousPasswords::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PreviousPasswords::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum PreviousPasswords {
+    Table,
+    Id,
+    UserId,
+    PasswordHash,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MagicLinks::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(MagicLinks::Id).uuid().primary_key())
+                    .col(ColumnDef::new(MagicLinks::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(MagicLinks::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(MagicLinks::ExpiresAt).timestamp().not_null())
+                    // set once the link logged someone in; it never works again
+                    .col(ColumnDef::new(MagicLinks::ConsumedAt).timestamp())
+                    .col(ColumnDef::new(MagicLinks::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(MagicLinks::UpdatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_magic_links_user")
+                            .from(MagicLinks::Table, MagicLinks::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_magic_links_user_id")
+                    .table(MagicLinks::Table)
+                    .col(MagicLinks::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MagicLinks::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum MagicLinks {
+    Table,
+    Id,
+    UserId,
+    TokenHash,
+    ExpiresAt,
+    ConsumedAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,145 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Accounts at identity providers that users sign in with, by the
+        // subject the provider knows them as
+        manager
+            .create_table(
+                Table::create()
+                    .table(Identities::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Identities::Id).uuid().primary_key())
+                    .col(ColumnDef::new(Identities::UserId).integer().not_null())
+                    .col(ColumnDef::new(Identities::Provider).string().not_null())
+                    .col(ColumnDef::new(Identities::Subject).string().not_null())
+                    .col(ColumnDef::new(Identities::Email).string())
+                    .col(ColumnDef::new(Identities::LastSignedInAt).timestamp())
+                    .col(ColumnDef::new(Identities::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(Identities::UpdatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_identities_user")
+                            .from(Identities::Table, Identities::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_identities_provider_subject")
+                    .table(Identities::Table)
+                    .col(Identities::Provider)
+                    .col(Identities::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_identities_user_id")
+                    .table(Identities::Table)
+                    .col(Identities::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Sign ins sent off to a provider and not back yet: the state they
+        // are told apart by, and the PKCE verifier and nonce only the server
+        // may know
+        manager
+            .create_table(
+                Table::create()
+                    .table(OidcRequests::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(OidcRequests::Id).uuid().primary_key())
+                    .col(ColumnDef::new(OidcRequests::Provider).string().not_null())
+                    .col(
+                        ColumnDef::new(OidcRequests::StateHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OidcRequests::CodeVerifier)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OidcRequests::Nonce).string().not_null())
+                    .col(
+                        ColumnDef::new(OidcRequests::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OidcRequests::ConsumedAt).timestamp())
+                    .col(
+                        ColumnDef::new(OidcRequests::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OidcRequests::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OidcRequests::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Identities::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum Identities {
+    Table,
+    Id,
+    UserId,
+    Provider,
+    Subject,
+    Email,
+    LastSignedInAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum OidcRequests {
+    Table,
+    Id,
+    Provider,
+    StateHash,
+    CodeVerifier,
+    Nonce,
+    ExpiresAt,
+    ConsumedAt,
+    CreatedAt,
+    UpdatedAt,
+}
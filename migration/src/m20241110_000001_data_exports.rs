@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DataExports::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(DataExports::Id).uuid().primary_key())
+                    .col(ColumnDef::new(DataExports::UserId).integer().not_null())
+                    .col(ColumnDef::new(DataExports::Status).string().not_null())
+                    // the archive and the hash of the token of the link to
+                    // it, once it is ready
+                    .col(ColumnDef::new(DataExports::TokenHash).string().unique_key())
+                    .col(ColumnDef::new(DataExports::Archive).blob())
+                    .col(ColumnDef::new(DataExports::ExpiresAt).timestamp())
+                    .col(
+                        ColumnDef::new(DataExports::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DataExports::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_data_exports_user")
+                            .from(DataExports::Table, DataExports::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_data_exports_user_id")
+                    .table(DataExports::Table)
+                    .col(DataExports::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DataExports::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+pub enum DataExports {
+    Table,
+    Id,
+    UserId,
+    Status,
+    TokenHash,
+    Archive,
+    ExpiresAt,
+    CreatedAt,
+    UpdatedAt,
+}
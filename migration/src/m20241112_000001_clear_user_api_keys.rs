@@ -0,0 +1,59 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{prelude::Uuid, ConnectionTrait, FromQueryResult},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(FromQueryResult)]
+struct User {
+    id: i32,
+    pid: Uuid,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The plaintext API keys every user got are replaced by the hashed
+        // ones in api_keys. SQLite cannot drop a unique column, so it keeps
+        // the pid instead, which is no secret.
+        let db = manager.get_connection();
+        let backend = db.get_database_backend();
+        let users = User::find_by_statement(
+            backend.build(
+                Query::select()
+                    .columns([Users::Id, Users::Pid])
+                    .from(Users::Table),
+            ),
+        )
+        .all(db)
+        .await?;
+        for user in users {
+            db.execute(
+                backend.build(
+                    Query::update()
+                        .table(Users::Table)
+                        .value(Users::ApiKey, user.pid.to_string())
+                        .and_where(Expr::col(Users::Id).eq(user.id)),
+                ),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // the keys are gone for good
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Users {
+    Table,
+    Id,
+    Pid,
+    ApiKey,
+}
@@ -0,0 +1,178 @@
+//! A copy of everything tied to a user, for subject access requests: a zip
+//! archive of JSON files, one per kind of data, and a manifest counting
+//! what is in each.
+//!
+//! Secrets stay out of it: password, token and key hashes, and memory
+//! embeddings, are not serialized. There is no audit log table as such;
+//! what records what was done with the account is exported instead: its
+//! sign in sessions, failed sign ins counted against it, linked identities,
+//! API keys and the tool calls it approved or rejected.
+
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Write},
+};
+
+use chrono::{offset::Local, NaiveDateTime};
+use loco_rs::{Error, Result};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use serde_json::{json, Value};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use super::{memories_of, training_samples_of};
+use crate::{
+    lockout,
+    models::{
+        _entities::{
+            api_keys, conversations, identities, login_throttles, memberships, messages,
+            organizations, sessions, tool_approvals, usage_records,
+        },
+        login_throttles::Scope,
+        users,
+    },
+};
+
+const MANIFEST: &str = "manifest.json";
+const ACCOUNT: &str = "account.json";
+
+#[derive(Debug, Serialize)]
+struct Manifest {
+    exported_at: NaiveDateTime,
+    /// Rows per data file
+    files: BTreeMap<&'static str, usize>,
+}
+
+/// Puts the user's data together as a zip archive
+///
+/// # Errors
+///
+/// When has DB query error or the archive could not be written
+pub async fn archive(db: &DatabaseConnection, user: &users::Model) -> Result<Vec<u8>> {
+    let account = json!({
+        "pid": user.pid,
+        "email": user.email,
+        "name": user.name,
+        "plan": user.plan,
+        "created_at": user.created_at,
+        "email_verified_at": user.email_verified_at,
+        "pending_email": user.pending_email,
+        "two_factor_enabled_at": user.totp_enabled_at,
+        "delete_after": user.delete_after,
+    });
+
+    let conversations = conversations::Entity::find()
+        .filter(conversations::Column::UserId.eq(user.id))
+        .order_by_asc(conversations::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let messages = messages::Entity::find()
+        .filter(messages::Column::ConversationId.is_in(conversations.iter().map(|c| c.id)))
+        .order_by_asc(messages::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let training_samples = training_samples_of(db, user.pid).await?;
+    let memories = memories_of(db, user).await?;
+    let usage_records = usage_records::Entity::find()
+        .filter(usage_records::Column::UserId.eq(user.id))
+        .order_by_asc(usage_records::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let organizations: Vec<Value> = memberships::Entity::find()
+        .filter(memberships::Column::UserId.eq(user.id))
+        .find_also_related(organizations::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(membership, organization)| {
+            json!({
+                "id": membership.organization_id,
+                "name": organization.map(|organization| organization.name),
+                "role": membership.role,
+                "joined_at": membership.created_at,
+            })
+        })
+        .collect();
+    let sessions = sessions::Entity::find()
+        .filter(sessions::Column::UserId.eq(user.id))
+        .order_by_asc(sessions::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let identities = identities::Entity::find()
+        .filter(identities::Column::UserId.eq(user.id))
+        .all(db)
+        .await?;
+    let api_keys = api_keys::Entity::find()
+        .filter(api_keys::Column::UserId.eq(user.id))
+        .all(db)
+        .await?;
+    let login_throttles = login_throttles::Entity::find()
+        .filter(login_throttles::Column::Scope.eq(Scope::Account.as_str()))
+        .filter(login_throttles::Column::Key.eq(lockout::account_key(&user.email)))
+        .all(db)
+        .await?;
+    let tool_approvals = tool_approvals::Entity::find()
+        .filter(tool_approvals::Column::UserId.eq(user.id))
+        .order_by_asc(tool_approvals::Column::CreatedAt)
+        .all(db)
+        .await?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut files = BTreeMap::new();
+    write_json(&mut zip, ACCOUNT, &account)?;
+    files.insert(ACCOUNT, 1);
+    write_rows(&mut zip, &mut files, "conversations.json", &conversations)?;
+    write_rows(&mut zip, &mut files, "messages.json", &messages)?;
+    write_rows(
+        &mut zip,
+        &mut files,
+        "training_samples.json",
+        &training_samples,
+    )?;
+    write_rows(&mut zip, &mut files, "memories.json", &memories)?;
+    write_rows(&mut zip, &mut files, "usage_records.json", &usage_records)?;
+    write_rows(&mut zip, &mut files, "organizations.json", &organizations)?;
+    write_rows(&mut zip, &mut files, "sessions.json", &sessions)?;
+    write_rows(
+        &mut zip,
+        &mut files,
+        "login_throttles.json",
+        &login_throttles,
+    )?;
+    write_rows(&mut zip, &mut files, "identities.json", &identities)?;
+    write_rows(&mut zip, &mut files, "api_keys.json", &api_keys)?;
+    write_rows(&mut zip, &mut files, "tool_approvals.json", &tool_approvals)?;
+    let manifest = Manifest {
+        exported_at: Local::now().naive_local(),
+        files,
+    };
+    write_json(&mut zip, MANIFEST, &manifest)?;
+    Ok(zip.finish().map_err(archive_error)?.into_inner())
+}
+
+fn write_rows<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    files: &mut BTreeMap<&'static str, usize>,
+    name: &'static str,
+    rows: &[T],
+) -> Result<()> {
+    write_json(zip, name, rows)?;
+    files.insert(name, rows.len());
+    Ok(())
+}
+
+fn write_json<T: Serialize + ?Sized>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    value: &T,
+) -> Result<()> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(archive_error)?;
+    serde_json::to_writer_pretty(&mut *zip, value)?;
+    zip.write_all(b"\n")?;
+    Ok(())
+}
+
+fn archive_error(err: zip::result::ZipError) -> Error {
+    Error::string(&format!("could not write data export: {err}"))
+}
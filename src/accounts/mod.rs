@@ -0,0 +1,298 @@
+//! Users deleting their own account. Asking for it schedules the account to
+//! be erased once a grace period is over, during which the user may still
+//! sign in and change their mind; the `purge_accounts` task erases the
+//! accounts that are due.
+//!
+//! Erasing deletes what is the user's alone: their conversations with
+//! their messages, the training samples captured from their feedback, the
+//! memories of the agents they own or naming them, their keys, sessions
+//! and identities, and the organizations nobody else is a member of, with
+//! the agents, memories and knowledge bases in them. What stays with an
+//! organization others are in, its agents, tasks and knowledge bases, and
+//! the usage records kept for billing, no longer names the user.
+//!
+//! Users may also take a copy of their data, see [`export`].
+
+use axum::http::StatusCode;
+use chrono::{Duration, Local};
+use loco_rs::{controller::ErrorDetail, Error, Result};
+use sea_orm::{
+    sea_query::{Expr, SimpleExpr},
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    lockout,
+    models::{
+        _entities::{
+            agents, conversations, invitations, knowledge_base, learning_models, login_throttles,
+            memories, model_training_data, organizations, tasks, usage_records,
+        },
+        memberships::{self, Role},
+        users,
+    },
+};
+
+pub mod export;
+
+/// The `settings.accounts` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// How long after asking for it an account is erased
+    #[serde(default = "default_deletion_grace_secs")]
+    pub deletion_grace_secs: i64,
+    /// How long the link to a data export works
+    #[serde(default = "default_export_ttl_secs")]
+    pub export_ttl_secs: i64,
+    /// How long an export may stay pending before it is taken to have died
+    /// and asking again starts another
+    #[serde(default = "default_export_stale_secs")]
+    pub export_stale_secs: i64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            deletion_grace_secs: default_deletion_grace_secs(),
+            export_ttl_secs: default_export_ttl_secs(),
+            export_stale_secs: default_export_stale_secs(),
+        }
+    }
+}
+
+const fn default_deletion_grace_secs() -> i64 {
+    30 * 24 * 60 * 60
+}
+
+const fn default_export_ttl_secs() -> i64 {
+    7 * 24 * 60 * 60
+}
+
+const fn default_export_stale_secs() -> i64 {
+    60 * 60
+}
+
+impl Config {
+    #[must_use]
+    pub fn deletion_grace(&self) -> Duration {
+        Duration::seconds(self.deletion_grace_secs)
+    }
+
+    #[must_use]
+    pub fn export_ttl(&self) -> Duration {
+        Duration::seconds(self.export_ttl_secs)
+    }
+
+    #[must_use]
+    pub fn export_stale(&self) -> Duration {
+        Duration::seconds(self.export_stale_secs)
+    }
+}
+
+/// Schedules the account to be erased once the grace period is over. An
+/// account already scheduled keeps its date.
+///
+/// # Errors
+///
+/// When the user is the last owner of an organization others are in, or
+/// DB query error
+pub async fn schedule_deletion(
+    db: &DatabaseConnection,
+    config: &Config,
+    user: users::Model,
+) -> Result<users::Model> {
+    if user.delete_after.is_some() {
+        return Ok(user);
+    }
+    for (membership, organization) in memberships::Model::list_for_user(db, user.id).await? {
+        if membership.role() == Role::Owner
+            && memberships::Model::count_owners(db, organization.id).await? <= 1
+            && member_count(db, organization.id).await? > 1
+        {
+            return Err(Error::CustomError(
+                StatusCode::CONFLICT,
+                ErrorDetail::new(
+                    "last_owner",
+                    "hand over the organizations you are the last owner of first",
+                ),
+            ));
+        }
+    }
+
+    let delete_after = Local::now() + config.deletion_grace();
+    Ok(user
+        .into_active_model()
+        .schedule_deletion(db, delete_after.into())
+        .await?)
+}
+
+/// Erases the user and what is theirs alone, all at once
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn erase(db: &DatabaseConnection, user: &users::Model) -> Result<()> {
+    let txn = db.begin().await?;
+
+    let mut solo = Vec::new();
+    for membership in memberships::Entity::find()
+        .filter(memberships::memberships::Column::UserId.eq(user.id))
+        .all(&txn)
+        .await?
+    {
+        if member_count(&txn, membership.organization_id).await? == 1 {
+            solo.push(membership.organization_id);
+        }
+    }
+    let solo_agents: Vec<Uuid> = agents::Entity::find()
+        .select_only()
+        .column(agents::Column::Id)
+        .filter(agents::Column::OrganizationId.is_in(solo.clone()))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+
+    // messages and tool approvals go with their conversation
+    conversations::Entity::delete_many()
+        .filter(
+            Condition::any()
+                .add(conversations::Column::UserId.eq(user.id))
+                .add(conversations::Column::OrganizationId.is_in(solo.clone()))
+                .add(conversations::Column::AgentId.is_in(solo_agents.clone())),
+        )
+        .exec(&txn)
+        .await?;
+    erase_training_samples(&txn, user.pid).await?;
+    erase_memories(&txn, user).await?;
+
+    // memories, tasks and versions go with their agent, items with their
+    // knowledge base, memberships and invitations with their organization
+    learning_models::Entity::delete_many()
+        .filter(learning_models::Column::AgentId.is_in(solo_agents.clone()))
+        .exec(&txn)
+        .await?;
+    agents::Entity::delete_many()
+        .filter(agents::Column::Id.is_in(solo_agents))
+        .exec(&txn)
+        .await?;
+    knowledge_base::Entity::delete_many()
+        .filter(knowledge_base::Column::OrganizationId.is_in(solo.clone()))
+        .exec(&txn)
+        .await?;
+    organizations::Entity::delete_many()
+        .filter(organizations::Column::Id.is_in(solo))
+        .exec(&txn)
+        .await?;
+
+    agents::Entity::update_many()
+        .col_expr(agents::Column::UserId, Expr::value(None::<i32>))
+        .filter(agents::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+    tasks::Entity::update_many()
+        .col_expr(tasks::Column::UserId, Expr::value(None::<i32>))
+        .filter(tasks::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+    knowledge_base::Entity::update_many()
+        .col_expr(knowledge_base::Column::UserId, Expr::value(None::<i32>))
+        .filter(knowledge_base::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+    usage_records::Entity::update_many()
+        .col_expr(usage_records::Column::UserId, Expr::value(None::<i32>))
+        .filter(usage_records::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+
+    invitations::Entity::delete_many()
+        .filter(invitations::Column::Email.eq(&user.email))
+        .exec(&txn)
+        .await?;
+    login_throttles::Entity::delete_many()
+        .filter(login_throttles::Column::Key.eq(lockout::account_key(&user.email)))
+        .exec(&txn)
+        .await?;
+    // sessions, keys, codes, identities, links, schedules and memberships
+    // go with the user
+    users::Entity::delete_by_id(user.id).exec(&txn).await?;
+
+    txn.commit().await?;
+    tracing::info!(pid = user.pid.to_string(), "account erased");
+    Ok(())
+}
+
+async fn member_count<C: ConnectionTrait>(db: &C, organization_id: Uuid) -> Result<u64> {
+    Ok(memberships::Entity::find()
+        .filter(memberships::memberships::Column::OrganizationId.eq(organization_id))
+        .count(db)
+        .await?)
+}
+
+/// The training samples captured from the user's feedback, which hold what
+/// they wrote. They only name the user in their metadata.
+pub(crate) async fn training_samples_of<C: ConnectionTrait>(
+    db: &C,
+    pid: Uuid,
+) -> Result<Vec<model_training_data::Model>> {
+    Ok(model_training_data::Entity::find()
+        .filter(names_user(db, "model_training_data", pid))
+        .all(db)
+        .await?)
+}
+
+async fn erase_training_samples<C: ConnectionTrait>(db: &C, pid: Uuid) -> Result<()> {
+    let samples = training_samples_of(db, pid).await?;
+    model_training_data::Entity::delete_many()
+        .filter(model_training_data::Column::Id.is_in(samples.iter().map(|sample| sample.id)))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// The memories of the agents the user owns, wherever they are, and those
+/// naming the user in their metadata, as the agent remembered them from
+/// the user
+pub(crate) async fn memories_of<C: ConnectionTrait>(
+    db: &C,
+    user: &users::Model,
+) -> Result<Vec<memories::Model>> {
+    let owned: Vec<Uuid> = agents::Entity::find()
+        .select_only()
+        .column(agents::Column::Id)
+        .filter(agents::Column::UserId.eq(user.id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(memories::Entity::find()
+        .filter(
+            Condition::any()
+                .add(memories::Column::AgentId.is_in(owned))
+                .add(names_user(db, "memories", user.pid)),
+        )
+        .all(db)
+        .await?)
+}
+
+/// Whether the `user_pid` in the metadata of the rows of `table` is the
+/// user's, read by the database
+fn names_user<C: ConnectionTrait>(db: &C, table: &str, pid: Uuid) -> SimpleExpr {
+    let user_pid = match db.get_database_backend() {
+        DbBackend::Postgres => format!("{table}.metadata ->> 'user_pid'"),
+        DbBackend::Sqlite => format!("json_extract({table}.metadata, '$.user_pid')"),
+        DbBackend::MySql => format!("JSON_UNQUOTE(JSON_EXTRACT({table}.metadata, '$.user_pid'))"),
+    };
+    Expr::cust_with_values(format!("{user_pid} = ?"), [pid.to_string()])
+}
+
+async fn erase_memories<C: ConnectionTrait>(db: &C, user: &users::Model) -> Result<()> {
+    let memories = memories_of(db, user).await?;
+    memories::Entity::delete_many()
+        .filter(memories::Column::Id.is_in(memories.iter().map(|memory| memory.id)))
+        .exec(db)
+        .await?;
+    Ok(())
+}
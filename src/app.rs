@@ -14,7 +14,18 @@ use sea_orm::DatabaseConnection;
 use std::path::Path;
 
 use crate::{
-    controllers, initializers, models::_entities::users, tasks, workers::downloader::DownloadWorker,
+    bus, controllers, initializers,
+    models::_entities::{
+        agent_capabilities, agent_versions, agents, api_keys, bus_messages, conversations,
+        data_exports, identities, invitations, knowledge_base, knowledge_items, learning_models,
+        login_throttles, magic_links, memberships, messages, model_evaluations,
+        model_training_data, oidc_requests, organizations, previous_passwords, recovery_codes,
+        sessions, task_dependencies, task_schedules, tasks, tool_approvals, usage_records, users,
+    },
+    workers::{
+        bus::BusWorker, downloader::DownloadWorker, exporter::ExportWorker,
+        optimizer::OptimizerWorker, orchestrator::OrchestratorWorker, scheduler::ScheduleWorker,
+    },
 };
 
 pub struct App;
@@ -39,24 +50,80 @@ impl Hooks for App {
     }
 
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![Box::new(
-            initializers::view_engine::ViewEngineInitializer,
-        )])
+        Ok(vec![
+            Box::new(initializers::view_engine::ViewEngineInitializer),
+            Box::new(initializers::scheduler::SchedulerInitializer),
+        ])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
         AppRoutes::with_default_routes() // controller routes below
+            .add_route(controllers::account::routes())
+            .add_route(controllers::agents::routes())
+            .add_route(controllers::api_keys::routes())
+            .add_route(controllers::approvals::routes())
             .add_route(controllers::auth::routes())
+            .add_route(controllers::conversations::routes())
+            .add_route(controllers::events::routes())
+            .add_route(controllers::learning_models::routes())
+            .add_route(controllers::messages::routes())
+            .add_route(controllers::oidc::routes())
+            .add_route(controllers::organizations::routes())
+            .add_route(controllers::schedules::routes())
+            .add_route(controllers::tasks::routes())
+            .add_route(controllers::training_samples::routes())
+            .add_route(controllers::usage::routes())
     }
     async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
         queue.register(DownloadWorker::build(ctx)).await?;
+        queue.register(ExportWorker::build(ctx)).await?;
+        queue.register(OptimizerWorker::build(ctx)).await?;
+        queue.register(OrchestratorWorker::build(ctx)).await?;
+        queue.register(BusWorker::build(ctx)).await?;
+        queue.register(ScheduleWorker::build(ctx)).await?;
+        bus::recover(ctx, queue).await?;
         Ok(())
     }
     fn register_tasks(tasks: &mut Tasks) {
-        tasks.register(tasks::seed::SeedData);
+        tasks.register(crate::tasks::seed::SeedData);
+        tasks.register(crate::tasks::optimize_model::OptimizeModel);
+        tasks.register(crate::tasks::expire_approvals::ExpireApprovals);
+        tasks.register(crate::tasks::export_agent::ExportAgent);
+        tasks.register(crate::tasks::import_agent::ImportAgent);
+        tasks.register(crate::tasks::usage_report::UsageReport);
+        tasks.register(crate::tasks::assign_plan::AssignPlan);
+        tasks.register(crate::tasks::purge_accounts::PurgeAccounts);
         // tasks-inject (do not remove)
     }
     async fn truncate(db: &DatabaseConnection) -> Result<()> {
+        truncate_table(db, usage_records::Entity).await?;
+        truncate_table(db, tool_approvals::Entity).await?;
+        truncate_table(db, messages::Entity).await?;
+        truncate_table(db, conversations::Entity).await?;
+        truncate_table(db, model_evaluations::Entity).await?;
+        truncate_table(db, model_training_data::Entity).await?;
+        truncate_table(db, learning_models::Entity).await?;
+        truncate_table(db, knowledge_items::Entity).await?;
+        truncate_table(db, knowledge_base::Entity).await?;
+        truncate_table(db, bus_messages::Entity).await?;
+        truncate_table(db, task_dependencies::Entity).await?;
+        truncate_table(db, task_schedules::Entity).await?;
+        truncate_table(db, tasks::Entity).await?;
+        truncate_table(db, agent_capabilities::Entity).await?;
+        truncate_table(db, agent_versions::Entity).await?;
+        truncate_table(db, agents::Entity).await?;
+        truncate_table(db, invitations::Entity).await?;
+        truncate_table(db, memberships::Entity).await?;
+        truncate_table(db, organizations::Entity).await?;
+        truncate_table(db, sessions::Entity).await?;
+        truncate_table(db, api_keys::Entity).await?;
+        truncate_table(db, previous_passwords::Entity).await?;
+        truncate_table(db, data_exports::Entity).await?;
+        truncate_table(db, identities::Entity).await?;
+        truncate_table(db, magic_links::Entity).await?;
+        truncate_table(db, recovery_codes::Entity).await?;
+        truncate_table(db, login_throttles::Entity).await?;
+        truncate_table(db, oidc_requests::Entity).await?;
         truncate_table(db, users::Entity).await?;
         Ok(())
     }
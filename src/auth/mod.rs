@@ -0,0 +1,199 @@
+//! Account emails and the tokens they carry. Verification and password
+//! reset links hold a random token; only its hash is stored, on the user,
+//! next to when it was sent. A token is good for its TTL and for one use.
+//! Changing the email works the same way, with the link sent to the new
+//! address, and so do magic links, which log in without a password; those
+//! are kept in `magic_links` so each can be spent once.
+//!
+//! Also what it takes to sign in: a verified email, unless turned off, and
+//! a password that meets the [`password::Policy`].
+//!
+//! Loco reads the top level `auth` section of the config file for itself
+//! and drops keys it does not know, so these settings live under
+//! `settings.auth`.
+
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime};
+use loco_rs::Result;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::models::login_throttles::{self, Scope};
+
+pub mod password;
+
+/// The `settings.auth` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Whether users must verify their email before they can log in
+    #[serde(default = "default_require_verified_email")]
+    pub require_verified_email: bool,
+    #[serde(default)]
+    pub password: password::Policy,
+    /// How long an email verification link works
+    #[serde(default = "default_verification_ttl_secs")]
+    pub verification_ttl_secs: i64,
+    /// How long a password reset link works
+    #[serde(default = "default_reset_ttl_secs")]
+    pub reset_ttl_secs: i64,
+    /// How long the link confirming a new email works
+    #[serde(default = "default_email_change_ttl_secs")]
+    pub email_change_ttl_secs: i64,
+    /// Verification emails that may be asked for again per address within
+    /// `resend_window_secs`
+    #[serde(default = "default_resend_max")]
+    pub resend_max: i32,
+    #[serde(default = "default_resend_window_secs")]
+    pub resend_window_secs: i64,
+    /// How long a magic login link works
+    #[serde(default = "default_magic_link_ttl_secs")]
+    pub magic_link_ttl_secs: i64,
+    /// Magic links that may be asked for per address within
+    /// `magic_link_window_secs`
+    #[serde(default = "default_magic_link_max")]
+    pub magic_link_max: i32,
+    #[serde(default = "default_magic_link_window_secs")]
+    pub magic_link_window_secs: i64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            require_verified_email: default_require_verified_email(),
+            password: password::Policy::default(),
+            verification_ttl_secs: default_verification_ttl_secs(),
+            reset_ttl_secs: default_reset_ttl_secs(),
+            email_change_ttl_secs: default_email_change_ttl_secs(),
+            resend_max: default_resend_max(),
+            resend_window_secs: default_resend_window_secs(),
+            magic_link_ttl_secs: default_magic_link_ttl_secs(),
+            magic_link_max: default_magic_link_max(),
+            magic_link_window_secs: default_magic_link_window_secs(),
+        }
+    }
+}
+
+const fn default_require_verified_email() -> bool {
+    true
+}
+
+const fn default_verification_ttl_secs() -> i64 {
+    24 * 60 * 60
+}
+
+const fn default_reset_ttl_secs() -> i64 {
+    60 * 60
+}
+
+const fn default_email_change_ttl_secs() -> i64 {
+    24 * 60 * 60
+}
+
+const fn default_resend_max() -> i32 {
+    3
+}
+
+const fn default_resend_window_secs() -> i64 {
+    60 * 60
+}
+
+const fn default_magic_link_ttl_secs() -> i64 {
+    15 * 60
+}
+
+const fn default_magic_link_max() -> i32 {
+    5
+}
+
+const fn default_magic_link_window_secs() -> i64 {
+    60 * 60
+}
+
+impl Config {
+    #[must_use]
+    pub fn verification_ttl(&self) -> Duration {
+        Duration::seconds(self.verification_ttl_secs)
+    }
+
+    #[must_use]
+    pub fn reset_ttl(&self) -> Duration {
+        Duration::seconds(self.reset_ttl_secs)
+    }
+
+    #[must_use]
+    pub fn email_change_ttl(&self) -> Duration {
+        Duration::seconds(self.email_change_ttl_secs)
+    }
+
+    #[must_use]
+    pub fn magic_link_ttl(&self) -> Duration {
+        Duration::seconds(self.magic_link_ttl_secs)
+    }
+}
+
+/// Whether a token sent at `sent_at` still works
+#[must_use]
+pub fn is_fresh(sent_at: Option<DateTime<FixedOffset>>, ttl: Duration) -> bool {
+    sent_at.is_some_and(|sent_at| sent_at + ttl > Local::now())
+}
+
+/// Counts a request for another verification email to the address.
+/// Returns until when such requests are refused, when this one is too.
+/// Addresses no user has are counted alike, so the answers tell nothing
+/// about who has an account.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn throttle_resend(
+    db: &DatabaseConnection,
+    config: &Config,
+    email: &str,
+) -> Result<Option<NaiveDateTime>> {
+    throttle(
+        db,
+        Scope::Resend,
+        email,
+        config.resend_max,
+        Duration::seconds(config.resend_window_secs),
+    )
+    .await
+}
+
+/// Counts a request for a magic link to the address, like
+/// [`throttle_resend`]
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn throttle_magic_link(
+    db: &DatabaseConnection,
+    config: &Config,
+    email: &str,
+) -> Result<Option<NaiveDateTime>> {
+    throttle(
+        db,
+        Scope::MagicLink,
+        email,
+        config.magic_link_max,
+        Duration::seconds(config.magic_link_window_secs),
+    )
+    .await
+}
+
+async fn throttle(
+    db: &DatabaseConnection,
+    scope: Scope,
+    email: &str,
+    max: i32,
+    window: Duration,
+) -> Result<Option<NaiveDateTime>> {
+    let key = email.trim().to_lowercase();
+    let now = Local::now().naive_local();
+    if let Some(throttle) = login_throttles::Model::find_for(db, scope, &key).await? {
+        if let Some(until) = throttle.locked_until(now) {
+            return Ok(Some(until));
+        }
+    }
+    login_throttles::Model::record_failure(db, scope, &key, max, window, window).await?;
+    Ok(None)
+}
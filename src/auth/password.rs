@@ -0,0 +1,147 @@
+//! The password policy, checked whenever a password is chosen: when
+//! registering and when resetting it.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::LazyLock;
+
+use loco_rs::{hash, validation::ModelValidationMessage, Result};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{previous_passwords, users};
+
+/// The bundled list of breached passwords, read into a set the first time a
+/// password is checked
+static BREACHED: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    include_str!("breached_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+/// The `settings.auth.password` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Policy {
+    /// The fewest characters a password may have
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+    /// How many of the most recent passwords, the current one included,
+    /// may not be chosen again. 0 allows any.
+    #[serde(default = "default_history")]
+    pub history: u64,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            min_length: default_min_length(),
+            history: default_history(),
+        }
+    }
+}
+
+const fn default_min_length() -> usize {
+    12
+}
+
+const fn default_history() -> u64 {
+    5
+}
+
+/// A way a password breaks the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    TooShort { min_length: usize },
+    Breached,
+    Reused,
+}
+
+impl Violation {
+    #[must_use]
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::TooShort { .. } => "too_short",
+            Self::Breached => "breached",
+            Self::Reused => "reused",
+        }
+    }
+
+    #[must_use]
+    pub fn message(self) -> String {
+        match self {
+            Self::TooShort { min_length } => {
+                format!("Password must be at least {min_length} characters long.")
+            }
+            Self::Breached => "Password is known from data breaches.".to_string(),
+            Self::Reused => "Password was used recently.".to_string(),
+        }
+    }
+}
+
+/// The violations as validation messages by field, the shape model
+/// validation errors take
+#[must_use]
+pub fn validation_errors(
+    violations: &[Violation],
+) -> BTreeMap<&'static str, Vec<ModelValidationMessage>> {
+    let messages = violations
+        .iter()
+        .map(|violation| ModelValidationMessage {
+            code: violation.code().to_string(),
+            message: Some(violation.message()),
+        })
+        .collect();
+    BTreeMap::from([("password", messages)])
+}
+
+impl Policy {
+    /// How many passwords to keep besides the current one
+    #[must_use]
+    pub const fn previous_kept(&self) -> u64 {
+        self.history.saturating_sub(1)
+    }
+
+    /// How the password breaks the policy, leaving out reuse, which takes
+    /// knowing whose it is
+    #[must_use]
+    pub fn check(&self, password: &str) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        if password.chars().count() < self.min_length {
+            violations.push(Violation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+        if BREACHED.contains(password.to_lowercase().as_str()) {
+            violations.push(Violation::Breached);
+        }
+        violations
+    }
+
+    /// How the password breaks the policy as the next password of the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn check_for(
+        &self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+        password: &str,
+    ) -> Result<Vec<Violation>> {
+        let mut violations = self.check(password);
+        if self.history > 0 {
+            let mut hashes = vec![user.password.clone()];
+            hashes.extend(
+                previous_passwords::Model::recent_hashes(db, user.id, self.previous_kept()).await?,
+            );
+            if hashes
+                .iter()
+                .any(|hash| hash::verify_password(password, hash))
+            {
+                violations.push(Violation::Reused);
+            }
+        }
+        Ok(violations)
+    }
+}
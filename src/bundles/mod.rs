@@ -0,0 +1,533 @@
+//! Agent bundles move an agent between environments. A bundle is a zip
+//! archive holding a YAML manifest that describes the agent and JSONL files
+//! with its capabilities, the knowledge bases it links to under
+//! `knowledge_base_ids` in its configuration, and its learning model
+//! versions with their training data.
+//!
+//! Imports always create fresh ids and rewrite every reference to them, so a
+//! bundle can be imported next to the agent it was exported from.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Read, Write},
+};
+
+use axum::http::StatusCode;
+use chrono::{offset::Local, NaiveDateTime};
+use loco_rs::{controller::ErrorDetail, Error, Result};
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::models::{
+    _entities::{
+        agent_capabilities, agents, knowledge_base, knowledge_items, learning_models,
+        model_training_data,
+    },
+    agent_versions,
+};
+
+/// Bumped whenever the layout of a bundle changes incompatibly
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.yaml";
+const CAPABILITIES: &str = "capabilities.jsonl";
+const KNOWLEDGE_BASES: &str = "knowledge_bases.jsonl";
+const KNOWLEDGE_ITEMS: &str = "knowledge_items.jsonl";
+const LEARNING_MODELS: &str = "learning_models.jsonl";
+const TRAINING_DATA: &str = "training_data.jsonl";
+
+/// The agent configuration key listing its knowledge bases
+pub const KNOWLEDGE_BASE_IDS: &str = "knowledge_base_ids";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub exported_at: NaiveDateTime,
+    /// The agent version the bundle was taken from
+    pub agent_version: Option<i32>,
+    pub agent: AgentSpec,
+    /// Rows per data file
+    pub files: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AgentSpec {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub r#type: String,
+    pub status: String,
+    pub configuration: Option<Value>,
+}
+
+/// What to do when the target already has an agent or a learning model
+/// with the name of an imported one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Refuse the import
+    #[default]
+    Fail,
+    /// Keep the existing agent and import nothing
+    Skip,
+    /// Import under a new name
+    Rename,
+}
+
+impl std::str::FromStr for OnConflict {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        serde_json::from_value(Value::String(value.to_string()))
+            .map_err(|_| Error::BadRequest(format!("unknown conflict policy: {value}")))
+    }
+}
+
+/// Who imported rows belong to
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Owner {
+    /// The user the agent and knowledge count against
+    pub user_id: Option<i32>,
+    /// The organization sharing them
+    pub organization_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub agent_id: Uuid,
+    pub name: String,
+    /// The agent existed already and was kept as it is
+    pub skipped: bool,
+    /// Names that were taken, with the name used instead
+    pub renamed: BTreeMap<String, String>,
+    /// Rows imported per data file
+    pub imported: BTreeMap<String, usize>,
+}
+
+struct Contents {
+    manifest: Manifest,
+    capabilities: Vec<agent_capabilities::Model>,
+    knowledge_bases: Vec<knowledge_base::Model>,
+    knowledge_items: Vec<knowledge_items::Model>,
+    learning_models: Vec<learning_models::Model>,
+    training_data: Vec<model_training_data::Model>,
+}
+
+/// Packs the agent and everything it depends on into a bundle
+///
+/// # Errors
+///
+/// When the agent does not exist or DB query error
+pub async fn export(db: &DatabaseConnection, agent_id: Uuid) -> Result<Vec<u8>> {
+    let agent = agents::Entity::find_by_id(agent_id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let version = agent_versions::Model::record(db, agent.id).await?;
+
+    let capabilities = agent_capabilities::Entity::find()
+        .filter(agent_capabilities::Column::AgentId.eq(agent.id))
+        .all(db)
+        .await?;
+    let knowledge_bases: Vec<_> = knowledge_base::Entity::find()
+        .filter(
+            knowledge_base::Column::Id.is_in(linked_knowledge_bases(agent.configuration.as_ref())),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        // ownership does not travel with the bundle
+        .map(|kb| knowledge_base::Model {
+            user_id: None,
+            organization_id: None,
+            ..kb
+        })
+        .collect();
+    let knowledge_items = knowledge_items::Entity::find()
+        .filter(
+            knowledge_items::Column::KnowledgeBaseId.is_in(knowledge_bases.iter().map(|kb| kb.id)),
+        )
+        .all(db)
+        .await?;
+    let learning_models = learning_models::Entity::find()
+        .filter(learning_models::Column::AgentId.eq(agent.id))
+        .all(db)
+        .await?;
+    let training_data = model_training_data::Entity::find()
+        .filter(model_training_data::Column::ModelId.is_in(learning_models.iter().map(|m| m.id)))
+        .all(db)
+        .await?;
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        exported_at: Local::now().naive_local(),
+        agent_version: Some(version.version),
+        agent: AgentSpec {
+            id: agent.id,
+            name: agent.name,
+            description: agent.description,
+            r#type: agent.r#type,
+            status: agent.status,
+            configuration: agent.configuration,
+        },
+        files: BTreeMap::from([
+            (CAPABILITIES.to_string(), capabilities.len()),
+            (KNOWLEDGE_BASES.to_string(), knowledge_bases.len()),
+            (KNOWLEDGE_ITEMS.to_string(), knowledge_items.len()),
+            (LEARNING_MODELS.to_string(), learning_models.len()),
+            (TRAINING_DATA.to_string(), training_data.len()),
+        ]),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file(MANIFEST, options).map_err(archive_error)?;
+    zip.write_all(
+        serde_yaml::to_string(&manifest)
+            .map_err(Error::wrap)?
+            .as_bytes(),
+    )?;
+    write_jsonl(&mut zip, CAPABILITIES, &capabilities)?;
+    write_jsonl(&mut zip, KNOWLEDGE_BASES, &knowledge_bases)?;
+    write_jsonl(&mut zip, KNOWLEDGE_ITEMS, &knowledge_items)?;
+    write_jsonl(&mut zip, LEARNING_MODELS, &learning_models)?;
+    write_jsonl(&mut zip, TRAINING_DATA, &training_data)?;
+    Ok(zip.finish().map_err(archive_error)?.into_inner())
+}
+
+/// Unpacks a bundle into new rows. The agent and its knowledge bases are
+/// owned by `owner`.
+///
+/// # Errors
+///
+/// `BadRequest` when the bundle is malformed or of an unknown format
+/// version, 409 when a name is taken and `on_conflict` is `fail`, or DB
+/// query error
+pub async fn import(
+    db: &DatabaseConnection,
+    bundle: &[u8],
+    on_conflict: OnConflict,
+    owner: Owner,
+) -> Result<ImportReport> {
+    let contents = read(bundle)?;
+    let txn = db.begin().await?;
+
+    let mut renamed = BTreeMap::new();
+    let mut name = contents.manifest.agent.name.clone();
+    if let Some(existing) = agents::Entity::find()
+        .filter(agents::Column::Name.eq(&name))
+        .one(&txn)
+        .await?
+    {
+        match on_conflict {
+            OnConflict::Fail => return Err(conflict(&format!("agent {name} already exists"))),
+            OnConflict::Skip => {
+                return Ok(ImportReport {
+                    agent_id: existing.id,
+                    name,
+                    skipped: true,
+                    renamed,
+                    imported: BTreeMap::new(),
+                })
+            }
+            OnConflict::Rename => {
+                let free = free_name(&txn, &name, agents::Column::Name).await?;
+                renamed.insert(name, free.clone());
+                name = free;
+            }
+        }
+    }
+
+    let mut model_names: Vec<&String> = contents
+        .learning_models
+        .iter()
+        .map(|model| &model.name)
+        .collect();
+    model_names.sort();
+    model_names.dedup();
+    let mut model_renames = HashMap::new();
+    for model_name in model_names {
+        let taken = learning_models::Entity::find()
+            .filter(learning_models::Column::Name.eq(model_name))
+            .one(&txn)
+            .await?
+            .is_some();
+        if taken {
+            if on_conflict == OnConflict::Fail {
+                return Err(conflict(&format!(
+                    "learning model {model_name} already exists"
+                )));
+            }
+            let free = free_name(&txn, model_name, learning_models::Column::Name).await?;
+            renamed.insert(model_name.clone(), free.clone());
+            model_renames.insert(model_name.clone(), free);
+        }
+    }
+
+    let mut ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut remap = |id: Uuid| *ids.entry(id).or_insert_with(Uuid::new_v4);
+    let agent_id = remap(contents.manifest.agent.id);
+    for kb in &contents.knowledge_bases {
+        remap(kb.id);
+    }
+    for item in &contents.knowledge_items {
+        remap(item.id);
+    }
+    for model in &contents.learning_models {
+        remap(model.id);
+    }
+    let ids = ids;
+    let mapped = |id: &Uuid| ids.get(id).copied();
+
+    // bulk inserts skip the model hooks; the agent gets a single version
+    // once everything is in place
+    let mut configuration = contents.manifest.agent.configuration.clone();
+    if let Some(Value::Array(kb_ids)) = configuration
+        .as_mut()
+        .and_then(|configuration| configuration.get_mut(KNOWLEDGE_BASE_IDS))
+    {
+        for kb_id in kb_ids.iter_mut() {
+            if let Some(new_id) = kb_id
+                .as_str()
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .and_then(|id| mapped(&id))
+            {
+                *kb_id = Value::String(new_id.to_string());
+            }
+        }
+    }
+    let now = Local::now().naive_local();
+    agents::Entity::insert(agents::ActiveModel {
+        id: ActiveValue::set(agent_id),
+        name: ActiveValue::set(name.clone()),
+        description: ActiveValue::set(contents.manifest.agent.description.clone()),
+        r#type: ActiveValue::set(contents.manifest.agent.r#type.clone()),
+        status: ActiveValue::set(contents.manifest.agent.status.clone()),
+        configuration: ActiveValue::set(configuration),
+        created_at: ActiveValue::set(now),
+        updated_at: ActiveValue::set(now),
+        user_id: ActiveValue::set(owner.user_id),
+        organization_id: ActiveValue::set(owner.organization_id),
+    })
+    .exec(&txn)
+    .await?;
+
+    for capability in &contents.capabilities {
+        agent_capabilities::Entity::insert(agent_capabilities::ActiveModel {
+            id: ActiveValue::set(Uuid::new_v4()),
+            agent_id: ActiveValue::set(agent_id),
+            name: ActiveValue::set(capability.name.clone()),
+            description: ActiveValue::set(capability.description.clone()),
+            parameters: ActiveValue::set(capability.parameters.clone()),
+            requires_approval: ActiveValue::set(capability.requires_approval),
+        })
+        .exec(&txn)
+        .await?;
+    }
+
+    for kb in &contents.knowledge_bases {
+        let mut kb = kb.clone();
+        kb.id = ids[&kb.id];
+        kb.user_id = owner.user_id;
+        kb.organization_id = owner.organization_id;
+        knowledge_base::Entity::insert(knowledge_base::ActiveModel::from(kb))
+            .exec(&txn)
+            .await?;
+    }
+    for item in &contents.knowledge_items {
+        let mut item = item.clone();
+        item.id = ids[&item.id];
+        item.knowledge_base_id = mapped(&item.knowledge_base_id)
+            .ok_or_else(|| invalid("a knowledge item belongs to a missing knowledge base"))?;
+        // chunks point at the document they were cut from
+        if let Some(Value::String(document_id)) = item
+            .metadata
+            .as_mut()
+            .and_then(|metadata| metadata.get_mut("document_id"))
+        {
+            if let Some(new_id) = Uuid::parse_str(document_id).ok().and_then(|id| mapped(&id)) {
+                *document_id = new_id.to_string();
+            }
+        }
+        knowledge_items::Entity::insert(knowledge_items::ActiveModel::from(item))
+            .exec(&txn)
+            .await?;
+    }
+
+    for model in &contents.learning_models {
+        let mut model = model.clone();
+        model.id = ids[&model.id];
+        model.agent_id = Some(agent_id);
+        model.parent_id = model.parent_id.as_ref().and_then(mapped);
+        if let Some(new_name) = model_renames.get(&model.name) {
+            model.name = new_name.clone();
+        }
+        learning_models::Entity::insert(learning_models::ActiveModel::from(model))
+            .exec(&txn)
+            .await?;
+    }
+    for sample in &contents.training_data {
+        let mut sample = sample.clone();
+        sample.id = Uuid::new_v4();
+        sample.model_id = mapped(&sample.model_id)
+            .ok_or_else(|| invalid("a training sample belongs to a missing learning model"))?;
+        model_training_data::Entity::insert(model_training_data::ActiveModel::from(sample))
+            .exec(&txn)
+            .await?;
+    }
+
+    agent_versions::Model::record(&txn, agent_id).await?;
+    txn.commit().await?;
+
+    Ok(ImportReport {
+        agent_id,
+        name,
+        skipped: false,
+        renamed,
+        imported: BTreeMap::from([
+            (CAPABILITIES.to_string(), contents.capabilities.len()),
+            (KNOWLEDGE_BASES.to_string(), contents.knowledge_bases.len()),
+            (KNOWLEDGE_ITEMS.to_string(), contents.knowledge_items.len()),
+            (LEARNING_MODELS.to_string(), contents.learning_models.len()),
+            (TRAINING_DATA.to_string(), contents.training_data.len()),
+        ]),
+    })
+}
+
+/// Reads the manifest of a bundle without importing it
+///
+/// # Errors
+///
+/// When the bundle is malformed or of an unknown format version
+pub fn manifest(bundle: &[u8]) -> Result<Manifest> {
+    Ok(read(bundle)?.manifest)
+}
+
+/// The size of the knowledge a bundle brings along, in bytes of content
+///
+/// # Errors
+///
+/// When the bundle is malformed or of an unknown format version
+pub fn knowledge_bytes(bundle: &[u8]) -> Result<u64> {
+    Ok(read(bundle)?
+        .knowledge_items
+        .iter()
+        .map(|item| item.content.len() as u64)
+        .sum())
+}
+
+/// The knowledge bases an agent configuration links to
+#[must_use]
+pub fn linked_knowledge_bases(configuration: Option<&Value>) -> Vec<Uuid> {
+    configuration
+        .and_then(|configuration| configuration.get(KNOWLEDGE_BASE_IDS))
+        .and_then(|ids| serde_json::from_value(ids.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn read(bundle: &[u8]) -> Result<Contents> {
+    let mut archive = ZipArchive::new(Cursor::new(bundle))
+        .map_err(|err| invalid(format!("not a zip archive: {err}")))?;
+    let manifest: Manifest = serde_yaml::from_str(&read_file(&mut archive, MANIFEST)?)
+        .map_err(|err| invalid(format!("invalid manifest: {err}")))?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(invalid(format!(
+            "format version {} is not supported, expected {FORMAT_VERSION}",
+            manifest.format_version
+        )));
+    }
+    Ok(Contents {
+        capabilities: read_jsonl(&mut archive, CAPABILITIES)?,
+        knowledge_bases: read_jsonl(&mut archive, KNOWLEDGE_BASES)?,
+        knowledge_items: read_jsonl(&mut archive, KNOWLEDGE_ITEMS)?,
+        learning_models: read_jsonl(&mut archive, LEARNING_MODELS)?,
+        training_data: read_jsonl(&mut archive, TRAINING_DATA)?,
+        manifest,
+    })
+}
+
+fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|_| invalid(format!("{name} is missing")))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn read_jsonl<T: DeserializeOwned>(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Vec<T>> {
+    read_file(archive, name)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| invalid(format!("{name} line {}: {err}", index + 1)))
+        })
+        .collect()
+}
+
+fn write_jsonl<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    rows: &[T],
+) -> Result<()> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(archive_error)?;
+    for row in rows {
+        serde_json::to_writer(&mut *zip, row)?;
+        zip.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// The first of `name (imported)`, `name (imported 2)`, ... not yet taken
+async fn free_name<C>(txn: &DatabaseTransaction, name: &str, column: C) -> Result<String>
+where
+    C: ColumnTrait,
+{
+    for attempt in 1.. {
+        let candidate = if attempt == 1 {
+            format!("{name} (imported)")
+        } else {
+            format!("{name} (imported {attempt})")
+        };
+        let statement = sea_orm::sea_query::Query::select()
+            .expr(sea_orm::sea_query::Expr::val(1))
+            .from(column.entity_name())
+            .and_where(column.eq(candidate.clone()))
+            .to_owned();
+        if txn
+            .query_one(txn.get_database_backend().build(&statement))
+            .await?
+            .is_none()
+        {
+            return Ok(candidate);
+        }
+    }
+    unreachable!("ran out of names")
+}
+
+fn invalid(reason: impl std::fmt::Display) -> Error {
+    Error::BadRequest(format!("invalid bundle: {reason}"))
+}
+
+fn conflict(message: &str) -> Error {
+    Error::CustomError(
+        StatusCode::CONFLICT,
+        ErrorDetail::new("conflict".to_string(), message.to_string()),
+    )
+}
+
+fn archive_error(err: zip::result::ZipError) -> Error {
+    Error::string(&format!("could not write bundle: {err}"))
+}
@@ -0,0 +1,60 @@
+//! Delivery within the process
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use async_trait::async_trait;
+use loco_rs::Result;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+
+use super::{Envelope, Recipient, Subscription, Transport};
+
+/// How many messages a slow subscriber may fall behind before it skips
+/// ahead
+const CAPACITY: usize = 256;
+
+static SHARED: LazyLock<Arc<Local>> = LazyLock::new(|| Arc::new(Local::default()));
+
+/// One broadcast channel per recipient
+#[derive(Default)]
+pub struct Local {
+    channels: Mutex<HashMap<String, broadcast::Sender<Envelope>>>,
+}
+
+impl Local {
+    /// The transport shared by everything in the process
+    #[must_use]
+    pub fn shared() -> Arc<Self> {
+        SHARED.clone()
+    }
+
+    fn sender(&self, recipient: &Recipient) -> broadcast::Sender<Envelope> {
+        let mut channels = self
+            .channels
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        channels
+            .entry(recipient.channel())
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .clone()
+    }
+}
+
+#[async_trait]
+impl Transport for Local {
+    async fn publish(&self, envelope: &Envelope) -> Result<()> {
+        // an error only means nobody is listening right now
+        let _ = self.sender(&envelope.recipient).send(envelope.clone());
+        Ok(())
+    }
+
+    async fn subscribe(&self, recipient: &Recipient) -> Result<Subscription> {
+        let receiver = self.sender(recipient).subscribe();
+        Ok(Box::pin(
+            BroadcastStream::new(receiver).filter_map(Result::ok),
+        ))
+    }
+}
@@ -0,0 +1,360 @@
+//! Message bus between agents. Agents send typed messages to another agent
+//! or to a topic; every message is stored in `bus_messages` before it is
+//! handed to the background queue for delivery, so messages in flight when
+//! the worker process stops are delivered once it is back.
+//!
+//! Delivery goes through a [`Transport`]: [`local::Local`] fans messages
+//! out within the process, [`redis::Redis`] across processes sharing a
+//! Redis server. Subscribers only see messages published while they
+//! listen; the stored messages double as an inbox for catching up.
+
+use std::{pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use loco_rs::{
+    app::AppContext,
+    bgworker::{BackgroundWorker, Queue},
+    config::QueueConfig,
+    Error, Result,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+use crate::{
+    common::settings::Settings,
+    models::bus_messages::{self, Status},
+    workers::bus::{BusWorker, BusWorkerArgs},
+};
+
+pub mod local;
+pub mod redis;
+
+/// The `settings.bus` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default)]
+    pub transport: TransportKind,
+    /// Redis server for the `redis` transport, defaults to the one of the
+    /// Redis queue
+    #[serde(default)]
+    pub redis_uri: Option<String>,
+    /// Delivery attempts before a message is marked failed
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: i32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            transport: TransportKind::default(),
+            redis_uri: None,
+            max_attempts: default_max_attempts(),
+        }
+    }
+}
+
+const fn default_max_attempts() -> i32 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportKind {
+    #[default]
+    Local,
+    Redis,
+}
+
+/// Who a message is for
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recipient {
+    Agent(Uuid),
+    Topic(String),
+}
+
+impl Recipient {
+    /// The channel subscribers of this recipient listen on
+    #[must_use]
+    pub fn channel(&self) -> String {
+        match self {
+            Self::Agent(id) => format!("agent:{id}"),
+            Self::Topic(topic) => format!("topic:{topic}"),
+        }
+    }
+}
+
+/// A message type that can travel over the bus. `KIND` tells receivers
+/// how to decode the payload.
+pub trait Message: Serialize + DeserializeOwned {
+    const KIND: &'static str;
+}
+
+/// Free text from one agent to another, the message agents send with the
+/// `send_message` tool
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Note {
+    pub text: String,
+    /// The conversation the note was written in
+    pub conversation_id: Option<Uuid>,
+}
+
+impl Message for Note {
+    const KIND: &'static str = "note";
+}
+
+/// A message as it is delivered to subscribers
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Envelope {
+    pub id: Uuid,
+    pub kind: String,
+    pub sender_id: Option<Uuid>,
+    pub recipient: Recipient,
+    pub payload: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl Envelope {
+    /// Rebuilds the envelope of a stored message
+    ///
+    /// # Errors
+    ///
+    /// When the message has neither a recipient agent nor a topic
+    pub fn from_model(message: &bus_messages::Model) -> Result<Self> {
+        let recipient = match (&message.recipient_id, &message.topic) {
+            (Some(agent_id), _) => Recipient::Agent(*agent_id),
+            (None, Some(topic)) => Recipient::Topic(topic.clone()),
+            (None, None) => {
+                return Err(Error::string(&format!(
+                    "bus message {} has no recipient",
+                    message.id
+                )))
+            }
+        };
+        Ok(Self {
+            id: message.id,
+            kind: message.kind.clone(),
+            sender_id: message.sender_id,
+            recipient,
+            payload: message.payload.clone(),
+            created_at: message.created_at,
+        })
+    }
+
+    #[must_use]
+    pub fn is<M: Message>(&self) -> bool {
+        self.kind == M::KIND
+    }
+
+    /// Reads the payload as `M`
+    ///
+    /// # Errors
+    ///
+    /// When the message is of another kind or its payload does not match
+    pub fn decode<M: Message>(&self) -> Result<M> {
+        if !self.is::<M>() {
+            return Err(Error::string(&format!(
+                "expected a {} message, got {}",
+                M::KIND,
+                self.kind
+            )));
+        }
+        Ok(serde_json::from_value(self.payload.clone())?)
+    }
+}
+
+pub type Subscription = Pin<Box<dyn Stream<Item = Envelope> + Send>>;
+
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Hands the message to everyone subscribed to its recipient
+    async fn publish(&self, envelope: &Envelope) -> Result<()>;
+
+    /// Receives the messages published to the recipient from now on
+    async fn subscribe(&self, recipient: &Recipient) -> Result<Subscription>;
+}
+
+/// The transport selected in the config
+///
+/// # Errors
+///
+/// When the Redis transport is selected without a Redis server to use
+pub fn from_context(ctx: &AppContext) -> Result<Arc<dyn Transport>> {
+    let config = Settings::from_context(ctx)?.bus;
+    match config.transport {
+        TransportKind::Local => Ok(local::Local::shared()),
+        TransportKind::Redis => {
+            let uri = config
+                .redis_uri
+                .or_else(|| match &ctx.config.queue {
+                    Some(QueueConfig::Redis(queue)) => Some(queue.uri.clone()),
+                    _ => None,
+                })
+                .ok_or_else(|| Error::string("bus: the redis transport needs a redis_uri"))?;
+            Ok(Arc::new(redis::Redis::new(&uri)?))
+        }
+    }
+}
+
+pub struct Bus {
+    transport: Arc<dyn Transport>,
+    max_attempts: i32,
+}
+
+impl Bus {
+    #[must_use]
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        Self {
+            transport,
+            max_attempts: default_max_attempts(),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// The bus as configured for the app
+    ///
+    /// # Errors
+    ///
+    /// When the settings or the transport are invalid
+    pub fn from_context(ctx: &AppContext) -> Result<Self> {
+        let settings = Settings::from_context(ctx)?;
+        Ok(Self::new(from_context(ctx)?).with_max_attempts(settings.bus.max_attempts))
+    }
+
+    /// Stores a message and queues its delivery
+    ///
+    /// # Errors
+    ///
+    /// When could not store or enqueue the message
+    pub async fn send<M: Message>(
+        ctx: &AppContext,
+        sender_id: Option<Uuid>,
+        recipient: Recipient,
+        message: &M,
+    ) -> Result<bus_messages::Model> {
+        let stored = Self::post(
+            &ctx.db,
+            sender_id,
+            recipient,
+            M::KIND,
+            serde_json::to_value(message)?,
+        )
+        .await?;
+        BusWorker::perform_later(
+            ctx,
+            BusWorkerArgs {
+                message_id: stored.id,
+            },
+        )
+        .await?;
+        Ok(stored)
+    }
+
+    /// Stores a message for delivery without queueing it
+    ///
+    /// # Errors
+    ///
+    /// When could not store the message
+    pub async fn post(
+        db: &DatabaseConnection,
+        sender_id: Option<Uuid>,
+        recipient: Recipient,
+        kind: &str,
+        payload: Value,
+    ) -> Result<bus_messages::Model> {
+        let (recipient_id, topic) = match recipient {
+            Recipient::Agent(id) => (Some(id), None),
+            Recipient::Topic(topic) => (None, Some(topic)),
+        };
+        Ok(bus_messages::ActiveModel {
+            sender_id: ActiveValue::set(sender_id),
+            recipient_id: ActiveValue::set(recipient_id),
+            topic: ActiveValue::set(topic),
+            kind: ActiveValue::set(kind.to_string()),
+            payload: ActiveValue::set(payload),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// Publishes a stored message and marks it delivered. Messages that
+    /// were delivered already are left alone, so a job that runs twice does
+    /// not deliver twice.
+    ///
+    /// # Errors
+    ///
+    /// When the transport fails; the message stays pending until it runs
+    /// out of attempts
+    pub async fn deliver(
+        &self,
+        db: &DatabaseConnection,
+        message: bus_messages::Model,
+    ) -> Result<bus_messages::Model> {
+        if !message.is_pending() {
+            return Ok(message);
+        }
+        let envelope = Envelope::from_model(&message)?;
+        match self.transport.publish(&envelope).await {
+            Ok(()) => Ok(message
+                .into_active_model()
+                .record_attempt(db, Status::Delivered)
+                .await?),
+            Err(err) => {
+                let status = if message.attempts + 1 >= self.max_attempts {
+                    Status::Failed
+                } else {
+                    Status::Pending
+                };
+                message
+                    .into_active_model()
+                    .record_attempt(db, status)
+                    .await?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Receives the messages delivered to the recipient from now on
+    ///
+    /// # Errors
+    ///
+    /// When the transport cannot subscribe
+    pub async fn subscribe(&self, recipient: &Recipient) -> Result<Subscription> {
+        self.transport.subscribe(recipient).await
+    }
+}
+
+/// Queues the delivery of every message still pending, typically those in
+/// flight when the worker process stopped. Returns how many were queued.
+///
+/// # Errors
+///
+/// When has DB query error or could not enqueue a message
+pub async fn recover(ctx: &AppContext, queue: &Queue) -> Result<usize> {
+    let pending = bus_messages::Model::list_pending(&ctx.db).await?;
+    for message in &pending {
+        queue
+            .enqueue(
+                BusWorker::class_name(),
+                BusWorker::queue(),
+                BusWorkerArgs {
+                    message_id: message.id,
+                },
+            )
+            .await?;
+    }
+    if !pending.is_empty() {
+        tracing::info!(count = pending.len(), "requeued pending bus messages");
+    }
+    Ok(pending.len())
+}
@@ -0,0 +1,68 @@
+//! Delivery across processes through Redis pub/sub
+
+use async_trait::async_trait;
+use loco_rs::{Error, Result};
+use redis::{aio::ConnectionManager, AsyncCommands};
+use tokio::sync::OnceCell;
+use tokio_stream::StreamExt;
+
+use super::{Envelope, Recipient, Subscription, Transport};
+
+/// Namespaces the bus channels on a Redis server shared with the queue
+const CHANNEL_PREFIX: &str = "bus:";
+
+pub struct Redis {
+    client: redis::Client,
+    /// The one connection every message is published on, opened with the
+    /// first and reconnecting when it drops
+    publisher: OnceCell<ConnectionManager>,
+}
+
+impl Redis {
+    /// # Errors
+    ///
+    /// When the URI is not a valid Redis URI
+    pub fn new(uri: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(uri).map_err(redis_error)?,
+            publisher: OnceCell::new(),
+        })
+    }
+
+    async fn publisher(&self) -> Result<ConnectionManager> {
+        let connection = self
+            .publisher
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await
+            .map_err(redis_error)?;
+        Ok(connection.clone())
+    }
+}
+
+#[async_trait]
+impl Transport for Redis {
+    async fn publish(&self, envelope: &Envelope) -> Result<()> {
+        let mut connection = self.publisher().await?;
+        let channel = format!("{CHANNEL_PREFIX}{}", envelope.recipient.channel());
+        connection
+            .publish::<_, _, ()>(channel, serde_json::to_string(envelope)?)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn subscribe(&self, recipient: &Recipient) -> Result<Subscription> {
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(redis_error)?;
+        pubsub
+            .subscribe(format!("{CHANNEL_PREFIX}{}", recipient.channel()))
+            .await
+            .map_err(redis_error)?;
+        Ok(Box::pin(pubsub.into_on_message().filter_map(|message| {
+            let payload: String = message.get_payload().ok()?;
+            serde_json::from_str(&payload).ok()
+        })))
+    }
+}
+
+fn redis_error(err: redis::RedisError) -> Error {
+    Error::string(&format!("bus: {err}"))
+}
@@ -0,0 +1,47 @@
+//! In-process event stream pushed to connected clients. Events are
+//! addressed to a single user and are not persisted; clients that are not
+//! listening when an event is published miss it.
+
+use std::sync::LazyLock;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+/// How many events a slow subscriber may fall behind before it skips ahead
+const CAPACITY: usize = 256;
+
+static CHANNEL: LazyLock<broadcast::Sender<Event>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    /// The user allowed to see the event
+    #[serde(skip)]
+    pub user_id: i32,
+    pub kind: String,
+    pub data: Value,
+}
+
+impl Event {
+    #[must_use]
+    pub fn new(user_id: i32, kind: impl Into<String>, data: Value) -> Self {
+        Self {
+            user_id,
+            kind: kind.into(),
+            data,
+        }
+    }
+}
+
+/// Sends an event to every current subscriber
+pub fn publish(event: Event) {
+    // an error only means nobody is listening right now
+    let _ = CHANNEL.send(event);
+}
+
+/// Starts receiving every event published from now on
+#[must_use]
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    CHANNEL.subscribe()
+}
@@ -0,0 +1,20 @@
+//! Secrets handed out once and stored only as a hash: refresh tokens, API
+//! keys. They are random enough that a plain SHA-256 is all the hashing
+//! they need, unlike passwords.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// A new random secret of 32 bytes, hex encoded
+#[must_use]
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// The hash a secret is stored and looked up by
+#[must_use]
+pub fn hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
@@ -0,0 +1,55 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts, auth, bus, fetch, learning, llm, lockout, mfa, oidc, orchestration, plans, runtime,
+    scheduling, sessions,
+};
+
+/// Application specific settings, read from the `settings:` section of the
+/// environment config file.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub llm: llm::Config,
+    #[serde(default)]
+    pub fetch: fetch::Config,
+    #[serde(default)]
+    pub learning: learning::Config,
+    #[serde(default)]
+    pub runtime: runtime::Config,
+    #[serde(default)]
+    pub orchestration: orchestration::Config,
+    #[serde(default)]
+    pub bus: bus::Config,
+    #[serde(default)]
+    pub scheduler: scheduling::Config,
+    #[serde(default)]
+    pub plans: plans::Config,
+    #[serde(default)]
+    pub sessions: sessions::Config,
+    #[serde(default)]
+    pub mfa: mfa::Config,
+    #[serde(default)]
+    pub lockout: lockout::Config,
+    #[serde(default)]
+    pub auth: auth::Config,
+    #[serde(default)]
+    pub oidc: oidc::Config,
+    #[serde(default)]
+    pub accounts: accounts::Config,
+}
+
+impl Settings {
+    /// Reads the settings out of the loaded app config
+    ///
+    /// # Errors
+    ///
+    /// When the `settings` section does not match the expected shape
+    pub fn from_context(ctx: &AppContext) -> Result<Self> {
+        ctx.config.settings.as_ref().map_or_else(
+            || Ok(Self::default()),
+            |settings| Ok(serde_json::from_value(settings.clone())?),
+        )
+    }
+}
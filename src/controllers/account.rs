@@ -0,0 +1,179 @@
+use axum::{
+    body::Body,
+    debug_handler,
+    http::{header, StatusCode},
+};
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts,
+    common::settings::Settings,
+    controllers::auth::{invalid_password, wrong_password},
+    mailers::account::AccountMailer,
+    models::{data_exports, sessions as session_rows, users},
+    sessions,
+    views::account::{DataExportResponse, ProfileResponse},
+    workers::exporter::{ExportWorker, ExportWorkerArgs},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateParams {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangePasswordParams {
+    pub current_password: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteParams {
+    /// The current password, confirming it is the user asking
+    pub password: String,
+}
+
+#[debug_handler]
+async fn show(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(ProfileResponse::new(&user))
+}
+
+#[debug_handler]
+async fn update(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<UpdateParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let name = params.name.trim();
+    if name.chars().count() < 2 {
+        return bad_request("name must be at least 2 characters long");
+    }
+    let user = user.into_active_model().set_name(&ctx.db, name).await?;
+    format::json(ProfileResponse::new(&user))
+}
+
+/// Changes the password of a signed in user, who proves they know the
+/// current one. The user's other sessions are signed out.
+#[debug_handler]
+async fn change_password(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ChangePasswordParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if !user.verify_password(&params.current_password) {
+        return wrong_password();
+    }
+    let policy = &settings.auth.password;
+    let violations = policy.check_for(&ctx.db, &user, &params.password).await?;
+    if !violations.is_empty() {
+        return invalid_password(&violations);
+    }
+    let user = user
+        .into_active_model()
+        .reset_password(&ctx.db, &params.password, policy.previous_kept())
+        .await?;
+    let current = sessions::family_of(auth.claims.claims.as_ref());
+    let revoked = session_rows::Model::revoke_others(&ctx.db, user.id, current).await?;
+    tracing::info!(pid = user.pid.to_string(), revoked, "password changed");
+
+    format::json(())
+}
+
+/// Schedules the account to be erased once `settings.accounts`'
+/// `deletion_grace_secs` are over, and emails the user when. Asking again
+/// keeps the date.
+#[debug_handler]
+async fn schedule_deletion(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<DeleteParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if !user.verify_password(&params.password) {
+        return wrong_password();
+    }
+    if user.delete_after.is_some() {
+        return format::json(ProfileResponse::new(&user));
+    }
+    let user = accounts::schedule_deletion(&ctx.db, &settings.accounts, user).await?;
+    tracing::info!(pid = user.pid.to_string(), "account deletion scheduled");
+    AccountMailer::send_deletion_scheduled(&ctx, &user).await?;
+
+    format::json(ProfileResponse::new(&user))
+}
+
+/// Keeps an account whose deletion was asked for
+#[debug_handler]
+async fn cancel_deletion(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = user.into_active_model().cancel_deletion(&ctx.db).await?;
+    tracing::info!(pid = user.pid.to_string(), "account deletion cancelled");
+    format::json(ProfileResponse::new(&user))
+}
+
+/// Starts putting together a copy of the user's data. The link to it is
+/// emailed once it is ready, and works for `settings.accounts`'
+/// `export_ttl_secs`. Asking again while one is underway answers with that
+/// one, without queueing it again, as each run emails a link of its own,
+/// unless it has been pending past `export_stale_secs`.
+#[debug_handler]
+async fn export(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let (data_export, started) =
+        data_exports::Model::start_for(&ctx.db, &user, settings.accounts.export_stale()).await?;
+    if started {
+        ExportWorker::perform_later(
+            &ctx,
+            ExportWorkerArgs {
+                export_id: data_export.id,
+            },
+        )
+        .await?;
+    }
+    format::render()
+        .status(StatusCode::ACCEPTED)
+        .json(DataExportResponse::new(&data_export))
+}
+
+/// Downloads a data export by the token of the emailed link. The token is
+/// what proves who is asking, so the link works without signing in.
+#[debug_handler]
+async fn download_export(
+    Path(token): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let Ok(data_export) = data_exports::Model::find_live(&ctx.db, &token).await else {
+        return not_found();
+    };
+    let archive = data_export.archive.unwrap_or_default();
+    Ok(format::render()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"data-export-{}.zip\"",
+                data_export.created_at.format("%Y-%m-%d")
+            ),
+        )
+        .response()
+        .body(Body::from(archive))?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/me")
+        .add("/", get(show))
+        .add("/", patch(update))
+        .add("/password", post(change_password))
+        .add("/deletion", post(schedule_deletion))
+        .add("/deletion", delete(cancel_deletion))
+        .add("/export", post(export))
+        .add("/export/:token", get(download_export))
+}
@@ -0,0 +1,276 @@
+use axum::{
+    body::{Body, Bytes},
+    debug_handler,
+    extract::Query,
+    http::header,
+};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    bundles::{self, OnConflict, Owner},
+    common::settings::Settings,
+    models::{agent_versions, agents},
+    plans::{Entitlement, Quota},
+    policy::{Action, Actor, Caller, Scope},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub name: String,
+    pub description: Option<String>,
+    pub r#type: String,
+    pub status: Option<String>,
+    pub configuration: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateParams {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub r#type: Option<String>,
+    pub status: Option<String>,
+    pub configuration: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportParams {
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffParams {
+    pub from: i32,
+    pub to: i32,
+}
+
+async fn load_item(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+    action: Action,
+) -> Result<agents::Model> {
+    let agent = agents::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor.authorize_agent(&ctx.db, &agent, action).await?;
+    Ok(agent)
+}
+
+async fn load_version(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+    version: i32,
+    action: Action,
+) -> Result<agent_versions::Model> {
+    let agent = load_item(ctx, actor, id, action).await?;
+    agent_versions::Model::find_version(&ctx.db, agent.id, version)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+#[debug_handler]
+async fn list(caller: Caller, State(ctx): State<AppContext>) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    actor.require(Action::View)?;
+    format::json(agents::Model::list_for_organization(&ctx.db, actor.organization.id).await?)
+}
+
+/// Creates an agent shared in the organization acted in, within the agent
+/// count of the current user's plan
+#[debug_handler]
+async fn create(
+    caller: Caller,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsWrite)?;
+    actor.require(Action::Edit)?;
+    let settings = Settings::from_context(&ctx)?;
+    let mut entitlement =
+        Entitlement::measure(&ctx.db, &settings.plans, &actor.user, &[Quota::Agents]).await?;
+    if let Err(exceeded) = entitlement.require(&[(Quota::Agents, 1)]) {
+        return exceeded.into_response(&entitlement);
+    }
+
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set(params.name),
+        description: ActiveValue::set(params.description),
+        r#type: ActiveValue::set(params.r#type),
+        status: ActiveValue::set(params.status.unwrap_or_else(|| "active".to_string())),
+        configuration: ActiveValue::set(params.configuration),
+        user_id: ActiveValue::set(Some(actor.user.id)),
+        organization_id: ActiveValue::set(Some(actor.organization.id)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+    entitlement.consume(Quota::Agents, 1);
+    Ok(entitlement.with_headers(format::json(agent)?))
+}
+
+#[debug_handler]
+async fn get_one(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    format::json(load_item(&ctx, &actor, id, Action::View).await?)
+}
+
+/// Updates the agent. A changed configuration is recorded as a new
+/// version.
+#[debug_handler]
+async fn update(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<UpdateParams>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsWrite)?;
+    let mut agent = load_item(&ctx, &actor, id, Action::Edit)
+        .await?
+        .into_active_model();
+    if let Some(name) = params.name {
+        agent.name = ActiveValue::set(name);
+    }
+    if let Some(description) = params.description {
+        agent.description = ActiveValue::set(Some(description));
+    }
+    if let Some(r#type) = params.r#type {
+        agent.r#type = ActiveValue::set(r#type);
+    }
+    if let Some(status) = params.status {
+        agent.status = ActiveValue::set(status);
+    }
+    if let Some(configuration) = params.configuration {
+        agent.configuration = ActiveValue::set(Some(configuration));
+    }
+    format::json(agent.update(&ctx.db).await?)
+}
+
+#[debug_handler]
+async fn list_versions(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    let agent = load_item(&ctx, &actor, id, Action::View).await?;
+    format::json(agent_versions::Model::list_for_agent(&ctx.db, agent.id).await?)
+}
+
+#[debug_handler]
+async fn get_version(
+    caller: Caller,
+    Path((id, version)): Path<(Uuid, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    format::json(load_version(&ctx, &actor, id, version, Action::View).await?)
+}
+
+/// What changed between two versions of the agent
+#[debug_handler]
+async fn diff(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    Query(params): Query<DiffParams>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    let from = load_version(&ctx, &actor, id, params.from, Action::View).await?;
+    let to = load_version(&ctx, &actor, id, params.to, Action::View).await?;
+    format::json(from.diff(&to)?)
+}
+
+/// Restores the agent to an earlier version. The restored state is
+/// recorded as a new version, history is never rewritten.
+#[debug_handler]
+async fn rollback(
+    caller: Caller,
+    Path((id, version)): Path<(Uuid, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsWrite)?;
+    let version = load_version(&ctx, &actor, id, version, Action::Edit).await?;
+    format::json(version.restore(&ctx.db).await?)
+}
+
+/// Downloads the agent as a bundle for importing elsewhere
+#[debug_handler]
+async fn export(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsRead)?;
+    let agent = load_item(&ctx, &actor, id, Action::View).await?;
+    let bundle = bundles::export(&ctx.db, agent.id).await?;
+    Ok(format::render()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"agent-{}.zip\"", agent.id),
+        )
+        .response()
+        .body(Body::from(bundle))?)
+}
+
+/// Imports a bundle sent as the request body into the organization acted
+/// in. The agent and the knowledge it brings count against the current
+/// user's plan.
+#[debug_handler]
+async fn import(
+    caller: Caller,
+    Query(params): Query<ImportParams>,
+    State(ctx): State<AppContext>,
+    body: Bytes,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::AgentsWrite)?;
+    actor.require(Action::Edit)?;
+    let settings = Settings::from_context(&ctx)?;
+    let mut entitlement = Entitlement::measure(
+        &ctx.db,
+        &settings.plans,
+        &actor.user,
+        &[Quota::Agents, Quota::KnowledgeBytes],
+    )
+    .await?;
+    let knowledge = bundles::knowledge_bytes(&body)?;
+    if let Err(exceeded) =
+        entitlement.require(&[(Quota::Agents, 1), (Quota::KnowledgeBytes, knowledge)])
+    {
+        return exceeded.into_response(&entitlement);
+    }
+
+    let owner = Owner {
+        user_id: Some(actor.user.id),
+        organization_id: Some(actor.organization.id),
+    };
+    let report = bundles::import(&ctx.db, &body, params.on_conflict, owner).await?;
+    if !report.skipped {
+        entitlement.consume(Quota::Agents, 1);
+        entitlement.consume(Quota::KnowledgeBytes, knowledge);
+    }
+    Ok(entitlement.with_headers(format::json(report)?))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/agents")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/import", post(import))
+        .add("/:id", get(get_one))
+        .add("/:id", patch(update))
+        .add("/:id/diff", get(diff))
+        .add("/:id/export", get(export))
+        .add("/:id/versions", get(list_versions))
+        .add("/:id/versions/:version", get(get_version))
+        .add("/:id/versions/:version/rollback", post(rollback))
+}
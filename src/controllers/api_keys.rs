@@ -0,0 +1,104 @@
+use axum::debug_handler;
+use chrono::{Duration, Local};
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        api_keys::{self, Scope},
+        users,
+    },
+    views::api_keys::ApiKeyResponse,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Leave out for a key that does not expire
+    pub expires_in_days: Option<i64>,
+}
+
+async fn load_item(ctx: &AppContext, user: &users::Model, id: Uuid) -> Result<api_keys::Model> {
+    api_keys::Model::find_for_user(&ctx.db, id, user.id)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+#[debug_handler]
+async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let keys = api_keys::Model::list_for_user(&ctx.db, user.id).await?;
+    format::json(
+        keys.iter()
+            .map(|key| ApiKeyResponse::new(key, None))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Creates a key for scripts and CI jobs. The answer is the only time the
+/// key itself is shown. Keys are managed from a signed-in session only, so
+/// a leaked key cannot mint more keys.
+#[debug_handler]
+async fn create(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let name = params.name.trim();
+    if name.is_empty() {
+        return bad_request("name must not be empty");
+    }
+    if params.scopes.is_empty() {
+        return bad_request("a key needs at least one scope");
+    }
+    let expires_at = match params.expires_in_days {
+        Some(days) if days <= 0 => return bad_request("expires_in_days must be positive"),
+        Some(days) => Some(Local::now().naive_local() + Duration::days(days)),
+        None => None,
+    };
+    let (model, key) =
+        api_keys::Model::create_for(&ctx.db, &user, name, &params.scopes, expires_at).await?;
+    format::json(ApiKeyResponse::new(&model, Some(key)))
+}
+
+/// Replaces the key with a new one, keeping its name, scopes and expiry
+#[debug_handler]
+async fn rotate(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let (model, key) = load_item(&ctx, &user, id)
+        .await?
+        .into_active_model()
+        .rotate(&ctx.db)
+        .await?;
+    format::json(ApiKeyResponse::new(&model, Some(key)))
+}
+
+#[debug_handler]
+async fn revoke(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    load_item(&ctx, &user, id)
+        .await?
+        .into_active_model()
+        .revoke(&ctx.db)
+        .await?;
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/keys")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id", delete(revoke))
+        .add("/:id/rotate", post(rotate))
+}
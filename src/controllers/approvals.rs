@@ -0,0 +1,110 @@
+use axum::{debug_handler, http::StatusCode};
+use chrono::offset::Local;
+use loco_rs::{controller::ErrorDetail, prelude::*};
+
+use crate::{
+    common::settings::Settings,
+    llm,
+    models::{
+        conversations,
+        tool_approvals::{self, Status},
+        users,
+    },
+    policy::{Action, Actor},
+    runtime::Runtime,
+    tools::Registry,
+};
+
+async fn load_item(
+    ctx: &AppContext,
+    user: &users::Model,
+    id: Uuid,
+) -> Result<tool_approvals::Model> {
+    tool_approvals::Model::find_for_user(&ctx.db, id, user.id)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+/// Applies the decision and carries on with the paused turn, as long as
+/// the user may still use the conversation
+async fn decide(ctx: &AppContext, actor: &Actor, id: Uuid, decision: Status) -> Result<Response> {
+    let approval = load_item(ctx, &actor.user, id).await?;
+    let conversation = conversations::Model::find_by_id(&ctx.db, approval.conversation_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor
+        .authorize_conversation(&ctx.db, &conversation, Action::Use)
+        .await?;
+    if !approval.is_pending() {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new(
+                "decided".to_string(),
+                format!("approval is already {}", approval.status),
+            ),
+        ));
+    }
+
+    let settings = Settings::from_context(ctx)?;
+    let provider = llm::from_config(&settings.llm)?;
+    let registry = Registry::builtin();
+    let runtime = Runtime::new(provider.as_ref(), &registry)
+        .with_config(&settings.runtime)
+        .with_prices(&settings.llm.prices);
+
+    if approval.is_overdue(Local::now().naive_local()) {
+        runtime.decide(ctx, approval, Status::Expired).await?;
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new(
+                "expired".to_string(),
+                "approval request expired".to_string(),
+            ),
+        ));
+    }
+
+    format::json(runtime.decide(ctx, approval, decision).await?)
+}
+
+#[debug_handler]
+async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(tool_approvals::Model::list_pending_for_user(&ctx.db, user.id).await?)
+}
+
+#[debug_handler]
+async fn get_one(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    format::json(load_item(&ctx, &user, id).await?)
+}
+
+#[debug_handler]
+async fn approve(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    decide(&ctx, &actor, id, Status::Approved).await
+}
+
+#[debug_handler]
+async fn reject(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    decide(&ctx, &actor, id, Status::Rejected).await
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/approvals")
+        .add("/", get(list))
+        .add("/:id", get(get_one))
+        .add("/:id/approve", post(approve))
+        .add("/:id/reject", post(reject))
+}
@@ -1,14 +1,34 @@
-use axum::debug_handler;
-use loco_rs::prelude::*;
+use std::net::SocketAddr;
+
+use axum::{
+    debug_handler,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use chrono::{Local, NaiveDateTime};
+use loco_rs::{
+    controller::{bad_request, ErrorDetail},
+    prelude::*,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 use crate::{
+    auth::password::{self, Violation},
+    common::settings::Settings,
+    lockout::{self, Attempt},
     mailers::auth::AuthMailer,
+    mfa::{self, totp},
     models::{
         _entities::users,
+        magic_links, recovery_codes, sessions as session_rows,
         users::{LoginParams, RegisterParams},
     },
-    views::auth::{CurrentResponse, LoginResponse},
+    sessions::{self, Client, Issued},
+    views::auth::{
+        CurrentResponse, LoginResponse, MfaChallengeResponse, RecoveryCodesResponse,
+        SessionResponse, TotpEnrollmentResponse,
+    },
 };
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VerifyParams {
@@ -20,12 +40,170 @@ pub struct ForgotParams {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResendParams {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MagicLinkParams {
+    pub email: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ResetParams {
     pub token: String,
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangeEmailParams {
+    /// The address to move to
+    pub email: String,
+    /// The current password, confirming it is the user asking
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefreshParams {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CodeParams {
+    /// From the authenticator app, or a recovery code where accepted
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChallengeParams {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+fn invalid_code() -> Result<Response> {
+    unauthorized("invalid code")
+}
+
+/// Refuses a password that breaks the policy, saying how, by field
+pub(crate) fn invalid_password(violations: &[Violation]) -> Result<Response> {
+    format::render()
+        .status(StatusCode::UNPROCESSABLE_ENTITY)
+        .json(json!({
+            "error": "invalid_password",
+            "description": "the password does not meet the password policy",
+            "errors": password::validation_errors(violations),
+        }))
+}
+
+/// Refuses an email that cannot be moved to, in the shape of
+/// [`invalid_password`]
+fn invalid_email(code: &str, message: &str) -> Result<Response> {
+    format::render()
+        .status(StatusCode::UNPROCESSABLE_ENTITY)
+        .json(json!({
+            "error": "invalid_email",
+            "description": message,
+            "errors": { "email": [{ "code": code, "message": message }] },
+        }))
+}
+
+/// Refuses a change a signed in user confirmed with the wrong password
+pub(crate) fn wrong_password() -> Result<Response> {
+    Err(Error::CustomError(
+        StatusCode::FORBIDDEN,
+        ErrorDetail::new("wrong_password", "the current password is wrong"),
+    ))
+}
+
+/// Answers an attempt made while the account or the address is locked out
+pub(crate) fn locked_out(until: NaiveDateTime) -> Result<Response> {
+    too_many(until, "too many failed attempts, try again later")
+}
+
+/// Refuses a request until the given time, telling the client how long to
+/// wait
+fn too_many(until: NaiveDateTime, description: &str) -> Result<Response> {
+    let wait = (until - Local::now().naive_local()).num_seconds().max(1);
+    let mut response = format::render()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .json(json!({
+            "error": "too_many_attempts",
+            "description": description,
+        }))?;
+    if let Ok(value) = HeaderValue::try_from(wait.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    Ok(response)
+}
+
+/// Counts a failed attempt, tells the user when it locked their account,
+/// and refuses it once the delay for it passed
+async fn refuse(
+    ctx: &AppContext,
+    settings: &Settings,
+    attempt: Attempt<'_>,
+    user: Option<&users::Model>,
+    reason: &str,
+) -> Result<Response> {
+    let failure = lockout::failed(&ctx.db, &settings.lockout, attempt).await?;
+    if let (Some(user), Some(until)) = (user, failure.account_locked_until) {
+        AuthMailer::send_locked_out(ctx, user, &until).await?;
+    }
+    tokio::time::sleep(failure.delay).await;
+    unauthorized(reason)
+}
+
+/// What the request tells about the client a session is used from. Its
+/// address is the peer's, unless that is a trusted proxy naming another.
+pub(crate) fn client(config: &lockout::Config, peer: SocketAddr, headers: &HeaderMap) -> Client {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    Client {
+        user_agent: header("user-agent").map(ToString::to_string),
+        ip_address: Some(config.client_address(peer.ip(), headers).to_string()),
+    }
+}
+
+/// Answers with a new access token for the session of the refresh token
+fn issue(ctx: &AppContext, user: &users::Model, issued: &Issued) -> Result<Response> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+
+    let token = user
+        .generate_jwt_with_claims(
+            &jwt_secret.secret,
+            &jwt_secret.expiration,
+            sessions::claims(&issued.session),
+        )
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    format::json(LoginResponse::new(user, &token, &issued.refresh_token))
+}
+
+/// Signs in a user who proved who they are some other way than with their
+/// password: with the challenge of two factor sign in, when it is on, or
+/// with a new session
+pub(crate) async fn sign_in(
+    ctx: &AppContext,
+    settings: &Settings,
+    user: &users::Model,
+    client: Client,
+) -> Result<Response> {
+    if user.has_totp() {
+        let jwt_config = ctx.config.get_jwt_config()?;
+        let challenge = mfa::issue_challenge(jwt_config, &settings.mfa, user)?;
+        return format::json(MfaChallengeResponse::new(challenge));
+    }
+    let issued = sessions::start(&ctx.db, &settings.sessions, user, client).await?;
+
+    issue(ctx, user, &issued)
+}
+
 /// Register function creates a new user with the given parameters and sends a
 /// welcome email to the user
 #[debug_handler]
@@ -33,6 +211,12 @@ async fn register(
     State(ctx): State<AppContext>,
     Json(params): Json<RegisterParams>,
 ) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let violations = settings.auth.password.check(&params.password);
+    if !violations.is_empty() {
+        return invalid_password(&violations);
+    }
+
     let res = users::Model::create_with_password(&ctx.db, &params).await;
 
     let user = match res {
@@ -47,24 +231,31 @@ async fn register(
         }
     };
 
-    let user = user
+    let (user, verify_token) = user
         .into_active_model()
         .set_email_verification_sent(&ctx.db)
         .await?;
 
-    AuthMailer::send_welcome(&ctx, &user).await?;
+    AuthMailer::send_welcome(&ctx, &user, &verify_token).await?;
 
     format::json(())
 }
 
 /// Verify register user. if the user not verified his email, he can't login to
-/// the system.
+/// the system. A token works once, and only for `settings.auth`'s
+/// `verification_ttl_secs`.
 #[debug_handler]
 async fn verify(
     State(ctx): State<AppContext>,
     Json(params): Json<VerifyParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_verification_token(&ctx.db, &params.token).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let user = users::Model::find_by_verification_token(
+        &ctx.db,
+        &params.token,
+        settings.auth.verification_ttl(),
+    )
+    .await?;
 
     if user.email_verified_at.is_some() {
         tracing::info!(pid = user.pid.to_string(), "user already verified");
@@ -92,51 +283,425 @@ async fn forgot(
         return format::json(());
     };
 
-    let user = user
+    let (user, reset_token) = user
         .into_active_model()
         .set_forgot_password_sent(&ctx.db)
         .await?;
 
-    AuthMailer::forgot_password(&ctx, &user).await?;
+    AuthMailer::forgot_password(&ctx, &user, &reset_token).await?;
+
+    format::json(())
+}
+
+/// Sends another verification email, for users whose link expired or got
+/// lost. Like `forgot`, it answers the same whether the email belongs to
+/// an unverified user or not, and requests per address are limited.
+#[debug_handler]
+async fn resend_verification(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ResendParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    if let Some(until) =
+        crate::auth::throttle_resend(&ctx.db, &settings.auth, &params.email).await?
+    {
+        return too_many(
+            until,
+            "too many verification emails asked for, try again later",
+        );
+    }
+
+    let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+        return format::json(());
+    };
+    if user.email_verified_at.is_some() {
+        tracing::info!(pid = user.pid.to_string(), "user already verified");
+        return format::json(());
+    }
+
+    let (user, verify_token) = user
+        .into_active_model()
+        .set_email_verification_sent(&ctx.db)
+        .await?;
+
+    AuthMailer::send_welcome(&ctx, &user, &verify_token).await?;
 
     format::json(())
 }
 
-/// reset user password by the given parameters
+/// reset user password by the given parameters. A token works once, and
+/// only for `settings.auth`'s `reset_ttl_secs`. Every session ends with it,
+/// so whoever had the account before is signed out.
 #[debug_handler]
 async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -> Result<Response> {
-    let Ok(user) = users::Model::find_by_reset_token(&ctx.db, &params.token).await else {
+    let settings = Settings::from_context(&ctx)?;
+    let Ok(user) =
+        users::Model::find_by_reset_token(&ctx.db, &params.token, settings.auth.reset_ttl()).await
+    else {
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
         tracing::info!("reset token not found");
 
         return format::json(());
     };
-    user.into_active_model()
-        .reset_password(&ctx.db, &params.password)
+    let policy = &settings.auth.password;
+    let violations = policy.check_for(&ctx.db, &user, &params.password).await?;
+    if !violations.is_empty() {
+        return invalid_password(&violations);
+    }
+    let user = user
+        .into_active_model()
+        .reset_password(&ctx.db, &params.password, policy.previous_kept())
         .await?;
+    let revoked = session_rows::Model::revoke_others(&ctx.db, user.id, None).await?;
+    tracing::info!(pid = user.pid.to_string(), revoked, "password reset");
 
     format::json(())
 }
 
-/// Creates a user login and returns a token
+/// Starts moving the user to another email: the link confirming it goes to
+/// the new address, and the current one is told. Nothing changes until the
+/// link is followed.
 #[debug_handler]
-async fn login(State(ctx): State<AppContext>, Json(params): Json<LoginParams>) -> Result<Response> {
-    let user = users::Model::find_by_email(&ctx.db, &params.email).await?;
+async fn change_email(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ChangeEmailParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if !user.verify_password(&params.password) {
+        return wrong_password();
+    }
+    let new_email = params.email.trim();
+    if validation::is_valid_email(new_email).is_err() {
+        return invalid_email("invalid_email", "Email is not valid.");
+    }
+    if new_email == user.email {
+        return invalid_email("unchanged", "Email is the current one.");
+    }
 
-    let valid = user.verify_password(&params.password);
+    let (user, confirm_token) = user
+        .into_active_model()
+        .set_email_change_sent(&ctx.db, new_email)
+        .await?;
 
-    if !valid {
-        return unauthorized("unauthorized!");
+    AuthMailer::send_email_change(&ctx, &user, new_email, &confirm_token).await?;
+    AuthMailer::send_email_changing(&ctx, &user, new_email).await?;
+
+    format::json(())
+}
+
+/// Moves the user to the email a change was asked for, once the link sent
+/// there is followed. A token works once, and only for `settings.auth`'s
+/// `email_change_ttl_secs`.
+#[debug_handler]
+async fn confirm_email_change(
+    State(ctx): State<AppContext>,
+    Json(params): Json<VerifyParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let user = users::Model::find_by_email_change_token(
+        &ctx.db,
+        &params.token,
+        settings.auth.email_change_ttl(),
+    )
+    .await?;
+
+    match user.into_active_model().confirm_email_change(&ctx.db).await {
+        Ok(user) => {
+            tracing::info!(pid = user.pid.to_string(), "user email changed");
+            format::json(())
+        }
+        Err(ModelError::EntityAlreadyExists) => Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new("email_taken", "another account has this email"),
+        )),
+        Err(err) => Err(err.into()),
     }
+}
 
-    let jwt_secret = ctx.config.get_jwt_config()?;
+/// Creates a user login and returns a short-lived access token, plus a
+/// refresh token starting a new session. Users with two factor sign in on
+/// get a challenge token to exchange along with a code instead. Unless
+/// `settings.auth` says otherwise, the email has to be verified first.
+#[debug_handler]
+async fn login(
+    State(ctx): State<AppContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<LoginParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let client = client(&settings.lockout, peer, &headers);
+    let attempt = Attempt {
+        email: &params.email,
+        ip_address: client.ip_address.as_deref(),
+    };
+    if let Some(until) = lockout::locked_until(&ctx.db, attempt).await? {
+        return locked_out(until);
+    }
 
-    let token = user
-        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration)
-        .or_else(|_| unauthorized("unauthorized!"))?;
+    let user = match users::Model::find_by_email(&ctx.db, &params.email).await {
+        Ok(user) if user.verify_password(&params.password) => user,
+        found => {
+            // unknown emails are refused just like wrong passwords, and as
+            // slowly
+            if found.is_err() {
+                lockout::check_stand_in(&params.password);
+            }
+            let user = found.ok();
+            return refuse(&ctx, &settings, attempt, user.as_ref(), "unauthorized!").await;
+        }
+    };
+
+    if settings.auth.require_verified_email && user.email_verified_at.is_none() {
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new("email_not_verified", "verify your email before logging in"),
+        ));
+    }
+
+    // failures are only forgotten once the second factor is passed too, so
+    // a known password does not buy unlimited guesses at codes
+    if user.has_totp() {
+        let jwt_config = ctx.config.get_jwt_config()?;
+        let challenge = mfa::issue_challenge(jwt_config, &settings.mfa, &user)?;
+        return format::json(MfaChallengeResponse::new(challenge));
+    }
+    lockout::succeeded(&ctx.db, attempt).await?;
+    let issued = sessions::start(&ctx.db, &settings.sessions, &user, client).await?;
+
+    issue(&ctx, &user, &issued)
+}
+
+/// Emails a link that logs the user in without a password. Like `forgot`,
+/// it answers the same whether the email belongs to a user or not, and
+/// requests per address are limited.
+#[debug_handler]
+async fn magic_link(
+    State(ctx): State<AppContext>,
+    Json(params): Json<MagicLinkParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    if let Some(until) =
+        crate::auth::throttle_magic_link(&ctx.db, &settings.auth, &params.email).await?
+    {
+        return too_many(until, "too many login links asked for, try again later");
+    }
+
+    let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+        return format::json(());
+    };
+
+    let ttl = settings.auth.magic_link_ttl();
+    let (_, login_token) = magic_links::Model::create_for(&ctx.db, &user, ttl).await?;
+
+    AuthMailer::send_magic_link(&ctx, &user, &login_token, ttl).await?;
+
+    format::json(())
+}
+
+/// Exchanges a magic link for a login, once. Following the link proves the
+/// email is the user's, so it verifies it too. Lockouts and two factor
+/// sign in apply as they do to `login`.
+#[debug_handler]
+async fn verify_magic_link(
+    State(ctx): State<AppContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<VerifyParams>,
+) -> Result<Response> {
+    let Ok(link) = magic_links::Model::find_live(&ctx.db, &params.token).await else {
+        return unauthorized("invalid link");
+    };
+    let user = users::Entity::find_by_id(link.user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    let settings = Settings::from_context(&ctx)?;
+    let client = client(&settings.lockout, peer, &headers);
+    let attempt = Attempt {
+        email: &user.email,
+        ip_address: client.ip_address.as_deref(),
+    };
+    if let Some(until) = lockout::locked_until(&ctx.db, attempt).await? {
+        return locked_out(until);
+    }
+    if !link.consume(&ctx.db).await? {
+        return unauthorized("invalid link");
+    }
 
-    format::json(LoginResponse::new(&user, &token))
+    let user = if user.email_verified_at.is_none() {
+        user.into_active_model().verified(&ctx.db).await?
+    } else {
+        user
+    };
+
+    sign_in(&ctx, &settings, &user, client).await
+}
+
+/// Completes a login of a user with two factor sign in on, trading the
+/// challenge token and a code from their authenticator, or a recovery
+/// code, for an access token and a refresh token
+#[debug_handler]
+async fn challenge(
+    State(ctx): State<AppContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<ChallengeParams>,
+) -> Result<Response> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let pid = mfa::verify_challenge(jwt_config, &params.challenge_token)?;
+    let user = users::Model::find_by_pid(&ctx.db, &pid).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let client = client(&settings.lockout, peer, &headers);
+    let attempt = Attempt {
+        email: &user.email,
+        ip_address: client.ip_address.as_deref(),
+    };
+    if let Some(until) = lockout::locked_until(&ctx.db, attempt).await? {
+        return locked_out(until);
+    }
+    if !mfa::accept(&ctx.db, &user, &params.code).await? {
+        return refuse(&ctx, &settings, attempt, Some(&user), "invalid code").await;
+    }
+    lockout::succeeded(&ctx.db, attempt).await?;
+
+    let issued = sessions::start(&ctx.db, &settings.sessions, &user, client).await?;
+
+    issue(&ctx, &user, &issued)
+}
+
+/// Starts turning on two factor sign in with a new secret for the user's
+/// authenticator app. Nothing changes until it is confirmed with a code.
+#[debug_handler]
+async fn enroll_totp(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if user.has_totp() {
+        return Err(Error::CustomError(
+            StatusCode::CONFLICT,
+            ErrorDetail::new(
+                "already_enabled".to_string(),
+                "two factor sign in is already on".to_string(),
+            ),
+        ));
+    }
+    let settings = Settings::from_context(&ctx)?;
+    let secret = totp::generate_secret();
+    let user = user
+        .into_active_model()
+        .set_pending_totp(&ctx.db, &secret)
+        .await?;
+    format::json(TotpEnrollmentResponse {
+        otpauth_uri: totp::uri(&settings.mfa.issuer, &user.email, &secret),
+        secret,
+    })
+}
+
+/// Turns on two factor sign in once a code shows the authenticator was set
+/// up, and hands out the recovery codes
+#[debug_handler]
+async fn verify_totp(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CodeParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if user.has_totp() || user.totp_secret.is_none() {
+        return bad_request("start enrolling first");
+    }
+    let Some(step) = mfa::check_totp(&user, &params.code) else {
+        return invalid_code();
+    };
+    let user = user.into_active_model().enable_totp(&ctx.db, step).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let recovery_codes =
+        recovery_codes::Model::regenerate(&ctx.db, user.id, settings.mfa.recovery_codes).await?;
+    format::json(RecoveryCodesResponse { recovery_codes })
+}
+
+/// Turns off two factor sign in, given a code or a recovery code
+#[debug_handler]
+async fn disable_totp(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CodeParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if !user.has_totp() {
+        return bad_request("two factor sign in is not on");
+    }
+    if !mfa::accept(&ctx.db, &user, &params.code).await? {
+        return invalid_code();
+    }
+    let user = user.into_active_model().disable_totp(&ctx.db).await?;
+    recovery_codes::Model::delete_for_user(&ctx.db, user.id).await?;
+    format::empty()
+}
+
+/// Trades a refresh token for a new access token and the next refresh
+/// token. A token used twice revokes its whole session.
+#[debug_handler]
+async fn refresh(
+    State(ctx): State<AppContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<RefreshParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let Some(issued) = sessions::refresh(
+        &ctx.db,
+        &settings.sessions,
+        &params.refresh_token,
+        client(&settings.lockout, peer, &headers),
+    )
+    .await?
+    else {
+        return unauthorized("unauthorized!");
+    };
+    let user = users::Entity::find_by_id(issued.session.user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::Unauthorized("unauthorized!".to_string()))?;
+
+    issue(&ctx, &user, &issued)
+}
+
+/// Ends the session of the refresh token
+#[debug_handler]
+async fn logout(
+    State(ctx): State<AppContext>,
+    Json(params): Json<RefreshParams>,
+) -> Result<Response> {
+    sessions::logout(&ctx.db, &params.refresh_token).await?;
+    format::empty()
+}
+
+/// Lists the sessions the user is signed in with
+#[debug_handler]
+async fn list_sessions(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let current = sessions::family_of(auth.claims.claims.as_ref());
+    let list = session_rows::Model::list_active_for_user(&ctx.db, user.id).await?;
+    format::json(
+        list.iter()
+            .map(|session| SessionResponse::new(session, current))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Signs the user out of one of their sessions
+#[debug_handler]
+async fn revoke_session(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if session_rows::Model::revoke_family(&ctx.db, id, Some(user.id)).await? == 0 {
+        return Err(Error::NotFound);
+    }
+    format::empty()
 }
 
 #[debug_handler]
@@ -150,8 +715,21 @@ pub fn routes() -> Routes {
         .prefix("/api/auth")
         .add("/register", post(register))
         .add("/verify", post(verify))
+        .add("/resend-verification", post(resend_verification))
         .add("/login", post(login))
+        .add("/magic-link", post(magic_link))
+        .add("/magic-link/verify", post(verify_magic_link))
         .add("/forgot", post(forgot))
         .add("/reset", post(reset))
+        .add("/change-email", post(change_email))
+        .add("/change-email/confirm", post(confirm_email_change))
         .add("/current", get(current))
+        .add("/refresh", post(refresh))
+        .add("/logout", post(logout))
+        .add("/sessions", get(list_sessions))
+        .add("/sessions/:id", delete(revoke_session))
+        .add("/2fa/enroll", post(enroll_totp))
+        .add("/2fa/verify", post(verify_totp))
+        .add("/2fa/disable", post(disable_totp))
+        .add("/2fa/challenge", post(challenge))
 }
@@ -0,0 +1,129 @@
+use axum::debug_handler;
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::settings::Settings,
+    llm,
+    models::{agents, conversations, messages},
+    plans::{Entitlement, Quota},
+    policy::{Action, Actor},
+    runtime::Runtime,
+    tools::Registry,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub agent_id: Uuid,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MessageParams {
+    pub content: String,
+}
+
+async fn load_item(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+    action: Action,
+) -> Result<conversations::Model> {
+    let conversation = conversations::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor
+        .authorize_conversation(&ctx.db, &conversation, action)
+        .await?;
+    Ok(conversation)
+}
+
+#[debug_handler]
+async fn list(actor: Actor, State(ctx): State<AppContext>) -> Result<Response> {
+    actor.require(Action::View)?;
+    format::json(
+        conversations::Model::list_for_organization(&ctx.db, actor.organization.id, actor.user.id)
+            .await?,
+    )
+}
+
+/// Starts a conversation with the agent, shared in the agent's
+/// organization
+#[debug_handler]
+async fn create(
+    actor: Actor,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let agent = agents::Model::find_by_id(&ctx.db, params.agent_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let actor = actor.authorize_agent(&ctx.db, &agent, Action::Use).await?;
+    let conversation = conversations::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(actor.user.id),
+        organization_id: ActiveValue::set(Some(actor.organization.id)),
+        title: ActiveValue::set(params.title),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+    format::json(conversation)
+}
+
+#[debug_handler]
+async fn list_messages(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let conversation = load_item(&ctx, &actor, id, Action::View).await?;
+    format::json(messages::Model::list_for_conversation(&ctx.db, conversation.id).await?)
+}
+
+/// Sends a message to the agent and returns the messages of the turn,
+/// including any tool calls the agent made before answering. The turn
+/// stops early with a list of `approvals` when a tool call needs the
+/// owner's approval.
+#[debug_handler]
+async fn send_message(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<MessageParams>,
+) -> Result<Response> {
+    if params.content.trim().is_empty() {
+        return bad_request("message must not be empty");
+    }
+    let conversation = load_item(&ctx, &actor, id, Action::Use).await?;
+    let user = actor.user;
+    let settings = Settings::from_context(&ctx)?;
+    let quotas = [Quota::MessagesPerDay, Quota::TokensPerMonth];
+    let entitlement = Entitlement::measure(&ctx.db, &settings.plans, &user, &quotas).await?;
+    if let Err(exceeded) =
+        entitlement.require(&[(Quota::MessagesPerDay, 1), (Quota::TokensPerMonth, 1)])
+    {
+        return exceeded.into_response(&entitlement);
+    }
+
+    let provider = llm::from_config(&settings.llm)?;
+    let registry = Registry::builtin();
+
+    let turn = Runtime::new(provider.as_ref(), &registry)
+        .with_config(&settings.runtime)
+        .with_prices(&settings.llm.prices)
+        .reply(&ctx, &conversation, &params.content)
+        .await?;
+    // measured again so the headers include what the turn used
+    let entitlement = Entitlement::measure(&ctx.db, &settings.plans, &user, &quotas).await?;
+    Ok(entitlement.with_headers(format::json(turn)?))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/conversations")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id/messages", get(list_messages))
+        .add("/:id/messages", post(send_message))
+}
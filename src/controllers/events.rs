@@ -0,0 +1,34 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    debug_handler,
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
+};
+use loco_rs::prelude::*;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+
+use crate::{common::events, models::users};
+
+/// Streams the events addressed to the current user as server-sent events
+#[debug_handler]
+async fn stream(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user_id = user.id;
+
+    // lagging behind only drops the missed events, the stream continues
+    let events = BroadcastStream::new(events::subscribe()).filter_map(move |event| {
+        let event = event.ok().filter(|event| event.user_id == user_id)?;
+        let data = serde_json::to_string(&event.data).ok()?;
+        Some(Ok::<_, Infallible>(
+            SseEvent::default().event(event.kind).data(data),
+        ))
+    });
+
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+        .into_response())
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/events").add("/", get(stream))
+}
@@ -0,0 +1,161 @@
+use axum::{debug_handler, http::StatusCode};
+use loco_rs::{controller::ErrorDetail, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::settings::Settings,
+    learning::{
+        evaluation::{self, Comparison, Evaluator},
+        metrics::{self, Metric},
+    },
+    llm,
+    models::{learning_models, model_evaluations},
+    policy::{Action, Actor},
+    usage::{Metered, Scope},
+};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct EvaluateParams {
+    /// Names of built-in metrics, defaults to exact match and F1
+    pub metrics: Option<Vec<String>>,
+}
+
+async fn load_item(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+    action: Action,
+) -> Result<learning_models::Model> {
+    let model = learning_models::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor
+        .authorize_learning_model(&ctx.db, &model, action)
+        .await?;
+    Ok(model)
+}
+
+/// Runs the model version against its dev split and stores the scores
+#[debug_handler]
+async fn evaluate(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<EvaluateParams>,
+) -> Result<Response> {
+    let model = load_item(&ctx, &actor, id, Action::Edit).await?;
+    let settings = Settings::from_context(&ctx)?;
+
+    let names = params
+        .metrics
+        .unwrap_or_else(|| vec!["exact_match".to_string(), "f1".to_string()]);
+    let metrics = names
+        .iter()
+        .map(|name| {
+            metrics::from_name(name)
+                .ok_or_else(|| Error::BadRequest(format!("unknown metric: {name}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let provider = llm::from_config(&settings.llm)?;
+    let provider = Metered::new(
+        provider.as_ref(),
+        &ctx.db,
+        &settings.llm.prices,
+        Scope {
+            agent_id: model.agent_id,
+            ..Default::default()
+        },
+    );
+    let evaluation = Evaluator::new(
+        &provider,
+        metrics
+            .iter()
+            .map(|metric| metric.as_ref() as &dyn Metric)
+            .collect(),
+    )
+    .with_dev_ratio(settings.learning.dev_ratio)
+    .evaluate(&ctx.db, &model)
+    .await?;
+
+    format::json(evaluation)
+}
+
+#[debug_handler]
+async fn list_evaluations(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let model = load_item(&ctx, &actor, id, Action::View).await?;
+    format::json(model_evaluations::Model::list_for_model(&ctx.db, model.id).await?)
+}
+
+/// Diffs the latest evaluations of two model versions
+#[debug_handler]
+async fn compare(
+    actor: Actor,
+    Path((id, candidate_id)): Path<(Uuid, Uuid)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    load_item(&ctx, &actor, id, Action::View).await?;
+    load_item(&ctx, &actor, candidate_id, Action::View).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let base = model_evaluations::Model::latest_for_model(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let candidate = model_evaluations::Model::latest_for_model(&ctx.db, candidate_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+
+    format::json(Comparison::new(
+        &base,
+        &candidate,
+        settings.learning.pass_threshold,
+    )?)
+}
+
+/// Makes the version active, unless it scores below the currently active
+/// version on its latest evaluation
+#[debug_handler]
+async fn promote(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let model = load_item(&ctx, &actor, id, Action::Edit).await?;
+    let settings = Settings::from_context(&ctx)?;
+
+    let comparison =
+        evaluation::promotion_comparison(&ctx.db, &model, settings.learning.pass_threshold).await?;
+    if let Some(comparison) = comparison {
+        if comparison.regresses(settings.learning.promotion_tolerance) {
+            tracing::info!(
+                model_id = model.id.to_string(),
+                flipped = comparison.flipped.len(),
+                "refusing to promote regressing model version",
+            );
+            return Err(Error::CustomError(
+                StatusCode::CONFLICT,
+                ErrorDetail::new(
+                    "regression".to_string(),
+                    format!(
+                        "model version regresses against the active version: {:?}",
+                        comparison.deltas
+                    ),
+                ),
+            ));
+        }
+    }
+
+    format::json(model.promote(&ctx.db).await?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/learning_models")
+        .add("/:id/evaluations", post(evaluate))
+        .add("/:id/evaluations", get(list_evaluations))
+        .add("/:id/compare/:candidate_id", get(compare))
+        .add("/:id/promote", post(promote))
+}
@@ -0,0 +1,99 @@
+use axum::debug_handler;
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    learning::feedback::{self, Feedback, Rating},
+    models::{conversations, messages},
+    policy::{Action, Actor},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RatingParams {
+    pub rating: Rating,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CorrectionParams {
+    pub content: String,
+    pub comment: Option<String>,
+}
+
+/// The message and its conversation, if the conversation may be used
+async fn load_item(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+) -> Result<(messages::Model, conversations::Model)> {
+    let message = messages::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let conversation = conversations::Model::find_by_id(&ctx.db, message.conversation_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor
+        .authorize_conversation(&ctx.db, &conversation, Action::Use)
+        .await?;
+    Ok((message, conversation))
+}
+
+/// Thumbs up or down on an assistant message. A thumbs up queues the turn
+/// for review as a training sample.
+#[debug_handler]
+async fn rate(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RatingParams>,
+) -> Result<Response> {
+    let (message, conversation) = load_item(&ctx, &actor, id).await?;
+    let outcome = feedback::record(
+        &ctx.db,
+        &actor.user,
+        &conversation,
+        message,
+        &Feedback {
+            rating: params.rating,
+            correction: None,
+            comment: params.comment,
+        },
+    )
+    .await?;
+    format::json(outcome)
+}
+
+/// Supplies the answer the assistant should have given. The corrected turn
+/// is queued for review as a training sample.
+#[debug_handler]
+async fn correct(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CorrectionParams>,
+) -> Result<Response> {
+    if params.content.trim().is_empty() {
+        return bad_request("correction must not be empty");
+    }
+    let (message, conversation) = load_item(&ctx, &actor, id).await?;
+    let outcome = feedback::record(
+        &ctx.db,
+        &actor.user,
+        &conversation,
+        message,
+        &Feedback {
+            rating: Rating::Down,
+            correction: Some(params.content),
+            comment: params.comment,
+        },
+    )
+    .await?;
+    format::json(outcome)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/messages")
+        .add("/:id/feedback", post(rate))
+        .add("/:id/correction", post(correct))
+}
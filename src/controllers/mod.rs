@@ -1 +1,15 @@
+pub mod account;
+pub mod agents;
+pub mod api_keys;
+pub mod approvals;
 pub mod auth;
+pub mod conversations;
+pub mod events;
+pub mod learning_models;
+pub mod messages;
+pub mod oidc;
+pub mod organizations;
+pub mod schedules;
+pub mod tasks;
+pub mod training_samples;
+pub mod usage;
@@ -0,0 +1,124 @@
+use std::net::SocketAddr;
+
+use axum::{
+    debug_handler,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderValue},
+};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::settings::Settings,
+    controllers::auth::{client, locked_out, sign_in},
+    lockout::{self, Attempt},
+    models::oidc_requests,
+    oidc,
+    views::auth::OidcAuthorizationResponse,
+};
+
+/// The cookie keeping the state of a sign in with the browser that started
+/// it, so that a state from anywhere else is refused
+const STATE_COOKIE: &str = "oidc_state";
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CallbackParams {
+    /// What the provider sent the user back with
+    pub code: String,
+    pub state: String,
+}
+
+/// Starts signing in with the provider, answering with the URL to send the
+/// user to, and the state in a cookie the callback checks
+#[debug_handler]
+async fn authorize(
+    Path(provider): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let Some(oidc_client) = oidc::Client::new(&settings.oidc, &provider)? else {
+        return not_found();
+    };
+    let (request, state) =
+        oidc_requests::Model::start(&ctx.db, &provider, settings.oidc.request_ttl()).await?;
+    let authorization_url = oidc_client.authorization_url(&request, &state).await?;
+
+    let mut response = format::json(OidcAuthorizationResponse { authorization_url })?;
+    response.headers_mut().append(
+        header::SET_COOKIE,
+        state_cookie(&state, settings.oidc.request_ttl_secs)?,
+    );
+    Ok(response)
+}
+
+/// Completes signing in with the code and state the provider sent the user
+/// back with, from the browser that started it. Answers like `login`.
+#[debug_handler]
+async fn callback(
+    Path(provider): Path<String>,
+    State(ctx): State<AppContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(params): Json<CallbackParams>,
+) -> Result<Response> {
+    let settings = Settings::from_context(&ctx)?;
+    let Some(oidc_client) = oidc::Client::new(&settings.oidc, &provider)? else {
+        return not_found();
+    };
+    if state_of(&headers) != Some(params.state.as_str()) {
+        return unauthorized("invalid state");
+    }
+    let Ok(request) = oidc_requests::Model::take(&ctx.db, &provider, &params.state).await else {
+        return unauthorized("invalid state");
+    };
+    let identity = oidc_client.exchange(&request, &params.code).await?;
+
+    // a locked account or address gets nothing linked or created
+    let client = client(&settings.lockout, peer, &headers);
+    let email = oidc::account_email(&ctx.db, &provider, &identity)
+        .await?
+        .unwrap_or_default();
+    let attempt = Attempt {
+        email: &email,
+        ip_address: client.ip_address.as_deref(),
+    };
+    if let Some(until) = lockout::locked_until(&ctx.db, attempt).await? {
+        return locked_out(until);
+    }
+
+    let user = oidc::user_for(&ctx.db, &provider, &identity).await?;
+    let mut response = sign_in(&ctx, &settings, &user, client).await?;
+    response
+        .headers_mut()
+        .append(header::SET_COOKIE, state_cookie("", 0)?);
+    Ok(response)
+}
+
+/// The cookie holding `state` for `max_age_secs`, only sent back to the
+/// callback
+fn state_cookie(state: &str, max_age_secs: i64) -> Result<HeaderValue> {
+    HeaderValue::from_str(&format!(
+        "{STATE_COOKIE}={state}; Max-Age={max_age_secs}; Path=/api/auth/oidc; HttpOnly; Secure; SameSite=Lax"
+    ))
+    .map_err(|err| Error::Message(err.to_string()))
+}
+
+/// The state the browser was given when it started signing in
+fn state_of(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == STATE_COOKIE)
+        .map(|(_, state)| state)
+        .filter(|state| !state.is_empty())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/auth/oidc")
+        .add("/:provider/authorize", get(authorize))
+        .add("/:provider/callback", post(callback))
+}
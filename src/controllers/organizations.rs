@@ -0,0 +1,330 @@
+use axum::{debug_handler, http::StatusCode};
+use loco_rs::{
+    controller::{bad_request, ErrorDetail},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mailers::organizations::OrganizationMailer,
+    models::{
+        invitations,
+        memberships::{self, Role},
+        organizations, users,
+    },
+    policy::{Action, Actor},
+    views::organizations::{MemberResponse, OrganizationResponse},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OrganizationParams {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleParams {
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InvitationParams {
+    pub email: String,
+    pub role: Role,
+}
+
+fn conflict(code: &str, description: &str) -> Error {
+    Error::CustomError(
+        StatusCode::CONFLICT,
+        ErrorDetail::new(code.to_string(), description.to_string()),
+    )
+}
+
+/// The current user acting in the organization of the path
+async fn load_actor(ctx: &AppContext, auth: &auth::JWT, id: Uuid) -> Result<Actor> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    Actor::within(&ctx.db, user, id).await
+}
+
+async fn load_member(
+    ctx: &AppContext,
+    actor: &Actor,
+    pid: &str,
+) -> Result<(memberships::Model, users::Model)> {
+    let user = users::Model::find_by_pid(&ctx.db, pid)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let membership = memberships::Model::find_for(&ctx.db, actor.organization.id, user.id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    Ok((membership, user))
+}
+
+/// Refuses to leave the organization without an owner
+async fn keep_an_owner(ctx: &AppContext, membership: &memberships::Model) -> Result<()> {
+    if membership.role() == Role::Owner
+        && memberships::Model::count_owners(&ctx.db, membership.organization_id).await? <= 1
+    {
+        return Err(conflict(
+            "last_owner",
+            "the organization must keep at least one owner",
+        ));
+    }
+    Ok(())
+}
+
+/// The organizations of the current user with their role in each
+#[debug_handler]
+async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let organizations = memberships::Model::list_for_user(&ctx.db, user.id).await?;
+    format::json(
+        organizations
+            .iter()
+            .map(|(membership, organization)| OrganizationResponse::new(organization, membership))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Creates an organization owned by the current user
+#[debug_handler]
+async fn create(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<OrganizationParams>,
+) -> Result<Response> {
+    let name = params.name.trim();
+    if name.is_empty() {
+        return bad_request("name must not be empty");
+    }
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let organization = organizations::Model::create_with_owner(&ctx.db, name, &user).await?;
+    let membership = memberships::Model::find_for(&ctx.db, organization.id, user.id).await?;
+    format::json(OrganizationResponse::new(&organization, &membership))
+}
+
+#[debug_handler]
+async fn get_one(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::View)?;
+    let membership = memberships::Model::find_for(&ctx.db, id, actor.user.id).await?;
+    format::json(OrganizationResponse::new(&actor.organization, &membership))
+}
+
+#[debug_handler]
+async fn update(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<OrganizationParams>,
+) -> Result<Response> {
+    let name = params.name.trim();
+    if name.is_empty() {
+        return bad_request("name must not be empty");
+    }
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Administer)?;
+    let mut organization = actor.organization.clone().into_active_model();
+    organization.name = ActiveValue::set(name.to_string());
+    let organization = organization.update(&ctx.db).await?;
+    let membership = memberships::Model::find_for(&ctx.db, id, actor.user.id).await?;
+    format::json(OrganizationResponse::new(&organization, &membership))
+}
+
+/// Deletes the organization with its memberships and invitations. Agents,
+/// knowledge bases and conversations have to be moved or deleted first.
+#[debug_handler]
+async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Administer)?;
+    if !actor.organization.is_unused(&ctx.db).await? {
+        return Err(conflict(
+            "not_empty",
+            "the organization still shares agents, knowledge bases or conversations",
+        ));
+    }
+    actor.organization.delete(&ctx.db).await?;
+    format::empty()
+}
+
+#[debug_handler]
+async fn list_members(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::View)?;
+    let members = memberships::Model::list_for_organization(&ctx.db, id).await?;
+    format::json(
+        members
+            .iter()
+            .map(|(membership, user)| MemberResponse::new(membership, user))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Changes the role of a member. Only owners appoint or demote owners.
+#[debug_handler]
+async fn update_member(
+    auth: auth::JWT,
+    Path((id, pid)): Path<(Uuid, String)>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<RoleParams>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Manage)?;
+    let (membership, user) = load_member(&ctx, &actor, &pid).await?;
+    if membership.role() == Role::Owner || params.role == Role::Owner {
+        actor.require(Action::Administer)?;
+    }
+    if params.role != Role::Owner {
+        keep_an_owner(&ctx, &membership).await?;
+    }
+    let membership = membership
+        .into_active_model()
+        .set_role(&ctx.db, params.role)
+        .await?;
+    format::json(MemberResponse::new(&membership, &user))
+}
+
+/// Removes a member. Anyone may leave; removing others takes a role that
+/// manages members, and an owner to remove an owner.
+#[debug_handler]
+async fn remove_member(
+    auth: auth::JWT,
+    Path((id, pid)): Path<(Uuid, String)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    let (membership, user) = load_member(&ctx, &actor, &pid).await?;
+    if user.id != actor.user.id {
+        actor.require(Action::Manage)?;
+        if membership.role() == Role::Owner {
+            actor.require(Action::Administer)?;
+        }
+    }
+    keep_an_owner(&ctx, &membership).await?;
+    membership.delete(&ctx.db).await?;
+    format::empty()
+}
+
+#[debug_handler]
+async fn list_invitations(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Manage)?;
+    format::json(invitations::Model::list_pending_for_organization(&ctx.db, id).await?)
+}
+
+/// Emails an invitation to join with the given role
+#[debug_handler]
+async fn invite(
+    auth: auth::JWT,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<InvitationParams>,
+) -> Result<Response> {
+    let email = params.email.trim();
+    if !email.contains('@') {
+        return bad_request("invalid email address");
+    }
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Manage)?;
+    if params.role == Role::Owner {
+        actor.require(Action::Administer)?;
+    }
+    if let Ok(user) = users::Model::find_by_email(&ctx.db, email).await {
+        if memberships::Model::find_for(&ctx.db, id, user.id)
+            .await
+            .is_ok()
+        {
+            return Err(conflict("member", &format!("{email} is already a member")));
+        }
+    }
+
+    let invitation = invitations::ActiveModel {
+        organization_id: ActiveValue::set(id),
+        email: ActiveValue::set(email.to_string()),
+        role: ActiveValue::set(params.role.as_str().to_string()),
+        invited_by: ActiveValue::set(Some(actor.user.id)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+    OrganizationMailer::send_invitation(&ctx, &actor.organization, &actor.user, &invitation)
+        .await?;
+    format::json(invitation)
+}
+
+#[debug_handler]
+async fn revoke_invitation(
+    auth: auth::JWT,
+    Path((id, invitation_id)): Path<(Uuid, Uuid)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let actor = load_actor(&ctx, &auth, id).await?;
+    actor.require(Action::Manage)?;
+    invitations::Model::find_for_organization(&ctx.db, invitation_id, id)
+        .await
+        .map_err(|_| Error::NotFound)?
+        .delete(&ctx.db)
+        .await?;
+    format::empty()
+}
+
+/// Joins the organization of an invitation sent to the current user's
+/// address
+#[debug_handler]
+async fn accept_invitation(
+    auth: auth::JWT,
+    Path(token): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let invitation = invitations::Model::find_pending_by_token(&ctx.db, &token)
+        .await
+        .ok()
+        .filter(|invitation| invitation.is_for(&user))
+        .ok_or(Error::NotFound)?;
+    let organization = organizations::Model::find_by_id(&ctx.db, invitation.organization_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let membership = invitation
+        .accept(&ctx.db, &user)
+        .await
+        .map_err(|err| match err {
+            ModelError::EntityAlreadyExists => {
+                conflict("member", "already a member of the organization")
+            }
+            err => err.into(),
+        })?;
+    format::json(OrganizationResponse::new(&organization, &membership))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/organizations")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/invitations/:token/accept", post(accept_invitation))
+        .add("/:id", get(get_one))
+        .add("/:id", patch(update))
+        .add("/:id", delete(remove))
+        .add("/:id/members", get(list_members))
+        .add("/:id/members/:pid", patch(update_member))
+        .add("/:id/members/:pid", delete(remove_member))
+        .add("/:id/invitations", get(list_invitations))
+        .add("/:id/invitations", post(invite))
+        .add("/:id/invitations/:invitation_id", delete(revoke_invitation))
+}
@@ -0,0 +1,213 @@
+use axum::{debug_handler, http::StatusCode};
+use chrono::Local;
+use loco_rs::{controller::ErrorDetail, prelude::*};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    common::settings::Settings,
+    models::{
+        agents,
+        task_schedules::{self, CatchUp, Status},
+    },
+    plans::{Entitlement, Quota},
+    policy::{Action, Actor},
+    scheduling::{self, Cadence},
+    workers::orchestrator::{OrchestratorWorker, OrchestratorWorkerArgs},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub agent_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub input: Option<Value>,
+    /// Five field cron expression, or six and seven fields starting with
+    /// seconds
+    pub cron: String,
+    /// IANA name of the timezone the expression is read in
+    pub timezone: Option<String>,
+    pub catch_up: Option<CatchUp>,
+}
+
+/// A schedule of the user's own
+async fn load_own(ctx: &AppContext, actor: &Actor, id: Uuid) -> Result<task_schedules::Model> {
+    task_schedules::Model::find_for_user(&ctx.db, id, actor.user.id)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+/// A schedule of the user's own, or one on an agent of an organization
+/// they manage
+async fn load_item(ctx: &AppContext, actor: &Actor, id: Uuid) -> Result<task_schedules::Model> {
+    let schedule = task_schedules::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    if schedule.user_id != actor.user.id {
+        let agent = agents::Model::find_by_id(&ctx.db, schedule.agent_id)
+            .await
+            .map_err(|_| Error::NotFound)?;
+        if agent.organization_id.is_none() {
+            return Err(Error::NotFound);
+        }
+        actor
+            .authorize_agent(&ctx.db, &agent, Action::Manage)
+            .await?;
+    }
+    Ok(schedule)
+}
+
+fn next_run(schedule: &task_schedules::Model) -> Result<Option<chrono::NaiveDateTime>> {
+    Ok(Cadence::parse(&schedule.cron, &schedule.timezone)?.next_after(Local::now().naive_local()))
+}
+
+/// Creates a schedule that turns into a task for the agent every time its
+/// cron expression fires
+#[debug_handler]
+async fn create(
+    actor: Actor,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let agent = agents::Model::find_by_id(&ctx.db, params.agent_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let actor = actor.authorize_agent(&ctx.db, &agent, Action::Use).await?;
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let cadence = Cadence::parse(&params.cron, &timezone)?;
+    let schedule = task_schedules::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(actor.user.id),
+        name: ActiveValue::set(params.name),
+        description: ActiveValue::set(params.description),
+        input: ActiveValue::set(params.input),
+        cron: ActiveValue::set(params.cron),
+        timezone: ActiveValue::set(timezone),
+        catch_up: ActiveValue::set(params.catch_up.unwrap_or_default().as_str().to_string()),
+        next_run_at: ActiveValue::set(cadence.next_after(Local::now().naive_local())),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+    format::json(schedule)
+}
+
+/// Lists the user's schedules, and to those who manage the organization
+/// acted in, the schedules on its agents too
+#[debug_handler]
+async fn list(actor: Actor, State(ctx): State<AppContext>) -> Result<Response> {
+    let organization_id = actor
+        .role
+        .allows(Action::Manage)
+        .then_some(actor.organization.id);
+    format::json(
+        task_schedules::Model::list_for_user_or_organization(
+            &ctx.db,
+            actor.user.id,
+            organization_id,
+        )
+        .await?,
+    )
+}
+
+#[debug_handler]
+async fn get_one(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    format::json(load_item(&ctx, &actor, id).await?)
+}
+
+#[debug_handler]
+async fn remove(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    load_item(&ctx, &actor, id).await?.delete(&ctx.db).await?;
+    format::empty()
+}
+
+/// Stops the schedule from creating tasks until it is resumed
+#[debug_handler]
+async fn pause(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let schedule = load_item(&ctx, &actor, id).await?;
+    let schedule = schedule
+        .into_active_model()
+        .set_status(&ctx.db, Status::Paused, None)
+        .await?;
+    format::json(schedule)
+}
+
+/// Starts the schedule again from its next occurrence, as long as its user
+/// may still use the agent. Runs missed while it was paused are not made.
+#[debug_handler]
+async fn resume(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let schedule = load_item(&ctx, &actor, id).await?;
+    if !scheduling::may_run(&ctx.db, &schedule).await? {
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new(
+                "forbidden",
+                "the user of the schedule may no longer use its agent",
+            ),
+        ));
+    }
+    let next_run_at = next_run(&schedule)?;
+    let schedule = schedule
+        .into_active_model()
+        .set_status(&ctx.db, Status::Active, next_run_at)
+        .await?;
+    format::json(schedule)
+}
+
+/// Creates a run of one of the user's schedules right away and returns its
+/// task, if the user has tokens left in their plan. Scheduled runs are
+/// checked when they run.
+#[debug_handler]
+async fn run_now(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let schedule = load_own(&ctx, &actor, id).await?;
+    let agent = agents::Model::find_by_id(&ctx.db, schedule.agent_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor.authorize_agent(&ctx.db, &agent, Action::Use).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let entitlement = Entitlement::measure(
+        &ctx.db,
+        &settings.plans,
+        &actor.user,
+        &[Quota::TokensPerMonth],
+    )
+    .await?;
+    if let Err(exceeded) = entitlement.require(&[(Quota::TokensPerMonth, 1)]) {
+        return exceeded.into_response(&entitlement);
+    }
+    let task = scheduling::trigger(&ctx.db, &schedule).await?;
+    OrchestratorWorker::perform_later(&ctx, OrchestratorWorkerArgs { task_id: task.id }).await?;
+    Ok(entitlement.with_headers(format::json(task)?))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/schedules")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id", delete(remove))
+        .add("/:id/pause", post(pause))
+        .add("/:id/resume", post(resume))
+        .add("/:id/run", post(run_now))
+}
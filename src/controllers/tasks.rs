@@ -0,0 +1,96 @@
+use axum::debug_handler;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    common::settings::Settings,
+    models::{agents, tasks, users},
+    orchestration::trace,
+    plans::{Entitlement, Quota},
+    policy::{Action, Caller, Scope},
+    workers::orchestrator::{OrchestratorWorker, OrchestratorWorkerArgs},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateParams {
+    pub agent_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub input: Option<Value>,
+}
+
+async fn load_item(ctx: &AppContext, user: &users::Model, id: Uuid) -> Result<tasks::Model> {
+    tasks::Model::find_for_user(&ctx.db, id, user.id)
+        .await
+        .map_err(|_| Error::NotFound)
+}
+
+/// Creates a task for the agent and runs it in the background. Tasks for
+/// planner agents are split up and delegated to other agents. The tokens
+/// the task uses count against the user's plan.
+#[debug_handler]
+async fn create(
+    caller: Caller,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let actor = caller.scoped(Scope::TasksRun)?;
+    let agent = agents::Model::find_by_id(&ctx.db, params.agent_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor.authorize_agent(&ctx.db, &agent, Action::Use).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let entitlement = Entitlement::measure(
+        &ctx.db,
+        &settings.plans,
+        &actor.user,
+        &[Quota::TokensPerMonth],
+    )
+    .await?;
+    if let Err(exceeded) = entitlement.require(&[(Quota::TokensPerMonth, 1)]) {
+        return exceeded.into_response(&entitlement);
+    }
+    let task = tasks::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(Some(actor.user.id)),
+        name: ActiveValue::set(params.name),
+        description: ActiveValue::set(params.description),
+        input: ActiveValue::set(params.input),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+    OrchestratorWorker::perform_later(&ctx, OrchestratorWorkerArgs { task_id: task.id }).await?;
+    Ok(entitlement.with_headers(format::json(task)?))
+}
+
+#[debug_handler]
+async fn get_one(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = caller.scoped(Scope::TasksRead)?.user;
+    format::json(load_item(&ctx, &user, id).await?)
+}
+
+/// Returns the task with the tree of tasks it delegated
+#[debug_handler]
+async fn get_trace(
+    caller: Caller,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = caller.scoped(Scope::TasksRead)?.user;
+    let task = load_item(&ctx, &user, id).await?;
+    format::json(trace::build(&ctx.db, task).await?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/tasks")
+        .add("/", post(create))
+        .add("/:id", get(get_one))
+        .add("/:id/trace", get(get_trace))
+}
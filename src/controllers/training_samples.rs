@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use axum::{debug_handler, extract::Query};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{
+        learning_models,
+        model_training_data::{self, Status},
+    },
+    policy::{Action, Actor},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ListParams {
+    /// Defaults to the pending review queue
+    pub status: Option<Status>,
+    pub model_id: Option<Uuid>,
+}
+
+/// Loads the sample if the model it trains may be edited
+async fn load_item(
+    ctx: &AppContext,
+    actor: &Actor,
+    id: Uuid,
+) -> Result<model_training_data::Model> {
+    let sample = model_training_data::Model::find_by_id(&ctx.db, id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    let model = learning_models::Model::find_by_id(&ctx.db, sample.model_id)
+        .await
+        .map_err(|_| Error::NotFound)?;
+    actor
+        .authorize_learning_model(&ctx.db, &model, Action::Edit)
+        .await?;
+    Ok(sample)
+}
+
+/// Lists the samples of the models the current user may review in the
+/// organization acted in
+#[debug_handler]
+async fn list(
+    actor: Actor,
+    Query(params): Query<ListParams>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    actor.require(Action::Edit)?;
+    let samples = model_training_data::Model::list_by_status(
+        &ctx.db,
+        params.status.unwrap_or(Status::Pending),
+        params.model_id,
+    )
+    .await?;
+
+    let mut reviewable: HashMap<Uuid, bool> = HashMap::new();
+    let mut visible = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let allowed = match reviewable.get(&sample.model_id) {
+            Some(allowed) => *allowed,
+            None => {
+                let allowed =
+                    match learning_models::Model::find_by_id(&ctx.db, sample.model_id).await {
+                        Ok(model) => actor
+                            .authorize_learning_model(&ctx.db, &model, Action::Edit)
+                            .await
+                            .is_ok_and(|owner| owner.organization.id == actor.organization.id),
+                        Err(_) => false,
+                    };
+                reviewable.insert(sample.model_id, allowed);
+                allowed
+            }
+        };
+        if allowed {
+            visible.push(sample);
+        }
+    }
+    format::json(visible)
+}
+
+#[debug_handler]
+async fn approve(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let sample = load_item(&ctx, &actor, id).await?;
+    format::json(
+        sample
+            .into_active_model()
+            .set_status(&ctx.db, Status::Approved)
+            .await?,
+    )
+}
+
+#[debug_handler]
+async fn reject(
+    actor: Actor,
+    Path(id): Path<Uuid>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let sample = load_item(&ctx, &actor, id).await?;
+    format::json(
+        sample
+            .into_active_model()
+            .set_status(&ctx.db, Status::Rejected)
+            .await?,
+    )
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/training_samples")
+        .add("/", get(list))
+        .add("/:id/approve", post(approve))
+        .add("/:id/reject", post(reject))
+}
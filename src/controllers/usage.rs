@@ -0,0 +1,62 @@
+use axum::{debug_handler, extract::Query};
+use chrono::{Duration, Local, NaiveDate};
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{usage_records, users},
+    usage::report::{self, Dimension, Period},
+};
+
+/// How many days the report covers when no range is given
+const DEFAULT_DAYS: i64 = 30;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UsageParams {
+    /// First day included, defaults to 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last day included, defaults to today
+    pub to: Option<NaiveDate>,
+    pub group_by: Option<Period>,
+    /// Comma separated dimensions: `agent`, `conversation`, `model`
+    pub by: Option<String>,
+}
+
+/// The token usage and cost of the current user's calls over a range of
+/// days, split by period and by agent, conversation or model
+#[debug_handler]
+async fn index(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<UsageParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let to = params.to.unwrap_or_else(|| Local::now().date_naive());
+    let from = params
+        .from
+        .unwrap_or_else(|| to - Duration::days(DEFAULT_DAYS - 1));
+    if from > to {
+        return bad_request("from must not be after to");
+    }
+    let by = params
+        .by
+        .as_deref()
+        .map_or_else(|| Ok(Vec::new()), Dimension::parse_list)?;
+    if by.contains(&Dimension::User) {
+        return bad_request("usage is always limited to the current user");
+    }
+
+    let (start, end) = report::day_bounds(from, to);
+    let records = usage_records::Model::list_between(&ctx.db, start, end, Some(user.id)).await?;
+    format::json(report::build(
+        &records,
+        from,
+        to,
+        params.group_by.unwrap_or_default(),
+        &by,
+    ))
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/usage").add("/", get(index))
+}
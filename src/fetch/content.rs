@@ -0,0 +1,141 @@
+//! Working out what a response really contains and turning it into text.
+
+/// Types detected from the leading bytes of a body
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"MZ", "application/x-msdownload"),
+];
+
+/// Guesses the media type of a body from its content
+#[must_use]
+pub fn sniff(body: &[u8]) -> &'static str {
+    if let Some((_, media_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| body.starts_with(signature))
+    {
+        return media_type;
+    }
+    let Ok(text) = std::str::from_utf8(body) else {
+        return "application/octet-stream";
+    };
+    if text.contains('\0') {
+        return "application/octet-stream";
+    }
+
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else if start.starts_with("<?xml") {
+        "application/xml"
+    } else if (start.starts_with('{') || start.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+    {
+        "application/json"
+    } else {
+        "text/plain"
+    }
+}
+
+/// The media type to treat the body as. The declared `Content-Type` is
+/// trusted unless the body is clearly binary, so a server cannot pass off
+/// an executable or archive as text.
+#[must_use]
+pub fn media_type(declared: Option<&str>, body: &[u8]) -> String {
+    let sniffed = sniff(body);
+    let declared = declared
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty());
+
+    match declared {
+        Some(declared) if is_textual(sniffed) => {
+            if declared == "application/octet-stream" {
+                sniffed.to_string()
+            } else {
+                declared
+            }
+        }
+        // a binary body is only accepted under a specific binary declaration
+        Some(declared) if !is_textual(&declared) && declared != "application/octet-stream" => {
+            declared
+        }
+        _ => sniffed.to_string(),
+    }
+}
+
+fn is_textual(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || matches!(
+            media_type,
+            "application/json" | "application/xml" | "application/xhtml+xml"
+        )
+        || media_type.ends_with("+json")
+        || media_type.ends_with("+xml")
+}
+
+/// Extracts readable text from a body of the given media type
+#[must_use]
+pub fn to_text(media_type: &str, body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    if media_type == "text/html" || media_type == "application/xhtml+xml" {
+        html_to_text(&text)
+    } else {
+        text.into_owned()
+    }
+}
+
+/// Strips tags, scripts and styles from HTML and collapses whitespace
+#[must_use]
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        rest = &rest[start..];
+
+        let lower = rest.get(..8).unwrap_or(rest).to_ascii_lowercase();
+        let skip_until = if lower.starts_with("<script") {
+            Some("</script")
+        } else if lower.starts_with("<style") {
+            Some("</style")
+        } else if lower.starts_with("<!--") {
+            Some("-->")
+        } else {
+            None
+        };
+        if let Some(end_marker) = skip_until {
+            let lowered = rest.to_ascii_lowercase();
+            rest = lowered
+                .find(end_marker)
+                .map_or("", |end| &rest[end + end_marker.len()..]);
+            if end_marker == "-->" {
+                continue;
+            }
+        }
+        rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+    }
+    text.push_str(rest);
+
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,71 @@
+//! Checks that keep fetches away from hosts an agent was not given and from
+//! internal networks (SSRF).
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Whether `host` is covered by the allowlist. Entries match exactly, or
+/// with a leading `*.` match any subdomain (but not the domain itself).
+#[must_use]
+pub fn domain_allowed(host: &str, allowed: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    allowed.iter().any(|entry| {
+        let entry = entry.trim_end_matches('.').to_ascii_lowercase();
+        entry.strip_prefix("*.").map_or_else(
+            || host == entry,
+            |parent| {
+                host.strip_suffix(parent)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.'))
+            },
+        )
+    })
+}
+
+/// Whether the address is reachable on the public internet. Loopback,
+/// private, link-local, shared, documentation, multicast and unspecified
+/// ranges are not.
+#[must_use]
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => ip
+            .to_ipv4_mapped()
+            .map_or_else(|| is_public_v6(ip), is_public_v4),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network"
+        || a == 0
+        // shared address space (carrier grade NAT)
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking
+        || (a == 198 && (b == 18 || b == 19))
+        // reserved
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local
+        || (first & 0xfe00) == 0xfc00
+        // link local
+        || (first & 0xffc0) == 0xfe80
+        // documentation
+        || first == 0x2001 && ip.segments()[1] == 0x0db8
+        // IPv4 compatible and NAT64 prefixes can smuggle internal addresses
+        || ip.to_ipv4().is_some()
+        || (first == 0x0064 && ip.segments()[1] == 0xff9b))
+}
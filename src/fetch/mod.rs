@@ -0,0 +1,257 @@
+//! Sandboxed HTTP fetching for agents. Every request is checked against the
+//! agent's domain allowlist, resolved addresses must be public, redirects
+//! are followed by hand so each hop is checked again, and the body is capped
+//! in size and time before its content type is verified.
+
+use std::{net::SocketAddr, time::Duration};
+
+use loco_rs::{Error, Result};
+use reqwest::{header, redirect, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::models::agents;
+
+pub mod content;
+pub mod guard;
+
+/// The `settings.fetch` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Largest body accepted, in bytes
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+    /// Time allowed for the whole fetch, redirects included
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    /// Media types that may be fetched
+    #[serde(default = "default_allowed_content_types")]
+    pub allowed_content_types: Vec<String>,
+    /// Allows loopback and private addresses. Only meant for development
+    /// and tests.
+    #[serde(default)]
+    pub allow_private_networks: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_max_bytes(),
+            timeout_secs: default_timeout_secs(),
+            max_redirects: default_max_redirects(),
+            allowed_content_types: default_allowed_content_types(),
+            allow_private_networks: false,
+        }
+    }
+}
+
+const fn default_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+const fn default_timeout_secs() -> u64 {
+    10
+}
+
+const fn default_max_redirects() -> usize {
+    3
+}
+
+fn default_allowed_content_types() -> Vec<String> {
+    [
+        "text/html",
+        "text/plain",
+        "text/markdown",
+        "text/csv",
+        "application/json",
+        "application/xml",
+        "text/xml",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
+
+/// A fetched resource
+#[derive(Debug, Clone, Serialize)]
+pub struct Document {
+    /// Where the body was finally served from, after redirects
+    pub url: String,
+    pub content_type: String,
+    pub bytes: usize,
+    /// The readable text of the body
+    pub text: String,
+}
+
+pub struct Fetcher {
+    config: Config,
+    allowed_domains: Vec<String>,
+}
+
+impl Fetcher {
+    #[must_use]
+    pub const fn new(config: Config, allowed_domains: Vec<String>) -> Self {
+        Self {
+            config,
+            allowed_domains,
+        }
+    }
+
+    /// A fetcher limited to the domains listed in the agent configuration
+    /// under `allowed_domains`. Agents without the list cannot fetch
+    /// anything.
+    #[must_use]
+    pub fn for_agent(config: Config, agent: &agents::Model) -> Self {
+        let allowed_domains = agent
+            .configuration
+            .as_ref()
+            .and_then(|configuration| configuration.get("allowed_domains"))
+            .and_then(|domains| serde_json::from_value(domains.clone()).ok())
+            .unwrap_or_default();
+        Self::new(config, allowed_domains)
+    }
+
+    /// Fetches the URL within the configured limits
+    ///
+    /// # Errors
+    ///
+    /// `BadRequest` when the URL, a redirect target, the size or the content
+    /// type is not allowed; a string error when the request fails or times
+    /// out
+    pub async fn fetch(&self, url: &str) -> Result<Document> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        tokio::time::timeout(timeout, self.fetch_inner(url, timeout))
+            .await
+            .map_err(|_| Error::string(&format!("fetching {url} timed out")))?
+    }
+
+    async fn fetch_inner(&self, url: &str, timeout: Duration) -> Result<Document> {
+        let mut url = Url::parse(url).map_err(|err| refused(format!("invalid url: {err}")))?;
+        let mut redirects = 0;
+        loop {
+            let (host, addr) = self.check(&url).await?;
+            // pin the checked address so a second DNS answer cannot point
+            // the connection somewhere else, and connect to it directly, as a
+            // proxy from the environment would be connected to instead
+            let client = reqwest::Client::builder()
+                .no_proxy()
+                .redirect(redirect::Policy::none())
+                .timeout(timeout)
+                .resolve(&host, addr)
+                .build()
+                .map_err(fetch_error)?;
+            let mut response = client.get(url.clone()).send().await.map_err(fetch_error)?;
+
+            if response.status().is_redirection() {
+                redirects += 1;
+                if redirects > self.config.max_redirects {
+                    return Err(refused("too many redirects"));
+                }
+                let location = response
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| refused("redirect without a location"))?;
+                url = url
+                    .join(location)
+                    .map_err(|err| refused(format!("invalid redirect: {err}")))?;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(Error::string(&format!(
+                    "fetching {url} failed with status {}",
+                    response.status()
+                )));
+            }
+
+            if response
+                .content_length()
+                .is_some_and(|length| length > self.config.max_bytes as u64)
+            {
+                return Err(refused(format!(
+                    "response is larger than {} bytes",
+                    self.config.max_bytes
+                )));
+            }
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+                if body.len() + chunk.len() > self.config.max_bytes {
+                    return Err(refused(format!(
+                        "response is larger than {} bytes",
+                        self.config.max_bytes
+                    )));
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            let declared = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok());
+            let content_type = content::media_type(declared, &body);
+            if !self
+                .config
+                .allowed_content_types
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&content_type))
+            {
+                return Err(refused(format!(
+                    "content type {content_type} is not allowed"
+                )));
+            }
+
+            return Ok(Document {
+                url: url.to_string(),
+                text: content::to_text(&content_type, &body),
+                bytes: body.len(),
+                content_type,
+            });
+        }
+    }
+
+    /// Checks scheme, allowlist and resolved addresses of a URL and returns
+    /// the host with the address to connect to
+    async fn check(&self, url: &Url) -> Result<(String, SocketAddr)> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(refused(format!("scheme {} is not allowed", url.scheme())));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| refused("url has no host"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        if !guard::domain_allowed(&host, &self.allowed_domains) {
+            return Err(refused(format!("{host} is not in the agent's allowlist")));
+        }
+
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|err| Error::string(&format!("could not resolve {host}: {err}")))?
+            .collect();
+        if !self.config.allow_private_networks
+            && addrs.iter().any(|addr| !guard::is_public(addr.ip()))
+        {
+            return Err(refused(format!("{host} resolves to a private address")));
+        }
+        let addr = addrs
+            .first()
+            .copied()
+            .ok_or_else(|| Error::string(&format!("could not resolve {host}")))?;
+        Ok((host, addr))
+    }
+}
+
+fn refused(reason: impl std::fmt::Display) -> Error {
+    Error::BadRequest(format!("fetch refused: {reason}"))
+}
+
+fn fetch_error(err: reqwest::Error) -> Error {
+    if err.is_timeout() {
+        Error::string("fetch timed out")
+    } else {
+        Error::string(&format!("fetch failed: {err}"))
+    }
+}
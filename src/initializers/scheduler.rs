@@ -0,0 +1,27 @@
+use axum::async_trait;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+
+use crate::{common::settings::Settings, scheduling};
+
+/// Starts the schedule ticker for every process the app runs in. The ticks
+/// go through the configured worker mode, and concurrent ticks are safe.
+#[allow(clippy::module_name_repetitions)]
+pub struct SchedulerInitializer;
+
+#[async_trait]
+impl Initializer for SchedulerInitializer {
+    fn name(&self) -> String {
+        "scheduler".to_string()
+    }
+
+    async fn before_run(&self, ctx: &AppContext) -> Result<()> {
+        let settings = Settings::from_context(ctx)?;
+        if settings.scheduler.enabled {
+            scheduling::start(ctx, &settings.scheduler);
+        }
+        Ok(())
+    }
+}
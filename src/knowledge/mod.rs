@@ -0,0 +1,168 @@
+//! Ingestion of documents into knowledge bases. A document is stored whole
+//! and split into chunks that can be retrieved and embedded on their own.
+
+use loco_rs::{Error, Result};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, TransactionTrait};
+use serde_json::{json, Value};
+
+use crate::{
+    models::{
+        agents, knowledge_base,
+        knowledge_items::{self, TYPE_CHUNK, TYPE_DOCUMENT},
+        users,
+    },
+    plans::{self, Entitlement, Quota},
+};
+
+/// Chunks aim for this many characters and are cut at whitespace
+pub const CHUNK_CHARS: usize = 1000;
+
+#[derive(Debug)]
+pub struct Ingested {
+    pub document: knowledge_items::Model,
+    pub chunks: Vec<knowledge_items::Model>,
+}
+
+/// Stores `text` as a document of the knowledge base together with its
+/// chunks. `metadata` describes where the text came from and is copied onto
+/// the document.
+///
+/// # Errors
+///
+/// When the knowledge base does not exist or the items could not be saved
+pub async fn ingest(
+    db: &DatabaseConnection,
+    knowledge_base_id: uuid::Uuid,
+    text: &str,
+    metadata: Value,
+) -> Result<Ingested> {
+    let knowledge_base = knowledge_base::Model::find_by_id(db, knowledge_base_id).await?;
+
+    let txn = db.begin().await?;
+    let document = knowledge_items::ActiveModel {
+        knowledge_base_id: ActiveValue::set(knowledge_base.id),
+        r#type: ActiveValue::set(TYPE_DOCUMENT.to_string()),
+        content: ActiveValue::set(text.to_string()),
+        metadata: ActiveValue::set(Some(metadata)),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    let mut chunks = vec![];
+    for (index, content) in chunk(text, CHUNK_CHARS).into_iter().enumerate() {
+        chunks.push(
+            knowledge_items::ActiveModel {
+                knowledge_base_id: ActiveValue::set(knowledge_base.id),
+                r#type: ActiveValue::set(TYPE_CHUNK.to_string()),
+                content: ActiveValue::set(content),
+                metadata: ActiveValue::set(Some(json!({
+                    "document_id": document.id,
+                    "index": index,
+                }))),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?,
+        );
+    }
+    txn.commit().await?;
+
+    Ok(Ingested { document, chunks })
+}
+
+/// Finds a knowledge base the agent may add to, one of its organization
+///
+/// # Errors
+///
+/// When there is no such knowledge base within the agent's reach or DB
+/// query error
+pub async fn knowledge_base_for(
+    db: &DatabaseConnection,
+    agent: &agents::Model,
+    knowledge_base_id: uuid::Uuid,
+) -> Result<knowledge_base::Model> {
+    knowledge_base::Model::find_by_id(db, knowledge_base_id)
+        .await
+        .ok()
+        .filter(|knowledge_base| agent.reaches(knowledge_base.organization_id))
+        .ok_or_else(|| Error::string(&format!("unknown knowledge base: {knowledge_base_id}")))
+}
+
+/// Checks that ingesting `text` into the knowledge base fits the knowledge
+/// storage of its owner's plan. The document and its chunks both count.
+///
+/// # Errors
+///
+/// When the knowledge base does not exist, the quota would be exceeded or
+/// DB query error
+pub async fn require_room(
+    db: &DatabaseConnection,
+    plans: &plans::Config,
+    knowledge_base_id: uuid::Uuid,
+    text: &str,
+) -> Result<()> {
+    let knowledge_base = knowledge_base::Model::find_by_id(db, knowledge_base_id).await?;
+    let Some(owner_id) = knowledge_base.user_id else {
+        return Ok(());
+    };
+    let Some(owner) = users::Entity::find_by_id(owner_id).one(db).await? else {
+        return Ok(());
+    };
+    let bytes = text.len()
+        + chunk(text, CHUNK_CHARS)
+            .iter()
+            .map(String::len)
+            .sum::<usize>();
+    Entitlement::measure(db, plans, &owner, &[Quota::KnowledgeBytes])
+        .await?
+        .require(&[(Quota::KnowledgeBytes, bytes as u64)])
+        .map_err(plans::Exceeded::into_error)
+}
+
+/// Splits text into pieces of at most `max_chars` characters, breaking at
+/// whitespace where possible. Words longer than `max_chars` are cut.
+#[must_use]
+pub fn chunk(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut current_chars = 0;
+    for word in text.split_whitespace() {
+        let mut word = word;
+        loop {
+            let word_chars = word.chars().count();
+            let needed = if current.is_empty() {
+                word_chars
+            } else {
+                word_chars + 1
+            };
+            if current_chars + needed <= max_chars {
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+                current_chars += needed;
+                break;
+            }
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_chars = 0;
+                continue;
+            }
+            // a single word longer than a chunk
+            let split = word
+                .char_indices()
+                .nth(max_chars)
+                .map_or(word.len(), |(idx, _)| idx);
+            chunks.push(word[..split].to_string());
+            word = &word[split..];
+            if word.is_empty() {
+                break;
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use loco_rs::{model::ModelError, Error, Result};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::{metrics::Metric, program::Program, split::split, Config};
+use crate::{
+    llm::Provider,
+    models::{learning_models, model_evaluations},
+};
+
+pub const DEV_SPLIT: &str = "dev";
+
+/// Outcome of running a model version on a single dev example
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExampleResult {
+    pub example_id: Uuid,
+    pub expected: Value,
+    pub predicted: Value,
+    pub scores: BTreeMap<String, f64>,
+}
+
+/// Runs a learning model version against the held-out dev split and stores
+/// the results as a `model_evaluations` row.
+pub struct Evaluator<'a> {
+    provider: &'a dyn Provider,
+    metrics: Vec<&'a dyn Metric>,
+    dev_ratio: f64,
+}
+
+impl<'a> Evaluator<'a> {
+    #[must_use]
+    pub fn new(provider: &'a dyn Provider, metrics: Vec<&'a dyn Metric>) -> Self {
+        Self {
+            provider,
+            metrics,
+            dev_ratio: Config::default().dev_ratio,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_dev_ratio(mut self, dev_ratio: f64) -> Self {
+        self.dev_ratio = dev_ratio;
+        self
+    }
+
+    /// Evaluates `model` on the dev split of its training data
+    ///
+    /// # Errors
+    ///
+    /// When there are no dev examples, a provider call fails or the results
+    /// could not be saved
+    pub async fn evaluate(
+        &self,
+        db: &DatabaseConnection,
+        model: &learning_models::Model,
+    ) -> Result<model_evaluations::Model> {
+        if self.metrics.is_empty() {
+            return Err(Error::string("evaluation needs at least one metric"));
+        }
+
+        let dev = split(model.training_data(db).await?, self.dev_ratio).dev;
+        if dev.is_empty() {
+            return Err(Error::string("model has no dev examples to evaluate on"));
+        }
+
+        let program = Program::from_model(model)?;
+        let mut results = Vec::with_capacity(dev.len());
+        let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+        for example in &dev {
+            let predicted = program.predict(self.provider, &example.input).await?;
+            let scores = self
+                .metrics
+                .iter()
+                .map(|metric| {
+                    (
+                        metric.name().to_string(),
+                        metric.score(&example.output, &predicted),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>();
+            for (name, score) in &scores {
+                *totals.entry(name.clone()).or_default() += score;
+            }
+            results.push(ExampleResult {
+                example_id: example.id,
+                expected: example.output.clone(),
+                predicted,
+                scores,
+            });
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let scores = totals
+            .into_iter()
+            .map(|(name, total)| (name, total / dev.len() as f64))
+            .collect::<BTreeMap<_, _>>();
+
+        let evaluation = model_evaluations::ActiveModel {
+            model_id: ActiveValue::set(model.id),
+            split: ActiveValue::set(DEV_SPLIT.to_string()),
+            example_count: ActiveValue::set(i32::try_from(dev.len()).unwrap_or(i32::MAX)),
+            scores: ActiveValue::set(serde_json::to_value(&scores)?),
+            results: ActiveValue::set(serde_json::to_value(&results)?),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        tracing::info!(
+            model_id = model.id.to_string(),
+            evaluation_id = evaluation.id.to_string(),
+            examples = dev.len(),
+            "evaluated learning model",
+        );
+
+        Ok(evaluation)
+    }
+}
+
+/// A dev example whose pass/fail outcome differs between two versions
+#[derive(Debug, Clone, Serialize)]
+pub struct Flip {
+    pub example_id: Uuid,
+    pub metric: String,
+    pub base_score: f64,
+    pub candidate_score: f64,
+    pub expected: Value,
+    pub base_predicted: Value,
+    pub candidate_predicted: Value,
+    pub regressed: bool,
+}
+
+/// Diff between the evaluations of two model versions
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub base_model_id: Uuid,
+    pub candidate_model_id: Uuid,
+    pub base_evaluation_id: Uuid,
+    pub candidate_evaluation_id: Uuid,
+    pub base_scores: BTreeMap<String, f64>,
+    pub candidate_scores: BTreeMap<String, f64>,
+    /// Candidate score minus base score, for metrics present in both
+    pub deltas: BTreeMap<String, f64>,
+    pub flipped: Vec<Flip>,
+}
+
+impl Comparison {
+    /// Compares two evaluations example by example. Only examples present in
+    /// both evaluations can flip.
+    ///
+    /// # Errors
+    ///
+    /// When a stored evaluation cannot be decoded
+    pub fn new(
+        base: &model_evaluations::Model,
+        candidate: &model_evaluations::Model,
+        pass_threshold: f64,
+    ) -> Result<Self> {
+        let base_scores: BTreeMap<String, f64> = serde_json::from_value(base.scores.clone())?;
+        let candidate_scores: BTreeMap<String, f64> =
+            serde_json::from_value(candidate.scores.clone())?;
+        let deltas = candidate_scores
+            .iter()
+            .filter_map(|(name, score)| {
+                base_scores
+                    .get(name)
+                    .map(|base_score| (name.clone(), score - base_score))
+            })
+            .collect();
+
+        let base_results: Vec<ExampleResult> = serde_json::from_value(base.results.clone())?;
+        let candidate_results: Vec<ExampleResult> =
+            serde_json::from_value(candidate.results.clone())?;
+
+        let mut flipped = Vec::new();
+        for candidate_result in &candidate_results {
+            let Some(base_result) = base_results
+                .iter()
+                .find(|result| result.example_id == candidate_result.example_id)
+            else {
+                continue;
+            };
+            for (metric, candidate_score) in &candidate_result.scores {
+                let Some(base_score) = base_result.scores.get(metric) else {
+                    continue;
+                };
+                let base_passed = *base_score >= pass_threshold;
+                let candidate_passed = *candidate_score >= pass_threshold;
+                if base_passed != candidate_passed {
+                    flipped.push(Flip {
+                        example_id: candidate_result.example_id,
+                        metric: metric.clone(),
+                        base_score: *base_score,
+                        candidate_score: *candidate_score,
+                        expected: candidate_result.expected.clone(),
+                        base_predicted: base_result.predicted.clone(),
+                        candidate_predicted: candidate_result.predicted.clone(),
+                        regressed: base_passed,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            base_model_id: base.model_id,
+            candidate_model_id: candidate.model_id,
+            base_evaluation_id: base.id,
+            candidate_evaluation_id: candidate.id,
+            base_scores,
+            candidate_scores,
+            deltas,
+            flipped,
+        })
+    }
+
+    /// Whether the candidate scores lower than the base on any shared metric
+    /// by more than `tolerance`
+    #[must_use]
+    pub fn regresses(&self, tolerance: f64) -> bool {
+        self.deltas.values().any(|delta| *delta < -tolerance)
+    }
+}
+
+/// Compares the latest evaluation of `candidate` with the one of the
+/// currently active version. Returns `None` when there is nothing to compare
+/// against.
+///
+/// # Errors
+///
+/// When the candidate was never evaluated or has DB query error
+pub async fn promotion_comparison(
+    db: &DatabaseConnection,
+    candidate: &learning_models::Model,
+    pass_threshold: f64,
+) -> Result<Option<Comparison>> {
+    let candidate_evaluation = model_evaluations::Model::latest_for_model(db, candidate.id)
+        .await
+        .map_err(|_| Error::BadRequest("model version has not been evaluated".to_string()))?;
+
+    let active = match candidate.active_version(db).await {
+        Ok(active) if active.id != candidate.id => active,
+        Ok(_) | Err(ModelError::EntityNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let base_evaluation = match model_evaluations::Model::latest_for_model(db, active.id).await {
+        Ok(evaluation) => evaluation,
+        Err(ModelError::EntityNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(Some(Comparison::new(
+        &base_evaluation,
+        &candidate_evaluation,
+        pass_threshold,
+    )?))
+}
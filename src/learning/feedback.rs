@@ -0,0 +1,169 @@
+use chrono::offset::Local;
+use loco_rs::{model::ModelError, Error, Result};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, IntoActiveModel};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::models::{
+    agents, conversations, messages,
+    model_training_data::{self, Status as SampleStatus},
+    users,
+};
+
+pub const FEEDBACK_SOURCE: &str = "conversation_feedback";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Feedback {
+    pub rating: Rating,
+    /// What the assistant should have answered instead
+    pub correction: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackOutcome {
+    pub message_id: Uuid,
+    pub rating: Rating,
+    /// The pending training sample created from this feedback, if any
+    pub sample_id: Option<Uuid>,
+}
+
+/// Records a user's feedback on an assistant message.
+///
+/// Approved (thumbs up) or corrected turns become pending
+/// `model_training_data` rows for the agent's active learning model, using
+/// the prompting user message as input and the approved or corrected answer
+/// as output. Giving feedback again replaces a sample that is still pending.
+///
+/// The caller checks that the user may use the conversation.
+///
+/// # Errors
+///
+/// When the message is not an assistant message, or has DB query error
+pub async fn record(
+    db: &DatabaseConnection,
+    user: &users::Model,
+    conversation: &conversations::Model,
+    message: messages::Model,
+    feedback: &Feedback,
+) -> Result<FeedbackOutcome> {
+    if message.role != messages::ROLE_ASSISTANT {
+        return Err(Error::BadRequest(
+            "feedback can only be given on assistant messages".to_string(),
+        ));
+    }
+
+    let previous_sample_id = message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata["feedback"]["sample_id"].as_str())
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    let output = match (&feedback.correction, feedback.rating) {
+        (Some(correction), _) => Some(correction.clone()),
+        (None, Rating::Up) => Some(message.content.clone()),
+        (None, Rating::Down) => None,
+    };
+
+    let sample_id = match output {
+        Some(output) => capture_sample(db, user, conversation, &message, feedback, output).await?,
+        None => None,
+    };
+
+    // a sample from earlier feedback that nobody reviewed yet is superseded
+    if let Some(previous_sample_id) = previous_sample_id {
+        if let Some(previous) = model_training_data::Entity::find_by_id(previous_sample_id)
+            .one(db)
+            .await?
+        {
+            if previous.status == SampleStatus::Pending.as_str() {
+                model_training_data::Entity::delete_by_id(previous.id)
+                    .exec(db)
+                    .await?;
+            }
+        }
+    }
+
+    let mut metadata = message.metadata.clone().unwrap_or_else(|| json!({}));
+    if !metadata.is_object() {
+        metadata = json!({});
+    }
+    metadata["feedback"] = json!({
+        "rating": feedback.rating,
+        "correction": feedback.correction,
+        "comment": feedback.comment,
+        "user_pid": user.pid,
+        "sample_id": sample_id,
+        "at": Local::now().naive_local(),
+    });
+    let mut active = message.into_active_model();
+    active.metadata = ActiveValue::set(Some(metadata));
+    let message = active.update(db).await?;
+
+    Ok(FeedbackOutcome {
+        message_id: message.id,
+        rating: feedback.rating,
+        sample_id,
+    })
+}
+
+async fn capture_sample(
+    db: &DatabaseConnection,
+    user: &users::Model,
+    conversation: &conversations::Model,
+    message: &messages::Model,
+    feedback: &Feedback,
+    output: String,
+) -> Result<Option<Uuid>> {
+    let agent = agents::Model::find_by_id(db, conversation.agent_id).await?;
+    let model = match agent.active_learning_model(db).await {
+        Ok(model) => model,
+        Err(ModelError::EntityNotFound) => {
+            tracing::info!(
+                agent_id = agent.id.to_string(),
+                message_id = message.id.to_string(),
+                "agent has no active learning model, not capturing feedback sample",
+            );
+            return Ok(None);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let prompt = match message.prompting_user_message(db).await {
+        Ok(prompt) => prompt,
+        Err(ModelError::EntityNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let corrected = feedback.correction.is_some();
+    let sample = model_training_data::ActiveModel {
+        model_id: ActiveValue::set(model.id),
+        input: ActiveValue::set(Value::String(prompt.content)),
+        output: ActiveValue::set(Value::String(output)),
+        status: ActiveValue::set(SampleStatus::Pending.as_str().to_string()),
+        metadata: ActiveValue::set(Some(json!({
+            "source": FEEDBACK_SOURCE,
+            "agent_id": agent.id,
+            "conversation_id": conversation.id,
+            "message_id": message.id,
+            "prompt_message_id": prompt.id,
+            "user_pid": user.pid,
+            "rating": feedback.rating,
+            "corrected": corrected,
+            "original_output": if corrected { Some(&message.content) } else { None },
+            "comment": feedback.comment,
+        }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(Some(sample.id))
+}
@@ -0,0 +1,99 @@
+use serde_json::Value;
+
+/// Scores a prediction against the expected output. Scores are in `0.0..=1.0`.
+///
+/// Implement this trait to plug a custom scorer into the optimizer or the
+/// evaluator.
+pub trait Metric: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn score(&self, expected: &Value, predicted: &Value) -> f64;
+}
+
+/// 1.0 when the normalized answers are identical, 0.0 otherwise
+pub struct ExactMatch;
+
+impl Metric for ExactMatch {
+    fn name(&self) -> &str {
+        "exact_match"
+    }
+
+    fn score(&self, expected: &Value, predicted: &Value) -> f64 {
+        if normalize(expected) == normalize(predicted) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Token level F1 between the normalized answers
+pub struct F1;
+
+impl Metric for F1 {
+    fn name(&self) -> &str {
+        "f1"
+    }
+
+    fn score(&self, expected: &Value, predicted: &Value) -> f64 {
+        let expected = normalize(expected);
+        let predicted = normalize(predicted);
+        let expected = expected.split_whitespace().collect::<Vec<_>>();
+        let mut predicted = predicted.split_whitespace().collect::<Vec<_>>();
+
+        if expected.is_empty() || predicted.is_empty() {
+            return if expected.len() == predicted.len() {
+                1.0
+            } else {
+                0.0
+            };
+        }
+
+        let predicted_len = predicted.len();
+        let mut common = 0usize;
+        for token in &expected {
+            if let Some(idx) = predicted.iter().position(|p| p == token) {
+                predicted.swap_remove(idx);
+                common += 1;
+            }
+        }
+        if common == 0 {
+            return 0.0;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let (precision, recall) = (
+            common as f64 / predicted_len as f64,
+            common as f64 / expected.len() as f64,
+        );
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+/// Looks up one of the built-in metrics by name
+#[must_use]
+pub fn from_name(name: &str) -> Option<Box<dyn Metric>> {
+    match name {
+        "exact_match" => Some(Box::new(ExactMatch)),
+        "f1" => Some(Box::new(F1)),
+        _ => None,
+    }
+}
+
+/// Reduces an output to comparable answer text: objects with a single field
+/// are unwrapped, strings are lowercased and whitespace is collapsed.
+#[must_use]
+pub fn normalize(value: &Value) -> String {
+    let text = match value {
+        Value::Object(map) if map.len() == 1 => {
+            return map.values().next().map(normalize).unwrap_or_default();
+        }
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,44 @@
+//! Prompt programs backed by `learning_models` rows, and the tooling used to
+//! tune and evaluate them from `model_training_data` examples.
+
+use serde::{Deserialize, Serialize};
+
+pub mod evaluation;
+pub mod feedback;
+pub mod metrics;
+pub mod optimizer;
+pub mod program;
+pub mod split;
+
+/// The `settings.learning` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Share of the training data held out as the dev set
+    #[serde(default = "default_dev_ratio")]
+    pub dev_ratio: f64,
+    /// How far below the active version a candidate may score and still be
+    /// promoted
+    #[serde(default)]
+    pub promotion_tolerance: f64,
+    /// Score from which an example counts as passed when looking for flips
+    #[serde(default = "default_pass_threshold")]
+    pub pass_threshold: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dev_ratio: default_dev_ratio(),
+            promotion_tolerance: 0.0,
+            pass_threshold: default_pass_threshold(),
+        }
+    }
+}
+
+const fn default_dev_ratio() -> f64 {
+    0.2
+}
+
+const fn default_pass_threshold() -> f64 {
+    0.5
+}
@@ -0,0 +1,193 @@
+use loco_rs::{Error, Result};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::{
+    metrics::Metric,
+    program::{Demo, Program},
+    split::split,
+    Config,
+};
+use crate::{
+    llm::Provider,
+    models::{learning_models, model_training_data},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OptimizerConfig {
+    /// How many random demonstration sets to try besides the zero-shot prompt
+    pub candidates: usize,
+    /// Upper bound on the demonstrations in a single prompt
+    pub max_demos: usize,
+    /// Seed for sampling demonstration sets, so runs are reproducible
+    pub seed: u64,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        Self {
+            candidates: 8,
+            max_demos: 4,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateScore {
+    pub demo_ids: Vec<Uuid>,
+    pub score: f64,
+    pub evaluated: usize,
+}
+
+#[derive(Debug)]
+pub struct OptimizationReport {
+    /// The newly created model version
+    pub model: learning_models::Model,
+    pub best: CandidateScore,
+    pub candidates: Vec<CandidateScore>,
+}
+
+/// Bootstraps few-shot demonstrations for a learning model: candidate demo
+/// sets are sampled from the train split, each is scored on the train
+/// examples it does not contain, and the winner is saved as a new model
+/// version. The dev split is left untouched for evaluation.
+pub struct Optimizer<'a> {
+    provider: &'a dyn Provider,
+    metric: &'a dyn Metric,
+    config: OptimizerConfig,
+    dev_ratio: f64,
+}
+
+impl<'a> Optimizer<'a> {
+    #[must_use]
+    pub fn new(provider: &'a dyn Provider, metric: &'a dyn Metric) -> Self {
+        Self {
+            provider,
+            metric,
+            config: OptimizerConfig::default(),
+            dev_ratio: Config::default().dev_ratio,
+        }
+    }
+
+    #[must_use]
+    pub fn with_config(mut self, config: OptimizerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_dev_ratio(mut self, dev_ratio: f64) -> Self {
+        self.dev_ratio = dev_ratio;
+        self
+    }
+
+    /// Runs the optimization and stores the best candidate as a new version
+    /// of `model`.
+    ///
+    /// # Errors
+    ///
+    /// When there is not enough training data, a provider call fails or the
+    /// new version could not be saved
+    pub async fn optimize(
+        &self,
+        db: &DatabaseConnection,
+        model: &learning_models::Model,
+    ) -> Result<OptimizationReport> {
+        let examples = split(model.training_data(db).await?, self.dev_ratio).train;
+        if examples.len() < 2 {
+            return Err(Error::string(
+                "optimizer needs at least two training examples",
+            ));
+        }
+
+        let base = Program::from_model(model)?;
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let demo_count = self.config.max_demos.min(examples.len() - 1);
+
+        let mut demo_sets: Vec<Vec<&model_training_data::Model>> = vec![vec![]];
+        if demo_count > 0 {
+            for _ in 0..self.config.candidates {
+                demo_sets.push(examples.choose_multiple(&mut rng, demo_count).collect());
+            }
+        }
+
+        let mut candidates = Vec::with_capacity(demo_sets.len());
+        let mut best: Option<(usize, f64)> = None;
+        for (idx, demos) in demo_sets.iter().enumerate() {
+            let program = base.with_demos(demos.iter().map(|demo| Demo::from(*demo)).collect());
+            let held_out = examples
+                .iter()
+                .filter(|example| !demos.iter().any(|demo| demo.id == example.id))
+                .collect::<Vec<_>>();
+
+            let score = self.score(&program, &held_out).await?;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((idx, score));
+            }
+            candidates.push(CandidateScore {
+                demo_ids: demos.iter().map(|demo| demo.id).collect(),
+                score,
+                evaluated: held_out.len(),
+            });
+        }
+
+        let (best_idx, _) = best.ok_or_else(|| Error::string("no candidates were evaluated"))?;
+        let best = candidates[best_idx].clone();
+        let program = base.with_demos(
+            demo_sets[best_idx]
+                .iter()
+                .map(|demo| Demo::from(*demo))
+                .collect(),
+        );
+
+        let metrics = json!({
+            "metric": self.metric.name(),
+            "score": best.score,
+            "examples": examples.len(),
+            "candidates": candidates,
+        });
+        let new_version = model
+            .create_version(
+                db,
+                program.merge_into(model.configuration.as_ref()),
+                metrics,
+            )
+            .await?;
+
+        tracing::info!(
+            model_id = new_version.id.to_string(),
+            version = new_version.version,
+            score = best.score,
+            "optimized learning model",
+        );
+
+        Ok(OptimizationReport {
+            model: new_version,
+            best,
+            candidates,
+        })
+    }
+
+    async fn score(
+        &self,
+        program: &Program,
+        examples: &[&model_training_data::Model],
+    ) -> Result<f64> {
+        if examples.is_empty() {
+            return Ok(0.0);
+        }
+
+        let mut total = 0.0;
+        for example in examples {
+            let predicted = program.predict(self.provider, &example.input).await?;
+            total += self.metric.score(&example.output, &predicted);
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        Ok(total / examples.len() as f64)
+    }
+}
@@ -0,0 +1,115 @@
+use loco_rs::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    llm::{ChatMessage, CompletionRequest, Provider},
+    models::{learning_models, model_training_data},
+};
+
+/// An input/output pair shown to the model before the real input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Demo {
+    pub input: Value,
+    pub output: Value,
+}
+
+impl From<&model_training_data::Model> for Demo {
+    fn from(example: &model_training_data::Model) -> Self {
+        Self {
+            input: example.input.clone(),
+            output: example.output.clone(),
+        }
+    }
+}
+
+/// The prompt a learning model version runs: instructions plus few-shot
+/// demonstrations. Stored in `learning_models.configuration`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Program {
+    #[serde(default)]
+    pub instructions: String,
+    #[serde(default)]
+    pub demos: Vec<Demo>,
+}
+
+impl Program {
+    /// Reads the program out of a learning model configuration. Missing
+    /// fields fall back to an empty program.
+    ///
+    /// # Errors
+    ///
+    /// When the configuration has fields of the wrong type
+    pub fn from_model(model: &learning_models::Model) -> Result<Self> {
+        match &model.configuration {
+            Some(configuration) => Ok(serde_json::from_value(configuration.clone())?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Writes the program into an existing configuration, keeping any
+    /// unrelated keys.
+    #[must_use]
+    pub fn merge_into(&self, configuration: Option<&Value>) -> Value {
+        let mut merged = match configuration {
+            Some(Value::Object(map)) => map.clone(),
+            _ => serde_json::Map::new(),
+        };
+        merged.insert(
+            "instructions".to_string(),
+            Value::String(self.instructions.clone()),
+        );
+        merged.insert(
+            "demos".to_string(),
+            serde_json::to_value(&self.demos).unwrap_or_default(),
+        );
+        Value::Object(merged)
+    }
+
+    #[must_use]
+    pub fn with_demos(&self, demos: Vec<Demo>) -> Self {
+        Self {
+            instructions: self.instructions.clone(),
+            demos,
+        }
+    }
+
+    /// Renders the chat transcript sent to the provider for the given input
+    #[must_use]
+    pub fn messages(&self, input: &Value) -> Vec<ChatMessage> {
+        let mut messages = Vec::with_capacity(self.demos.len() * 2 + 2);
+        if !self.instructions.is_empty() {
+            messages.push(ChatMessage::system(&self.instructions));
+        }
+        for demo in &self.demos {
+            messages.push(ChatMessage::user(render(&demo.input)));
+            messages.push(ChatMessage::assistant(render(&demo.output)));
+        }
+        messages.push(ChatMessage::user(render(input)));
+        messages
+    }
+
+    /// Runs the program against the provider. Replies that parse as JSON are
+    /// returned as such, anything else as a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// When the provider call fails
+    pub async fn predict(&self, provider: &dyn Provider, input: &Value) -> Result<Value> {
+        let completion = provider
+            .complete(&CompletionRequest::new(self.messages(input)))
+            .await?;
+        let content = completion.content.trim();
+
+        Ok(serde_json::from_str(content).unwrap_or_else(|_| Value::String(content.to_string())))
+    }
+}
+
+/// Strings are sent as-is, everything else as compact JSON
+#[must_use]
+pub fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,66 @@
+use uuid::Uuid;
+
+use crate::models::model_training_data;
+
+const BUCKETS: u64 = 10_000;
+
+#[derive(Debug, Default)]
+pub struct Split {
+    pub train: Vec<model_training_data::Model>,
+    pub dev: Vec<model_training_data::Model>,
+}
+
+/// Splits examples into train and dev sets. The split only depends on each
+/// example id, so an example never changes sides as more data is added.
+///
+/// With two or more examples both sides are guaranteed to be non-empty.
+#[must_use]
+pub fn split(examples: Vec<model_training_data::Model>, dev_ratio: f64) -> Split {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let cutoff = (dev_ratio.clamp(0.0, 1.0) * BUCKETS as f64) as u64;
+
+    let (mut dev, mut train): (Vec<_>, Vec<_>) = examples
+        .into_iter()
+        .partition(|example| bucket(&example.id) < cutoff);
+
+    if dev.is_empty() && train.len() > 1 {
+        if let Some(idx) = position_by_bucket(&train, std::cmp::Ordering::Less) {
+            dev.push(train.remove(idx));
+        }
+    } else if train.is_empty() && dev.len() > 1 {
+        if let Some(idx) = position_by_bucket(&dev, std::cmp::Ordering::Greater) {
+            train.push(dev.remove(idx));
+        }
+    }
+
+    Split { train, dev }
+}
+
+/// Stable bucket of an example id, using FNV-1a so it does not change between
+/// builds or platforms
+#[must_use]
+pub fn bucket(id: &Uuid) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in id.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash % BUCKETS
+}
+
+fn position_by_bucket(
+    examples: &[model_training_data::Model],
+    wanted: std::cmp::Ordering,
+) -> Option<usize> {
+    (0..examples.len()).reduce(|best, idx| {
+        if bucket(&examples[idx].id).cmp(&bucket(&examples[best].id)) == wanted {
+            idx
+        } else {
+            best
+        }
+    })
+}
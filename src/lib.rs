@@ -1,8 +1,28 @@
+pub mod accounts;
 pub mod app;
+pub mod auth;
+pub mod bundles;
+pub mod bus;
+pub mod common;
 pub mod controllers;
+pub mod fetch;
 pub mod initializers;
+pub mod knowledge;
+pub mod learning;
+pub mod llm;
+pub mod lockout;
 pub mod mailers;
+pub mod mfa;
 pub mod models;
+pub mod oidc;
+pub mod orchestration;
+pub mod plans;
+pub mod policy;
+pub mod runtime;
+pub mod scheduling;
+pub mod sessions;
 pub mod tasks;
+pub mod tools;
+pub mod usage;
 pub mod views;
 pub mod workers;
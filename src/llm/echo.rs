@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use loco_rs::Result;
+
+use super::{Completion, CompletionRequest, Provider, Usage};
+
+/// Replies with the content of the last user message. Usage is reported
+/// with one token per word.
+pub struct Echo;
+
+#[async_trait]
+impl Provider for Echo {
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion> {
+        let content = request
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(|message| message.content.clone())
+            .unwrap_or_default();
+
+        let words = |text: &str| u32::try_from(text.split_whitespace().count()).unwrap_or(u32::MAX);
+        let usage = Usage {
+            provider: "echo".to_string(),
+            model: "echo".to_string(),
+            prompt_tokens: request
+                .messages
+                .iter()
+                .map(|message| words(&message.content))
+                .sum(),
+            completion_tokens: words(&content),
+        };
+
+        Ok(Completion {
+            content,
+            usage: Some(usage),
+            ..Default::default()
+        })
+    }
+}
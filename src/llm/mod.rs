@@ -0,0 +1,245 @@
+//! Chat completion providers used by agents and the learning subsystem.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use loco_rs::{app::AppContext, Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{common::settings::Settings, usage};
+
+pub mod echo;
+pub mod openai;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    /// Tools the assistant asked to run in this turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// For `tool` messages, the call this message is the result of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    #[must_use]
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    /// An assistant turn that asks for tools to be run
+    #[must_use]
+    pub fn tool_request(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+            tool_calls,
+            ..Default::default()
+        }
+    }
+
+    /// The result of running a tool, answering the call with the given id
+    #[must_use]
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: content.into(),
+            tool_call_id: Some(tool_call_id.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A tool the model may call. `parameters` is a JSON schema describing the
+/// arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: Value,
+}
+
+/// A request from the model to run a tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+}
+
+impl CompletionRequest {
+    #[must_use]
+    pub fn new(messages: Vec<ChatMessage>) -> Self {
+        Self {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Completion {
+    pub content: String,
+    /// Tools the model wants run before it gives its final answer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// What the call consumed, when the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// Tokens billed for a single call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    /// The provider the call went to, such as `open_ai`
+    pub provider: String,
+    /// The model that answered, as reported by the provider
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl Completion {
+    #[must_use]
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A backend able to answer chat completion requests
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Sends the conversation to the model and returns its reply
+    ///
+    /// # Errors
+    ///
+    /// When the provider could not be reached or returned an invalid response
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Any OpenAI compatible chat completions API
+    OpenAi,
+    /// Replies with the last user message. Useful for development and tests.
+    #[default]
+    Echo,
+}
+
+/// The `settings.llm` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// What tokens cost, keyed by model name
+    #[serde(default)]
+    pub prices: usage::Prices,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::default(),
+            base_url: default_base_url(),
+            api_key: None,
+            model: default_model(),
+            timeout_secs: default_timeout_secs(),
+            prices: usage::Prices::default(),
+        }
+    }
+}
+
+fn default_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_model() -> String {
+    "gpt-4o-mini".to_string()
+}
+
+const fn default_timeout_secs() -> u64 {
+    60
+}
+
+/// Builds the provider described by the config
+///
+/// # Errors
+///
+/// When the HTTP client could not be created
+pub fn from_config(config: &Config) -> Result<Box<dyn Provider>> {
+    match config.provider {
+        ProviderKind::Echo => Ok(Box::new(echo::Echo)),
+        ProviderKind::OpenAi => Ok(Box::new(openai::OpenAi::new(
+            &config.base_url,
+            config.api_key.as_deref(),
+            &config.model,
+            Duration::from_secs(config.timeout_secs),
+        )?)),
+    }
+}
+
+/// Builds the provider configured for the running app
+///
+/// # Errors
+///
+/// When the settings are invalid or the provider could not be created
+pub fn from_context(ctx: &AppContext) -> Result<Box<dyn Provider>> {
+    from_config(&Settings::from_context(ctx)?.llm)
+}
+
+pub(crate) fn provider_error(err: impl std::fmt::Display) -> Error {
+    Error::string(&format!("llm provider error: {err}"))
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use loco_rs::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+    provider_error, ChatMessage, Completion, CompletionRequest, Provider, ToolCall, Usage,
+};
+
+/// Client for OpenAI compatible `/chat/completions` endpoints
+pub struct OpenAi {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    model: Option<String>,
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ResponseToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseToolCall {
+    id: String,
+    function: ResponseFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseFunction {
+    name: String,
+    /// JSON encoded arguments, as produced by the model
+    arguments: String,
+}
+
+impl From<ResponseToolCall> for ToolCall {
+    fn from(call: ResponseToolCall) -> Self {
+        // arguments that are not valid JSON are passed on as a string so
+        // that schema validation can reject them with a useful message
+        let arguments = serde_json::from_str(&call.function.arguments)
+            .unwrap_or(Value::String(call.function.arguments));
+        Self {
+            id: call.id,
+            name: call.function.name,
+            arguments,
+        }
+    }
+}
+
+/// Converts a message into the wire format, where tool call arguments are
+/// JSON encoded strings
+fn wire_message(message: &ChatMessage) -> Value {
+    let mut value = json!({
+        "role": message.role,
+        "content": message.content,
+    });
+    if !message.tool_calls.is_empty() {
+        value["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.name,
+                        "arguments": call.arguments.to_string(),
+                    },
+                })
+            })
+            .collect();
+    }
+    if let Some(tool_call_id) = &message.tool_call_id {
+        value["tool_call_id"] = json!(tool_call_id);
+    }
+    value
+}
+
+impl OpenAi {
+    /// Creates a new client
+    ///
+    /// # Errors
+    ///
+    /// When the HTTP client could not be built
+    pub fn new(
+        base_url: &str,
+        api_key: Option<&str>,
+        model: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(provider_error)?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key
+                .filter(|key| !key.is_empty())
+                .map(ToString::to_string),
+            model: model.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAi {
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion> {
+        let mut body = json!({
+            "model": self.model,
+            "messages": request.messages.iter().map(wire_message).collect::<Vec<_>>(),
+        });
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        },
+                    })
+                })
+                .collect();
+        }
+
+        let mut req = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            req = req.bearer_auth(api_key);
+        }
+
+        let response: ChatResponse = req
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        let usage = response.usage.map(|usage| Usage {
+            provider: "open_ai".to_string(),
+            model: response.model.unwrap_or_else(|| self.model.clone()),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        });
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| provider_error("response contained no choices"))?;
+
+        Ok(Completion {
+            content: message.content.unwrap_or_default(),
+            tool_calls: message.tool_calls.into_iter().map(Into::into).collect(),
+            usage,
+        })
+    }
+}
@@ -0,0 +1,240 @@
+//! Brute-force protection for logins. Failed attempts are counted per
+//! account, by the email tried, and per client address. Every failure is
+//! answered a little later than the one before, and too many of them lock
+//! the account or the address out for a while.
+//!
+//! Accounts are counted by email whether a user has it or not, and unknown
+//! emails still have a password checked against a stand-in hash, so the
+//! answers to known and unknown emails look and take the same.
+//!
+//! The address counted is the one the connection comes from. Forwarded
+//! headers only name it when that connection is from a trusted proxy, as
+//! anyone else can send them with whatever address they like.
+
+use std::{net::IpAddr, time::Duration};
+
+use axum::http::HeaderMap;
+use chrono::{Local, NaiveDateTime};
+use loco_rs::{hash, Result};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::models::login_throttles::{self, Scope};
+
+/// A hash no password is expected to match, checked for unknown emails so
+/// they take as long to refuse as wrong passwords
+const STAND_IN_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$ETQBx4rTgNAZhSaeYZKOZg$eYTdH26CRT6nUJtacLDEboP0li6xUwUF/q5nSlQ8uuc";
+
+/// The `settings.lockout` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Failures in a row that lock an account
+    #[serde(default = "default_account_max_failures")]
+    pub account_max_failures: i32,
+    /// Failures that lock a client address, across all accounts
+    #[serde(default = "default_ip_max_failures")]
+    pub ip_max_failures: i32,
+    /// How long failures are remembered after the last one
+    #[serde(default = "default_window_secs")]
+    pub window_secs: i64,
+    /// How long a lockout lasts
+    #[serde(default = "default_lockout_secs")]
+    pub lockout_secs: i64,
+    /// The delay after the first failure, doubled with every further one
+    #[serde(default = "default_delay_base_ms")]
+    pub delay_base_ms: u64,
+    /// The longest delay
+    #[serde(default = "default_delay_max_ms")]
+    pub delay_max_ms: u64,
+    /// Proxies in front of the app, whose `X-Forwarded-For` and `X-Real-IP`
+    /// headers are believed
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            account_max_failures: default_account_max_failures(),
+            ip_max_failures: default_ip_max_failures(),
+            window_secs: default_window_secs(),
+            lockout_secs: default_lockout_secs(),
+            delay_base_ms: default_delay_base_ms(),
+            delay_max_ms: default_delay_max_ms(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+const fn default_account_max_failures() -> i32 {
+    5
+}
+
+const fn default_ip_max_failures() -> i32 {
+    50
+}
+
+const fn default_window_secs() -> i64 {
+    900
+}
+
+const fn default_lockout_secs() -> i64 {
+    900
+}
+
+const fn default_delay_base_ms() -> u64 {
+    250
+}
+
+const fn default_delay_max_ms() -> u64 {
+    4000
+}
+
+impl Config {
+    /// The delay after the given number of failures in a row
+    #[must_use]
+    pub fn delay(&self, failures: i32) -> Duration {
+        let doublings = u32::try_from(failures.saturating_sub(1))
+            .unwrap_or(0)
+            .min(16);
+        Duration::from_millis(
+            self.delay_base_ms
+                .saturating_mul(1 << doublings)
+                .min(self.delay_max_ms),
+        )
+    }
+
+    /// The address of the client behind a connection from `peer`. Past a
+    /// trusted proxy, that is the last address in `X-Forwarded-For` not of
+    /// another trusted proxy, or else `X-Real-IP`.
+    #[must_use]
+    pub fn client_address(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.trusts(peer) {
+            return peer;
+        }
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let forwarded = header("x-forwarded-for").and_then(|list| {
+            list.rsplit(',')
+                .map_while(|ip| ip.trim().parse::<IpAddr>().ok())
+                .find(|ip| !self.trusts(*ip))
+        });
+        forwarded
+            .or_else(|| header("x-real-ip").and_then(|ip| ip.trim().parse().ok()))
+            .unwrap_or(peer)
+    }
+
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.contains(&ip.to_canonical())
+    }
+}
+
+/// Who is trying to log in
+#[derive(Debug, Clone, Copy)]
+pub struct Attempt<'a> {
+    pub email: &'a str,
+    pub ip_address: Option<&'a str>,
+}
+
+/// The key failures against the account of `email` are counted by, the
+/// same whatever the case and spacing it is typed with
+#[must_use]
+pub fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+impl Attempt<'_> {
+    fn keys(&self) -> Vec<(Scope, String)> {
+        let mut keys = vec![(Scope::Account, account_key(self.email))];
+        if let Some(ip) = self.ip_address {
+            keys.push((Scope::Ip, ip.to_string()));
+        }
+        keys
+    }
+}
+
+/// What a failed attempt led to
+#[derive(Debug, Clone, Copy)]
+pub struct Failure {
+    /// How long to wait before answering
+    pub delay: Duration,
+    /// Set when this attempt locked the account
+    pub account_locked_until: Option<NaiveDateTime>,
+}
+
+/// Until when the account or the address of the attempt is locked out, if
+/// either is now
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn locked_until(
+    db: &DatabaseConnection,
+    attempt: Attempt<'_>,
+) -> Result<Option<NaiveDateTime>> {
+    let now = Local::now().naive_local();
+    let mut until = None;
+    for (scope, key) in attempt.keys() {
+        if let Some(throttle) = login_throttles::Model::find_for(db, scope, &key).await? {
+            until = until.max(throttle.locked_until(now));
+        }
+    }
+    Ok(until)
+}
+
+/// Counts a failed attempt against the account and the address
+///
+/// # Errors
+///
+/// When could not save the counts
+pub async fn failed(
+    db: &DatabaseConnection,
+    config: &Config,
+    attempt: Attempt<'_>,
+) -> Result<Failure> {
+    let window = chrono::Duration::seconds(config.window_secs);
+    let lockout = chrono::Duration::seconds(config.lockout_secs);
+    let mut failure = Failure {
+        delay: Duration::ZERO,
+        account_locked_until: None,
+    };
+    for (scope, key) in attempt.keys() {
+        let max_failures = if scope == Scope::Account {
+            config.account_max_failures
+        } else {
+            config.ip_max_failures
+        };
+        let throttle =
+            login_throttles::Model::record_failure(db, scope, &key, max_failures, window, lockout)
+                .await?;
+        failure.delay = failure.delay.max(config.delay(throttle.failures));
+        if scope == Scope::Account && throttle.failures == max_failures {
+            failure.account_locked_until = throttle.locked_until;
+        }
+    }
+    tracing::info!(
+        email = attempt.email,
+        ip_address = attempt.ip_address,
+        locked = failure.account_locked_until.is_some(),
+        "failed login"
+    );
+    Ok(failure)
+}
+
+/// Forgets the failures of the account after a successful login. Those of
+/// the address stay, so one good account does not cover for guessing at
+/// others.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn succeeded(db: &DatabaseConnection, attempt: Attempt<'_>) -> Result<()> {
+    login_throttles::Model::clear(db, Scope::Account, &account_key(attempt.email)).await?;
+    Ok(())
+}
+
+/// Checks the password against a stand-in hash, for attempts on emails no
+/// user has, so they take as long as a wrong password
+pub fn check_stand_in(password: &str) {
+    let _ = hash::verify_password(password, STAND_IN_HASH);
+}
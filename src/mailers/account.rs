@@ -0,0 +1,100 @@
+// account mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::json;
+
+use crate::models::{data_exports, users};
+
+static deletion_scheduled: Dir<'_> = include_dir!("src/mailers/account/deletion_scheduled");
+static deleted: Dir<'_> = include_dir!("src/mailers/account/deleted");
+static export_ready: Dir<'_> = include_dir!("src/mailers/account/export_ready");
+
+#[allow(clippy::module_name_repetitions)]
+pub struct AccountMailer {}
+impl Mailer for AccountMailer {}
+impl AccountMailer {
+    /// Confirms the user asked for their account to be deleted, and when it
+    /// will be
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_deletion_scheduled(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        let delete_after = user
+            .delete_after
+            .map(|at| at.to_rfc2822())
+            .unwrap_or_default();
+        Self::mail_template(
+            ctx,
+            &deletion_scheduled,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "deleteAfter": delete_after,
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Tells a user whose account was erased that it is done
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_deleted(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &deleted,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sends the link to download a data export the user asked for
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_export_ready(
+        ctx: &AppContext,
+        user: &users::Model,
+        export: &data_exports::Model,
+        download_token: &str,
+    ) -> Result<()> {
+        let expires_at = export
+            .expires_at
+            .map(|at| at.to_string())
+            .unwrap_or_default();
+        Self::mail_template(
+            ctx,
+            &export_ready,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "downloadToken": download_token,
+                  "expiresAt": expires_at,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>As you asked, your account and the data tied to it have been erased. This is the last email you will get from us.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Your account was deleted
\ No newline at end of file
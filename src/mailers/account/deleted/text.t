@@ -0,0 +1,2 @@
+Hey {{name}},
+  As you asked, your account and the data tied to it have been erased. This is the last email you will get from us.
@@ -0,0 +1,10 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>You asked for your account to be deleted. It will be erased for good on {{deleteAfter}}, along with your conversations and what was captured from them.</p>
+  <p>Changed your mind? Sign in and cancel the deletion before then. If this was not you, cancel it and reset your password now.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Your account will be deleted
\ No newline at end of file
@@ -0,0 +1,4 @@
+Hey {{name}},
+  You asked for your account to be deleted. It will be erased for good on {{deleteAfter}}, along with your conversations and what was captured from them.
+
+  Changed your mind? Sign in and cancel the deletion before then. If this was not you, cancel it and reset your password now.
@@ -0,0 +1,13 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>The copy of your data you asked for is ready. Download it here:</p>
+  <a href="http://{{domain}}/api/me/export/{{downloadToken}}">
+    Download your data
+  </a>
+  <p>The link works until {{expiresAt}}. If you did not ask for this, reset your password now.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Your data export is ready
\ No newline at end of file
@@ -0,0 +1,5 @@
+Hey {{name}},
+  The copy of your data you asked for is ready. Download it here:
+  http://{{domain}}/api/me/export/{{downloadToken}}
+
+  The link works until {{expiresAt}}. If you did not ask for this, reset your password now.
@@ -0,0 +1,47 @@
+// approvals mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::json;
+
+use crate::models::{agents, tool_approvals, users};
+
+static requested: Dir<'_> = include_dir!("src/mailers/approvals/requested");
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ApprovalMailer {}
+impl Mailer for ApprovalMailer {}
+impl ApprovalMailer {
+    /// Asks the conversation owner to approve a tool call
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_requested(
+        ctx: &AppContext,
+        user: &users::Model,
+        agent: &agents::Model,
+        approval: &tool_approvals::Model,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &requested,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "agent": agent.name,
+                  "tool": approval.name,
+                  "arguments": serde_json::to_string_pretty(&approval.arguments)?,
+                  "expiresAt": approval.expires_at.to_string(),
+                  "approvalId": approval.id,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+;<html>
+
+<body>
+  Dear {{name}},
+  <p>{{agent}} wants to run the tool <strong>{{tool}}</strong> with these arguments:</p>
+  <pre>{{arguments}}</pre>
+  <p>The request expires at {{expiresAt}}.</p>
+  <a href="{{domain}}/approvals/{{approvalId}}">
+    Review the request
+  </a>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
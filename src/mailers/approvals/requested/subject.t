@@ -0,0 +1 @@
+{{agent}} is waiting for your approval
\ No newline at end of file
@@ -0,0 +1,8 @@
+Dear {{name}},
+  {{agent}} wants to run the tool "{{tool}}" with these arguments:
+
+  {{arguments}}
+
+  The request expires at {{expiresAt}}. Review it here:
+
+  {{domain}}/approvals/{{approvalId}}
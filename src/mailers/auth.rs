@@ -8,6 +8,10 @@ use crate::models::users;
 
 static welcome: Dir<'_> = include_dir!("src/mailers/auth/welcome");
 static forgot: Dir<'_> = include_dir!("src/mailers/auth/forgot");
+static locked: Dir<'_> = include_dir!("src/mailers/auth/locked");
+static change_email: Dir<'_> = include_dir!("src/mailers/auth/change_email");
+static email_changing: Dir<'_> = include_dir!("src/mailers/auth/email_changing");
+static magic_link: Dir<'_> = include_dir!("src/mailers/auth/magic_link");
 // #[derive(Mailer)] // -- disabled for faster build speed. it works. but lets
 // move on for now.
 
@@ -15,12 +19,17 @@ static forgot: Dir<'_> = include_dir!("src/mailers/auth/forgot");
 pub struct AuthMailer {}
 impl Mailer for AuthMailer {}
 impl AuthMailer {
-    /// Sending welcome email the the given user
+    /// Sending welcome email the the given user, with the link to verify
+    /// their email
     ///
     /// # Errors
     ///
     /// When email sending is failed
-    pub async fn send_welcome(ctx: &AppContext, user: &users::Model) -> Result<()> {
+    pub async fn send_welcome(
+        ctx: &AppContext,
+        user: &users::Model,
+        verify_token: &str,
+    ) -> Result<()> {
         Self::mail_template(
             ctx,
             &welcome,
@@ -28,7 +37,7 @@ impl AuthMailer {
                 to: user.email.to_string(),
                 locals: json!({
                   "name": user.name,
-                  "verifyToken": user.email_verification_token,
+                  "verifyToken": verify_token,
                   "domain": ctx.config.server.full_url()
                 }),
                 ..Default::default()
@@ -44,7 +53,11 @@ impl AuthMailer {
     /// # Errors
     ///
     /// When email sending is failed
-    pub async fn forgot_password(ctx: &AppContext, user: &users::Model) -> Result<()> {
+    pub async fn forgot_password(
+        ctx: &AppContext,
+        user: &users::Model,
+        reset_token: &str,
+    ) -> Result<()> {
         Self::mail_template(
             ctx,
             &forgot,
@@ -52,7 +65,122 @@ impl AuthMailer {
                 to: user.email.to_string(),
                 locals: json!({
                   "name": user.name,
-                  "resetToken": user.reset_token,
+                  "resetToken": reset_token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Tells the user signing in is paused after too many failed attempts
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_locked_out(
+        ctx: &AppContext,
+        user: &users::Model,
+        locked_until: &chrono::NaiveDateTime,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &locked,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "lockedUntil": locked_until.to_string(),
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sends the link confirming the address the user asked to move to,
+    /// to that address
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_email_change(
+        ctx: &AppContext,
+        user: &users::Model,
+        new_email: &str,
+        confirm_token: &str,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &change_email,
+            mailer::Args {
+                to: new_email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "confirmToken": confirm_token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Tells the user, at the address they have now, that a move to another
+    /// one was asked for
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_email_changing(
+        ctx: &AppContext,
+        user: &users::Model,
+        new_email: &str,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &email_changing,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "newEmail": new_email,
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sends a link that logs the user in without a password
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_magic_link(
+        ctx: &AppContext,
+        user: &users::Model,
+        login_token: &str,
+        ttl: chrono::Duration,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &magic_link,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "loginToken": login_token,
+                  "minutes": ttl.num_minutes(),
                   "domain": ctx.config.server.full_url()
                 }),
                 ..Default::default()
@@ -0,0 +1,13 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>Confirm that this is the new address of your account by clicking the link below:</p>
+  <a href="http://{{domain}}/change-email#{{confirmToken}}">
+    Confirm Your New Email
+  </a>
+  <p>If you did not ask for this, ignore this email and nothing changes.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
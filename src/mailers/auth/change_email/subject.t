@@ -0,0 +1 @@
+Confirm your new email
\ No newline at end of file
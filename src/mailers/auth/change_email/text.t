@@ -0,0 +1,6 @@
+Hey {{name}},
+  Confirm that this is the new address of your account with the link below:
+
+  http://localhost/change-email#{{confirmToken}}
+
+  If you did not ask for this, ignore this email and nothing changes.
@@ -0,0 +1,10 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>Someone asked to move your account to {{newEmail}}. It moves once that address is confirmed.</p>
+  <p>If this was not you, reset your password now; until the new address is confirmed, this one stays.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Your account email is being changed
\ No newline at end of file
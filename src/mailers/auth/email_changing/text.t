@@ -0,0 +1,4 @@
+Hey {{name}},
+  Someone asked to move your account to {{newEmail}}. It moves once that address is confirmed.
+
+  If this was not you, reset your password now; until the new address is confirmed, this one stays.
@@ -0,0 +1,10 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>There were too many failed attempts to sign in to your account, so signing in is paused until {{lockedUntil}}.</p>
+  <p>If this was not you, someone may be guessing your password. Consider resetting it once the pause is over.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Signing in to your account is paused
\ No newline at end of file
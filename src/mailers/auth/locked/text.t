@@ -0,0 +1,4 @@
+Hey {{name}},
+  There were too many failed attempts to sign in to your account, so signing in is paused until {{lockedUntil}}.
+
+  If this was not you, someone may be guessing your password. Consider resetting it once the pause is over.
@@ -0,0 +1,13 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  <p>Log in to your account by clicking the link below. It works once, for the next {{minutes}} minutes.</p>
+  <a href="http://{{domain}}/magic-link#{{loginToken}}">
+    Log In
+  </a>
+  <p>If you did not ask for this, ignore this email.</p>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1,6 @@
+Hey {{name}},
+  Log in to your account with the link below. It works once, for the next {{minutes}} minutes.
+
+  http://localhost/magic-link#{{loginToken}}
+
+  If you did not ask for this, ignore this email.
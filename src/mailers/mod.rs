@@ -1 +1,4 @@
+pub mod account;
+pub mod approvals;
 pub mod auth;
+pub mod organizations;
@@ -0,0 +1,46 @@
+// organizations mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::json;
+
+use crate::models::{invitations, organizations, users};
+
+static invited: Dir<'_> = include_dir!("src/mailers/organizations/invited");
+
+#[allow(clippy::module_name_repetitions)]
+pub struct OrganizationMailer {}
+impl Mailer for OrganizationMailer {}
+impl OrganizationMailer {
+    /// Invites someone to join the organization
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_invitation(
+        ctx: &AppContext,
+        organization: &organizations::Model,
+        inviter: &users::Model,
+        invitation: &invitations::Model,
+    ) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &invited,
+            mailer::Args {
+                to: invitation.email.to_string(),
+                locals: json!({
+                  "inviter": inviter.name,
+                  "organization": organization.name,
+                  "role": invitation.role,
+                  "token": invitation.token,
+                  "expiresAt": invitation.expires_at.to_string(),
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+;<html>
+
+<body>
+  Hello,
+  <p>{{inviter}} invited you to join <strong>{{organization}}</strong> as {{role}}.</p>
+  <p>The invitation expires at {{expiresAt}}.</p>
+  <a href="{{domain}}/invitations#{{token}}">
+    Accept the invitation
+  </a>
+  <p>Best regards,<br>The Loco Team</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+{{inviter}} invited you to {{organization}}
\ No newline at end of file
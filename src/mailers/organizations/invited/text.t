@@ -0,0 +1,6 @@
+Hello,
+  {{inviter}} invited you to join {{organization}} as {{role}}.
+
+  The invitation expires at {{expiresAt}}. Accept it here:
+
+  {{domain}}/invitations#{{token}}
@@ -0,0 +1,131 @@
+//! Two factor sign in with authenticator apps. A user enrolls by storing a
+//! TOTP secret and confirming it with a code, which turns it on and hands
+//! out single use recovery codes.
+//!
+//! Once on, logging in with the password only answers with a challenge
+//! token: a short-lived JWT signed with a key of its own, so it is never
+//! taken for an access token. Exchanging it along with a code from the
+//! authenticator, or a recovery code, completes the login.
+
+use chrono::Utc;
+use loco_rs::{auth::jwt, config::JWT as JwtConfig, Error, Result};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    common::secrets,
+    models::{recovery_codes, users},
+};
+
+pub mod totp;
+
+/// The claim telling challenge tokens apart
+const PURPOSE: &str = "mfa_challenge";
+
+/// The `settings.mfa` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// The name authenticator apps list the account under
+    #[serde(default = "default_issuer")]
+    pub issuer: String,
+    /// How long a challenge token may be exchanged
+    #[serde(default = "default_challenge_ttl_secs")]
+    pub challenge_ttl_secs: u64,
+    /// How many recovery codes are handed out at once
+    #[serde(default = "default_recovery_codes")]
+    pub recovery_codes: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            issuer: default_issuer(),
+            challenge_ttl_secs: default_challenge_ttl_secs(),
+            recovery_codes: default_recovery_codes(),
+        }
+    }
+}
+
+fn default_issuer() -> String {
+    "myapp".to_string()
+}
+
+const fn default_challenge_ttl_secs() -> u64 {
+    300
+}
+
+const fn default_recovery_codes() -> usize {
+    10
+}
+
+/// Challenge tokens are signed with a key derived from the access token
+/// secret, which the access token extractor rejects. Hex, since the JWT
+/// secret is read as base64.
+fn challenge_secret(jwt_config: &JwtConfig) -> String {
+    secrets::hash(&format!("{}:{PURPOSE}", jwt_config.secret))
+}
+
+/// A token the user trades for an access token along with a second factor
+///
+/// # Errors
+///
+/// When could not sign the token
+pub fn issue_challenge(
+    jwt_config: &JwtConfig,
+    config: &Config,
+    user: &users::Model,
+) -> Result<String> {
+    Ok(user.generate_jwt_with_claims(
+        &challenge_secret(jwt_config),
+        &config.challenge_ttl_secs,
+        json!({ "purpose": PURPOSE }),
+    )?)
+}
+
+/// The pid of the user a challenge token was issued to
+///
+/// # Errors
+///
+/// `Unauthorized` when the token is malformed, expired or not a challenge
+pub fn verify_challenge(jwt_config: &JwtConfig, token: &str) -> Result<String> {
+    let unauthorized = || Error::Unauthorized("invalid challenge".to_string());
+    let claims = jwt::JWT::new(&challenge_secret(jwt_config))
+        .validate(token)
+        .map_err(|_| unauthorized())?
+        .claims;
+    let purpose = claims
+        .claims
+        .as_ref()
+        .and_then(|claims| claims.get("purpose"))
+        .and_then(|purpose| purpose.as_str());
+    if purpose != Some(PURPOSE) {
+        return Err(unauthorized());
+    }
+    Ok(claims.pid)
+}
+
+/// Checks a code from the authenticator against the stored secret, enabled
+/// or not, and returns the time step it was for
+#[must_use]
+pub fn check_totp(user: &users::Model, code: &str) -> Option<i64> {
+    let secret = user.totp_secret.as_deref()?;
+    totp::verify(secret, code, Utc::now().timestamp(), user.totp_last_step)
+}
+
+/// Accepts a second factor of a user with two factor sign in on: a code
+/// from the authenticator, never the same one twice, or an unused
+/// recovery code, which is used up
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn accept(db: &DatabaseConnection, user: &users::Model, code: &str) -> Result<bool> {
+    if !user.has_totp() {
+        return Ok(false);
+    }
+    if let Some(step) = check_totp(user, code) {
+        return Ok(user.claim_totp_step(db, step).await?);
+    }
+    Ok(recovery_codes::Model::redeem(db, user.id, code).await?)
+}
@@ -1,3 +1,32 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.0
 
+pub use super::agent_capabilities::Entity as AgentCapabilities;
+pub use super::agent_versions::Entity as AgentVersions;
+pub use super::agents::Entity as Agents;
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::bus_messages::Entity as BusMessages;
+pub use super::conversations::Entity as Conversations;
+pub use super::data_exports::Entity as DataExports;
+pub use super::identities::Entity as Identities;
+pub use super::invitations::Entity as Invitations;
+pub use super::knowledge_base::Entity as KnowledgeBase;
+pub use super::knowledge_items::Entity as KnowledgeItems;
+pub use super::learning_models::Entity as LearningModels;
+pub use super::login_throttles::Entity as LoginThrottles;
+pub use super::magic_links::Entity as MagicLinks;
+pub use super::memberships::Entity as Memberships;
+pub use super::memories::Entity as Memories;
+pub use super::messages::Entity as Messages;
+pub use super::model_evaluations::Entity as ModelEvaluations;
+pub use super::model_training_data::Entity as ModelTrainingData;
+pub use super::oidc_requests::Entity as OidcRequests;
+pub use super::organizations::Entity as Organizations;
+pub use super::previous_passwords::Entity as PreviousPasswords;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::sessions::Entity as Sessions;
+pub use super::task_dependencies::Entity as TaskDependencies;
+pub use super::task_schedules::Entity as TaskSchedules;
+pub use super::tasks::Entity as Tasks;
+pub use super::tool_approvals::Entity as ToolApprovals;
+pub use super::usage_records::Entity as UsageRecords;
 pub use super::users::Entity as Users;
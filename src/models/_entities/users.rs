@@ -17,12 +17,105 @@ pub struct Model {
     #[sea_orm(unique)]
     pub api_key: String,
     pub name: String,
+    #[serde(skip_serializing)]
     pub reset_token: Option<String>,
     pub reset_sent_at: Option<DateTimeWithTimeZone>,
+    #[serde(skip_serializing)]
     pub email_verification_token: Option<String>,
     pub email_verification_sent_at: Option<DateTimeWithTimeZone>,
     pub email_verified_at: Option<DateTimeWithTimeZone>,
+    pub plan: String,
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeWithTimeZone>,
+    pub totp_last_step: Option<i64>,
+    pub pending_email: Option<String>,
+    #[serde(skip_serializing)]
+    pub email_change_token: Option<String>,
+    pub email_change_sent_at: Option<DateTimeWithTimeZone>,
+    pub delete_after: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::conversations::Entity")]
+    Conversations,
+    #[sea_orm(has_many = "super::usage_records::Entity")]
+    UsageRecords,
+    #[sea_orm(has_many = "super::memberships::Entity")]
+    Memberships,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
+    #[sea_orm(has_many = "super::api_keys::Entity")]
+    ApiKeys,
+    #[sea_orm(has_many = "super::recovery_codes::Entity")]
+    RecoveryCodes,
+    #[sea_orm(has_many = "super::previous_passwords::Entity")]
+    PreviousPasswords,
+    #[sea_orm(has_many = "super::magic_links::Entity")]
+    MagicLinks,
+    #[sea_orm(has_many = "super::identities::Entity")]
+    Identities,
+    #[sea_orm(has_many = "super::data_exports::Entity")]
+    DataExports,
+}
+
+impl Related<super::conversations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Conversations.def()
+    }
+}
+
+impl Related<super::usage_records::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UsageRecords.def()
+    }
+}
+
+impl Related<super::memberships::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Memberships.def()
+    }
+}
+
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
+    }
+}
+
+impl Related<super::api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiKeys.def()
+    }
+}
+
+impl Related<super::recovery_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCodes.def()
+    }
+}
+
+impl Related<super::previous_passwords::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PreviousPasswords.def()
+    }
+}
+
+impl Related<super::magic_links::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MagicLinks.def()
+    }
+}
+
+impl Related<super::identities::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Identities.def()
+    }
+}
+
+impl Related<super::data_exports::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DataExports.def()
+    }
+}
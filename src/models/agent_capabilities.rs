@@ -0,0 +1,85 @@
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde_json::json;
+use uuid::Uuid;
+
+pub use super::_entities::agent_capabilities::{self, ActiveModel, Entity, Model};
+use super::agent_versions;
+use crate::llm::ToolDefinition;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::agent_capabilities::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.requires_approval.is_not_set() {
+                this.requires_approval = ActiveValue::Set(false);
+            }
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+
+    async fn after_save<C>(model: Model, db: &C, _insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        agent_versions::Model::record(db, model.agent_id).await?;
+        Ok(model)
+    }
+
+    async fn after_delete<C>(self, db: &C) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if let Some(agent_id) = self.agent_id.try_as_ref() {
+            agent_versions::Model::record(db, *agent_id).await?;
+        }
+        Ok(self)
+    }
+}
+
+impl super::_entities::agent_capabilities::Model {
+    /// Lists the capabilities of an agent, ordered by name
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_agent(db: &DatabaseConnection, agent_id: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(agent_capabilities::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(agent_capabilities::Column::AgentId, agent_id)
+                    .build(),
+            )
+            .order_by_asc(agent_capabilities::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    /// The argument schema of this capability. Capabilities without
+    /// parameters take an empty object.
+    #[must_use]
+    pub fn schema(&self) -> serde_json::Value {
+        self.parameters
+            .clone()
+            .unwrap_or_else(|| json!({ "type": "object", "properties": {} }))
+    }
+
+    /// Describes this capability as a tool the model may call
+    #[must_use]
+    pub fn tool_definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: self.schema(),
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use super::_entities::agent_versions::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::{agent_capabilities, agents, learning_models},
+    learning_models::Status as LearningModelStatus,
+};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::agent_versions::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert {
+            return Err(DbErr::Custom(
+                "agent versions cannot be changed".to_string(),
+            ));
+        }
+        let mut this = self;
+        if this.id.is_not_set() {
+            this.id = ActiveValue::Set(Uuid::new_v4());
+        }
+        if this.created_at.is_not_set() {
+            this.created_at = ActiveValue::Set(Local::now().naive_local());
+        }
+        Ok(this)
+    }
+}
+
+/// A capability as it was when the version was taken
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CapabilitySnapshot {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Option<Value>,
+    pub requires_approval: bool,
+}
+
+impl From<&agent_capabilities::Model> for CapabilitySnapshot {
+    fn from(capability: &agent_capabilities::Model) -> Self {
+        Self {
+            name: capability.name.clone(),
+            description: capability.description.clone(),
+            parameters: capability.parameters.clone(),
+            requires_approval: capability.requires_approval,
+        }
+    }
+}
+
+/// A value that differs between two versions. Missing values are `null`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    /// JSON pointer into the configuration, or the capability name
+    pub path: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diff {
+    pub from: i32,
+    pub to: i32,
+    pub configuration: Vec<Change>,
+    pub capabilities: Vec<Change>,
+    pub learning_model: Option<Change>,
+}
+
+impl super::_entities::agent_versions::Model {
+    /// Snapshots the agent unless its latest version already matches its
+    /// configuration, capabilities and active learning model. Returns the
+    /// version describing the agent as it is now.
+    ///
+    /// # Errors
+    ///
+    /// When the agent does not exist or DB query error
+    pub async fn record<C: ConnectionTrait>(db: &C, agent_id: Uuid) -> Result<Self, DbErr> {
+        let agent = agents::Entity::find_by_id(agent_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("agent {agent_id}")))?;
+        let capabilities: Vec<CapabilitySnapshot> = agent_capabilities::Entity::find()
+            .filter(agent_capabilities::Column::AgentId.eq(agent_id))
+            .order_by_asc(agent_capabilities::Column::Name)
+            .all(db)
+            .await?
+            .iter()
+            .map(CapabilitySnapshot::from)
+            .collect();
+        let capabilities =
+            serde_json::to_value(capabilities).map_err(|err| DbErr::Custom(err.to_string()))?;
+        let learning_model_id = learning_models::Entity::find()
+            .filter(learning_models::Column::AgentId.eq(agent_id))
+            .filter(learning_models::Column::Status.eq(LearningModelStatus::Active.as_str()))
+            .one(db)
+            .await?
+            .map(|model| model.id);
+
+        let latest = Self::latest(db, agent_id).await?;
+        if let Some(latest) = latest.as_ref() {
+            if latest.configuration == agent.configuration
+                && latest.capabilities == capabilities
+                && latest.learning_model_id == learning_model_id
+            {
+                return Ok(latest.clone());
+            }
+        }
+
+        agent_versions::ActiveModel {
+            agent_id: ActiveValue::set(agent_id),
+            version: ActiveValue::set(latest.map_or(1, |latest| latest.version + 1)),
+            configuration: ActiveValue::set(agent.configuration),
+            capabilities: ActiveValue::set(capabilities),
+            learning_model_id: ActiveValue::set(learning_model_id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// The most recent version of the agent, if it has any
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn latest<C: ConnectionTrait>(db: &C, agent_id: Uuid) -> Result<Option<Self>, DbErr> {
+        agent_versions::Entity::find()
+            .filter(agent_versions::Column::AgentId.eq(agent_id))
+            .order_by_desc(agent_versions::Column::Version)
+            .one(db)
+            .await
+    }
+
+    /// finds a version of the agent by its number
+    ///
+    /// # Errors
+    ///
+    /// When could not find the version or DB query error
+    pub async fn find_version(
+        db: &DatabaseConnection,
+        agent_id: Uuid,
+        version: i32,
+    ) -> ModelResult<Self> {
+        let version = agent_versions::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(agent_versions::Column::AgentId, agent_id)
+                    .eq(agent_versions::Column::Version, version)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        version.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the versions of an agent, newest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_agent(db: &DatabaseConnection, agent_id: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(agent_versions::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(agent_versions::Column::AgentId, agent_id)
+                    .build(),
+            )
+            .order_by_desc(agent_versions::Column::Version)
+            .all(db)
+            .await?)
+    }
+
+    /// The capabilities of this version
+    ///
+    /// # Errors
+    ///
+    /// When the stored snapshot is malformed
+    pub fn capability_snapshots(&self) -> ModelResult<Vec<CapabilitySnapshot>> {
+        serde_json::from_value(self.capabilities.clone()).map_err(|err| ModelError::Any(err.into()))
+    }
+
+    /// What changed from this version to `other`
+    ///
+    /// # Errors
+    ///
+    /// When a stored capability snapshot is malformed
+    pub fn diff(&self, other: &Self) -> ModelResult<Diff> {
+        let mut configuration = Vec::new();
+        diff_values(
+            "",
+            self.configuration.as_ref().unwrap_or(&Value::Null),
+            other.configuration.as_ref().unwrap_or(&Value::Null),
+            &mut configuration,
+        );
+
+        let before: BTreeMap<String, CapabilitySnapshot> = self
+            .capability_snapshots()?
+            .into_iter()
+            .map(|capability| (capability.name.clone(), capability))
+            .collect();
+        let after: BTreeMap<String, CapabilitySnapshot> = other
+            .capability_snapshots()?
+            .into_iter()
+            .map(|capability| (capability.name.clone(), capability))
+            .collect();
+        let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+        names.sort();
+        names.dedup();
+        let capabilities = names
+            .into_iter()
+            .filter(|name| before.get(*name) != after.get(*name))
+            .map(|name| Change {
+                path: name.clone(),
+                from: before.get(name).map_or(Value::Null, json_of),
+                to: after.get(name).map_or(Value::Null, json_of),
+            })
+            .collect();
+
+        let learning_model = (self.learning_model_id != other.learning_model_id).then(|| Change {
+            path: "learning_model_id".to_string(),
+            from: self
+                .learning_model_id
+                .map_or(Value::Null, |id| Value::String(id.to_string())),
+            to: other
+                .learning_model_id
+                .map_or(Value::Null, |id| Value::String(id.to_string())),
+        });
+
+        Ok(Diff {
+            from: self.version,
+            to: other.version,
+            configuration,
+            capabilities,
+            learning_model,
+        })
+    }
+
+    /// Puts the agent back the way this version describes it and records
+    /// the result as a new version. Capabilities are matched by name, so
+    /// ones that survive keep their id.
+    ///
+    /// # Errors
+    ///
+    /// When the snapshot is malformed or DB query error
+    pub async fn restore(&self, db: &DatabaseConnection) -> ModelResult<Self> {
+        let snapshots = self.capability_snapshots()?;
+        let txn = db.begin().await?;
+
+        // bulk statements skip the model hooks, so the rollback lands as a
+        // single version instead of one per change
+        agents::Entity::update_many()
+            .col_expr(
+                agents::Column::Configuration,
+                Expr::value(self.configuration.clone()),
+            )
+            .col_expr(
+                agents::Column::UpdatedAt,
+                Expr::value(Local::now().naive_local()),
+            )
+            .filter(agents::Column::Id.eq(self.agent_id))
+            .exec(&txn)
+            .await?;
+
+        let existing = agent_capabilities::Entity::find()
+            .filter(agent_capabilities::Column::AgentId.eq(self.agent_id))
+            .all(&txn)
+            .await?;
+        let removed: Vec<Uuid> = existing
+            .iter()
+            .filter(|capability| !snapshots.iter().any(|s| s.name == capability.name))
+            .map(|capability| capability.id)
+            .collect();
+        if !removed.is_empty() {
+            agent_capabilities::Entity::delete_many()
+                .filter(agent_capabilities::Column::Id.is_in(removed))
+                .exec(&txn)
+                .await?;
+        }
+        for snapshot in &snapshots {
+            if let Some(capability) = existing.iter().find(|c| c.name == snapshot.name) {
+                agent_capabilities::Entity::update_many()
+                    .col_expr(
+                        agent_capabilities::Column::Description,
+                        Expr::value(snapshot.description.clone()),
+                    )
+                    .col_expr(
+                        agent_capabilities::Column::Parameters,
+                        Expr::value(snapshot.parameters.clone()),
+                    )
+                    .col_expr(
+                        agent_capabilities::Column::RequiresApproval,
+                        Expr::value(snapshot.requires_approval),
+                    )
+                    .filter(agent_capabilities::Column::Id.eq(capability.id))
+                    .exec(&txn)
+                    .await?;
+            } else {
+                agent_capabilities::Entity::insert(agent_capabilities::ActiveModel {
+                    id: ActiveValue::set(Uuid::new_v4()),
+                    agent_id: ActiveValue::set(self.agent_id),
+                    name: ActiveValue::set(snapshot.name.clone()),
+                    description: ActiveValue::set(snapshot.description.clone()),
+                    parameters: ActiveValue::set(snapshot.parameters.clone()),
+                    requires_approval: ActiveValue::set(snapshot.requires_approval),
+                })
+                .exec(&txn)
+                .await?;
+            }
+        }
+
+        learning_models::Entity::update_many()
+            .col_expr(
+                learning_models::Column::Status,
+                Expr::value(LearningModelStatus::Archived.as_str()),
+            )
+            .filter(learning_models::Column::AgentId.eq(self.agent_id))
+            .filter(learning_models::Column::Status.eq(LearningModelStatus::Active.as_str()))
+            .exec(&txn)
+            .await?;
+        if let Some(learning_model_id) = self.learning_model_id {
+            learning_models::Entity::update_many()
+                .col_expr(
+                    learning_models::Column::Status,
+                    Expr::value(LearningModelStatus::Active.as_str()),
+                )
+                .filter(learning_models::Column::Id.eq(learning_model_id))
+                .filter(learning_models::Column::AgentId.eq(self.agent_id))
+                .exec(&txn)
+                .await?;
+        }
+
+        let version = Self::record(&txn, self.agent_id).await?;
+        txn.commit().await?;
+        Ok(version)
+    }
+}
+
+fn json_of(capability: &CapabilitySnapshot) -> Value {
+    serde_json::to_value(capability).unwrap_or(Value::Null)
+}
+
+/// Collects the leaves that differ between two JSON values. Objects are
+/// compared key by key, anything else as a whole.
+fn diff_values(path: &str, from: &Value, to: &Value, changes: &mut Vec<Change>) {
+    match (from, to) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff_values(
+                    &format!("{path}/{}", key.replace('~', "~0").replace('/', "~1")),
+                    before.get(key).unwrap_or(&Value::Null),
+                    after.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if from != to => changes.push(Change {
+            path: if path.is_empty() {
+                "/".to_string()
+            } else {
+                path.to_string()
+            },
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => {}
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{Condition, QueryOrder};
+use uuid::Uuid;
+
+pub use super::_entities::agents::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::learning_models, agent_versions, learning_models::Status as LearningModelStatus,
+};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::agents::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+
+    async fn after_save<C>(model: Model, db: &C, _insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        agent_versions::Model::record(db, model.id).await?;
+        Ok(model)
+    }
+}
+
+impl super::_entities::agents::Model {
+    /// finds an agent by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the agent or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let agent = agents::Entity::find_by_id(id).one(db).await?;
+        agent.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the agents shared in an organization, together with those from
+    /// before organizations, by name
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_organization(
+        db: &DatabaseConnection,
+        organization_id: Uuid,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(agents::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(agents::Column::OrganizationId.eq(organization_id))
+                    .add(agents::Column::OrganizationId.is_null()),
+            )
+            .order_by_asc(agents::Column::Name)
+            .all(db)
+            .await?)
+    }
+
+    /// Whether something in `organization_id` is within the agent's reach.
+    /// Agents only work with agents and knowledge of their own organization,
+    /// those from before organizations only with each other.
+    #[must_use]
+    pub fn reaches(&self, organization_id: Option<Uuid>) -> bool {
+        self.organization_id == organization_id
+    }
+
+    /// finds the active learning model version linked to this agent
+    ///
+    /// # Errors
+    ///
+    /// When the agent has no active learning model or DB query error
+    pub async fn active_learning_model(
+        &self,
+        db: &DatabaseConnection,
+    ) -> ModelResult<learning_models::Model> {
+        let model = learning_models::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(learning_models::Column::AgentId, self.id)
+                    .eq(
+                        learning_models::Column::Status,
+                        LearningModelStatus::Active.as_str(),
+                    )
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        model.ok_or_else(|| ModelError::EntityNotFound)
+    }
+}
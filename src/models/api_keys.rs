@@ -0,0 +1,229 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::api_keys::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+use crate::common::secrets;
+
+/// What every API key starts with, telling keys apart from access tokens
+pub const KEY_PREFIX: &str = "lo-";
+
+/// How many characters of a key are kept to show it by
+const SHOWN_CHARS: usize = 11;
+
+/// What an API key may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Scope {
+    #[serde(rename = "agents:read")]
+    AgentsRead,
+    #[serde(rename = "agents:write")]
+    AgentsWrite,
+    #[serde(rename = "tasks:read")]
+    TasksRead,
+    #[serde(rename = "tasks:run")]
+    TasksRun,
+}
+
+impl Scope {
+    pub const ALL: [Self; 4] = [
+        Self::AgentsRead,
+        Self::AgentsWrite,
+        Self::TasksRead,
+        Self::TasksRun,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::AgentsRead => "agents:read",
+            Self::AgentsWrite => "agents:write",
+            Self::TasksRead => "tasks:read",
+            Self::TasksRun => "tasks:run",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::api_keys::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+/// A new key, and the hash and prefix it is stored with
+fn mint() -> (String, String, String) {
+    let key = format!("{KEY_PREFIX}{}", secrets::generate());
+    let hash = secrets::hash(&key);
+    let prefix = key[..SHOWN_CHARS].to_string();
+    (key, hash, prefix)
+}
+
+impl super::_entities::api_keys::Model {
+    /// Creates a key for the user and returns it along with the only copy
+    /// of the key itself
+    ///
+    /// # Errors
+    ///
+    /// When could not save the key
+    pub async fn create_for(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        name: &str,
+        scopes: &[Scope],
+        expires_at: Option<DateTime>,
+    ) -> ModelResult<(Self, String)> {
+        let (key, key_hash, prefix) = mint();
+        let model = api_keys::ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            name: ActiveValue::set(name.to_string()),
+            prefix: ActiveValue::set(prefix),
+            key_hash: ActiveValue::set(key_hash),
+            scopes: ActiveValue::set(
+                scopes
+                    .iter()
+                    .map(|scope| scope.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            expires_at: ActiveValue::set(expires_at),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((model, key))
+    }
+
+    /// finds a key of the user that was not revoked
+    ///
+    /// # Errors
+    ///
+    /// When could not find the key or DB query error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Self> {
+        let key = api_keys::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(api_keys::Column::Id, id)
+                    .eq(api_keys::Column::UserId, user_id)
+                    .is_null(api_keys::Column::RevokedAt)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        key.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the keys of a user that were not revoked, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        Ok(api_keys::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(api_keys::Column::UserId, user_id)
+                    .is_null(api_keys::Column::RevokedAt)
+                    .build(),
+            )
+            .order_by_asc(api_keys::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// finds the live key a request was made with, and records that it was
+    /// used
+    ///
+    /// # Errors
+    ///
+    /// When the key is unknown, revoked or expired, or DB query error
+    pub async fn authenticate(db: &DatabaseConnection, key: &str) -> ModelResult<Self> {
+        let now = Local::now().naive_local();
+        let found = api_keys::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(api_keys::Column::KeyHash, secrets::hash(key))
+                    .is_null(api_keys::Column::RevokedAt)
+                    .build(),
+            )
+            .one(db)
+            .await?
+            .filter(|found| found.expires_at.is_none_or(|at| at > now))
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        api_keys::Entity::update_many()
+            .col_expr(api_keys::Column::LastUsedAt, Expr::value(Some(now)))
+            .filter(
+                model::query::condition()
+                    .eq(api_keys::Column::Id, found.id)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(found)
+    }
+
+    /// The scopes of the key. Scopes no longer known are left out.
+    #[must_use]
+    pub fn scopes(&self) -> Vec<Scope> {
+        self.scopes
+            .split_whitespace()
+            .filter_map(Scope::parse)
+            .collect()
+    }
+
+    #[must_use]
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes().contains(&scope)
+    }
+}
+
+impl super::_entities::api_keys::ActiveModel {
+    /// Replaces the key with a new one, keeping its name, scopes and
+    /// expiry. The old key stops working at once.
+    ///
+    /// # Errors
+    ///
+    /// When could not save the key
+    pub async fn rotate(mut self, db: &DatabaseConnection) -> ModelResult<(Model, String)> {
+        let (key, key_hash, prefix) = mint();
+        self.key_hash = ActiveValue::set(key_hash);
+        self.prefix = ActiveValue::set(prefix);
+        self.last_used_at = ActiveValue::set(None);
+        Ok((self.update(db).await?, key))
+    }
+
+    /// Stops the key from working, keeping it on record
+    ///
+    /// # Errors
+    ///
+    /// When could not save the key
+    pub async fn revoke(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.revoked_at = ActiveValue::set(Some(Local::now().naive_local()));
+        Ok(self.update(db).await?)
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::bus_messages::{self, ActiveModel, Entity, Model};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Delivered,
+    /// Gave up after too many delivery attempts
+    Failed,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::bus_messages::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Pending.as_str().to_string());
+            }
+            if this.attempts.is_not_set() {
+                this.attempts = ActiveValue::Set(0);
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::bus_messages::Model {
+    /// finds a message by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the message or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let message = bus_messages::Entity::find_by_id(id).one(db).await?;
+        message.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the messages still waiting for delivery, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_pending(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        Ok(bus_messages::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(bus_messages::Column::Status, Status::Pending.as_str())
+                    .build(),
+            )
+            .order_by_asc(bus_messages::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Lists the messages addressed to an agent, oldest first, so an agent
+    /// that was not subscribed can catch up
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn inbox(db: &DatabaseConnection, agent_id: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(bus_messages::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(bus_messages::Column::RecipientId, agent_id)
+                    .build(),
+            )
+            .order_by_asc(bus_messages::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.status == Status::Pending.as_str()
+    }
+}
+
+impl super::_entities::bus_messages::ActiveModel {
+    /// Records a delivery attempt and moves the message to `status`
+    ///
+    /// # Errors
+    ///
+    /// When could not save the message
+    pub async fn record_attempt(
+        mut self,
+        db: &DatabaseConnection,
+        status: Status,
+    ) -> ModelResult<Model> {
+        let attempts = self.attempts.take().unwrap_or_default();
+        self.attempts = ActiveValue::set(attempts + 1);
+        self.status = ActiveValue::set(status.as_str().to_string());
+        if status == Status::Delivered {
+            self.delivered_at = ActiveValue::set(Some(Local::now().naive_local()));
+        }
+        Ok(self.update(db).await?)
+    }
+}
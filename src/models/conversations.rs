@@ -0,0 +1,76 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{Condition, QueryOrder};
+use uuid::Uuid;
+
+pub use super::_entities::conversations::{self, ActiveModel, Entity, Model};
+use super::agent_versions;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::conversations::ActiveModel {
+    async fn before_save<C>(self, db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            // the conversation is handled by the agent as it is now
+            if this.agent_version_id.is_not_set() {
+                if let Some(agent_id) = this.agent_id.try_as_ref() {
+                    let version = agent_versions::Model::record(db, *agent_id).await?;
+                    this.agent_version_id = ActiveValue::Set(Some(version.id));
+                }
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set("active".to_string());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::conversations::Model {
+    /// finds a conversation by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the conversation or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let conversation = conversations::Entity::find_by_id(id).one(db).await?;
+        conversation.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the conversations shared in an organization, together with the
+    /// user's own from before organizations, most recently active first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_organization(
+        db: &DatabaseConnection,
+        organization_id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(conversations::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(conversations::Column::OrganizationId.eq(organization_id))
+                    .add(
+                        Condition::all()
+                            .add(conversations::Column::OrganizationId.is_null())
+                            .add(conversations::Column::UserId.eq(user_id)),
+                    ),
+            )
+            .order_by_desc(conversations::Column::UpdatedAt)
+            .all(db)
+            .await?)
+    }
+}
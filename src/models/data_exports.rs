@@ -0,0 +1,193 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::data_exports::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+use crate::common::secrets;
+
+/// Where an export is at. The archive can only be downloaded once ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Ready => "ready",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::data_exports::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::data_exports::Model {
+    /// Starts an export of the user's data, or returns the one still being
+    /// put together, if any. Tells which along with it: `true` when the
+    /// export is new and has yet to be queued. An export pending for longer
+    /// than `stale_after` is taken to have died, marked failed and replaced.
+    ///
+    /// # Errors
+    ///
+    /// When could not save the export
+    pub async fn start_for(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        stale_after: Duration,
+    ) -> ModelResult<(Self, bool)> {
+        let pending = data_exports::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(data_exports::Column::UserId, user.id)
+                    .eq(data_exports::Column::Status, Status::Pending.as_str())
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        if let Some(pending) = pending {
+            if pending.created_at > Local::now().naive_local() - stale_after {
+                return Ok((pending, false));
+            }
+            tracing::warn!(
+                export_id = %pending.id,
+                created_at = %pending.created_at,
+                "data export stuck pending, starting another"
+            );
+            pending.into_active_model().failed(db).await?;
+        }
+        let started = data_exports::ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            status: ActiveValue::set(Status::Pending.as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((started, true))
+    }
+
+    /// finds an export by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the export or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let export = data_exports::Entity::find_by_id(id).one(db).await?;
+        export.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds the ready export the token of a download link is for, if the
+    /// link still works
+    ///
+    /// # Errors
+    ///
+    /// When the token is unknown or expired, or DB query error
+    pub async fn find_live(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
+        let export = data_exports::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(data_exports::Column::TokenHash, secrets::hash(token))
+                    .eq(data_exports::Column::Status, Status::Ready.as_str())
+                    .gt(data_exports::Column::ExpiresAt, Local::now().naive_local())
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        export.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Deletes the archives whose download link expired. Returns how many.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn delete_expired(db: &DatabaseConnection) -> ModelResult<u64> {
+        let result = data_exports::Entity::delete_many()
+            .filter(
+                model::query::condition()
+                    .lte(data_exports::Column::ExpiresAt, Local::now().naive_local())
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Deletes the user's exports other than this one, so only the latest
+    /// archive is kept
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn delete_older(&self, db: &DatabaseConnection) -> ModelResult<u64> {
+        let result = data_exports::Entity::delete_many()
+            .filter(
+                model::query::condition()
+                    .eq(data_exports::Column::UserId, self.user_id)
+                    .ne(data_exports::Column::Id, self.id)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+impl super::_entities::data_exports::ActiveModel {
+    /// Stores the finished archive behind a download link that works for
+    /// `ttl`, and returns the export along with the only copy of the link's
+    /// token
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn ready(
+        mut self,
+        db: &DatabaseConnection,
+        archive: Vec<u8>,
+        ttl: Duration,
+    ) -> ModelResult<(Model, String)> {
+        let token = secrets::generate();
+        self.status = ActiveValue::set(Status::Ready.as_str().to_string());
+        self.archive = ActiveValue::set(Some(archive));
+        self.token_hash = ActiveValue::set(Some(secrets::hash(&token)));
+        self.expires_at = ActiveValue::set(Some(Local::now().naive_local() + ttl));
+        Ok((self.update(db).await?, token))
+    }
+
+    /// Marks the export as failed, so another one can be asked for
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn failed(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.status = ActiveValue::set(Status::Failed.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+}
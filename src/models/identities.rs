@@ -0,0 +1,92 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+pub use super::_entities::identities::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::identities::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::identities::Model {
+    /// finds the identity a provider knows by the subject
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_subject(
+        db: &DatabaseConnection,
+        provider: &str,
+        subject: &str,
+    ) -> ModelResult<Option<Self>> {
+        Ok(identities::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(identities::Column::Provider, provider)
+                    .eq(identities::Column::Subject, subject)
+                    .build(),
+            )
+            .one(db)
+            .await?)
+    }
+
+    /// Links an account at a provider to the user
+    ///
+    /// # Errors
+    ///
+    /// When the identity is linked already or could not save it
+    pub async fn link<C: ConnectionTrait>(
+        db: &C,
+        user: &users::Model,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> ModelResult<Self> {
+        Ok(identities::ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            provider: ActiveValue::set(provider.to_string()),
+            subject: ActiveValue::set(subject.to_string()),
+            email: ActiveValue::set(email.map(ToString::to_string)),
+            last_signed_in_at: ActiveValue::set(Some(Local::now().naive_local())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+}
+
+impl super::_entities::identities::ActiveModel {
+    /// Records a sign in with the identity, and the email the provider has
+    /// for it now
+    ///
+    /// # Errors
+    ///
+    /// When could not save the identity
+    pub async fn signed_in(
+        mut self,
+        db: &DatabaseConnection,
+        email: Option<&str>,
+    ) -> ModelResult<Model> {
+        self.email = ActiveValue::set(email.map(ToString::to_string));
+        self.last_signed_in_at = ActiveValue::set(Some(Local::now().naive_local()));
+        Ok(self.update(db).await?)
+    }
+}
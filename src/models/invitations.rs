@@ -0,0 +1,151 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, TransactionTrait};
+use uuid::Uuid;
+
+pub use super::_entities::invitations::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::users,
+    memberships::{self, Role},
+};
+
+/// How long an invitation may be accepted
+const TTL_DAYS: i64 = 7;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::invitations::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.token.is_not_set() {
+                this.token = ActiveValue::Set(Uuid::new_v4().to_string());
+            }
+            if this.expires_at.is_not_set() {
+                this.expires_at = ActiveValue::Set(now + Duration::days(TTL_DAYS));
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::invitations::Model {
+    /// finds an invitation of the organization by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the invitation or DB query error
+    pub async fn find_for_organization(
+        db: &DatabaseConnection,
+        id: Uuid,
+        organization_id: Uuid,
+    ) -> ModelResult<Self> {
+        let invitation = invitations::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(invitations::Column::Id, id)
+                    .eq(invitations::Column::OrganizationId, organization_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        invitation.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds an invitation that can still be accepted by its token
+    ///
+    /// # Errors
+    ///
+    /// When the token is unknown, the invitation was accepted or expired, or
+    /// DB query error
+    pub async fn find_pending_by_token(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
+        let invitation = invitations::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(invitations::Column::Token, token)
+                    .is_null(invitations::Column::AcceptedAt)
+                    .gt(invitations::Column::ExpiresAt, Local::now().naive_local())
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        invitation.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the invitations of an organization nobody accepted yet, newest
+    /// first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_pending_for_organization(
+        db: &DatabaseConnection,
+        organization_id: Uuid,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(invitations::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(invitations::Column::OrganizationId, organization_id)
+                    .is_null(invitations::Column::AcceptedAt)
+                    .build(),
+            )
+            .order_by_desc(invitations::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// The role, read as the least privileged one when unknown
+    #[must_use]
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Viewer)
+    }
+
+    /// Whether the invitation was sent to the user's address
+    #[must_use]
+    pub fn is_for(&self, user: &users::Model) -> bool {
+        self.email.eq_ignore_ascii_case(&user.email)
+    }
+
+    /// Makes the user a member with the invited role and marks the
+    /// invitation accepted
+    ///
+    /// # Errors
+    ///
+    /// When the user is already a member or could not save
+    pub async fn accept(
+        self,
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> ModelResult<memberships::Model> {
+        let txn = db.begin().await?;
+        if memberships::Model::find_for(&txn, self.organization_id, user.id)
+            .await
+            .is_ok()
+        {
+            return Err(ModelError::EntityAlreadyExists);
+        }
+        let membership = memberships::ActiveModel {
+            organization_id: ActiveValue::set(self.organization_id),
+            user_id: ActiveValue::set(user.id),
+            role: ActiveValue::set(self.role().as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let mut invitation: ActiveModel = self.into();
+        invitation.accepted_at = ActiveValue::set(Some(Local::now().naive_local()));
+        invitation.update(&txn).await?;
+        txn.commit().await?;
+        Ok(membership)
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+pub use super::_entities::knowledge_base::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::knowledge_base::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::knowledge_base::Model {
+    /// finds a knowledge base by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the knowledge base or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let knowledge_base = knowledge_base::Entity::find_by_id(id).one(db).await?;
+        knowledge_base.ok_or_else(|| ModelError::EntityNotFound)
+    }
+}
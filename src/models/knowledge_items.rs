@@ -0,0 +1,56 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use uuid::Uuid;
+
+pub use super::_entities::knowledge_items::{self, ActiveModel, Entity, Model};
+
+/// A fetched or uploaded source, stored whole
+pub const TYPE_DOCUMENT: &str = "document";
+/// A slice of a document small enough to retrieve and embed
+pub const TYPE_CHUNK: &str = "chunk";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::knowledge_items::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::knowledge_items::Model {
+    /// Lists the items of a knowledge base with the given type, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_by_type(
+        db: &DatabaseConnection,
+        knowledge_base_id: Uuid,
+        item_type: &str,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(knowledge_items::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(knowledge_items::Column::KnowledgeBaseId, knowledge_base_id)
+                    .eq(knowledge_items::Column::Type, item_type)
+                    .build(),
+            )
+            .order_by_asc(knowledge_items::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+}
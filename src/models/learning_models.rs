@@ -0,0 +1,242 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use super::_entities::learning_models::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::model_training_data, agent_versions, model_training_data::Status as SampleStatus,
+};
+
+/// Lifecycle of a learning model version. Only one version per model name is
+/// active at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Draft,
+    Active,
+    Archived,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Active => "active",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::learning_models::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Draft.as_str().to_string());
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+
+    /// A version becoming active changes the behavior of its agent.
+    /// Archiving the previous version is part of the same promotion and is
+    /// not snapshotted on its own.
+    async fn after_save<C>(model: Model, db: &C, _insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if let (Some(agent_id), true) = (model.agent_id, model.status == Status::Active.as_str()) {
+            agent_versions::Model::record(db, agent_id).await?;
+        }
+        Ok(model)
+    }
+}
+
+impl super::_entities::learning_models::Model {
+    /// finds a learning model by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the model or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let model = learning_models::Entity::find_by_id(id).one(db).await?;
+        model.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Returns every version of this model (rows sharing its name), oldest
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn versions(&self, db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        Ok(learning_models::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(learning_models::Column::Name, &self.name)
+                    .build(),
+            )
+            .order_by_asc(learning_models::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// finds the active version among the versions of this model
+    ///
+    /// # Errors
+    ///
+    /// When no version is active or DB query error
+    pub async fn active_version(&self, db: &DatabaseConnection) -> ModelResult<Self> {
+        let model = learning_models::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(learning_models::Column::Name, &self.name)
+                    .eq(learning_models::Column::Status, Status::Active.as_str())
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        model.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Makes this version the active one, archiving the previously active
+    /// version.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn promote(&self, db: &DatabaseConnection) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+
+        let previous = learning_models::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(learning_models::Column::Name, &self.name)
+                    .eq(learning_models::Column::Status, Status::Active.as_str())
+                    .build(),
+            )
+            .all(&txn)
+            .await?;
+        for version in previous {
+            if version.id != self.id {
+                let mut version = version.into_active_model();
+                version.status = ActiveValue::set(Status::Archived.as_str().to_string());
+                version.update(&txn).await?;
+            }
+        }
+
+        let mut this = self.clone().into_active_model();
+        this.status = ActiveValue::set(Status::Active.as_str().to_string());
+        let model = this.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(model)
+    }
+
+    /// Returns the approved training examples recorded against any version of
+    /// this model, in a stable order.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn training_data(
+        &self,
+        db: &DatabaseConnection,
+    ) -> ModelResult<Vec<model_training_data::Model>> {
+        let ids = self
+            .versions(db)
+            .await?
+            .into_iter()
+            .map(|version| version.id)
+            .collect::<Vec<_>>();
+
+        Ok(model_training_data::Entity::find()
+            .filter(model_training_data::Column::ModelId.is_in(ids))
+            .filter(model_training_data::Column::Status.eq(SampleStatus::Approved.as_str()))
+            .order_by_asc(model_training_data::Column::CreatedAt)
+            .order_by_asc(model_training_data::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    /// Saves a new version of this model with the given configuration and
+    /// metrics, marking it as trained now.
+    ///
+    /// # Errors
+    ///
+    /// When could not save the new version into the DB
+    pub async fn create_version(
+        &self,
+        db: &DatabaseConnection,
+        configuration: Value,
+        metrics: Value,
+    ) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+
+        let taken = learning_models::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(learning_models::Column::Name, &self.name)
+                    .build(),
+            )
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|version| version.version)
+            .collect::<Vec<_>>();
+
+        let mut version = next_version(&self.version);
+        while taken.contains(&version) {
+            version = next_version(&version);
+        }
+
+        let model = learning_models::ActiveModel {
+            name: ActiveValue::set(self.name.clone()),
+            r#type: ActiveValue::set(self.r#type.clone()),
+            version: ActiveValue::set(version),
+            configuration: ActiveValue::set(Some(configuration)),
+            metrics: ActiveValue::set(Some(metrics)),
+            last_trained_at: ActiveValue::set(Some(Local::now().naive_local())),
+            parent_id: ActiveValue::set(Some(self.id)),
+            agent_id: ActiveValue::set(self.agent_id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(model)
+    }
+}
+
+/// Bumps the last numeric component of a version string (`1.0.3` becomes
+/// `1.0.4`). Versions without a numeric tail get `.1` appended.
+#[must_use]
+pub fn next_version(version: &str) -> String {
+    match version.rsplit_once('.') {
+        Some((head, tail)) => tail
+            .parse::<u64>()
+            .map_or_else(|_| format!("{version}.1"), |n| format!("{head}.{}", n + 1)),
+        None => version
+            .parse::<u64>()
+            .map_or_else(|_| format!("{version}.1"), |n| (n + 1).to_string()),
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::{
+    sea_query::{Expr, OnConflict, SimpleExpr},
+    Condition,
+};
+use uuid::Uuid;
+
+pub use super::_entities::login_throttles::{self, ActiveModel, Entity, Model};
+
+/// What attempts are counted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The email logged in with, whether an account has it or not
+    Account,
+    /// The address of the client
+    Ip,
+    /// The email another verification email was asked for
+    Resend,
+    /// The email a magic link was asked for
+    MagicLink,
+}
+
+impl Scope {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Account => "account",
+            Self::Ip => "ip",
+            Self::Resend => "resend",
+            Self::MagicLink => "magic_link",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::login_throttles::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::login_throttles::Model {
+    /// finds the failure count of an account or an address
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_for(
+        db: &DatabaseConnection,
+        scope: Scope,
+        key: &str,
+    ) -> ModelResult<Option<Self>> {
+        Ok(login_throttles::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(login_throttles::Column::Scope, scope.as_str())
+                    .eq(login_throttles::Column::Key, key)
+                    .build(),
+            )
+            .one(db)
+            .await?)
+    }
+
+    /// Counts a failed login. Failures older than `window` are forgotten,
+    /// as is a lockout that ran out; reaching `max_failures` locks for
+    /// `lockout`. The count is bumped in the database, so concurrent
+    /// failures are all counted.
+    ///
+    /// # Errors
+    ///
+    /// When could not save the count
+    pub async fn record_failure(
+        db: &DatabaseConnection,
+        scope: Scope,
+        key: &str,
+        max_failures: i32,
+        window: Duration,
+        lockout: Duration,
+    ) -> ModelResult<Self> {
+        let now = Local::now().naive_local();
+        // the first failure of a key makes its row, later ones leave it be
+        login_throttles::Entity::insert(login_throttles::ActiveModel {
+            id: ActiveValue::set(Uuid::new_v4()),
+            scope: ActiveValue::set(scope.as_str().to_string()),
+            key: ActiveValue::set(key.to_string()),
+            failures: ActiveValue::set(0),
+            last_failure_at: ActiveValue::set(None),
+            locked_until: ActiveValue::set(None),
+            created_at: ActiveValue::set(now),
+            updated_at: ActiveValue::set(now),
+        })
+        .on_conflict(
+            OnConflict::columns([login_throttles::Column::Scope, login_throttles::Column::Key])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        // the count starts over when the last failure is older than the
+        // window, or a lockout ran out since
+        let stale = Condition::any()
+            .add(login_throttles::Column::LockedUntil.lte(now))
+            .add(login_throttles::Column::LastFailureAt.is_null())
+            .add(login_throttles::Column::LastFailureAt.lt(now - window));
+        let failures: SimpleExpr = Expr::case(stale, 1)
+            .finally(Expr::col(login_throttles::Column::Failures).add(1))
+            .into();
+        let locked_until: SimpleExpr = Expr::case(
+            Expr::expr(failures.clone()).gte(max_failures),
+            Some(now + lockout),
+        )
+        .case(
+            login_throttles::Column::LockedUntil.gt(now),
+            Expr::col(login_throttles::Column::LockedUntil),
+        )
+        .finally(Option::<DateTime>::None)
+        .into();
+        login_throttles::Entity::update_many()
+            .col_expr(login_throttles::Column::Failures, failures)
+            .col_expr(login_throttles::Column::LockedUntil, locked_until)
+            .col_expr(
+                login_throttles::Column::LastFailureAt,
+                Expr::value(Some(now)),
+            )
+            .col_expr(login_throttles::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(login_throttles::Column::Scope, scope.as_str())
+                    .eq(login_throttles::Column::Key, key)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+
+        Self::find_for(db, scope, key)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Forgets the failures of an account or an address
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn clear(db: &DatabaseConnection, scope: Scope, key: &str) -> ModelResult<()> {
+        login_throttles::Entity::delete_many()
+            .filter(
+                model::query::condition()
+                    .eq(login_throttles::Column::Scope, scope.as_str())
+                    .eq(login_throttles::Column::Key, key)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Until when logins are refused, if they are now
+    #[must_use]
+    pub fn locked_until(&self, now: DateTime) -> Option<DateTime> {
+        self.locked_until.filter(|until| *until > now)
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use uuid::Uuid;
+
+pub use super::_entities::magic_links::{self, ActiveModel, Entity, Model};
+use super::_entities::users;
+use crate::common::secrets;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::magic_links::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::magic_links::Model {
+    /// Creates a link for the user that works for `ttl`, and returns it
+    /// along with the only copy of its token
+    ///
+    /// # Errors
+    ///
+    /// When could not save the link
+    pub async fn create_for(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        ttl: Duration,
+    ) -> ModelResult<(Self, String)> {
+        let token = secrets::generate();
+        let link = magic_links::ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            token_hash: ActiveValue::set(secrets::hash(&token)),
+            expires_at: ActiveValue::set(Local::now().naive_local() + ttl),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((link, token))
+    }
+
+    /// finds the link of a token, if it still works
+    ///
+    /// # Errors
+    ///
+    /// When the token is unknown, expired or used, or DB query error
+    pub async fn find_live(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
+        let now = Local::now().naive_local();
+        magic_links::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(magic_links::Column::TokenHash, secrets::hash(token))
+                    .is_null(magic_links::Column::ConsumedAt)
+                    .build(),
+            )
+            .one(db)
+            .await?
+            .filter(|link| link.expires_at > now)
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Uses up the link, but only if it was still unused when this copy was
+    /// read. Returns whether it was ours to use; of two requests racing
+    /// with the same link only one gets it.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn consume(&self, db: &DatabaseConnection) -> ModelResult<bool> {
+        let now = Local::now().naive_local();
+        let result = magic_links::Entity::update_many()
+            .col_expr(magic_links::Column::ConsumedAt, Expr::value(Some(now)))
+            .col_expr(magic_links::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(magic_links::Column::Id, self.id)
+                    .is_null(magic_links::Column::ConsumedAt)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{PaginatorTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::memberships::{self, ActiveModel, Entity, Model};
+use super::_entities::{organizations, users};
+
+/// What a member may do in an organization, from most to least
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Everything, including managing owners and deleting the organization
+    Owner,
+    /// Manages members and invitations besides what members do
+    Admin,
+    /// Creates and edits agents, chats and runs tasks
+    Member,
+    /// Only reads
+    Viewer,
+}
+
+impl Role {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Admin => "admin",
+            Self::Member => "member",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Owner, Self::Admin, Self::Member, Self::Viewer]
+            .into_iter()
+            .find(|role| role.as_str() == value)
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::memberships::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::memberships::Model {
+    /// finds the membership of a user in an organization
+    ///
+    /// # Errors
+    ///
+    /// When the user is not a member or DB query error
+    pub async fn find_for<C: ConnectionTrait>(
+        db: &C,
+        organization_id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Self> {
+        let membership = memberships::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(memberships::Column::OrganizationId, organization_id)
+                    .eq(memberships::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        membership.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the organizations of a user with the user's membership in each,
+    /// oldest membership first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> ModelResult<Vec<(Self, organizations::Model)>> {
+        Ok(memberships::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(memberships::Column::UserId, user_id)
+                    .build(),
+            )
+            .order_by_asc(memberships::Column::CreatedAt)
+            .find_also_related(organizations::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(membership, organization)| Some((membership, organization?)))
+            .collect())
+    }
+
+    /// Lists the members of an organization, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_organization(
+        db: &DatabaseConnection,
+        organization_id: Uuid,
+    ) -> ModelResult<Vec<(Self, users::Model)>> {
+        Ok(memberships::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(memberships::Column::OrganizationId, organization_id)
+                    .build(),
+            )
+            .order_by_asc(memberships::Column::CreatedAt)
+            .find_also_related(users::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(membership, user)| Some((membership, user?)))
+            .collect())
+    }
+
+    /// How many owners the organization has
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn count_owners(db: &DatabaseConnection, organization_id: Uuid) -> ModelResult<u64> {
+        Ok(memberships::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(memberships::Column::OrganizationId, organization_id)
+                    .eq(memberships::Column::Role, Role::Owner.as_str())
+                    .build(),
+            )
+            .count(db)
+            .await?)
+    }
+
+    /// The role, read as the least privileged one when unknown
+    #[must_use]
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Viewer)
+    }
+}
+
+impl super::_entities::memberships::ActiveModel {
+    /// Gives the member another role
+    ///
+    /// # Errors
+    ///
+    /// When could not save the membership
+    pub async fn set_role(mut self, db: &DatabaseConnection, role: Role) -> ModelResult<Model> {
+        self.role = ActiveValue::set(role.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+pub use super::_entities::memories::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::memories::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(Local::now().naive_local());
+            }
+        }
+        Ok(this)
+    }
+}
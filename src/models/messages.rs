@@ -0,0 +1,132 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use uuid::Uuid;
+
+pub use super::_entities::messages::{self, ActiveModel, Entity, Model};
+
+pub const ROLE_SYSTEM: &str = "system";
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ASSISTANT: &str = "assistant";
+pub const ROLE_TOOL: &str = "tool";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::messages::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(Local::now().naive_local());
+            }
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl super::_entities::messages::Model {
+    /// finds a message by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the message or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let message = messages::Entity::find_by_id(id).one(db).await?;
+        message.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Writes a new message at the end of a conversation
+    ///
+    /// # Errors
+    ///
+    /// When could not save the message
+    pub async fn append(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+        role: &str,
+        content: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> ModelResult<Self> {
+        Ok(messages::ActiveModel {
+            conversation_id: ActiveValue::set(conversation_id),
+            role: ActiveValue::set(role.to_string()),
+            content: ActiveValue::set(content.to_string()),
+            metadata: ActiveValue::set(metadata),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// Writes an answer of the agent at the end of a conversation, noting
+    /// the version of the agent that wrote it
+    ///
+    /// # Errors
+    ///
+    /// When could not save the message
+    pub async fn append_reply(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+        agent_version_id: Uuid,
+        content: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> ModelResult<Self> {
+        Ok(messages::ActiveModel {
+            conversation_id: ActiveValue::set(conversation_id),
+            role: ActiveValue::set(ROLE_ASSISTANT.to_string()),
+            content: ActiveValue::set(content.to_string()),
+            metadata: ActiveValue::set(metadata),
+            agent_version_id: ActiveValue::set(Some(agent_version_id)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    /// Lists the messages of a conversation in the order they were written
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_conversation(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(messages::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(messages::Column::ConversationId, conversation_id)
+                    .build(),
+            )
+            .order_by_asc(messages::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// finds the user message this message answered
+    ///
+    /// # Errors
+    ///
+    /// When there is no earlier user message or DB query error
+    pub async fn prompting_user_message(&self, db: &DatabaseConnection) -> ModelResult<Self> {
+        let message = messages::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(messages::Column::ConversationId, self.conversation_id)
+                    .eq(messages::Column::Role, ROLE_USER)
+                    .lt(messages::Column::CreatedAt, self.created_at)
+                    .build(),
+            )
+            .order_by_desc(messages::Column::CreatedAt)
+            .one(db)
+            .await?;
+        message.ok_or_else(|| ModelError::EntityNotFound)
+    }
+}
@@ -1,2 +1,31 @@
 pub mod _entities;
+pub mod agent_capabilities;
+pub mod agent_versions;
+pub mod agents;
+pub mod api_keys;
+pub mod bus_messages;
+pub mod conversations;
+pub mod data_exports;
+pub mod identities;
+pub mod invitations;
+pub mod knowledge_base;
+pub mod knowledge_items;
+pub mod learning_models;
+pub mod login_throttles;
+pub mod magic_links;
+pub mod memberships;
+pub mod memories;
+pub mod messages;
+pub mod model_evaluations;
+pub mod model_training_data;
+pub mod oidc_requests;
+pub mod organizations;
+pub mod previous_passwords;
+pub mod recovery_codes;
+pub mod sessions;
+pub mod task_dependencies;
+pub mod task_schedules;
+pub mod tasks;
+pub mod tool_approvals;
+pub mod usage_records;
 pub mod users;
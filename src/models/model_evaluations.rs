@@ -0,0 +1,64 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use uuid::Uuid;
+
+pub use super::_entities::model_evaluations::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::model_evaluations::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(Local::now().naive_local());
+            }
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl super::_entities::model_evaluations::Model {
+    /// Lists the evaluations of a model version, newest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_model(db: &DatabaseConnection, model_id: Uuid) -> ModelResult<Vec<Self>> {
+        Ok(model_evaluations::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(model_evaluations::Column::ModelId, model_id)
+                    .build(),
+            )
+            .order_by_desc(model_evaluations::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// finds the most recent evaluation of a model version
+    ///
+    /// # Errors
+    ///
+    /// When the model was never evaluated or DB query error
+    pub async fn latest_for_model(db: &DatabaseConnection, model_id: Uuid) -> ModelResult<Self> {
+        let evaluation = model_evaluations::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(model_evaluations::Column::ModelId, model_id)
+                    .build(),
+            )
+            .order_by_desc(model_evaluations::Column::CreatedAt)
+            .one(db)
+            .await?;
+        evaluation.ok_or_else(|| ModelError::EntityNotFound)
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::model_training_data::{self, ActiveModel, Entity, Model};
+
+/// Review state of a training sample. Only approved samples are used for
+/// optimization and evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::model_training_data::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(Local::now().naive_local());
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Approved.as_str().to_string());
+            }
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl super::_entities::model_training_data::Model {
+    /// finds a training sample by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the sample or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let sample = model_training_data::Entity::find_by_id(id).one(db).await?;
+        sample.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists samples in the given review state, oldest first, optionally for a
+    /// single model version
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_by_status(
+        db: &DatabaseConnection,
+        status: Status,
+        model_id: Option<Uuid>,
+    ) -> ModelResult<Vec<Self>> {
+        let mut condition =
+            model::query::condition().eq(model_training_data::Column::Status, status.as_str());
+        if let Some(model_id) = model_id {
+            condition = condition.eq(model_training_data::Column::ModelId, model_id);
+        }
+
+        Ok(model_training_data::Entity::find()
+            .filter(condition.build())
+            .order_by_asc(model_training_data::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+}
+
+impl super::_entities::model_training_data::ActiveModel {
+    /// Records the outcome of reviewing the sample
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_status(
+        mut self,
+        db: &DatabaseConnection,
+        status: Status,
+    ) -> ModelResult<Model> {
+        self.status = ActiveValue::set(status.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+}
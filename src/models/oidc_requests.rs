@@ -0,0 +1,94 @@
+use chrono::{offset::Local, Duration};
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use uuid::Uuid;
+
+pub use super::_entities::oidc_requests::{self, ActiveModel, Entity, Model};
+use crate::common::secrets;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::oidc_requests::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::oidc_requests::Model {
+    /// Starts a sign in with the provider that has `ttl` to come back, and
+    /// returns it along with the only copy of its state
+    ///
+    /// # Errors
+    ///
+    /// When could not save the request
+    pub async fn start(
+        db: &DatabaseConnection,
+        provider: &str,
+        ttl: Duration,
+    ) -> ModelResult<(Self, String)> {
+        let state = secrets::generate();
+        let request = oidc_requests::ActiveModel {
+            provider: ActiveValue::set(provider.to_string()),
+            state_hash: ActiveValue::set(secrets::hash(&state)),
+            code_verifier: ActiveValue::set(secrets::generate()),
+            nonce: ActiveValue::set(secrets::generate()),
+            expires_at: ActiveValue::set(Local::now().naive_local() + ttl),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok((request, state))
+    }
+
+    /// Takes the sign in a provider sent the user back from, once. Of two
+    /// requests racing with the same state only one gets it.
+    ///
+    /// # Errors
+    ///
+    /// When the state is unknown, for another provider, expired or used, or
+    /// DB query error
+    pub async fn take(db: &DatabaseConnection, provider: &str, state: &str) -> ModelResult<Self> {
+        let now = Local::now().naive_local();
+        let request = oidc_requests::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(oidc_requests::Column::StateHash, secrets::hash(state))
+                    .eq(oidc_requests::Column::Provider, provider)
+                    .is_null(oidc_requests::Column::ConsumedAt)
+                    .build(),
+            )
+            .one(db)
+            .await?
+            .filter(|request| request.expires_at > now)
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        let result = oidc_requests::Entity::update_many()
+            .col_expr(oidc_requests::Column::ConsumedAt, Expr::value(Some(now)))
+            .col_expr(oidc_requests::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(oidc_requests::Column::Id, request.id)
+                    .is_null(oidc_requests::Column::ConsumedAt)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        if result.rows_affected != 1 {
+            return Err(ModelError::EntityNotFound);
+        }
+        Ok(request)
+    }
+}
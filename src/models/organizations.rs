@@ -0,0 +1,117 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{PaginatorTrait, TransactionTrait};
+use uuid::Uuid;
+
+pub use super::_entities::organizations::{self, ActiveModel, Entity, Model};
+use super::{
+    _entities::{agents, conversations, knowledge_base, users},
+    memberships::{self, Role},
+};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::organizations::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::organizations::Model {
+    /// finds an organization by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the organization or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let organization = organizations::Entity::find_by_id(id).one(db).await?;
+        organization.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Creates an organization with the user as its owner
+    ///
+    /// # Errors
+    ///
+    /// When could not save the organization or the membership
+    pub async fn create_with_owner(
+        db: &DatabaseConnection,
+        name: &str,
+        user: &users::Model,
+    ) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+        let organization = organizations::ActiveModel {
+            name: ActiveValue::set(name.to_string()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        memberships::ActiveModel {
+            organization_id: ActiveValue::set(organization.id),
+            user_id: ActiveValue::set(user.id),
+            role: ActiveValue::set(Role::Owner.as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(organization)
+    }
+
+    /// The organization the user acts in unless another one is picked: the
+    /// one they joined first. Users in no organization get one of their
+    /// own.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn default_for(
+        db: &DatabaseConnection,
+        user: &users::Model,
+    ) -> ModelResult<(Self, memberships::Model)> {
+        if let Some((membership, organization)) = memberships::Model::list_for_user(db, user.id)
+            .await?
+            .into_iter()
+            .next()
+        {
+            return Ok((organization, membership));
+        }
+        let organization = Self::create_with_owner(db, &user.name, user).await?;
+        let membership = memberships::Model::find_for(db, organization.id, user.id).await?;
+        Ok((organization, membership))
+    }
+
+    /// Whether no agent, knowledge base or conversation is shared in the
+    /// organization
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn is_unused(&self, db: &DatabaseConnection) -> ModelResult<bool> {
+        let agents = agents::Entity::find()
+            .filter(agents::Column::OrganizationId.eq(self.id))
+            .count(db)
+            .await?;
+        let knowledge_bases = knowledge_base::Entity::find()
+            .filter(knowledge_base::Column::OrganizationId.eq(self.id))
+            .count(db)
+            .await?;
+        let conversations = conversations::Entity::find()
+            .filter(conversations::Column::OrganizationId.eq(self.id))
+            .count(db)
+            .await?;
+        Ok(agents + knowledge_bases + conversations == 0)
+    }
+}
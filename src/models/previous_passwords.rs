@@ -0,0 +1,97 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect};
+use uuid::Uuid;
+
+pub use super::_entities::previous_passwords::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::previous_passwords::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::previous_passwords::Model {
+    /// The hashes of the passwords the user had before the current one,
+    /// most recent first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn recent_hashes(
+        db: &DatabaseConnection,
+        user_id: i32,
+        limit: u64,
+    ) -> ModelResult<Vec<String>> {
+        Ok(previous_passwords::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(previous_passwords::Column::UserId, user_id)
+                    .build(),
+            )
+            .order_by_desc(previous_passwords::Column::CreatedAt)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|previous| previous.password_hash)
+            .collect())
+    }
+
+    /// Keeps a password the user is moving away from, and forgets all but
+    /// the `keep` most recent ones
+    ///
+    /// # Errors
+    ///
+    /// When could not save the password
+    pub async fn remember<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+        password_hash: &str,
+        keep: u64,
+    ) -> ModelResult<()> {
+        previous_passwords::ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            password_hash: ActiveValue::set(password_hash.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        let stale: Vec<Uuid> = previous_passwords::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(previous_passwords::Column::UserId, user_id)
+                    .build(),
+            )
+            .order_by_desc(previous_passwords::Column::CreatedAt)
+            .all(db)
+            .await?
+            .into_iter()
+            .skip(usize::try_from(keep).unwrap_or(usize::MAX))
+            .map(|previous| previous.id)
+            .collect();
+        if !stale.is_empty() {
+            previous_passwords::Entity::delete_many()
+                .filter(previous_passwords::Column::Id.is_in(stale))
+                .exec(db)
+                .await?;
+        }
+        Ok(())
+    }
+}
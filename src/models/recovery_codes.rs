@@ -0,0 +1,119 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{sea_query::Expr, TransactionTrait};
+use uuid::Uuid;
+
+pub use super::_entities::recovery_codes::{self, ActiveModel, Entity, Model};
+use crate::common::secrets;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::recovery_codes::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+/// A new code such as `k3f9a-q2m7x`, easy to read out and type
+fn generate() -> String {
+    let chars: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// Codes are compared without case, spaces or dashes
+fn normalize(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl super::_entities::recovery_codes::Model {
+    /// Replaces the codes of the user with `count` new ones and returns
+    /// them, the only time they are shown
+    ///
+    /// # Errors
+    ///
+    /// When could not save the codes
+    pub async fn regenerate(
+        db: &DatabaseConnection,
+        user_id: i32,
+        count: usize,
+    ) -> ModelResult<Vec<String>> {
+        let codes: Vec<String> = (0..count).map(|_| generate()).collect();
+        let txn = db.begin().await?;
+        Self::delete_for_user(&txn, user_id).await?;
+        for code in &codes {
+            recovery_codes::ActiveModel {
+                user_id: ActiveValue::set(user_id),
+                code_hash: ActiveValue::set(secrets::hash(&normalize(code))),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(codes)
+    }
+
+    /// Uses up one of the user's codes. Returns whether it was an unused
+    /// code of theirs.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn redeem(db: &DatabaseConnection, user_id: i32, code: &str) -> ModelResult<bool> {
+        let now = Local::now().naive_local();
+        let result = recovery_codes::Entity::update_many()
+            .col_expr(recovery_codes::Column::UsedAt, Expr::value(Some(now)))
+            .col_expr(recovery_codes::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(recovery_codes::Column::UserId, user_id)
+                    .eq(
+                        recovery_codes::Column::CodeHash,
+                        secrets::hash(&normalize(code)),
+                    )
+                    .is_null(recovery_codes::Column::UsedAt)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Deletes every code of the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn delete_for_user<C: ConnectionTrait>(db: &C, user_id: i32) -> ModelResult<()> {
+        recovery_codes::Entity::delete_many()
+            .filter(
+                model::query::condition()
+                    .eq(recovery_codes::Column::UserId, user_id)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
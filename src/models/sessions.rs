@@ -0,0 +1,159 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder};
+use uuid::Uuid;
+
+pub use super::_entities::sessions::{self, ActiveModel, Entity, Model};
+use crate::common::secrets;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::sessions::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.family_id.is_not_set() {
+                this.family_id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.signed_in_at.is_not_set() {
+                this.signed_in_at = ActiveValue::Set(now);
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::sessions::Model {
+    /// finds the session of a refresh token, whatever its state
+    ///
+    /// # Errors
+    ///
+    /// When could not find the session or DB query error
+    pub async fn find_by_token(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
+        let session = sessions::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(sessions::Column::TokenHash, secrets::hash(token))
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        session.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the sessions a user is still signed in with, one per token
+    /// family, most recently used first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_active_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(sessions::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(sessions::Column::UserId, user_id)
+                    .is_null(sessions::Column::RotatedAt)
+                    .is_null(sessions::Column::RevokedAt)
+                    .gt(sessions::Column::ExpiresAt, Local::now().naive_local())
+                    .build(),
+            )
+            .order_by_desc(sessions::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Revokes every token of a family, so none of them can be refreshed
+    /// again. Returns how many were still live.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke_family<C: ConnectionTrait>(
+        db: &C,
+        family_id: Uuid,
+        user_id: Option<i32>,
+    ) -> ModelResult<u64> {
+        let now = Local::now().naive_local();
+        let mut condition = model::query::condition()
+            .eq(sessions::Column::FamilyId, family_id)
+            .is_null(sessions::Column::RevokedAt);
+        if let Some(user_id) = user_id {
+            condition = condition.eq(sessions::Column::UserId, user_id);
+        }
+        let result = sessions::Entity::update_many()
+            .col_expr(sessions::Column::RevokedAt, Expr::value(Some(now)))
+            .col_expr(sessions::Column::UpdatedAt, Expr::value(now))
+            .filter(condition.build())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Revokes every session of the user but the one of `keep`, if given.
+    /// Returns how many tokens were still live.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke_others<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+        keep: Option<Uuid>,
+    ) -> ModelResult<u64> {
+        let now = Local::now().naive_local();
+        let mut condition = model::query::condition()
+            .eq(sessions::Column::UserId, user_id)
+            .is_null(sessions::Column::RevokedAt);
+        if let Some(keep) = keep {
+            condition = condition.ne(sessions::Column::FamilyId, keep);
+        }
+        let result = sessions::Entity::update_many()
+            .col_expr(sessions::Column::RevokedAt, Expr::value(Some(now)))
+            .col_expr(sessions::Column::UpdatedAt, Expr::value(now))
+            .filter(condition.build())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// Marks the token as used up, but only if it still was live when this
+    /// copy was read. Returns whether it was ours to rotate.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn mark_rotated<C: ConnectionTrait>(&self, db: &C) -> ModelResult<bool> {
+        let now = Local::now().naive_local();
+        let result = sessions::Entity::update_many()
+            .col_expr(sessions::Column::RotatedAt, Expr::value(Some(now)))
+            .col_expr(sessions::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(sessions::Column::Id, self.id)
+                    .is_null(sessions::Column::RotatedAt)
+                    .is_null(sessions::Column::RevokedAt)
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Local::now().naive_local()
+    }
+}
@@ -0,0 +1,37 @@
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+pub use super::_entities::task_dependencies::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::task_dependencies::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert && self.id.is_not_set() {
+            let mut this = self;
+            this.id = ActiveValue::Set(Uuid::new_v4());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl super::_entities::task_dependencies::Model {
+    /// Lists the dependencies of the given tasks
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_tasks(
+        db: &DatabaseConnection,
+        task_ids: &[Uuid],
+    ) -> ModelResult<Vec<Self>> {
+        Ok(task_dependencies::Entity::find()
+            .filter(task_dependencies::Column::TaskId.is_in(task_ids.iter().copied()))
+            .all(db)
+            .await?)
+    }
+}
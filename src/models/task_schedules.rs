@@ -0,0 +1,235 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, Condition, QueryOrder, QuerySelect, QueryTrait};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::_entities::agents;
+pub use super::_entities::task_schedules::{self, ActiveModel, Entity, Model};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Active,
+    Paused,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Paused => "paused",
+        }
+    }
+}
+
+/// What happens to runs that were due while no scheduler was running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Missed runs are dropped; only a run still within the grace period is
+    /// made
+    Skip,
+    /// A single run is made for the most recent missed occurrence
+    #[default]
+    Latest,
+    /// One run is made per missed occurrence, up to the configured limit
+    All,
+}
+
+impl CatchUp {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Latest => "latest",
+            Self::All => "all",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Skip, Self::Latest, Self::All]
+            .into_iter()
+            .find(|policy| policy.as_str() == value)
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::task_schedules::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.timezone.is_not_set() {
+                this.timezone = ActiveValue::Set("UTC".to_string());
+            }
+            if this.catch_up.is_not_set() {
+                this.catch_up = ActiveValue::Set(CatchUp::default().as_str().to_string());
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Active.as_str().to_string());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::task_schedules::Model {
+    /// finds a schedule by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the schedule or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let schedule = task_schedules::Entity::find_by_id(id).one(db).await?;
+        schedule.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds a schedule created by the given user
+    ///
+    /// # Errors
+    ///
+    /// When the schedule does not exist, belongs to someone else or DB query
+    /// error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Self> {
+        let schedule = task_schedules::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(task_schedules::Column::Id, id)
+                    .eq(task_schedules::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        schedule.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the schedules of a user along with those on the agents of the
+    /// organization, if given, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_user_or_organization(
+        db: &DatabaseConnection,
+        user_id: i32,
+        organization_id: Option<Uuid>,
+    ) -> ModelResult<Vec<Self>> {
+        let mut condition = Condition::any().add(task_schedules::Column::UserId.eq(user_id));
+        if let Some(organization_id) = organization_id {
+            condition = condition.add(
+                task_schedules::Column::AgentId.in_subquery(
+                    agents::Entity::find()
+                        .select_only()
+                        .column(agents::Column::Id)
+                        .filter(agents::Column::OrganizationId.eq(organization_id))
+                        .into_query(),
+                ),
+            );
+        }
+        Ok(task_schedules::Entity::find()
+            .filter(condition)
+            .order_by_asc(task_schedules::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Lists the active schedules whose next run is due at `now`
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_due(db: &DatabaseConnection, now: DateTime) -> ModelResult<Vec<Self>> {
+        Ok(task_schedules::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(task_schedules::Column::Status, Status::Active.as_str())
+                    .lte(task_schedules::Column::NextRunAt, now)
+                    .build(),
+            )
+            .order_by_asc(task_schedules::Column::NextRunAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Moves the next run forward, and records the last run when one is
+    /// made, but only if no other scheduler moved it since this copy was
+    /// read. Returns whether the runs are ours to make.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn claim<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        next_run_at: Option<DateTime>,
+        last_run_at: Option<DateTime>,
+    ) -> ModelResult<bool> {
+        let mut condition = model::query::condition()
+            .eq(task_schedules::Column::Id, self.id)
+            .eq(task_schedules::Column::Status, Status::Active.as_str());
+        condition = match self.next_run_at {
+            Some(current) => condition.eq(task_schedules::Column::NextRunAt, current),
+            None => condition.is_null(task_schedules::Column::NextRunAt),
+        };
+        let mut update = task_schedules::Entity::update_many()
+            .col_expr(task_schedules::Column::NextRunAt, Expr::value(next_run_at))
+            .col_expr(
+                task_schedules::Column::UpdatedAt,
+                Expr::value(Local::now().naive_local()),
+            );
+        if let Some(last_run_at) = last_run_at {
+            update = update.col_expr(
+                task_schedules::Column::LastRunAt,
+                Expr::value(Some(last_run_at)),
+            );
+        }
+        let result = update.filter(condition.build()).exec(db).await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.status == Status::Active.as_str()
+    }
+
+    /// The catch-up policy, falling back to the default for unknown values
+    #[must_use]
+    pub fn catch_up_policy(&self) -> CatchUp {
+        CatchUp::parse(&self.catch_up).unwrap_or_default()
+    }
+}
+
+impl super::_entities::task_schedules::ActiveModel {
+    /// Moves the schedule to a new status and sets when it runs next
+    ///
+    /// # Errors
+    ///
+    /// When could not save the schedule
+    pub async fn set_status(
+        mut self,
+        db: &DatabaseConnection,
+        status: Status,
+        next_run_at: Option<DateTime>,
+    ) -> ModelResult<Model> {
+        self.status = ActiveValue::set(status.as_str().to_string());
+        self.next_run_at = ActiveValue::set(next_run_at);
+        Ok(self.update(db).await?)
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use super::_entities::tasks::{self, ActiveModel, Entity, Model};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Running,
+    /// Delegated work is in progress
+    Waiting,
+    Completed,
+    Failed,
+    /// Not run because work it depended on failed
+    Cancelled,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Waiting => "waiting",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::tasks::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Pending.as_str().to_string());
+            }
+            if this.priority.is_not_set() {
+                this.priority = ActiveValue::Set(0);
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::tasks::Model {
+    /// finds a task by the provided id
+    ///
+    /// # Errors
+    ///
+    /// When could not find the task or DB query error
+    pub async fn find_by_id(db: &DatabaseConnection, id: Uuid) -> ModelResult<Self> {
+        let task = tasks::Entity::find_by_id(id).one(db).await?;
+        task.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds a task requested by the given user
+    ///
+    /// # Errors
+    ///
+    /// When the task does not exist, belongs to someone else or DB query
+    /// error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Self> {
+        let task = tasks::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tasks::Column::Id, id)
+                    .eq(tasks::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        task.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the tasks delegated by this task, in plan order
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn children(&self, db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        Ok(tasks::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tasks::Column::ParentId, self.id)
+                    .build(),
+            )
+            .order_by_asc(tasks::Column::Priority)
+            .order_by_asc(tasks::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        [Status::Completed, Status::Failed, Status::Cancelled]
+            .iter()
+            .any(|status| self.status == status.as_str())
+    }
+}
+
+impl super::_entities::tasks::ActiveModel {
+    /// Moves the task to a new status
+    ///
+    /// # Errors
+    ///
+    /// When could not save the task
+    pub async fn set_status(
+        mut self,
+        db: &DatabaseConnection,
+        status: Status,
+    ) -> ModelResult<Model> {
+        self.status = ActiveValue::set(status.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+
+    /// Finishes the task with its output. Failed tasks carry the error as
+    /// their output.
+    ///
+    /// # Errors
+    ///
+    /// When could not save the task
+    pub async fn finish(
+        mut self,
+        db: &DatabaseConnection,
+        status: Status,
+        output: Value,
+    ) -> ModelResult<Model> {
+        self.status = ActiveValue::set(status.as_str().to_string());
+        self.output = ActiveValue::set(Some(output));
+        self.completed_at = ActiveValue::set(Some(Local::now().naive_local()));
+        Ok(self.update(db).await?)
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::{offset::Local, NaiveDateTime};
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, QueryOrder};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use super::_entities::tool_approvals::{self, ActiveModel, Entity, Model};
+
+/// Where a gated tool call stands. Only pending approvals can be decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::tool_approvals::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        let now = Local::now().naive_local();
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.status.is_not_set() {
+                this.status = ActiveValue::Set(Status::Pending.as_str().to_string());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(now);
+            }
+        }
+        this.updated_at = ActiveValue::Set(now);
+        Ok(this)
+    }
+}
+
+impl super::_entities::tool_approvals::Model {
+    /// finds an approval addressed to the given user
+    ///
+    /// # Errors
+    ///
+    /// When the approval does not exist, belongs to someone else or DB query
+    /// error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        id: Uuid,
+        user_id: i32,
+    ) -> ModelResult<Self> {
+        let approval = tool_approvals::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tool_approvals::Column::Id, id)
+                    .eq(tool_approvals::Column::UserId, user_id)
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        approval.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the approvals waiting on a user, oldest first. Those past
+    /// their deadline are left out, as they can only expire now.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_pending_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(tool_approvals::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tool_approvals::Column::UserId, user_id)
+                    .eq(tool_approvals::Column::Status, Status::Pending.as_str())
+                    .gte(
+                        tool_approvals::Column::ExpiresAt,
+                        Local::now().naive_local(),
+                    )
+                    .build(),
+            )
+            .order_by_asc(tool_approvals::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Lists the approvals a conversation is waiting on
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_pending_for_conversation(
+        db: &DatabaseConnection,
+        conversation_id: Uuid,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(tool_approvals::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tool_approvals::Column::ConversationId, conversation_id)
+                    .eq(tool_approvals::Column::Status, Status::Pending.as_str())
+                    .build(),
+            )
+            .order_by_asc(tool_approvals::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    /// Lists pending approvals whose deadline passed before `now`
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_overdue(
+        db: &DatabaseConnection,
+        now: NaiveDateTime,
+    ) -> ModelResult<Vec<Self>> {
+        Ok(tool_approvals::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(tool_approvals::Column::Status, Status::Pending.as_str())
+                    .lt(tool_approvals::Column::ExpiresAt, now)
+                    .build(),
+            )
+            .order_by_asc(tool_approvals::Column::ExpiresAt)
+            .all(db)
+            .await?)
+    }
+
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.status == Status::Pending.as_str()
+    }
+
+    #[must_use]
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        self.is_pending() && self.expires_at < now
+    }
+
+    /// Records the decision, as long as nobody decided the approval in the
+    /// meantime. Returns whether this call was the one to decide it, so the
+    /// gated tool runs at most once.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn decide(&self, db: &DatabaseConnection, status: Status) -> ModelResult<bool> {
+        let now = Local::now().naive_local();
+        let result = tool_approvals::Entity::update_many()
+            .col_expr(tool_approvals::Column::Status, Expr::value(status.as_str()))
+            .col_expr(tool_approvals::Column::DecidedAt, Expr::value(Some(now)))
+            .col_expr(tool_approvals::Column::UpdatedAt, Expr::value(now))
+            .filter(
+                model::query::condition()
+                    .eq(tool_approvals::Column::Id, self.id)
+                    .eq(tool_approvals::Column::Status, Status::Pending.as_str())
+                    .build(),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::offset::Local;
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use uuid::Uuid;
+
+pub use super::_entities::usage_records::{self, ActiveModel, Entity, Model};
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for super::_entities::usage_records::ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut this = self;
+        if insert {
+            if this.id.is_not_set() {
+                this.id = ActiveValue::Set(Uuid::new_v4());
+            }
+            if this.created_at.is_not_set() {
+                this.created_at = ActiveValue::Set(Local::now().naive_local());
+            }
+        }
+        Ok(this)
+    }
+}
+
+impl super::_entities::usage_records::Model {
+    /// Lists the calls made from `from` up to but excluding `until`, oldest
+    /// first, optionally only those on behalf of one user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_between(
+        db: &DatabaseConnection,
+        from: DateTime,
+        until: DateTime,
+        user_id: Option<i32>,
+    ) -> ModelResult<Vec<Self>> {
+        let mut condition = model::query::condition()
+            .gte(usage_records::Column::CreatedAt, from)
+            .lt(usage_records::Column::CreatedAt, until);
+        if let Some(user_id) = user_id {
+            condition = condition.eq(usage_records::Column::UserId, user_id);
+        }
+        Ok(usage_records::Entity::find()
+            .filter(condition.build())
+            .order_by_asc(usage_records::Column::CreatedAt)
+            .all(db)
+            .await?)
+    }
+
+    #[must_use]
+    pub fn total_tokens(&self) -> i64 {
+        i64::from(self.prompt_tokens) + i64::from(self.completion_tokens)
+    }
+}
@@ -1,10 +1,14 @@
 use async_trait::async_trait;
 use chrono::offset::Local;
 use loco_rs::{auth::jwt, hash, prelude::*};
+use sea_orm::sea_query::Expr;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{auth, common::secrets, plans::Plan};
+
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
+use super::{api_keys, previous_passwords};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginParams {
@@ -45,8 +49,10 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
         self.validate()?;
         if insert {
             let mut this = self;
-            this.pid = ActiveValue::Set(Uuid::new_v4());
-            this.api_key = ActiveValue::Set(format!("lo-{}", Uuid::new_v4()));
+            let pid = Uuid::new_v4();
+            this.pid = ActiveValue::Set(pid);
+            // left from the plaintext API keys, see `api_keys` for those
+            this.api_key = ActiveValue::Set(pid.to_string());
             Ok(this)
         } else {
             Ok(self)
@@ -57,14 +63,8 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
 #[async_trait]
 impl Authenticable for super::_entities::users::Model {
     async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
-        let user = users::Entity::find()
-            .filter(
-                model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
-                    .build(),
-            )
-            .one(db)
-            .await?;
+        let key = api_keys::Model::authenticate(db, api_key).await?;
+        let user = users::Entity::find_by_id(key.user_id).one(db).await?;
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
@@ -74,6 +74,13 @@ impl Authenticable for super::_entities::users::Model {
 }
 
 impl super::_entities::users::Model {
+    /// The subscription plan of the user. Unknown plans fall back to the
+    /// free plan.
+    #[must_use]
+    pub fn plan(&self) -> Plan {
+        Plan::parse(&self.plan).unwrap_or_default()
+    }
+
     /// finds a user by the provided email
     ///
     /// # Errors
@@ -91,41 +98,78 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// finds a user by the provided verification token
+    /// finds a user by the provided verification token, if it was sent
+    /// less than `ttl` ago
     ///
     /// # Errors
     ///
-    /// When could not find user by the given token or DB query error
+    /// When could not find user by the given token, it expired or DB query
+    /// error
     pub async fn find_by_verification_token(
         db: &DatabaseConnection,
         token: &str,
+        ttl: chrono::Duration,
     ) -> ModelResult<Self> {
         let user = users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::EmailVerificationToken, token)
+                    .eq(users::Column::EmailVerificationToken, secrets::hash(token))
                     .build(),
             )
             .one(db)
             .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
+        user.filter(|user| auth::is_fresh(user.email_verification_sent_at, ttl))
+            .ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// finds a user by the provided reset token
+    /// finds a user by the provided reset token, if it was sent less than
+    /// `ttl` ago
     ///
     /// # Errors
     ///
-    /// When could not find user by the given token or DB query error
-    pub async fn find_by_reset_token(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
+    /// When could not find user by the given token, it expired or DB query
+    /// error
+    pub async fn find_by_reset_token(
+        db: &DatabaseConnection,
+        token: &str,
+        ttl: chrono::Duration,
+    ) -> ModelResult<Self> {
         let user = users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::ResetToken, token)
+                    .eq(users::Column::ResetToken, secrets::hash(token))
                     .build(),
             )
             .one(db)
             .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
+        user.filter(|user| auth::is_fresh(user.reset_sent_at, ttl))
+            .ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds a user by the token confirming the email they asked to move
+    /// to, if it was sent less than `ttl` ago
+    ///
+    /// # Errors
+    ///
+    /// When could not find user by the given token, it expired or DB query
+    /// error
+    pub async fn find_by_email_change_token(
+        db: &DatabaseConnection,
+        token: &str,
+        ttl: chrono::Duration,
+    ) -> ModelResult<Self> {
+        let user = users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::EmailChangeToken, secrets::hash(token))
+                    .build(),
+            )
+            .one(db)
+            .await?;
+        user.filter(|user| {
+            user.pending_email.is_some() && auth::is_fresh(user.email_change_sent_at, ttl)
+        })
+        .ok_or_else(|| ModelError::EntityNotFound)
     }
 
     /// finds a user by the provided pid
@@ -146,21 +190,21 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// finds a user by the provided api key
+    /// finds the users whose account deletion grace period is over
     ///
     /// # Errors
     ///
-    /// When could not find user by the given token or DB query error
-    pub async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
-        let user = users::Entity::find()
+    /// When has DB query error
+    pub async fn find_due_for_deletion(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let now: DateTimeWithTimeZone = Local::now().into();
+        Ok(users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
+                    .lte(users::Column::DeleteAfter, now)
                     .build(),
             )
-            .one(db)
-            .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
+            .all(db)
+            .await?)
     }
 
     /// Verifies whether the provided plain password matches the hashed password
@@ -222,6 +266,51 @@ impl super::_entities::users::Model {
     pub fn generate_jwt(&self, secret: &str, expiration: &u64) -> ModelResult<String> {
         Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), None)?)
     }
+
+    /// Creates a JWT carrying extra claims, such as the session it was
+    /// issued for
+    ///
+    /// # Errors
+    ///
+    /// when could not convert user claims to jwt token
+    pub fn generate_jwt_with_claims(
+        &self,
+        secret: &str,
+        expiration: &u64,
+        claims: serde_json::Value,
+    ) -> ModelResult<String> {
+        Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), Some(claims))?)
+    }
+
+    /// Whether signing in takes a second factor
+    #[must_use]
+    pub fn has_totp(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    /// Records that a TOTP code of the given step was accepted, unless a
+    /// code of that step or a later one was accepted already. Returns
+    /// whether the code may be accepted.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn claim_totp_step(&self, db: &DatabaseConnection, step: i64) -> ModelResult<bool> {
+        let result = users::Entity::update_many()
+            .col_expr(users::Column::TotpLastStep, Expr::value(Some(step)))
+            .filter(
+                sea_orm::Condition::all()
+                    .add(users::Column::Id.eq(self.id))
+                    .add(
+                        sea_orm::Condition::any()
+                            .add(users::Column::TotpLastStep.is_null())
+                            .add(users::Column::TotpLastStep.lt(step)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
 }
 
 impl super::_entities::users::ActiveModel {
@@ -229,7 +318,9 @@ impl super::_entities::users::ActiveModel {
     /// updates it in the database.
     ///
     /// This method is used to record the timestamp when the email verification
-    /// was sent and generate a unique verification token for the user.
+    /// was sent and generate a unique verification token for the user. Only
+    /// the hash of the token is stored; the token itself is returned, for
+    /// the email, and replaces any sent before.
     ///
     /// # Errors
     ///
@@ -237,10 +328,11 @@ impl super::_entities::users::ActiveModel {
     pub async fn set_email_verification_sent(
         mut self,
         db: &DatabaseConnection,
-    ) -> ModelResult<Model> {
+    ) -> ModelResult<(Model, String)> {
+        let token = secrets::generate();
         self.email_verification_sent_at = ActiveValue::set(Some(Local::now().into()));
-        self.email_verification_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
-        Ok(self.update(db).await?)
+        self.email_verification_token = ActiveValue::Set(Some(secrets::hash(&token)));
+        Ok((self.update(db).await?, token))
     }
 
     /// Sets the information for a reset password request,
@@ -248,30 +340,95 @@ impl super::_entities::users::ActiveModel {
     /// database.
     ///
     /// This method records the timestamp when the reset password token is sent
-    /// and generates a unique token for the user.
-    ///
-    /// # Arguments
+    /// and generates a unique token for the user. Only the hash of the token
+    /// is stored; the token itself is returned, for the email, and replaces
+    /// any sent before.
     ///
     /// # Errors
     ///
     /// when has DB query error
-    pub async fn set_forgot_password_sent(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+    pub async fn set_forgot_password_sent(
+        mut self,
+        db: &DatabaseConnection,
+    ) -> ModelResult<(Model, String)> {
+        let token = secrets::generate();
         self.reset_sent_at = ActiveValue::set(Some(Local::now().into()));
-        self.reset_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
-        Ok(self.update(db).await?)
+        self.reset_token = ActiveValue::Set(Some(secrets::hash(&token)));
+        Ok((self.update(db).await?, token))
+    }
+
+    /// Records that the user asked to move to another email, and generates
+    /// the token confirming it. Only the hash of the token is stored; the
+    /// token itself is returned, for the email to the new address, and
+    /// replaces any asked for before.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_email_change_sent(
+        mut self,
+        db: &DatabaseConnection,
+        new_email: &str,
+    ) -> ModelResult<(Model, String)> {
+        let token = secrets::generate();
+        self.pending_email = ActiveValue::set(Some(new_email.to_string()));
+        self.email_change_sent_at = ActiveValue::set(Some(Local::now().into()));
+        self.email_change_token = ActiveValue::Set(Some(secrets::hash(&token)));
+        Ok((self.update(db).await?, token))
+    }
+
+    /// Moves the user to the email they asked for, which confirming the
+    /// link proved is theirs. Whether another user has the address is
+    /// checked again, in the same transaction as the move.
+    ///
+    /// # Errors
+    ///
+    /// When another user has the email, there is none pending or DB query
+    /// error
+    pub async fn confirm_email_change(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        let Some(Some(new_email)) = self.pending_email.try_as_ref().cloned() else {
+            return Err(ModelError::EntityNotFound);
+        };
+        let txn = db.begin().await?;
+
+        if users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::Email, &new_email)
+                    .build(),
+            )
+            .one(&txn)
+            .await?
+            .is_some()
+        {
+            return Err(ModelError::EntityAlreadyExists {});
+        }
+
+        self.email = ActiveValue::set(new_email);
+        self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        self.pending_email = ActiveValue::set(None);
+        self.email_change_token = ActiveValue::set(None);
+        self.email_change_sent_at = ActiveValue::set(None);
+        let user = self.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(user)
     }
 
     /// Records the verification time when a user verifies their
     /// email and updates it in the database.
     ///
     /// This method sets the timestamp when the user successfully verifies their
-    /// email.
+    /// email, and uses up the verification token.
     ///
     /// # Errors
     ///
     /// when has DB query error
     pub async fn verified(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
         self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        self.email_verification_token = ActiveValue::Set(None);
+        self.email_verification_sent_at = ActiveValue::Set(None);
         Ok(self.update(db).await?)
     }
 
@@ -279,7 +436,8 @@ impl super::_entities::users::ActiveModel {
     /// updates it in the database.
     ///
     /// This method hashes the provided password and sets it as the new password
-    /// for the user.
+    /// for the user. The old one is kept among the `keep` previous passwords
+    /// that may not be chosen again.
     ///
     /// # Errors
     ///
@@ -288,11 +446,105 @@ impl super::_entities::users::ActiveModel {
         mut self,
         db: &DatabaseConnection,
         password: &str,
+        keep: u64,
     ) -> ModelResult<Model> {
+        let txn = db.begin().await?;
+        if let (ActiveValue::Unchanged(user_id), ActiveValue::Unchanged(old)) =
+            (&self.id, &self.password)
+        {
+            previous_passwords::Model::remember(&txn, *user_id, old, keep).await?;
+        }
         self.password =
             ActiveValue::set(hash::hash_password(password).map_err(|e| ModelError::Any(e.into()))?);
         self.reset_token = ActiveValue::Set(None);
         self.reset_sent_at = ActiveValue::Set(None);
+        let user = self.update(&txn).await?;
+        txn.commit().await?;
+        Ok(user)
+    }
+
+    /// Moves the user to another plan
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_plan(mut self, db: &DatabaseConnection, plan: Plan) -> ModelResult<Model> {
+        self.plan = ActiveValue::set(plan.as_str().to_string());
+        Ok(self.update(db).await?)
+    }
+
+    /// Stores a new TOTP secret for the user to confirm with a code. Two
+    /// factor sign in stays off until then.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn set_pending_totp(
+        mut self,
+        db: &DatabaseConnection,
+        secret: &str,
+    ) -> ModelResult<Model> {
+        self.totp_secret = ActiveValue::set(Some(secret.to_string()));
+        self.totp_enabled_at = ActiveValue::set(None);
+        self.totp_last_step = ActiveValue::set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Turns on two factor sign in with the stored secret, recording the
+    /// step of the code it was confirmed with
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn enable_totp(mut self, db: &DatabaseConnection, step: i64) -> ModelResult<Model> {
+        self.totp_enabled_at = ActiveValue::set(Some(Local::now().into()));
+        self.totp_last_step = ActiveValue::set(Some(step));
+        Ok(self.update(db).await?)
+    }
+
+    /// Turns off two factor sign in and forgets the secret
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn disable_totp(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.totp_secret = ActiveValue::set(None);
+        self.totp_enabled_at = ActiveValue::set(None);
+        self.totp_last_step = ActiveValue::set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Renames the user
+    ///
+    /// # Errors
+    ///
+    /// when the name is not valid or has DB query error
+    pub async fn set_name(mut self, db: &DatabaseConnection, name: &str) -> ModelResult<Model> {
+        self.name = ActiveValue::set(name.to_string());
+        Ok(self.update(db).await?)
+    }
+
+    /// Schedules the account to be erased at the given time
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn schedule_deletion(
+        mut self,
+        db: &DatabaseConnection,
+        delete_after: DateTimeWithTimeZone,
+    ) -> ModelResult<Model> {
+        self.delete_after = ActiveValue::set(Some(delete_after));
+        Ok(self.update(db).await?)
+    }
+
+    /// Keeps an account that was scheduled to be erased
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn cancel_deletion(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.delete_after = ActiveValue::set(None);
         Ok(self.update(db).await?)
     }
 }
@@ -0,0 +1,391 @@
+//! Signing in through OpenID Connect providers, with the authorization code
+//! flow and PKCE. Starting a sign in stores the state, the PKCE verifier
+//! and the nonce in `oidc_requests` and hands out the provider URL to send
+//! the user to; the provider sends them back with a code, which is traded,
+//! along with the verifier, for an ID token.
+//!
+//! The ID token is checked against the keys the provider publishes, or the
+//! client secret for HMAC signed tokens, and for its issuer, audience,
+//! expiry and nonce. Its subject is then looked up in `identities`; a
+//! subject seen for the first time is linked to the user with the same
+//! email when the provider vouches for the email, or gets a new user.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use axum::http::StatusCode;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use loco_rs::{controller::ErrorDetail, Error, Result};
+use reqwest::Url;
+use sea_orm::{DatabaseConnection, EntityTrait, IntoActiveModel};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    common::secrets,
+    models::{
+        identities, oidc_requests,
+        users::{self, RegisterParams},
+    },
+};
+
+/// The `settings.oidc` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// The providers users may sign in with, by the name used in the URLs
+    #[serde(default)]
+    pub providers: BTreeMap<String, Provider>,
+    /// How long a user has to come back from the provider
+    #[serde(default = "default_request_ttl_secs")]
+    pub request_ttl_secs: i64,
+    /// Time allowed for each request to a provider
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Provider {
+    /// Where the provider's `/.well-known/openid-configuration` is found
+    pub issuer: String,
+    pub client_id: String,
+    /// Sent along with the code, for providers that expect it
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Where the provider sends users back to, as registered with it
+    pub redirect_uri: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            providers: BTreeMap::new(),
+            request_ttl_secs: default_request_ttl_secs(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+const fn default_request_ttl_secs() -> i64 {
+    10 * 60
+}
+
+const fn default_timeout_secs() -> u64 {
+    10
+}
+
+fn default_scopes() -> Vec<String> {
+    ["openid", "email", "profile"]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+impl Config {
+    #[must_use]
+    pub fn request_ttl(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.request_ttl_secs)
+    }
+}
+
+/// What the provider tells about the user, out of a checked ID token
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// The parts of the provider metadata the flow uses
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    /// A boolean, though some providers send it as a string
+    email_verified: Option<serde_json::Value>,
+    name: Option<String>,
+}
+
+/// Talks to one configured provider
+pub struct Client<'a> {
+    provider: &'a Provider,
+    http: reqwest::Client,
+}
+
+impl<'a> Client<'a> {
+    /// A client for the provider configured under `name`, if any is
+    ///
+    /// # Errors
+    ///
+    /// When the HTTP client could not be built
+    pub fn new(config: &'a Config, name: &str) -> Result<Option<Self>> {
+        let Some(provider) = config.providers.get(name) else {
+            return Ok(None);
+        };
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|err| Error::string(&err.to_string()))?;
+        Ok(Some(Self { provider, http }))
+    }
+
+    /// The provider URL that starts the sign in of the request
+    ///
+    /// # Errors
+    ///
+    /// When the provider metadata could not be read
+    pub async fn authorization_url(
+        &self,
+        request: &oidc_requests::Model,
+        state: &str,
+    ) -> Result<String> {
+        let discovery = self.discover().await?;
+        let mut url = Url::parse(&discovery.authorization_endpoint)
+            .map_err(|err| provider_error(&format!("invalid authorization endpoint: {err}")))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.provider.client_id)
+            .append_pair("redirect_uri", &self.provider.redirect_uri)
+            .append_pair("scope", &self.provider.scopes.join(" "))
+            .append_pair("state", state)
+            .append_pair("nonce", &request.nonce)
+            .append_pair("code_challenge", &code_challenge(&request.code_verifier))
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.into())
+    }
+
+    /// Trades the code the provider sent the user back with for their
+    /// identity
+    ///
+    /// # Errors
+    ///
+    /// When the provider could not be reached or refused the code, or the
+    /// ID token does not check out
+    pub async fn exchange(&self, request: &oidc_requests::Model, code: &str) -> Result<Identity> {
+        let discovery = self.discover().await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.provider.redirect_uri.as_str()),
+            ("client_id", self.provider.client_id.as_str()),
+            ("code_verifier", request.code_verifier.as_str()),
+        ];
+        if let Some(secret) = &self.provider.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let response = self
+            .http
+            .post(&discovery.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|err| provider_error(&err.to_string()))?;
+        if !response.status().is_success() {
+            tracing::info!(status = %response.status(), "provider refused the code");
+            return Err(Error::Unauthorized("provider refused the code".to_string()));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|err| provider_error(&err.to_string()))?;
+
+        let claims = self.verify(&discovery, &tokens.id_token).await?;
+        if claims.nonce.as_deref() != Some(request.nonce.as_str()) {
+            return Err(Error::Unauthorized("id token nonce mismatch".to_string()));
+        }
+        let email_verified = match claims.email_verified {
+            Some(serde_json::Value::Bool(verified)) => verified,
+            Some(serde_json::Value::String(verified)) => verified == "true",
+            _ => false,
+        };
+        Ok(Identity {
+            subject: claims.sub,
+            email: claims.email,
+            email_verified,
+            name: claims.name,
+        })
+    }
+
+    async fn discover(&self) -> Result<Discovery> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.provider.issuer.trim_end_matches('/')
+        );
+        let discovery: Discovery = self.get_json(&url).await?;
+        if discovery.issuer.trim_end_matches('/') != self.provider.issuer.trim_end_matches('/') {
+            return Err(provider_error("provider metadata names another issuer"));
+        }
+        Ok(discovery)
+    }
+
+    async fn verify(&self, discovery: &Discovery, id_token: &str) -> Result<IdClaims> {
+        let invalid = |err: jsonwebtoken::errors::Error| {
+            Error::Unauthorized(format!("invalid id token: {err}"))
+        };
+        let header = jsonwebtoken::decode_header(id_token).map_err(invalid)?;
+        let key = match header.alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let Some(secret) = &self.provider.client_secret else {
+                    return Err(Error::Unauthorized(
+                        "id token signed with a client secret there is none of".to_string(),
+                    ));
+                };
+                DecodingKey::from_secret(secret.as_bytes())
+            }
+            _ => {
+                let keys: JwkSet = self.get_json(&discovery.jwks_uri).await?;
+                let jwk = match &header.kid {
+                    Some(kid) => keys.find(kid),
+                    None if keys.keys.len() == 1 => keys.keys.first(),
+                    None => None,
+                }
+                .ok_or_else(|| Error::Unauthorized("id token signed with an unknown key".into()))?;
+                DecodingKey::from_jwk(jwk).map_err(invalid)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&discovery.issuer]);
+        validation.set_audience(&[&self.provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        Ok(
+            jsonwebtoken::decode::<IdClaims>(id_token, &key, &validation)
+                .map_err(invalid)?
+                .claims,
+        )
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| provider_error(&err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| provider_error(&err.to_string()))
+    }
+}
+
+/// The PKCE challenge of a verifier, by the `S256` method
+#[must_use]
+pub fn code_challenge(verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+}
+
+fn provider_error(reason: &str) -> Error {
+    tracing::warn!(reason, "identity provider error");
+    Error::CustomError(
+        StatusCode::BAD_GATEWAY,
+        ErrorDetail::new("provider_error", "the identity provider could not be used"),
+    )
+}
+
+/// The email of the account an identity signs in to, found without
+/// linking or creating anything, so a lockout can be checked first: the
+/// linked user's, else the one the provider vouches for
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn account_email(
+    db: &DatabaseConnection,
+    provider: &str,
+    identity: &Identity,
+) -> Result<Option<String>> {
+    if let Some(linked) =
+        identities::Model::find_by_subject(db, provider, &identity.subject).await?
+    {
+        let user = users::Entity::find_by_id(linked.user_id).one(db).await?;
+        return Ok(user.map(|user| user.email));
+    }
+    Ok(identity.email.clone().filter(|_| identity.email_verified))
+}
+
+/// The user an identity signs in as: the one it is linked to, else the one
+/// with the email the provider vouches for, which it gets linked to, else a
+/// new one
+///
+/// # Errors
+///
+/// When the email is not vouched for, belongs to a user who never verified
+/// it, or DB query error
+pub async fn user_for(
+    db: &DatabaseConnection,
+    provider: &str,
+    identity: &Identity,
+) -> Result<users::Model> {
+    let email = identity.email.as_deref();
+    if let Some(linked) =
+        identities::Model::find_by_subject(db, provider, &identity.subject).await?
+    {
+        let user = users::Entity::find_by_id(linked.user_id)
+            .one(db)
+            .await?
+            .ok_or(Error::NotFound)?;
+        linked.into_active_model().signed_in(db, email).await?;
+        return Ok(user);
+    }
+
+    let Some(email) = email.filter(|_| identity.email_verified) else {
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new(
+                "email_not_verified",
+                "the identity provider does not vouch for an email",
+            ),
+        ));
+    };
+    let user = match users::Model::find_by_email(db, email).await {
+        Ok(user) if user.email_verified_at.is_none() => {
+            // whoever registered the address never proved it is theirs, and
+            // may know its password
+            return Err(Error::CustomError(
+                StatusCode::CONFLICT,
+                ErrorDetail::new(
+                    "account_unverified",
+                    "an account with this email awaits verification",
+                ),
+            ));
+        }
+        Ok(user) => user,
+        Err(_) => {
+            let params = RegisterParams {
+                email: email.to_string(),
+                // never handed out; a password can be set by resetting it
+                password: secrets::generate(),
+                name: identity
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| email.split('@').next().unwrap_or(email).to_string()),
+            };
+            let user = users::Model::create_with_password(db, &params).await?;
+            tracing::info!(
+                pid = user.pid.to_string(),
+                provider,
+                "user created by sign in"
+            );
+            user.into_active_model().verified(db).await?
+        }
+    };
+    identities::Model::link(db, &user, provider, &identity.subject, Some(email)).await?;
+    tracing::info!(pid = user.pid.to_string(), provider, "identity linked");
+    Ok(user)
+}
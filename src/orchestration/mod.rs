@@ -0,0 +1,427 @@
+//! Multi-agent orchestration. A planner agent splits a task into subtasks
+//! assigned to the agents it may delegate to, the subtasks run in
+//! dependency order with the outputs they depend on, and the planner
+//! synthesizes their outputs into the result of the original task.
+//!
+//! Subtasks are ordinary `tasks` rows pointing at their parent, so a
+//! planner can delegate to another planner up to the configured depth.
+
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use loco_rs::{Error, Result};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    llm::{ChatMessage, CompletionRequest, Provider},
+    models::{
+        agent_versions, agents, task_dependencies,
+        tasks::{self, Status},
+    },
+    usage::{Metered, Prices, Scope},
+};
+
+pub mod plan;
+pub mod trace;
+
+use plan::{Plan, Step};
+
+/// Agents of this type plan and delegate instead of answering directly
+pub const PLANNER_TYPE: &str = "planner";
+
+/// The `settings.orchestration` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// How many levels of planners may delegate below the task that was
+    /// requested
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Largest plan a planner may produce
+    #[serde(default = "default_max_subtasks")]
+    pub max_subtasks: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_depth: default_max_depth(),
+            max_subtasks: default_max_subtasks(),
+        }
+    }
+}
+
+const fn default_max_depth() -> usize {
+    2
+}
+
+const fn default_max_subtasks() -> usize {
+    8
+}
+
+const PLANNING_INSTRUCTIONS: &str = "Split the task into subtasks for the agents listed below. \
+Answer with JSON only, in the form \
+{\"tasks\": [{\"key\": \"short-unique-key\", \"agent_id\": \"<agent id>\", \"name\": \"...\", \
+\"description\": \"what the agent has to do\", \"depends_on\": [\"keys of subtasks whose \
+output it needs\"]}]}.";
+
+const SYNTHESIS_INSTRUCTIONS: &str = "Your subtasks are done. Combine their results into the \
+answer to the original task.";
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub struct Orchestrator<'a> {
+    provider: &'a dyn Provider,
+    config: Config,
+    prices: Prices,
+}
+
+impl<'a> Orchestrator<'a> {
+    #[must_use]
+    pub fn new(provider: &'a dyn Provider) -> Self {
+        Self {
+            provider,
+            config: Config::default(),
+            prices: Prices::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.config = config.clone();
+        self
+    }
+
+    /// Prices the usage recorded for every provider call
+    #[must_use]
+    pub fn with_prices(mut self, prices: &Prices) -> Self {
+        self.prices = prices.clone();
+        self
+    }
+
+    /// The provider, accounting calls to the agent working on the task
+    fn metered<'s>(
+        &'s self,
+        db: &'s DatabaseConnection,
+        agent: &agents::Model,
+        task: &tasks::Model,
+    ) -> Metered<'s> {
+        Metered::new(
+            self.provider,
+            db,
+            &self.prices,
+            Scope {
+                user_id: task.user_id,
+                agent_id: Some(agent.id),
+                conversation_id: None,
+                task_id: Some(task.id),
+            },
+        )
+    }
+
+    /// Runs a task to completion. Planner agents delegate, every other
+    /// agent answers the task directly. Provider and planning errors fail
+    /// the task instead of being returned.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn run(&self, db: &DatabaseConnection, task: tasks::Model) -> Result<tasks::Model> {
+        self.run_at(db, task, 0, Vec::new()).await
+    }
+
+    fn run_at<'s>(
+        &'s self,
+        db: &'s DatabaseConnection,
+        task: tasks::Model,
+        depth: usize,
+        upstream: Vec<(String, Value)>,
+    ) -> BoxFuture<'s, Result<tasks::Model>> {
+        Box::pin(async move {
+            let agent = agents::Model::find_by_id(db, task.agent_id).await?;
+            // record which version of the agent handles the task
+            let version = agent_versions::Model::record(db, agent.id).await?;
+            let mut task = task.into_active_model();
+            task.agent_version_id = ActiveValue::set(Some(version.id));
+            let task = task.set_status(db, Status::Running).await?;
+
+            let outcome = if agent.r#type == PLANNER_TYPE && depth < self.config.max_depth {
+                self.delegate(db, &agent, &task, depth).await
+            } else {
+                self.answer(db, &agent, &task, &upstream)
+                    .await
+                    .map(Outcome::Answered)
+            };
+
+            let (status, output) = match outcome {
+                Ok(Outcome::Answered(answer)) => (Status::Completed, json!({ "answer": answer })),
+                Ok(Outcome::SubtasksFailed(failed)) => (
+                    Status::Failed,
+                    json!({ "error": "delegated tasks failed", "failed": failed }),
+                ),
+                Err(err) => {
+                    tracing::warn!(task_id = %task.id, error = %err, "task failed");
+                    (Status::Failed, json!({ "error": err.to_string() }))
+                }
+            };
+            let current = tasks::Model::find_by_id(db, task.id).await?;
+            Ok(current
+                .into_active_model()
+                .finish(db, status, output)
+                .await?)
+        })
+    }
+
+    /// Answers the task with a single completion from the agent
+    async fn answer(
+        &self,
+        db: &DatabaseConnection,
+        agent: &agents::Model,
+        task: &tasks::Model,
+        upstream: &[(String, Value)],
+    ) -> Result<String> {
+        let mut prompt = describe(task);
+        if !upstream.is_empty() {
+            prompt.push_str("\n\nResults you can build on:");
+            for (name, output) in upstream {
+                prompt.push_str(&format!("\n\n## {name}\n{}", render(output)));
+            }
+        }
+        let completion = self
+            .metered(db, agent, task)
+            .complete(&CompletionRequest::new(vec![
+                ChatMessage::system(system_prompt(agent)),
+                ChatMessage::user(prompt),
+            ]))
+            .await?;
+        Ok(completion.content)
+    }
+
+    /// Plans, runs the subtasks and synthesizes their outputs
+    async fn delegate(
+        &self,
+        db: &DatabaseConnection,
+        planner: &agents::Model,
+        task: &tasks::Model,
+        depth: usize,
+    ) -> Result<Outcome> {
+        let delegates = delegates(db, planner).await?;
+        let roster = delegates
+            .iter()
+            .map(|agent| {
+                format!(
+                    "- {} ({}): {}",
+                    agent.id,
+                    agent.name,
+                    agent.description.as_deref().unwrap_or("no description")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let reply = self
+            .metered(db, planner, task)
+            .complete(&CompletionRequest::new(vec![
+                ChatMessage::system(format!(
+                    "{}\n\n{PLANNING_INSTRUCTIONS}\n\nAgents:\n{roster}",
+                    system_prompt(planner)
+                )),
+                ChatMessage::user(describe(task)),
+            ]))
+            .await?;
+        let ids: Vec<Uuid> = delegates.iter().map(|agent| agent.id).collect();
+        let steps = Plan::parse(&reply.content)?.validate(&ids, self.config.max_subtasks)?;
+
+        let children = create_subtasks(db, task, &steps).await?;
+        task.clone()
+            .into_active_model()
+            .set_status(db, Status::Waiting)
+            .await?;
+
+        let mut finished: HashMap<String, tasks::Model> = HashMap::new();
+        for (step, child) in steps.iter().zip(children) {
+            let dependencies: Vec<&tasks::Model> = step
+                .depends_on
+                .iter()
+                .filter_map(|key| finished.get(key))
+                .collect();
+            let child = if dependencies
+                .iter()
+                .all(|dependency| dependency.status == Status::Completed.as_str())
+            {
+                let upstream = dependencies
+                    .iter()
+                    .map(|dependency| {
+                        (
+                            dependency.name.clone(),
+                            dependency.output.clone().unwrap_or(Value::Null),
+                        )
+                    })
+                    .collect();
+                self.run_at(db, child, depth + 1, upstream).await?
+            } else {
+                child
+                    .into_active_model()
+                    .finish(
+                        db,
+                        Status::Cancelled,
+                        json!({ "error": "a task it depends on did not complete" }),
+                    )
+                    .await?
+            };
+            finished.insert(step.key.clone(), child);
+        }
+
+        let failed: Vec<String> = steps
+            .iter()
+            .filter_map(|step| finished.get(&step.key))
+            .filter(|child| child.status != Status::Completed.as_str())
+            .map(|child| child.name.clone())
+            .collect();
+        if !failed.is_empty() {
+            return Ok(Outcome::SubtasksFailed(failed));
+        }
+
+        let results = steps
+            .iter()
+            .filter_map(|step| finished.get(&step.key))
+            .map(|child| {
+                format!(
+                    "## {}\n{}",
+                    child.name,
+                    render(child.output.as_ref().unwrap_or(&Value::Null))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let completion = self
+            .metered(db, planner, task)
+            .complete(&CompletionRequest::new(vec![
+                ChatMessage::system(format!(
+                    "{}\n\n{SYNTHESIS_INSTRUCTIONS}",
+                    system_prompt(planner)
+                )),
+                ChatMessage::user(format!("{}\n\nResults:\n\n{results}", describe(task))),
+            ]))
+            .await?;
+        Ok(Outcome::Answered(completion.content))
+    }
+}
+
+enum Outcome {
+    Answered(String),
+    /// Names of the subtasks that failed or were cancelled
+    SubtasksFailed(Vec<String>),
+}
+
+/// The agents a planner may hand work to: the ids listed under `delegates`
+/// in its configuration, otherwise every other active agent, either way
+/// only those of the planner's organization
+async fn delegates(db: &DatabaseConnection, planner: &agents::Model) -> Result<Vec<agents::Model>> {
+    let listed: Option<Vec<Uuid>> = planner
+        .configuration
+        .as_ref()
+        .and_then(|configuration| configuration.get("delegates"))
+        .and_then(|delegates| serde_json::from_value(delegates.clone()).ok());
+    let query = agents::Entity::find().filter(agents::agents::Column::Id.ne(planner.id));
+    let query = match planner.organization_id {
+        Some(organization_id) => {
+            query.filter(agents::agents::Column::OrganizationId.eq(organization_id))
+        }
+        None => query.filter(agents::agents::Column::OrganizationId.is_null()),
+    };
+    let query = match listed {
+        Some(ids) => query.filter(agents::agents::Column::Id.is_in(ids)),
+        None => query.filter(agents::agents::Column::Status.eq("active")),
+    };
+    let delegates = query.all(db).await?;
+    if delegates.is_empty() {
+        return Err(Error::BadRequest(format!(
+            "planner {} has no agents to delegate to",
+            planner.name
+        )));
+    }
+    Ok(delegates)
+}
+
+/// Stores the plan as child tasks of `parent` and returns them in step
+/// order
+async fn create_subtasks(
+    db: &DatabaseConnection,
+    parent: &tasks::Model,
+    steps: &[Step],
+) -> Result<Vec<tasks::Model>> {
+    let txn = db.begin().await?;
+    let mut children: Vec<tasks::Model> = Vec::with_capacity(steps.len());
+    for (index, step) in steps.iter().enumerate() {
+        let child = tasks::ActiveModel {
+            agent_id: ActiveValue::set(step.agent_id),
+            parent_id: ActiveValue::set(Some(parent.id)),
+            user_id: ActiveValue::set(parent.user_id),
+            name: ActiveValue::set(step.name.clone()),
+            description: ActiveValue::set(Some(step.description.clone())),
+            priority: ActiveValue::set(i32::try_from(index).unwrap_or(i32::MAX)),
+            metadata: ActiveValue::set(Some(json!({
+                "key": step.key,
+                "depends_on": step.depends_on,
+            }))),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        for key in &step.depends_on {
+            // steps are ordered, so dependencies were created already
+            let Some(position) = steps.iter().position(|other| &other.key == key) else {
+                continue;
+            };
+            task_dependencies::ActiveModel {
+                task_id: ActiveValue::set(child.id),
+                depends_on_task_id: ActiveValue::set(children[position].id),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+        children.push(child);
+    }
+    txn.commit().await?;
+    Ok(children)
+}
+
+fn system_prompt(agent: &agents::Model) -> String {
+    agent
+        .configuration
+        .as_ref()
+        .and_then(|configuration| configuration.get("system_prompt"))
+        .and_then(Value::as_str)
+        .map_or_else(
+            || match &agent.description {
+                Some(description) => format!("You are {}. {description}", agent.name),
+                None => format!("You are {}.", agent.name),
+            },
+            ToString::to_string,
+        )
+}
+
+fn describe(task: &tasks::Model) -> String {
+    let mut prompt = format!("Task: {}", task.name);
+    if let Some(description) = &task.description {
+        prompt.push_str(&format!("\n\n{description}"));
+    }
+    if let Some(input) = &task.input {
+        prompt.push_str(&format!("\n\nInput:\n{input}"));
+    }
+    prompt
+}
+
+/// Shows a task output the way a model reads it best: answers as plain
+/// text, anything else as JSON
+fn render(output: &Value) -> String {
+    output
+        .get("answer")
+        .and_then(Value::as_str)
+        .map_or_else(|| output.to_string(), ToString::to_string)
+}
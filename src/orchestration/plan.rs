@@ -0,0 +1,124 @@
+//! The plan a planner agent answers with, and the checks it has to pass
+//! before any task is created.
+
+use std::collections::{HashMap, HashSet};
+
+use loco_rs::{Error, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Plan {
+    pub tasks: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Step {
+    /// Identifies the step within the plan so others can depend on it
+    pub key: String,
+    pub agent_id: Uuid,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+impl Plan {
+    /// Reads a plan out of a model reply, tolerating a surrounding markdown
+    /// code fence
+    ///
+    /// # Errors
+    ///
+    /// When the reply is not a plan
+    pub fn parse(reply: &str) -> Result<Self> {
+        let reply = reply.trim();
+        let json = reply
+            .strip_prefix("```json")
+            .or_else(|| reply.strip_prefix("```"))
+            .and_then(|rest| rest.strip_suffix("```"))
+            .unwrap_or(reply);
+        serde_json::from_str(json.trim())
+            .map_err(|err| Error::BadRequest(format!("planner returned an invalid plan: {err}")))
+    }
+
+    /// Checks the plan against the agents the planner may delegate to and
+    /// returns its steps ordered so every step comes after its dependencies
+    ///
+    /// # Errors
+    ///
+    /// When the plan is empty or too long, repeats a key, assigns an agent
+    /// outside `delegates`, depends on an unknown step or has a cycle
+    pub fn validate(mut self, delegates: &[Uuid], max_steps: usize) -> Result<Vec<Step>> {
+        if self.tasks.is_empty() {
+            return Err(invalid("the plan has no tasks"));
+        }
+        if self.tasks.len() > max_steps {
+            return Err(invalid(format!(
+                "the plan has {} tasks, at most {max_steps} are allowed",
+                self.tasks.len()
+            )));
+        }
+
+        // a dependency listed twice is still one dependency
+        for step in &mut self.tasks {
+            let mut seen = HashSet::new();
+            step.depends_on.retain(|key| seen.insert(key.clone()));
+        }
+
+        let mut keys = HashSet::new();
+        for step in &self.tasks {
+            if !keys.insert(step.key.as_str()) {
+                return Err(invalid(format!("task key {} is used twice", step.key)));
+            }
+            if !delegates.contains(&step.agent_id) {
+                return Err(invalid(format!(
+                    "task {} is assigned to agent {}, which cannot be delegated to",
+                    step.key, step.agent_id
+                )));
+            }
+        }
+        for step in &self.tasks {
+            if let Some(unknown) = step
+                .depends_on
+                .iter()
+                .find(|key| !keys.contains(key.as_str()))
+            {
+                return Err(invalid(format!(
+                    "task {} depends on unknown task {unknown}",
+                    step.key
+                )));
+            }
+        }
+
+        // Kahn's algorithm, keeping the planner's order among ready steps
+        let mut remaining: HashMap<&str, usize> = self
+            .tasks
+            .iter()
+            .map(|step| (step.key.as_str(), step.depends_on.len()))
+            .collect();
+        let mut ordered: Vec<Step> = Vec::with_capacity(self.tasks.len());
+        while ordered.len() < self.tasks.len() {
+            let Some(next) = self
+                .tasks
+                .iter()
+                .find(|step| remaining.get(step.key.as_str()) == Some(&0))
+            else {
+                return Err(invalid("the plan has a dependency cycle"));
+            };
+            remaining.remove(next.key.as_str());
+            for step in &self.tasks {
+                if step.depends_on.contains(&next.key) {
+                    if let Some(count) = remaining.get_mut(step.key.as_str()) {
+                        *count -= 1;
+                    }
+                }
+            }
+            ordered.push(next.clone());
+        }
+        Ok(ordered)
+    }
+}
+
+fn invalid(reason: impl std::fmt::Display) -> Error {
+    Error::BadRequest(format!("planner returned an invalid plan: {reason}"))
+}
@@ -0,0 +1,89 @@
+//! The delegation tree of a task, for inspecting how work was split up
+
+use std::collections::HashMap;
+
+use loco_rs::Result;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::{agents, task_dependencies, tasks};
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    #[serde(flatten)]
+    pub task: tasks::Model,
+    pub agent_name: Option<String>,
+    /// Sibling tasks whose output this task received
+    pub depends_on: Vec<Uuid>,
+    pub children: Vec<Node>,
+}
+
+/// Loads the task with everything it delegated, directly or indirectly
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn build(db: &DatabaseConnection, root: tasks::Model) -> Result<Node> {
+    let mut all = vec![root.clone()];
+    let mut frontier = vec![root.id];
+    while !frontier.is_empty() {
+        let children = tasks::Entity::find()
+            .filter(tasks::tasks::Column::ParentId.is_in(frontier))
+            .all(db)
+            .await?;
+        frontier = children.iter().map(|task| task.id).collect();
+        all.extend(children);
+    }
+
+    let ids: Vec<Uuid> = all.iter().map(|task| task.id).collect();
+    let mut depends_on: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for dependency in task_dependencies::Model::list_for_tasks(db, &ids).await? {
+        depends_on
+            .entry(dependency.task_id)
+            .or_default()
+            .push(dependency.depends_on_task_id);
+    }
+    let agent_names: HashMap<Uuid, String> = agents::Entity::find()
+        .filter(agents::agents::Column::Id.is_in(all.iter().map(|task| task.agent_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|agent| (agent.id, agent.name))
+        .collect();
+
+    let mut by_parent: HashMap<Option<Uuid>, Vec<tasks::Model>> = HashMap::new();
+    for task in all.into_iter().skip(1) {
+        by_parent.entry(task.parent_id).or_default().push(task);
+    }
+    for siblings in by_parent.values_mut() {
+        siblings.sort_by_key(|task| (task.priority, task.created_at));
+    }
+
+    Ok(assemble(
+        root,
+        &mut by_parent,
+        &mut depends_on,
+        &agent_names,
+    ))
+}
+
+fn assemble(
+    task: tasks::Model,
+    by_parent: &mut HashMap<Option<Uuid>, Vec<tasks::Model>>,
+    depends_on: &mut HashMap<Uuid, Vec<Uuid>>,
+    agent_names: &HashMap<Uuid, String>,
+) -> Node {
+    let children = by_parent
+        .remove(&Some(task.id))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| assemble(child, by_parent, depends_on, agent_names))
+        .collect();
+    Node {
+        agent_name: agent_names.get(&task.agent_id).cloned(),
+        depends_on: depends_on.remove(&task.id).unwrap_or_default(),
+        children,
+        task,
+    }
+}
@@ -0,0 +1,453 @@
+//! Subscription plans and the quotas that come with them. A user's plan is
+//! stored on `users.plan`; its limits come from the `settings.plans`
+//! section. Limits left out are unlimited.
+//!
+//! Rate quotas (messages per day, tokens per month) answer 429 once used
+//! up, since waiting helps; size quotas (agents, knowledge storage) answer
+//! 402, since only a bigger plan does. Metered responses carry the state
+//! of their quotas in `X-Quota-*` headers.
+
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use chrono::{Datelike, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use loco_rs::{controller::format, prelude::Response, Error, Result};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    JoinType, PaginatorTrait, QueryFilter, QuerySelect, RelationTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::{
+    agents, conversations, knowledge_base, knowledge_items,
+    messages::{self, ROLE_USER},
+    usage_records, users,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Plan {
+    #[default]
+    Free,
+    Pro,
+    Team,
+}
+
+impl Plan {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Free => "free",
+            Self::Pro => "pro",
+            Self::Team => "team",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Free, Self::Pro, Self::Team]
+            .into_iter()
+            .find(|plan| plan.as_str() == value)
+    }
+}
+
+/// What a plan allows. `None` is unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Limits {
+    #[serde(default)]
+    pub messages_per_day: Option<u64>,
+    #[serde(default)]
+    pub tokens_per_month: Option<u64>,
+    #[serde(default)]
+    pub agents: Option<u64>,
+    /// Size of the knowledge content the user's knowledge bases hold
+    #[serde(default)]
+    pub knowledge_bytes: Option<u64>,
+}
+
+/// The `settings.plans` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default = "default_free")]
+    pub free: Limits,
+    #[serde(default = "default_pro")]
+    pub pro: Limits,
+    #[serde(default = "default_team")]
+    pub team: Limits,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            free: default_free(),
+            pro: default_pro(),
+            team: default_team(),
+        }
+    }
+}
+
+impl Config {
+    #[must_use]
+    pub const fn limits(&self, plan: Plan) -> &Limits {
+        match plan {
+            Plan::Free => &self.free,
+            Plan::Pro => &self.pro,
+            Plan::Team => &self.team,
+        }
+    }
+}
+
+const MIB: u64 = 1024 * 1024;
+
+const fn default_free() -> Limits {
+    Limits {
+        messages_per_day: Some(100),
+        tokens_per_month: Some(200_000),
+        agents: Some(3),
+        knowledge_bytes: Some(10 * MIB),
+    }
+}
+
+const fn default_pro() -> Limits {
+    Limits {
+        messages_per_day: Some(2_000),
+        tokens_per_month: Some(5_000_000),
+        agents: Some(25),
+        knowledge_bytes: Some(1024 * MIB),
+    }
+}
+
+const fn default_team() -> Limits {
+    Limits {
+        messages_per_day: None,
+        tokens_per_month: Some(50_000_000),
+        agents: None,
+        knowledge_bytes: Some(10 * 1024 * MIB),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quota {
+    MessagesPerDay,
+    TokensPerMonth,
+    Agents,
+    KnowledgeBytes,
+}
+
+impl Quota {
+    /// The name used in the quota headers
+    #[must_use]
+    pub const fn header_name(self) -> &'static str {
+        match self {
+            Self::MessagesPerDay => "messages",
+            Self::TokensPerMonth => "tokens",
+            Self::Agents => "agents",
+            Self::KnowledgeBytes => "knowledge-bytes",
+        }
+    }
+
+    /// 429 for quotas that reset, 402 for those only a bigger plan lifts
+    #[must_use]
+    pub const fn status(self) -> StatusCode {
+        match self {
+            Self::MessagesPerDay | Self::TokensPerMonth => StatusCode::TOO_MANY_REQUESTS,
+            Self::Agents | Self::KnowledgeBytes => StatusCode::PAYMENT_REQUIRED,
+        }
+    }
+
+    const fn limit(self, limits: &Limits) -> Option<u64> {
+        match self {
+            Self::MessagesPerDay => limits.messages_per_day,
+            Self::TokensPerMonth => limits.tokens_per_month,
+            Self::Agents => limits.agents,
+            Self::KnowledgeBytes => limits.knowledge_bytes,
+        }
+    }
+
+    fn describe(self, limit: u64, plan: Plan) -> String {
+        let plan = plan.as_str();
+        match self {
+            Self::MessagesPerDay => {
+                format!("the {plan} plan allows {limit} messages per day")
+            }
+            Self::TokensPerMonth => {
+                format!("the {plan} plan allows {limit} tokens per month")
+            }
+            Self::Agents => format!("the {plan} plan allows {limit} agents"),
+            Self::KnowledgeBytes => {
+                format!("the {plan} plan allows {limit} bytes of knowledge")
+            }
+        }
+    }
+}
+
+/// How much of a quota is used
+#[derive(Debug, Clone, Serialize)]
+pub struct Meter {
+    pub quota: Quota,
+    pub limit: Option<u64>,
+    pub used: u64,
+    /// When the count starts over, for quotas that reset
+    pub resets_at: Option<NaiveDateTime>,
+}
+
+impl Meter {
+    #[must_use]
+    pub fn remaining(&self) -> Option<u64> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+
+    /// Whether `amount` more fits in the quota
+    #[must_use]
+    pub fn allows(&self, amount: u64) -> bool {
+        self.limit
+            .is_none_or(|limit| self.used.saturating_add(amount) <= limit)
+    }
+}
+
+/// The plan of a user with the quotas measured for a request
+#[derive(Debug, Clone, Serialize)]
+pub struct Entitlement {
+    pub plan: Plan,
+    pub meters: Vec<Meter>,
+}
+
+/// A quota a request would go over
+#[derive(Debug, Clone)]
+pub struct Exceeded {
+    pub plan: Plan,
+    pub meter: Meter,
+}
+
+impl Entitlement {
+    /// Measures the user's use of the given quotas
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn measure(
+        db: &DatabaseConnection,
+        config: &Config,
+        user: &users::Model,
+        quotas: &[Quota],
+    ) -> Result<Self> {
+        let plan = user.plan();
+        let limits = config.limits(plan);
+        let mut meters = Vec::with_capacity(quotas.len());
+        for &quota in quotas {
+            let (used, resets_at) = match quota {
+                Quota::MessagesPerDay => {
+                    let today = Local::now().date_naive();
+                    (
+                        messages_since(db, user.id, start_of(today)).await?,
+                        today.succ_opt().map(start_of),
+                    )
+                }
+                Quota::TokensPerMonth => {
+                    let month = Local::now().date_naive().with_day(1).unwrap_or_default();
+                    (
+                        tokens_since(db, user.id, start_of(month)).await?,
+                        month.checked_add_months(Months::new(1)).map(start_of),
+                    )
+                }
+                Quota::Agents => (agent_count(db, user.id).await?, None),
+                Quota::KnowledgeBytes => (knowledge_bytes(db, user.id).await?, None),
+            };
+            meters.push(Meter {
+                quota,
+                limit: quota.limit(limits),
+                used,
+                resets_at,
+            });
+        }
+        Ok(Self { plan, meters })
+    }
+
+    #[must_use]
+    pub fn meter(&self, quota: Quota) -> Option<&Meter> {
+        self.meters.iter().find(|meter| meter.quota == quota)
+    }
+
+    /// Checks that every quota has room for the amount the request adds
+    ///
+    /// # Errors
+    ///
+    /// The first quota that would be exceeded
+    pub fn require(&self, amounts: &[(Quota, u64)]) -> std::result::Result<(), Exceeded> {
+        for &(quota, amount) in amounts {
+            if let Some(meter) = self.meter(quota).filter(|meter| !meter.allows(amount)) {
+                return Err(Exceeded {
+                    plan: self.plan,
+                    meter: meter.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts what the request used towards a quota
+    pub fn consume(&mut self, quota: Quota, amount: u64) {
+        if let Some(meter) = self.meters.iter_mut().find(|meter| meter.quota == quota) {
+            meter.used = meter.used.saturating_add(amount);
+        }
+    }
+
+    /// Adds the plan and quota headers to a response
+    #[must_use]
+    pub fn with_headers(&self, mut response: Response) -> Response {
+        let headers = response.headers_mut();
+        headers.insert(
+            HeaderName::from_static("x-plan"),
+            HeaderValue::from_static(self.plan.as_str()),
+        );
+        for meter in &self.meters {
+            let name = meter.quota.header_name();
+            let mut set = |field: &str, value: String| {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::try_from(format!("x-quota-{name}-{field}")),
+                    HeaderValue::try_from(value),
+                ) {
+                    headers.insert(name, value);
+                }
+            };
+            set("used", meter.used.to_string());
+            if let Some(limit) = meter.limit {
+                set("limit", limit.to_string());
+            }
+            if let Some(remaining) = meter.remaining() {
+                set("remaining", remaining.to_string());
+            }
+            if let Some(resets_at) = meter.resets_at {
+                set("reset", timestamp(resets_at).to_string());
+            }
+        }
+        response
+    }
+}
+
+impl Exceeded {
+    /// Why the request was refused
+    #[must_use]
+    pub fn description(&self) -> String {
+        self.meter
+            .quota
+            .describe(self.meter.limit.unwrap_or_default(), self.plan)
+    }
+
+    /// The error for requests made away from a controller, such as by a
+    /// tool or a background job, where only its message is reported
+    #[must_use]
+    pub fn into_error(self) -> Error {
+        Error::Message(self.description())
+    }
+
+    /// The error response, with the quota headers and, for quotas that
+    /// reset, a `Retry-After`
+    ///
+    /// # Errors
+    ///
+    /// When the response could not be built
+    pub fn into_response(self, entitlement: &Entitlement) -> Result<Response> {
+        let mut response = format::render()
+            .status(self.meter.quota.status())
+            .json(json!({
+                "error": "quota_exceeded",
+                "description": self.description(),
+                "quota": self.meter.quota,
+                "plan": self.plan,
+            }))?;
+        if let Some(resets_at) = self.meter.resets_at {
+            let wait = (timestamp(resets_at) - Local::now().timestamp()).max(1);
+            if let Ok(value) = HeaderValue::try_from(wait.to_string()) {
+                response
+                    .headers_mut()
+                    .insert(axum::http::header::RETRY_AFTER, value);
+            }
+        }
+        Ok(entitlement.with_headers(response))
+    }
+}
+
+fn start_of(day: NaiveDate) -> NaiveDateTime {
+    day.and_time(NaiveTime::MIN)
+}
+
+fn timestamp(at: NaiveDateTime) -> i64 {
+    Local
+        .from_local_datetime(&at)
+        .earliest()
+        .map_or_else(|| at.and_utc().timestamp(), |at| at.timestamp())
+}
+
+fn to_u64(value: Option<i64>) -> u64 {
+    value
+        .and_then(|value| u64::try_from(value).ok())
+        .unwrap_or(0)
+}
+
+/// Messages the user sent to agents since `since`
+async fn messages_since(
+    db: &DatabaseConnection,
+    user_id: i32,
+    since: NaiveDateTime,
+) -> Result<u64> {
+    Ok(messages::Entity::find()
+        .join(
+            JoinType::InnerJoin,
+            messages::messages::Relation::Conversations.def(),
+        )
+        .filter(conversations::conversations::Column::UserId.eq(user_id))
+        .filter(messages::messages::Column::Role.eq(ROLE_USER))
+        .filter(messages::messages::Column::CreatedAt.gte(since))
+        .count(db)
+        .await?)
+}
+
+/// Tokens used by calls made for the user since `since`
+async fn tokens_since(db: &DatabaseConnection, user_id: i32, since: NaiveDateTime) -> Result<u64> {
+    let tokens: Option<Option<i64>> = usage_records::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::expr(
+                Expr::col(usage_records::usage_records::Column::PromptTokens).add(Expr::col(
+                    usage_records::usage_records::Column::CompletionTokens,
+                )),
+            )
+            .sum(),
+            "tokens",
+        )
+        .filter(usage_records::usage_records::Column::UserId.eq(user_id))
+        .filter(usage_records::usage_records::Column::CreatedAt.gte(since))
+        .into_tuple()
+        .one(db)
+        .await?;
+    Ok(to_u64(tokens.flatten()))
+}
+
+async fn agent_count(db: &DatabaseConnection, user_id: i32) -> Result<u64> {
+    Ok(agents::Entity::find()
+        .filter(agents::agents::Column::UserId.eq(user_id))
+        .count(db)
+        .await?)
+}
+
+/// Bytes of content in the knowledge bases the user owns
+async fn knowledge_bytes(db: &DatabaseConnection, user_id: i32) -> Result<u64> {
+    let size = match db.get_database_backend() {
+        DbBackend::Postgres => "SUM(OCTET_LENGTH(knowledge_items.content))",
+        DbBackend::Sqlite => "SUM(LENGTH(CAST(knowledge_items.content AS BLOB)))",
+        DbBackend::MySql => "SUM(LENGTH(knowledge_items.content))",
+    };
+    let bytes: Option<Option<i64>> = knowledge_items::Entity::find()
+        .select_only()
+        .column_as(Expr::cust(size), "bytes")
+        .join(
+            JoinType::InnerJoin,
+            knowledge_items::knowledge_items::Relation::KnowledgeBase.def(),
+        )
+        .filter(knowledge_base::knowledge_base::Column::UserId.eq(user_id))
+        .into_tuple()
+        .one(db)
+        .await?;
+    Ok(to_u64(bytes.flatten()))
+}
@@ -0,0 +1,342 @@
+//! Who may do what. Agents, knowledge bases and conversations are shared
+//! within an organization, and the role of a member decides which
+//! [`Action`]s they may take on them.
+//!
+//! Handlers take an [`Actor`]: the signed-in user in the organization they
+//! act in, picked with the `X-Organization` header and otherwise the first
+//! one they joined. Things from before organizations have no organization
+//! and are checked against the one the user acts in; conversations among
+//! them stay private to whoever started them.
+//!
+//! A user outside an organization gets 404 for its things, as if they did
+//! not exist; a member whose role falls short gets 403.
+//!
+//! Work done for a user while they are not around, like scheduled runs,
+//! is checked again with [`permits`] when it happens, as they may have left
+//! the organization or lost their role since.
+//!
+//! Handlers meant for scripts take a [`Caller`] instead, which also accepts
+//! an API key as the bearer token. A key acts as its user, limited to the
+//! [`Scope`]s it was given.
+
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+};
+use loco_rs::{
+    app::AppContext,
+    controller::{middleware::auth, ErrorDetail},
+    model::ModelError,
+    Error, Result,
+};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use uuid::Uuid;
+
+use crate::models::{
+    agents, api_keys, conversations, learning_models, memberships, organizations, users,
+};
+pub use crate::models::{api_keys::Scope, memberships::Role};
+
+/// Picks the organization a request acts in
+pub const ORGANIZATION_HEADER: &str = "x-organization";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Read agents, conversations, tasks and the member list
+    View,
+    /// Chat with agents, run tasks and give feedback on answers
+    Use,
+    /// Create, change and import agents, and train and review their models
+    Edit,
+    /// Invite and remove members and change their roles, short of owner
+    Manage,
+    /// Rename or delete the organization and appoint or remove owners
+    Administer,
+}
+
+impl Action {
+    const fn describe(self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Use => "use",
+            Self::Edit => "edit",
+            Self::Manage => "manage the members of",
+            Self::Administer => "administer",
+        }
+    }
+}
+
+impl Role {
+    #[must_use]
+    pub const fn allows(self, action: Action) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Admin => !matches!(action, Action::Administer),
+            Self::Member => matches!(action, Action::View | Action::Use | Action::Edit),
+            Self::Viewer => matches!(action, Action::View),
+        }
+    }
+}
+
+/// The signed-in user acting in an organization
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub user: users::Model,
+    pub organization: organizations::Model,
+    pub role: Role,
+}
+
+impl Actor {
+    /// The user acting in the given organization
+    ///
+    /// # Errors
+    ///
+    /// `NotFound` when the user is not a member, or DB query error
+    pub async fn within(
+        db: &DatabaseConnection,
+        user: users::Model,
+        organization_id: Uuid,
+    ) -> Result<Self> {
+        let membership = memberships::Model::find_for(db, organization_id, user.id)
+            .await
+            .map_err(|_| Error::NotFound)?;
+        let organization = organizations::Model::find_by_id(db, organization_id)
+            .await
+            .map_err(|_| Error::NotFound)?;
+        Ok(Self {
+            user,
+            organization,
+            role: membership.role(),
+        })
+    }
+
+    /// The user acting in the organization they joined first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn default_for(db: &DatabaseConnection, user: users::Model) -> Result<Self> {
+        let (organization, membership) = organizations::Model::default_for(db, &user).await?;
+        Ok(Self {
+            user,
+            organization,
+            role: membership.role(),
+        })
+    }
+
+    /// Checks the role allows the action in the organization acted in
+    ///
+    /// # Errors
+    ///
+    /// 403 when it does not
+    pub fn require(&self, action: Action) -> Result<()> {
+        if self.role.allows(action) {
+            return Ok(());
+        }
+        Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new(
+                "forbidden".to_string(),
+                format!(
+                    "the {} role cannot {} this organization",
+                    self.role.as_str(),
+                    action.describe()
+                ),
+            ),
+        ))
+    }
+
+    /// Checks the action is allowed on something owned by
+    /// `organization_id`, and returns the user acting in that organization
+    ///
+    /// # Errors
+    ///
+    /// `NotFound` when the user is not a member of it, 403 when their role
+    /// does not allow the action, or DB query error
+    pub async fn on(
+        &self,
+        db: &DatabaseConnection,
+        organization_id: Option<Uuid>,
+        action: Action,
+    ) -> Result<Self> {
+        let actor = match organization_id {
+            Some(id) if id != self.organization.id => {
+                Self::within(db, self.user.clone(), id).await?
+            }
+            _ => self.clone(),
+        };
+        actor.require(action)?;
+        Ok(actor)
+    }
+
+    /// Checks the action is allowed on the agent
+    ///
+    /// # Errors
+    ///
+    /// As [`Actor::on`]
+    pub async fn authorize_agent(
+        &self,
+        db: &DatabaseConnection,
+        agent: &agents::Model,
+        action: Action,
+    ) -> Result<Self> {
+        self.on(db, agent.organization_id, action).await
+    }
+
+    /// Checks the action is allowed on the learning model, which is shared
+    /// along with the agent it belongs to
+    ///
+    /// # Errors
+    ///
+    /// As [`Actor::on`]
+    pub async fn authorize_learning_model(
+        &self,
+        db: &DatabaseConnection,
+        model: &learning_models::Model,
+        action: Action,
+    ) -> Result<Self> {
+        let organization_id = match model.agent_id {
+            Some(agent_id) => agents::Model::find_by_id(db, agent_id)
+                .await
+                .ok()
+                .and_then(|agent| agent.organization_id),
+            None => None,
+        };
+        self.on(db, organization_id, action).await
+    }
+
+    /// Checks the action is allowed on the conversation
+    ///
+    /// # Errors
+    ///
+    /// As [`Actor::on`], and `NotFound` for someone else's conversation
+    /// from before organizations
+    pub async fn authorize_conversation(
+        &self,
+        db: &DatabaseConnection,
+        conversation: &conversations::Model,
+        action: Action,
+    ) -> Result<Self> {
+        if conversation.organization_id.is_none() && conversation.user_id != self.user.id {
+            return Err(Error::NotFound);
+        }
+        self.on(db, conversation.organization_id, action).await
+    }
+}
+
+/// Whether the user may take the action on the agent, as a member of its
+/// organization. Agents from before organizations are left to whoever had
+/// access to them when the work was set up.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn permits(
+    db: &DatabaseConnection,
+    user: &users::Model,
+    agent: &agents::Model,
+    action: Action,
+) -> Result<bool> {
+    let Some(organization_id) = agent.organization_id else {
+        return Ok(true);
+    };
+    match memberships::Model::find_for(db, organization_id, user.id).await {
+        Ok(membership) => Ok(membership.role().allows(action)),
+        Err(ModelError::EntityNotFound) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppContext> for Actor {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, ctx: &AppContext) -> Result<Self> {
+        let auth = auth::JWT::from_request_parts(parts, ctx).await?;
+        let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+        Self::picked_by(parts, ctx, user).await
+    }
+}
+
+impl Actor {
+    /// The user acting in the organization the request picked
+    async fn picked_by(parts: &Parts, ctx: &AppContext, user: users::Model) -> Result<Self> {
+        let picked = parts
+            .headers
+            .get(ORGANIZATION_HEADER)
+            .map(|value| {
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| Uuid::parse_str(value.trim()).ok())
+                    .ok_or_else(|| Error::BadRequest("invalid organization id".to_string()))
+            })
+            .transpose()?;
+        match picked {
+            Some(organization_id) => Self::within(&ctx.db, user, organization_id).await,
+            None => Self::default_for(&ctx.db, user).await,
+        }
+    }
+}
+
+/// Whoever made a request that API keys may be used for: an [`Actor`], and
+/// the key when one was used instead of a signed-in session
+#[derive(Debug, Clone)]
+pub struct Caller {
+    actor: Actor,
+    key: Option<api_keys::Model>,
+}
+
+impl Caller {
+    /// The actor, once checked the request may be made with the scope
+    ///
+    /// # Errors
+    ///
+    /// 403 when made with a key that was not given the scope
+    pub fn scoped(self, scope: Scope) -> Result<Actor> {
+        match &self.key {
+            Some(key) if !key.allows(scope) => Err(Error::CustomError(
+                StatusCode::FORBIDDEN,
+                ErrorDetail::new(
+                    "insufficient_scope".to_string(),
+                    format!("the API key is missing the {} scope", scope.as_str()),
+                ),
+            )),
+            _ => Ok(self.actor),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppContext> for Caller {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, ctx: &AppContext) -> Result<Self> {
+        let api_key = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| token.starts_with(api_keys::KEY_PREFIX));
+        let Some(api_key) = api_key else {
+            return Ok(Self {
+                actor: Actor::from_request_parts(parts, ctx).await?,
+                key: None,
+            });
+        };
+        let unauthorized = || Error::Unauthorized("invalid API key".to_string());
+        let key = api_keys::Model::authenticate(&ctx.db, api_key)
+            .await
+            .map_err(|_| unauthorized())?;
+        let user = users::Entity::find_by_id(key.user_id)
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(unauthorized)?;
+        Ok(Self {
+            actor: Actor::picked_by(parts, ctx, user).await?,
+            key: Some(key),
+        })
+    }
+}
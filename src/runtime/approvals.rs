@@ -0,0 +1,93 @@
+//! Pending approvals for tool calls to capabilities marked
+//! `requires_approval`, and how their owners hear about them.
+
+use chrono::{offset::Local, NaiveDateTime};
+use loco_rs::{app::AppContext, Result};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+use serde_json::json;
+
+use super::Runtime;
+use crate::{
+    common::events::{self, Event},
+    llm::ToolCall,
+    mailers::approvals::ApprovalMailer,
+    models::{
+        agent_capabilities, agents, conversations, messages,
+        tool_approvals::{self, Status},
+        users,
+    },
+};
+
+pub const EVENT_REQUESTED: &str = "approval_requested";
+pub const EVENT_DECIDED: &str = "approval_decided";
+
+/// Stores a pending approval for a tool call and notifies the conversation
+/// owner by email and on the event stream
+///
+/// # Errors
+///
+/// When the approval could not be saved or the email could not be queued
+pub async fn request(
+    ctx: &AppContext,
+    agent: &agents::Model,
+    conversation: &conversations::Model,
+    message: &messages::Model,
+    capability: &agent_capabilities::Model,
+    call: &ToolCall,
+    expires_at: NaiveDateTime,
+) -> Result<tool_approvals::Model> {
+    let approval = tool_approvals::ActiveModel {
+        conversation_id: ActiveValue::set(conversation.id),
+        message_id: ActiveValue::set(message.id),
+        capability_id: ActiveValue::set(capability.id),
+        user_id: ActiveValue::set(conversation.user_id),
+        tool_call_id: ActiveValue::set(call.id.clone()),
+        name: ActiveValue::set(call.name.clone()),
+        arguments: ActiveValue::set(call.arguments.clone()),
+        expires_at: ActiveValue::set(expires_at),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+
+    events::publish(Event::new(
+        approval.user_id,
+        EVENT_REQUESTED,
+        json!({ "agent_id": agent.id, "approval": approval }),
+    ));
+    let owner = users::Entity::find_by_id(approval.user_id)
+        .one(&ctx.db)
+        .await?;
+    if let Some(owner) = owner {
+        ApprovalMailer::send_requested(ctx, &owner, agent, &approval).await?;
+    }
+
+    Ok(approval)
+}
+
+pub(super) fn notify_decided(approval: &tool_approvals::Model) {
+    events::publish(Event::new(
+        approval.user_id,
+        EVENT_DECIDED,
+        json!({ "approval": approval }),
+    ));
+}
+
+/// Expires every approval whose deadline has passed and lets the affected
+/// conversations carry on. Returns how many approvals expired.
+///
+/// # Errors
+///
+/// When the approvals could not be loaded. Failures to resume a single
+/// conversation are logged and skipped.
+pub async fn expire_overdue(ctx: &AppContext, runtime: &Runtime<'_>) -> Result<usize> {
+    let overdue = tool_approvals::Model::list_overdue(&ctx.db, Local::now().naive_local()).await?;
+    let count = overdue.len();
+    for approval in overdue {
+        let id = approval.id;
+        if let Err(err) = runtime.decide(ctx, approval, Status::Expired).await {
+            tracing::error!(approval_id = %id, err = %err, "could not expire approval");
+        }
+    }
+    Ok(count)
+}
@@ -0,0 +1,532 @@
+//! Runs agent turns: sends the conversation and the agent's tools to the
+//! LLM provider, executes the tool calls it asks for and feeds the results
+//! back until the model answers.
+//!
+//! Calls to capabilities that require approval pause the turn. The turn is
+//! picked up again by [`Runtime::decide`] once every pending approval of the
+//! conversation has been approved, rejected or has expired.
+
+use axum::http::StatusCode;
+use chrono::{offset::Local, Duration};
+use loco_rs::{app::AppContext, controller::ErrorDetail, Error, Result};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    llm::{ChatMessage, CompletionRequest, Provider, ToolCall},
+    models::{
+        agent_capabilities, agent_versions, agents, conversations,
+        messages::{self, ROLE_ASSISTANT, ROLE_TOOL, ROLE_USER},
+        tool_approvals::{self, Status as ApprovalStatus},
+    },
+    tools::{schema, Invocation, Registry, Tool},
+    usage::{Metered, Prices, Scope},
+};
+
+pub mod approvals;
+
+/// The `settings.runtime` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// How many times the model may ask for tools before it has to answer
+    #[serde(default = "default_max_tool_rounds")]
+    pub max_tool_rounds: usize,
+    /// How long a tool call may wait for approval before it expires
+    #[serde(default = "default_approval_ttl_secs")]
+    pub approval_ttl_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_tool_rounds: default_max_tool_rounds(),
+            approval_ttl_secs: default_approval_ttl_secs(),
+        }
+    }
+}
+
+const fn default_max_tool_rounds() -> usize {
+    8
+}
+
+const fn default_approval_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+/// How a tool call ended, stored in the metadata of its `tool` message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolStatus {
+    Ok,
+    /// The arguments did not match the capability schema
+    Invalid,
+    /// The agent has no such capability or no tool implements it
+    Unknown,
+    Failed,
+    /// The conversation owner refused the call
+    Rejected,
+    /// Nobody decided on the call in time
+    Expired,
+}
+
+impl ToolStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Invalid => "invalid",
+            Self::Unknown => "unknown",
+            Self::Failed => "failed",
+            Self::Rejected => "rejected",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// The messages written while running the agent. When `approvals` is not
+/// empty the turn is paused until they are decided.
+#[derive(Debug, Default, Serialize)]
+pub struct Turn {
+    pub messages: Vec<messages::Model>,
+    pub approvals: Vec<tool_approvals::Model>,
+}
+
+impl Turn {
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        !self.approvals.is_empty()
+    }
+}
+
+/// A tool call that passed validation and is ready to run
+struct Resolved<'c, 'r> {
+    capability: &'c agent_capabilities::Model,
+    tool: &'r dyn Tool,
+}
+
+/// The tool message content and status of a call that could not be run
+type Rejection = (ToolStatus, Option<Uuid>, Value);
+
+pub struct Runtime<'a> {
+    provider: &'a dyn Provider,
+    registry: &'a Registry,
+    max_tool_rounds: usize,
+    approval_ttl: Duration,
+    prices: Prices,
+}
+
+impl<'a> Runtime<'a> {
+    #[must_use]
+    pub fn new(provider: &'a dyn Provider, registry: &'a Registry) -> Self {
+        Self {
+            provider,
+            registry,
+            max_tool_rounds: default_max_tool_rounds(),
+            approval_ttl: ttl(default_approval_ttl_secs()),
+            prices: Prices::default(),
+        }
+    }
+
+    /// Applies the limits from the `settings.runtime` config section
+    #[must_use]
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.max_tool_rounds = config.max_tool_rounds;
+        self.approval_ttl = ttl(config.approval_ttl_secs);
+        self
+    }
+
+    /// Prices the usage recorded for every provider call
+    #[must_use]
+    pub fn with_prices(mut self, prices: &Prices) -> Self {
+        self.prices = prices.clone();
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_tool_rounds(mut self, max_tool_rounds: usize) -> Self {
+        self.max_tool_rounds = max_tool_rounds;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_approval_ttl(mut self, approval_ttl: Duration) -> Self {
+        self.approval_ttl = approval_ttl;
+        self
+    }
+
+    /// Adds a user message to the conversation and runs the agent until it
+    /// answers or waits for approval. The returned messages start with the
+    /// user message.
+    ///
+    /// # Errors
+    ///
+    /// When the conversation is waiting for approvals, the agent does not
+    /// exist, a provider call fails or a message could not be saved
+    pub async fn reply(
+        &self,
+        ctx: &AppContext,
+        conversation: &conversations::Model,
+        content: &str,
+    ) -> Result<Turn> {
+        let pending =
+            tool_approvals::Model::list_pending_for_conversation(&ctx.db, conversation.id).await?;
+        if !pending.is_empty() {
+            return Err(Error::BadRequest(
+                "conversation is waiting for tool approvals".to_string(),
+            ));
+        }
+
+        let message =
+            messages::Model::append(&ctx.db, conversation.id, ROLE_USER, content, None).await?;
+        let mut turn = self.run(ctx, conversation).await?;
+        turn.messages.insert(0, message);
+        Ok(turn)
+    }
+
+    /// Settles a pending approval: runs the tool when approved, otherwise
+    /// tells the model why it did not run. Once nothing else is pending the
+    /// agent carries on with the turn.
+    ///
+    /// # Errors
+    ///
+    /// When the approval is no longer pending (a 409), a provider call fails
+    /// or a message could not be saved
+    pub async fn decide(
+        &self,
+        ctx: &AppContext,
+        approval: tool_approvals::Model,
+        decision: ApprovalStatus,
+    ) -> Result<Turn> {
+        if decision == ApprovalStatus::Pending {
+            return Err(Error::BadRequest("a decision is required".to_string()));
+        }
+        // claimed before anything runs, so a concurrent decision or the
+        // expiry task cannot run the gated tool a second time
+        if !approval.decide(&ctx.db, decision).await? {
+            return Err(already_decided());
+        }
+
+        let conversation = conversations::Entity::find_by_id(approval.conversation_id)
+            .one(&ctx.db)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let call = ToolCall {
+            id: approval.tool_call_id.clone(),
+            name: approval.name.clone(),
+            arguments: approval.arguments.clone(),
+        };
+
+        let (status, result) = match decision {
+            ApprovalStatus::Approved => {
+                let agent = agents::Model::find_by_id(&ctx.db, conversation.agent_id).await?;
+                let capabilities = self.capabilities(ctx, &agent).await?;
+                let invocation = Invocation {
+                    ctx,
+                    agent: &agent,
+                    conversation: &conversation,
+                };
+                match self.resolve(&capabilities, &call) {
+                    Ok(resolved) => {
+                        let (status, _, result) = run_tool(&invocation, &resolved, &call).await;
+                        (status, result)
+                    }
+                    Err((status, _, result)) => (status, result),
+                }
+            }
+            ApprovalStatus::Rejected => (
+                ToolStatus::Rejected,
+                json!({ "error": "the user rejected this tool call" }),
+            ),
+            ApprovalStatus::Expired => (
+                ToolStatus::Expired,
+                json!({ "error": "the approval request expired" }),
+            ),
+            ApprovalStatus::Pending => unreachable!("checked above"),
+        };
+
+        let approval = tool_approvals::Entity::find_by_id(approval.id)
+            .one(&ctx.db)
+            .await?
+            .ok_or(Error::NotFound)?;
+        approvals::notify_decided(&approval);
+        let message = save_tool_result(
+            ctx,
+            conversation.id,
+            &call,
+            Some(approval.capability_id),
+            status,
+            &result,
+        )
+        .await?;
+
+        let approvals =
+            tool_approvals::Model::list_pending_for_conversation(&ctx.db, conversation.id).await?;
+        let mut turn = if approvals.is_empty() {
+            self.run(ctx, &conversation).await?
+        } else {
+            Turn {
+                approvals,
+                ..Default::default()
+            }
+        };
+        turn.messages.insert(0, message);
+        Ok(turn)
+    }
+
+    /// Calls the model with the stored conversation until it answers or a
+    /// tool call needs approval
+    async fn run(&self, ctx: &AppContext, conversation: &conversations::Model) -> Result<Turn> {
+        let agent = agents::Model::find_by_id(&ctx.db, conversation.agent_id).await?;
+        // the agent may have changed since the conversation started
+        let version = agent_versions::Model::record(&ctx.db, agent.id).await?;
+        let capabilities = self.capabilities(ctx, &agent).await?;
+        let tools: Vec<_> = capabilities
+            .iter()
+            .map(agent_capabilities::Model::tool_definition)
+            .collect();
+
+        let history = messages::Model::list_for_conversation(&ctx.db, conversation.id).await?;
+        // rounds already spent since the user last spoke, when resuming
+        let mut round = history
+            .iter()
+            .rev()
+            .take_while(|message| message.role != ROLE_USER)
+            .filter(|message| message.role == ROLE_ASSISTANT)
+            .count();
+        let mut chat: Vec<_> = system_prompt(&agent)
+            .map(ChatMessage::system)
+            .into_iter()
+            .collect();
+        chat.extend(history.iter().map(chat_message));
+
+        let provider = Metered::new(
+            self.provider,
+            &ctx.db,
+            &self.prices,
+            Scope {
+                user_id: Some(conversation.user_id),
+                agent_id: Some(agent.id),
+                conversation_id: Some(conversation.id),
+                task_id: None,
+            },
+        );
+        let invocation = Invocation {
+            ctx,
+            agent: &agent,
+            conversation,
+        };
+        let mut turn = Turn::default();
+        loop {
+            // the last round offers no tools so the model has to answer
+            let offered = if round < self.max_tool_rounds {
+                tools.clone()
+            } else {
+                vec![]
+            };
+            let completion = provider
+                .complete(&CompletionRequest::new(chat.clone()).with_tools(offered))
+                .await?;
+
+            if completion.tool_calls.is_empty() || round >= self.max_tool_rounds {
+                turn.messages.push(
+                    messages::Model::append_reply(
+                        &ctx.db,
+                        conversation.id,
+                        version.id,
+                        &completion.content,
+                        None,
+                    )
+                    .await?,
+                );
+                return Ok(turn);
+            }
+
+            let request = messages::Model::append_reply(
+                &ctx.db,
+                conversation.id,
+                version.id,
+                &completion.content,
+                Some(json!({ "tool_calls": completion.tool_calls })),
+            )
+            .await?;
+            chat.push(ChatMessage::tool_request(
+                completion.content,
+                completion.tool_calls.clone(),
+            ));
+            turn.messages.push(request.clone());
+
+            for call in &completion.tool_calls {
+                let (status, capability_id, result) = match self.resolve(&capabilities, call) {
+                    Ok(resolved) if resolved.capability.requires_approval => {
+                        turn.approvals.push(
+                            approvals::request(
+                                ctx,
+                                &agent,
+                                conversation,
+                                &request,
+                                resolved.capability,
+                                call,
+                                Local::now().naive_local() + self.approval_ttl,
+                            )
+                            .await?,
+                        );
+                        continue;
+                    }
+                    Ok(resolved) => run_tool(&invocation, &resolved, call).await,
+                    Err(rejection) => rejection,
+                };
+                let message =
+                    save_tool_result(ctx, conversation.id, call, capability_id, status, &result)
+                        .await?;
+                chat.push(ChatMessage::tool(call.id.clone(), message.content.clone()));
+                turn.messages.push(message);
+            }
+
+            if turn.is_paused() {
+                return Ok(turn);
+            }
+            round += 1;
+        }
+    }
+
+    /// The capabilities of the agent that a registered tool implements
+    async fn capabilities(
+        &self,
+        ctx: &AppContext,
+        agent: &agents::Model,
+    ) -> Result<Vec<agent_capabilities::Model>> {
+        Ok(agent_capabilities::Model::list_for_agent(&ctx.db, agent.id)
+            .await?
+            .into_iter()
+            .filter(|capability| {
+                let bound = self.registry.get(&capability.name).is_some();
+                if !bound {
+                    tracing::warn!(
+                        agent_id = %agent.id,
+                        capability = capability.name,
+                        "no tool implements capability, not offering it to the model"
+                    );
+                }
+                bound
+            })
+            .collect())
+    }
+
+    /// Finds the tool behind a call and validates its arguments. Failures
+    /// are returned as an `error` object so the model can see what went
+    /// wrong and retry.
+    fn resolve<'c>(
+        &self,
+        capabilities: &'c [agent_capabilities::Model],
+        call: &ToolCall,
+    ) -> std::result::Result<Resolved<'c, 'a>, Rejection> {
+        let Some((capability, tool)) = capabilities
+            .iter()
+            .find(|capability| capability.name == call.name)
+            .and_then(|capability| Some((capability, self.registry.get(&capability.name)?)))
+        else {
+            return Err((
+                ToolStatus::Unknown,
+                None,
+                json!({ "error": format!("unknown tool: {}", call.name) }),
+            ));
+        };
+
+        if let Err(errors) = schema::validate(&capability.schema(), &call.arguments) {
+            return Err((
+                ToolStatus::Invalid,
+                Some(capability.id),
+                json!({ "error": "invalid arguments", "details": errors }),
+            ));
+        }
+
+        Ok(Resolved { capability, tool })
+    }
+}
+
+/// The 409 answered when another decision got to the approval first
+fn already_decided() -> Error {
+    Error::CustomError(
+        StatusCode::CONFLICT,
+        ErrorDetail::new(
+            "decided".to_string(),
+            "approval is already decided".to_string(),
+        ),
+    )
+}
+
+fn ttl(secs: u64) -> Duration {
+    Duration::seconds(i64::try_from(secs).unwrap_or(i64::MAX))
+}
+
+async fn run_tool(
+    invocation: &Invocation<'_>,
+    resolved: &Resolved<'_, '_>,
+    call: &ToolCall,
+) -> Rejection {
+    let capability_id = Some(resolved.capability.id);
+    match resolved.tool.call(invocation, &call.arguments).await {
+        Ok(result) => (ToolStatus::Ok, capability_id, result),
+        Err(err) => {
+            tracing::info!(tool = call.name, err = %err, "tool call failed");
+            (
+                ToolStatus::Failed,
+                capability_id,
+                json!({ "error": err.to_string() }),
+            )
+        }
+    }
+}
+
+async fn save_tool_result(
+    ctx: &AppContext,
+    conversation_id: Uuid,
+    call: &ToolCall,
+    capability_id: Option<Uuid>,
+    status: ToolStatus,
+    result: &Value,
+) -> Result<messages::Model> {
+    Ok(messages::Model::append(
+        &ctx.db,
+        conversation_id,
+        ROLE_TOOL,
+        &result.to_string(),
+        Some(json!({
+            "tool_call_id": call.id,
+            "name": call.name,
+            "arguments": call.arguments,
+            "capability_id": capability_id,
+            "status": status,
+        })),
+    )
+    .await?)
+}
+
+fn system_prompt(agent: &agents::Model) -> Option<String> {
+    agent
+        .configuration
+        .as_ref()
+        .and_then(|configuration| configuration.get("system_prompt"))
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+/// Rebuilds the provider view of a stored message, including the tool call
+/// bookkeeping kept in its metadata
+fn chat_message(message: &messages::Model) -> ChatMessage {
+    let metadata = message.metadata.as_ref();
+    ChatMessage {
+        role: message.role.clone(),
+        content: message.content.clone(),
+        tool_calls: metadata
+            .and_then(|metadata| metadata.get("tool_calls"))
+            .and_then(|calls| serde_json::from_value(calls.clone()).ok())
+            .unwrap_or_default(),
+        tool_call_id: metadata
+            .and_then(|metadata| metadata.get("tool_call_id"))
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+    }
+}
@@ -0,0 +1,74 @@
+//! Cron expressions evaluated in the timezone of a schedule. Times going in
+//! and out are naive server-local times, like every other timestamp stored
+//! by the app.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use loco_rs::{Error, Result};
+
+pub struct Cadence {
+    schedule: cron::Schedule,
+    timezone: Tz,
+}
+
+impl Cadence {
+    /// Parses a cron expression and an IANA timezone name. Standard five
+    /// field expressions fire at second zero; six and seven field
+    /// expressions start with seconds.
+    ///
+    /// # Errors
+    ///
+    /// `BadRequest` when the expression or the timezone is invalid
+    pub fn parse(expression: &str, timezone: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let expression = if expression.split_whitespace().count() == 5 {
+            format!("0 {expression}")
+        } else {
+            expression.to_string()
+        };
+        let schedule = cron::Schedule::from_str(&expression)
+            .map_err(|err| Error::BadRequest(format!("invalid cron expression: {err}")))?;
+        let timezone = Tz::from_str(timezone)
+            .map_err(|_| Error::BadRequest(format!("unknown timezone: {timezone}")))?;
+        Ok(Self { schedule, timezone })
+    }
+
+    /// The first occurrence strictly after `after`
+    #[must_use]
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.schedule
+            .after(&from_local(after).with_timezone(&self.timezone))
+            .next()
+            .map(|at| to_local(at.with_timezone(&Utc)))
+    }
+
+    /// The occurrences from `from` up to and including `until`, with `from`
+    /// itself counted as an occurrence
+    #[must_use]
+    pub fn between(&self, from: NaiveDateTime, until: NaiveDateTime) -> Vec<NaiveDateTime> {
+        if from > until {
+            return Vec::new();
+        }
+        let mut occurrences = vec![from];
+        occurrences.extend(
+            self.schedule
+                .after(&from_local(from).with_timezone(&self.timezone))
+                .map(|at| to_local(at.with_timezone(&Utc)))
+                .take_while(|at| *at <= until),
+        );
+        occurrences
+    }
+}
+
+fn from_local(at: NaiveDateTime) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&at)
+        .earliest()
+        .map_or_else(|| at.and_utc(), |at| at.with_timezone(&Utc))
+}
+
+fn to_local(at: DateTime<Utc>) -> NaiveDateTime {
+    at.with_timezone(&Local).naive_local()
+}
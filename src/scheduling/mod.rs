@@ -0,0 +1,240 @@
+//! Recurring tasks. A schedule is a task template with a cron expression;
+//! every time the expression fires, a `tasks` row is created from the
+//! template and run like any other task.
+//!
+//! Due schedules are picked up by `ScheduleWorker`, which a ticker enqueues
+//! every `poll_secs`, so materializing runs under whatever worker mode is
+//! configured. Each schedule is claimed by moving its next run forward
+//! only if nobody else did, so several processes may tick at once. The
+//! same tick expires the tool call approvals past their deadline.
+//!
+//! A schedule runs as the user who made it, so each run checks they may
+//! still use the agent; a schedule whose user may not is paused.
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use loco_rs::{app::AppContext, bgworker::BackgroundWorker, Result};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, IntoActiveModel,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    models::{
+        agents,
+        task_schedules::{self, CatchUp, Status},
+        tasks, users,
+    },
+    policy::{self, Action},
+    workers::scheduler::{ScheduleWorker, ScheduleWorkerArgs},
+};
+
+pub mod cadence;
+
+pub use cadence::Cadence;
+
+/// The `settings.scheduler` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// Starts the ticker when the app runs
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// How often due schedules are looked for
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u64,
+    /// How late a run may still be made under the `skip` policy
+    #[serde(default = "default_grace_secs")]
+    pub grace_secs: i64,
+    /// Most runs made at once for a schedule under the `all` policy
+    #[serde(default = "default_max_catch_up")]
+    pub max_catch_up: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            poll_secs: default_poll_secs(),
+            grace_secs: default_grace_secs(),
+            max_catch_up: default_max_catch_up(),
+        }
+    }
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+const fn default_poll_secs() -> u64 {
+    60
+}
+
+const fn default_grace_secs() -> i64 {
+    300
+}
+
+const fn default_max_catch_up() -> usize {
+    24
+}
+
+/// Creates the tasks of every schedule due at `now` and returns them, ready
+/// to run. A schedule that fails is logged and left for the next tick.
+///
+/// # Errors
+///
+/// When the due schedules could not be listed
+pub async fn tick(
+    db: &DatabaseConnection,
+    config: &Config,
+    now: NaiveDateTime,
+) -> Result<Vec<tasks::Model>> {
+    let mut created = Vec::new();
+    for schedule in task_schedules::Model::list_due(db, now).await? {
+        match materialize_due(db, config, &schedule, now).await {
+            Ok(tasks) => created.extend(tasks),
+            Err(err) => {
+                tracing::warn!(schedule_id = %schedule.id, error = %err, "schedule failed");
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// Creates a run of the schedule right away, whether it is paused or not,
+/// without moving its next run
+///
+/// # Errors
+///
+/// When could not save the task or the schedule
+pub async fn trigger(
+    db: &DatabaseConnection,
+    schedule: &task_schedules::Model,
+) -> Result<tasks::Model> {
+    let now = Local::now().naive_local();
+    let task = create_task(db, schedule, now).await?;
+    let mut schedule: task_schedules::ActiveModel = schedule.clone().into();
+    schedule.last_run_at = ActiveValue::set(Some(now));
+    schedule.update(db).await?;
+    Ok(task)
+}
+
+/// Whether the user of the schedule may still use its agent
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn may_run(db: &DatabaseConnection, schedule: &task_schedules::Model) -> Result<bool> {
+    let Some(user) = users::Entity::find_by_id(schedule.user_id).one(db).await? else {
+        return Ok(false);
+    };
+    let Some(agent) = agents::Entity::find_by_id(schedule.agent_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(false);
+    };
+    policy::permits(db, &user, &agent, Action::Use).await
+}
+
+/// Pauses a schedule whose user may no longer use its agent
+///
+/// # Errors
+///
+/// When could not save the schedule
+pub async fn suspend(db: &DatabaseConnection, schedule: task_schedules::Model) -> Result<()> {
+    tracing::info!(
+        schedule_id = %schedule.id,
+        user_id = schedule.user_id,
+        "schedule paused, its user may no longer use the agent"
+    );
+    schedule
+        .into_active_model()
+        .set_status(db, Status::Paused, None)
+        .await?;
+    Ok(())
+}
+
+/// Enqueues `ScheduleWorker` every `poll_secs` for as long as the process
+/// runs
+pub fn start(ctx: &AppContext, config: &Config) {
+    let ctx = ctx.clone();
+    let period = Duration::from_secs(config.poll_secs.max(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = ScheduleWorker::perform_later(&ctx, ScheduleWorkerArgs {}).await {
+                tracing::warn!(error = %err, "could not enqueue the scheduler");
+            }
+        }
+    });
+}
+
+async fn materialize_due(
+    db: &DatabaseConnection,
+    config: &Config,
+    schedule: &task_schedules::Model,
+    now: NaiveDateTime,
+) -> Result<Vec<tasks::Model>> {
+    let Some(due_at) = schedule.next_run_at else {
+        return Ok(Vec::new());
+    };
+    if !may_run(db, schedule).await? {
+        suspend(db, schedule.clone()).await?;
+        return Ok(Vec::new());
+    }
+    let cadence = Cadence::parse(&schedule.cron, &schedule.timezone)?;
+    let missed = cadence.between(due_at, now);
+    let runs: Vec<NaiveDateTime> = match schedule.catch_up_policy() {
+        CatchUp::Skip => missed
+            .last()
+            .filter(|at| (now - **at).num_seconds() <= config.grace_secs)
+            .into_iter()
+            .copied()
+            .collect(),
+        CatchUp::Latest => missed.last().into_iter().copied().collect(),
+        CatchUp::All => {
+            let skipped = missed.len().saturating_sub(config.max_catch_up);
+            missed[skipped..].to_vec()
+        }
+    };
+
+    let txn = db.begin().await?;
+    let last_run_at = (!runs.is_empty()).then_some(now);
+    if !schedule
+        .claim(&txn, cadence.next_after(now), last_run_at)
+        .await?
+    {
+        // another process got here first
+        return Ok(Vec::new());
+    }
+    let mut created = Vec::with_capacity(runs.len());
+    for scheduled_for in runs {
+        created.push(create_task(&txn, schedule, scheduled_for).await?);
+    }
+    txn.commit().await?;
+    Ok(created)
+}
+
+async fn create_task<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    schedule: &task_schedules::Model,
+    scheduled_for: NaiveDateTime,
+) -> Result<tasks::Model> {
+    Ok(tasks::ActiveModel {
+        agent_id: ActiveValue::set(schedule.agent_id),
+        user_id: ActiveValue::set(Some(schedule.user_id)),
+        schedule_id: ActiveValue::set(Some(schedule.id)),
+        name: ActiveValue::set(schedule.name.clone()),
+        description: ActiveValue::set(schedule.description.clone()),
+        input: ActiveValue::set(schedule.input.clone()),
+        metadata: ActiveValue::set(Some(json!({ "scheduled_for": scheduled_for }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?)
+}
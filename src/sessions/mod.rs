@@ -0,0 +1,181 @@
+//! Server-side sessions behind the short-lived access tokens. Logging in
+//! starts a token family: a refresh token whose hash is stored in
+//! `sessions`. Refreshing trades the token for a new one of the same family
+//! and a new access token, and the old token may never be used again.
+//!
+//! A refresh token presented twice means it was copied, so the whole
+//! family is revoked and whoever holds it, legitimate or not, has to log in
+//! again. Revoking a family stops it from being refreshed; access tokens
+//! already handed out live on until they expire, which is why they are
+//! kept short (`auth.jwt.expiration`).
+
+use chrono::{Duration, Local};
+use loco_rs::Result;
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    common::secrets,
+    models::{sessions, users},
+};
+
+/// The access token claim naming the token family it was issued for
+pub const SESSION_CLAIM: &str = "sid";
+
+/// The `settings.sessions` section of the config file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    /// How long a refresh token may be used. Every refresh starts the
+    /// period again.
+    #[serde(default = "default_refresh_ttl_secs")]
+    pub refresh_ttl_secs: i64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_ttl_secs: default_refresh_ttl_secs(),
+        }
+    }
+}
+
+const fn default_refresh_ttl_secs() -> i64 {
+    30 * 24 * 60 * 60
+}
+
+/// Where a session was started from, as far as the request tells
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// A refresh token handed out, with the session it is stored as
+#[derive(Debug, Clone)]
+pub struct Issued {
+    pub session: sessions::Model,
+    pub refresh_token: String,
+}
+
+/// The extra access token claims of a session
+#[must_use]
+pub fn claims(session: &sessions::Model) -> serde_json::Value {
+    json!({ SESSION_CLAIM: session.family_id })
+}
+
+/// The token family an access token was issued for, if any
+#[must_use]
+pub fn family_of(claims: Option<&serde_json::Value>) -> Option<Uuid> {
+    claims
+        .and_then(|claims| claims.get(SESSION_CLAIM))
+        .and_then(|sid| sid.as_str())
+        .and_then(|sid| Uuid::parse_str(sid).ok())
+}
+
+/// Starts a new token family for the user
+///
+/// # Errors
+///
+/// When could not save the session
+pub async fn start(
+    db: &DatabaseConnection,
+    config: &Config,
+    user: &users::Model,
+    client: Client,
+) -> Result<Issued> {
+    let refresh_token = secrets::generate();
+    let session = sessions::ActiveModel {
+        user_id: ActiveValue::set(user.id),
+        token_hash: ActiveValue::set(secrets::hash(&refresh_token)),
+        user_agent: ActiveValue::set(client.user_agent),
+        ip_address: ActiveValue::set(client.ip_address),
+        expires_at: ActiveValue::set(expires_at(config)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(Issued {
+        session,
+        refresh_token,
+    })
+}
+
+/// Trades a refresh token for the next one of its family. `None` when the
+/// token is unknown, expired or revoked, or when it was used before, in
+/// which case its family is revoked too.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn refresh(
+    db: &DatabaseConnection,
+    config: &Config,
+    refresh_token: &str,
+    client: Client,
+) -> Result<Option<Issued>> {
+    let Ok(session) = sessions::Model::find_by_token(db, refresh_token).await else {
+        return Ok(None);
+    };
+    if session.revoked_at.is_some() || session.is_expired() {
+        return Ok(None);
+    }
+    if session.rotated_at.is_some() {
+        revoke_reused(db, &session).await?;
+        return Ok(None);
+    }
+
+    let txn = db.begin().await?;
+    if !session.mark_rotated(&txn).await? {
+        // rotated by a concurrent request between the read and now
+        txn.rollback().await?;
+        revoke_reused(db, &session).await?;
+        return Ok(None);
+    }
+    let next_token = secrets::generate();
+    let next = sessions::ActiveModel {
+        family_id: ActiveValue::set(session.family_id),
+        user_id: ActiveValue::set(session.user_id),
+        token_hash: ActiveValue::set(secrets::hash(&next_token)),
+        user_agent: ActiveValue::set(client.user_agent.or(session.user_agent)),
+        ip_address: ActiveValue::set(client.ip_address.or(session.ip_address)),
+        signed_in_at: ActiveValue::set(session.signed_in_at),
+        expires_at: ActiveValue::set(expires_at(config)),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+    Ok(Some(Issued {
+        session: next,
+        refresh_token: next_token,
+    }))
+}
+
+/// Ends the session of a refresh token. Unknown tokens are ignored.
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn logout(db: &DatabaseConnection, refresh_token: &str) -> Result<()> {
+    if let Ok(session) = sessions::Model::find_by_token(db, refresh_token).await {
+        sessions::Model::revoke_family(db, session.family_id, None).await?;
+    }
+    Ok(())
+}
+
+async fn revoke_reused(db: &DatabaseConnection, session: &sessions::Model) -> Result<()> {
+    let revoked = sessions::Model::revoke_family(db, session.family_id, None).await?;
+    tracing::warn!(
+        family_id = %session.family_id,
+        user_id = session.user_id,
+        revoked,
+        "refresh token reused, revoked its session"
+    );
+    Ok(())
+}
+
+fn expires_at(config: &Config) -> chrono::NaiveDateTime {
+    Local::now().naive_local() + Duration::seconds(config.refresh_ttl_secs)
+}
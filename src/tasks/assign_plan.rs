@@ -0,0 +1,36 @@
+//! Moves a user to another plan.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task assign_plan email:user@example.com plan:pro
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::{models::users, plans::Plan};
+
+pub struct AssignPlan;
+#[async_trait]
+impl Task for AssignPlan {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "assign_plan".to_string(),
+            detail: "Assign a plan (free, pro or team) to a user".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = vars.cli_arg("email")?;
+        let name = vars.cli_arg("plan")?;
+        let plan =
+            Plan::parse(name).ok_or_else(|| Error::string(&format!("unknown plan {name}")))?;
+        let user = users::Model::find_by_email(&app_context.db, email).await?;
+        let user = user
+            .into_active_model()
+            .set_plan(&app_context.db, plan)
+            .await?;
+        println!("{} is on the {} plan", user.email, user.plan);
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+//! Expires tool call approvals that were not decided before their deadline
+//! and lets the paused conversations carry on. `ScheduleWorker` does this
+//! on every tick; the task does it at once.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task expire_approvals
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::{
+    common::settings::Settings,
+    llm,
+    runtime::{approvals, Runtime},
+    tools::Registry,
+};
+
+pub struct ExpireApprovals;
+#[async_trait]
+impl Task for ExpireApprovals {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "expire_approvals".to_string(),
+            detail: "Expire tool call approvals past their deadline".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let settings = Settings::from_context(app_context)?;
+        let provider = llm::from_config(&settings.llm)?;
+        let registry = Registry::builtin();
+        let runtime = Runtime::new(provider.as_ref(), &registry)
+            .with_config(&settings.runtime)
+            .with_prices(&settings.llm.prices);
+
+        let expired = approvals::expire_overdue(app_context, &runtime).await?;
+        println!("expired {expired} approvals");
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+//! Writes an agent bundle: the agent with its capabilities, linked
+//! knowledge bases and learning model versions.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task export_agent agent:<uuid> path:support.zip
+//! ```
+
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+use crate::bundles;
+
+pub struct ExportAgent;
+#[async_trait]
+impl Task for ExportAgent {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "export_agent".to_string(),
+            detail: "Export an agent bundle to a file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let agent_id = Uuid::parse_str(vars.cli_arg("agent")?)
+            .map_err(|_| Error::string("agent must be a valid uuid"))?;
+        let path = vars.cli_arg("path")?;
+
+        let bundle = bundles::export(&app_context.db, agent_id).await?;
+        std::fs::write(path, &bundle)?;
+        let manifest = bundles::manifest(&bundle)?;
+        println!(
+            "exported {} version {} to {path}",
+            manifest.agent.name,
+            manifest.agent_version.unwrap_or_default()
+        );
+        for (file, rows) in &manifest.files {
+            println!("  {file}: {rows}");
+        }
+        Ok(())
+    }
+}
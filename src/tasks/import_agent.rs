@@ -0,0 +1,49 @@
+//! Imports an agent bundle written by `export_agent`. Every row gets a new
+//! id.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task import_agent path:support.zip on_conflict:rename
+//! ```
+//!
+//! `on_conflict` decides what happens when the agent or a learning model
+//! name is taken: `fail` (the default), `skip` or `rename`.
+
+use loco_rs::prelude::*;
+
+use crate::bundles::{self, OnConflict, Owner};
+
+pub struct ImportAgent;
+#[async_trait]
+impl Task for ImportAgent {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "import_agent".to_string(),
+            detail: "Import an agent bundle from a file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let path = vars.cli_arg("path")?;
+        let on_conflict = vars
+            .cli_arg("on_conflict")
+            .map_or(Ok(OnConflict::default()), |value| value.parse())?;
+
+        let bundle = std::fs::read(path)?;
+        let report =
+            bundles::import(&app_context.db, &bundle, on_conflict, Owner::default()).await?;
+        if report.skipped {
+            println!("kept existing agent {} ({})", report.name, report.agent_id);
+            return Ok(());
+        }
+        println!("imported {} as {}", report.name, report.agent_id);
+        for (from, to) in &report.renamed {
+            println!("  renamed {from} to {to}");
+        }
+        for (file, rows) in &report.imported {
+            println!("  {file}: {rows}");
+        }
+        Ok(())
+    }
+}
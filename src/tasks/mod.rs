@@ -1 +1,8 @@
+pub mod assign_plan;
+pub mod expire_approvals;
+pub mod export_agent;
+pub mod import_agent;
+pub mod optimize_model;
+pub mod purge_accounts;
 pub mod seed;
+pub mod usage_report;
@@ -0,0 +1,70 @@
+//! Bootstraps few-shot demonstrations for a learning model and stores the
+//! best performing set as a new model version.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task optimize_model model:<uuid>
+//! ```
+//!
+//! Optional arguments: `metric:exact_match`, `candidates:8`, `max_demos:4`
+//! and `seed:0`.
+
+use loco_rs::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    learning::optimizer::OptimizerConfig,
+    workers::optimizer::{OptimizerWorker, OptimizerWorkerArgs},
+};
+
+pub struct OptimizeModel;
+#[async_trait]
+impl Task for OptimizeModel {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "optimize_model".to_string(),
+            detail: "Optimize the few-shot demos of a learning model".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let model_id = Uuid::parse_str(vars.cli_arg("model")?)
+            .map_err(|_| Error::string("model must be a valid uuid"))?;
+
+        let defaults = OptimizerConfig::default();
+        let config = OptimizerConfig {
+            candidates: parse_or(vars, "candidates", defaults.candidates)?,
+            max_demos: parse_or(vars, "max_demos", defaults.max_demos)?,
+            seed: parse_or(vars, "seed", defaults.seed)?,
+        };
+        let args = OptimizerWorkerArgs {
+            model_id,
+            metric: vars.cli_arg("metric").ok().cloned(),
+            config: Some(config),
+        };
+
+        let report = OptimizerWorker::build(app_context).run(&args).await?;
+        println!(
+            "created {} version {} ({}) with score {:.3}",
+            report.model.name, report.model.version, report.model.id, report.best.score
+        );
+        for candidate in &report.candidates {
+            println!(
+                "  {:.3} over {} examples with {} demos",
+                candidate.score,
+                candidate.evaluated,
+                candidate.demo_ids.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn parse_or<T: std::str::FromStr>(vars: &task::Vars, key: &str, default: T) -> Result<T> {
+    vars.cli_arg(key).map_or(Ok(default), |value| {
+        value
+            .parse()
+            .map_err(|_| Error::string(&format!("invalid value for {key}: {value}")))
+    })
+}
@@ -0,0 +1,48 @@
+//! Erases the accounts whose deletion was asked for and whose grace period
+//! is over, and tells each user it is done. Also drops the data export
+//! archives whose link expired. Meant to be run periodically, e.g. from the
+//! scheduler.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task purge_accounts
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::{
+    accounts,
+    mailers::account::AccountMailer,
+    models::{data_exports, users},
+};
+
+pub struct PurgeAccounts;
+#[async_trait]
+impl Task for PurgeAccounts {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "purge_accounts".to_string(),
+            detail: "Erase accounts past their deletion grace period".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let due = users::Model::find_due_for_deletion(&app_context.db).await?;
+        for user in &due {
+            accounts::erase(&app_context.db, user).await?;
+            // the account is gone either way, so a failed email is only logged
+            if let Err(err) = AccountMailer::send_deleted(app_context, user).await {
+                tracing::warn!(
+                    error = err.to_string(),
+                    "could not send account deleted email"
+                );
+            }
+        }
+        println!("erased {} accounts", due.len());
+
+        let expired = data_exports::Model::delete_expired(&app_context.db).await?;
+        println!("deleted {expired} expired data exports");
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! Prints the token usage and cost of a month, overall, per user and per
+//! agent. Defaults to the current month.
+//!
+//! # Example
+//!
+//! ```sh
+//! cargo loco task usage_report month:2024-10
+//! ```
+
+use chrono::Local;
+use loco_rs::prelude::*;
+
+use crate::usage::report;
+
+pub struct UsageReport;
+#[async_trait]
+impl Task for UsageReport {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "usage_report".to_string(),
+            detail: "Print the monthly token usage and cost report".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let month = vars
+            .cli_arg("month")
+            .map_or_else(|_| Local::now().format("%Y-%m").to_string(), Clone::clone);
+        print!(
+            "{}",
+            report::monthly_summary(&app_context.db, &month).await?
+        );
+        Ok(())
+    }
+}
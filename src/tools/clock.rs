@@ -0,0 +1,26 @@
+use async_trait::async_trait;
+use chrono::{Local, Utc};
+use loco_rs::{Error, Result};
+use serde_json::{json, Value};
+
+use super::{Invocation, Tool};
+
+/// Tells the model the current date and time. Accepts an optional
+/// `timezone` argument of `utc` (the default) or `local`.
+pub struct CurrentTime;
+
+#[async_trait]
+impl Tool for CurrentTime {
+    fn name(&self) -> &str {
+        "current_time"
+    }
+
+    async fn call(&self, _invocation: &Invocation<'_>, arguments: &Value) -> Result<Value> {
+        let now = match arguments.get("timezone").and_then(Value::as_str) {
+            None | Some("utc") => Utc::now().to_rfc3339(),
+            Some("local") => Local::now().to_rfc3339(),
+            Some(other) => return Err(Error::string(&format!("unknown timezone: {other}"))),
+        };
+        Ok(json!({ "now": now }))
+    }
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use loco_rs::{Error, Result};
+use sea_orm::EntityTrait;
+use serde_json::{json, Value};
+
+use super::{Invocation, Tool};
+use crate::{common::settings::Settings, fetch::Fetcher, knowledge, models::users};
+
+/// Longest text handed back to the model; the full text is only kept when
+/// it is ingested into a knowledge base
+const MAX_RESULT_CHARS: usize = 8000;
+
+/// Fetches a web page for the agent, limited to the domains in the agent's
+/// `allowed_domains`. Takes a `url` and, to keep the page, an optional
+/// `knowledge_base_id` of the agent's organization to ingest it into.
+pub struct HttpFetch;
+
+#[async_trait]
+impl Tool for HttpFetch {
+    fn name(&self) -> &str {
+        "http_fetch"
+    }
+
+    async fn call(&self, invocation: &Invocation<'_>, arguments: &Value) -> Result<Value> {
+        let url = arguments
+            .get("url")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::BadRequest("url is required".to_string()))?;
+        let settings = Settings::from_context(invocation.ctx)?;
+        let document = Fetcher::for_agent(settings.fetch, invocation.agent)
+            .fetch(url)
+            .await?;
+
+        let document_id = match arguments.get("knowledge_base_id").and_then(Value::as_str) {
+            Some(knowledge_base_id) => {
+                let knowledge_base_id = knowledge_base_id
+                    .parse()
+                    .map_err(|_| Error::BadRequest("invalid knowledge_base_id".to_string()))?;
+                knowledge::knowledge_base_for(
+                    &invocation.ctx.db,
+                    invocation.agent,
+                    knowledge_base_id,
+                )
+                .await?;
+                let user = users::Entity::find_by_id(invocation.conversation.user_id)
+                    .one(&invocation.ctx.db)
+                    .await?
+                    .ok_or(Error::NotFound)?;
+                knowledge::require_room(
+                    &invocation.ctx.db,
+                    &settings.plans,
+                    knowledge_base_id,
+                    &document.text,
+                )
+                .await?;
+                let ingested = knowledge::ingest(
+                    &invocation.ctx.db,
+                    knowledge_base_id,
+                    &document.text,
+                    json!({
+                        "source": document.url,
+                        "requested_url": url,
+                        "content_type": document.content_type,
+                        "bytes": document.bytes,
+                        "agent_id": invocation.agent.id,
+                        "conversation_id": invocation.conversation.id,
+                        "user_pid": user.pid,
+                    }),
+                )
+                .await?;
+                Some(ingested.document.id)
+            }
+            None => None,
+        };
+
+        let truncated = document.text.chars().count() > MAX_RESULT_CHARS;
+        Ok(json!({
+            "url": document.url,
+            "content_type": document.content_type,
+            "text": document.text.chars().take(MAX_RESULT_CHARS).collect::<String>(),
+            "truncated": truncated,
+            "document_id": document_id,
+        }))
+    }
+}
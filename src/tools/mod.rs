@@ -0,0 +1,74 @@
+//! Tools agents can call. Every `agent_capabilities` row names a tool in the
+//! [`Registry`] and carries the JSON schema its arguments must satisfy.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use loco_rs::{app::AppContext, Result};
+use serde_json::Value;
+
+use crate::models::{agents, conversations};
+
+pub mod clock;
+pub mod http_fetch;
+pub mod schema;
+pub mod send_message;
+
+/// What a tool knows about the call it is serving
+pub struct Invocation<'a> {
+    pub ctx: &'a AppContext,
+    pub agent: &'a agents::Model,
+    pub conversation: &'a conversations::Model,
+}
+
+/// A Rust implementation behind an agent capability
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// The capability name this tool answers to
+    fn name(&self) -> &str;
+
+    /// Runs the tool with arguments that already passed schema validation
+    ///
+    /// # Errors
+    ///
+    /// When the tool fails. The error is reported back to the model.
+    async fn call(&self, invocation: &Invocation<'_>, arguments: &Value) -> Result<Value>;
+}
+
+/// The tools available to the agent runtime, looked up by name
+#[derive(Clone, Default)]
+pub struct Registry {
+    tools: BTreeMap<String, Arc<dyn Tool>>,
+}
+
+impl Registry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding the tools that ship with the app
+    #[must_use]
+    pub fn builtin() -> Self {
+        Self::new()
+            .register(clock::CurrentTime)
+            .register(http_fetch::HttpFetch)
+            .register(send_message::SendMessage)
+    }
+
+    /// Adds a tool, replacing any tool with the same name
+    #[must_use]
+    pub fn register(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.insert(tool.name().to_string(), Arc::new(tool));
+        self
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools.get(name).map(AsRef::as_ref)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.keys().map(String::as_str)
+    }
+}
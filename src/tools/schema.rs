@@ -0,0 +1,203 @@
+//! Validation of tool arguments against the JSON schema stored in
+//! `agent_capabilities.parameters`.
+//!
+//! Supports the subset of JSON schema that function calling APIs use:
+//! `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, `minItems`, `maxItems`, `minLength`,
+//! `maxLength`, `minimum`, `maximum`, `exclusiveMinimum` and
+//! `exclusiveMaximum`. Other keywords are ignored.
+
+use serde_json::{Map, Value};
+
+/// Checks `value` against `schema`, returning every violation found. Each
+/// violation is prefixed with the JSON pointer of the offending value.
+///
+/// # Errors
+///
+/// When the value does not satisfy the schema
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true` and `{}` accept anything, `false` accepts nothing
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: is not allowed", pointer(path)));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                pointer(path),
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{}: must be one of {}",
+                pointer(path),
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{}: must be {expected}", pointer(path)));
+        }
+    }
+
+    match value {
+        Value::Object(object) => check_object(schema, object, path, errors),
+        Value::Array(items) => check_array(schema, items, path, errors),
+        Value::String(string) => {
+            let length = string.chars().count() as f64;
+            bound(schema, "minLength", length, path, errors, |len, min| {
+                len >= min
+            });
+            bound(schema, "maxLength", length, path, errors, |len, max| {
+                len <= max
+            });
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            bound(schema, "minimum", number, path, errors, |n, min| n >= min);
+            bound(schema, "maximum", number, path, errors, |n, max| n <= max);
+            bound(
+                schema,
+                "exclusiveMinimum",
+                number,
+                path,
+                errors,
+                |n, min| n > min,
+            );
+            bound(
+                schema,
+                "exclusiveMaximum",
+                number,
+                path,
+                errors,
+                |n, max| n < max,
+            );
+        }
+        _ => {}
+    }
+}
+
+fn check_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                errors.push(format!(
+                    "{}: missing required property `{name}`",
+                    pointer(path)
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (name, property) in object {
+        let child = format!("{path}/{name}");
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property_schema) => check(property_schema, property, &child, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("{}: unknown property `{name}`", pointer(path)));
+                }
+                Some(additional) => check(additional, property, &child, errors),
+                None => {}
+            },
+        }
+    }
+}
+
+fn check_array(schema: &Map<String, Value>, items: &[Value], path: &str, errors: &mut Vec<String>) {
+    let count = items.len() as f64;
+    bound(schema, "minItems", count, path, errors, |len, min| {
+        len >= min
+    });
+    bound(schema, "maxItems", count, path, errors, |len, max| {
+        len <= max
+    });
+    if let Some(item_schema) = schema.get("items") {
+        for (idx, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{path}/{idx}"), errors);
+        }
+    }
+}
+
+fn bound(
+    schema: &Map<String, Value>,
+    keyword: &str,
+    actual: f64,
+    path: &str,
+    errors: &mut Vec<String>,
+    holds: impl Fn(f64, f64) -> bool,
+) {
+    if let Some(limit) = schema.get(keyword).and_then(Value::as_f64) {
+        if !holds(actual, limit) {
+            errors.push(format!("{}: violates {keyword} {limit}", pointer(path)));
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|number| number.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+fn pointer(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
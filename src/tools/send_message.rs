@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use loco_rs::{Error, Result};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{Invocation, Tool};
+use crate::{
+    bus::{Bus, Note, Recipient},
+    models::agents,
+};
+
+/// Sends a note over the bus to another agent of the same organization
+/// (`agent_id`) or to a `topic`. The note carries the conversation it was
+/// written in.
+pub struct SendMessage;
+
+#[async_trait]
+impl Tool for SendMessage {
+    fn name(&self) -> &str {
+        "send_message"
+    }
+
+    async fn call(&self, invocation: &Invocation<'_>, arguments: &Value) -> Result<Value> {
+        let text = arguments
+            .get("text")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::string("text is required"))?;
+        let agent_id = arguments.get("agent_id").and_then(Value::as_str);
+        let topic = arguments.get("topic").and_then(Value::as_str);
+        let recipient = match (agent_id, topic) {
+            (Some(agent_id), None) => {
+                let agent_id = Uuid::parse_str(agent_id)
+                    .map_err(|_| Error::string(&format!("invalid agent id: {agent_id}")))?;
+                let agent = agents::Model::find_by_id(&invocation.ctx.db, agent_id)
+                    .await
+                    .ok()
+                    .filter(|agent| invocation.agent.reaches(agent.organization_id))
+                    .ok_or_else(|| Error::string(&format!("unknown agent: {agent_id}")))?;
+                Recipient::Agent(agent.id)
+            }
+            (None, Some(topic)) => Recipient::Topic(topic.to_string()),
+            _ => return Err(Error::string("give either agent_id or topic")),
+        };
+
+        let message = Bus::send(
+            invocation.ctx,
+            Some(invocation.agent.id),
+            recipient,
+            &Note {
+                text: text.to_string(),
+                conversation_id: Some(invocation.conversation.id),
+            },
+        )
+        .await?;
+        Ok(json!({ "message_id": message.id }))
+    }
+}
@@ -0,0 +1,130 @@
+//! Token usage and cost accounting. Every provider call that reports usage
+//! is stored with the user, agent, conversation or task it was made for and
+//! what it cost according to the price table in `settings.llm.prices`.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use loco_rs::Result;
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    llm::{Completion, CompletionRequest, Provider, Usage},
+    models::usage_records,
+};
+
+pub mod report;
+
+/// Model name looked up when a model has no price of its own
+pub const DEFAULT_PRICE: &str = "default";
+
+/// What a model charges, in dollars per million tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct Price {
+    #[serde(default)]
+    pub prompt: f64,
+    #[serde(default)]
+    pub completion: f64,
+}
+
+/// The price table, keyed by model name
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Prices(pub HashMap<String, Price>);
+
+impl Prices {
+    /// The cost of a call in dollars. Models missing from the table use the
+    /// `default` entry, or cost nothing without one.
+    #[must_use]
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let Some(price) = self
+            .0
+            .get(&usage.model)
+            .or_else(|| self.0.get(DEFAULT_PRICE))
+        else {
+            return 0.0;
+        };
+        (f64::from(usage.prompt_tokens) * price.prompt
+            + f64::from(usage.completion_tokens) * price.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Who a call is accounted to
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub user_id: Option<i32>,
+    pub agent_id: Option<Uuid>,
+    pub conversation_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+}
+
+/// Stores the usage of a call
+///
+/// # Errors
+///
+/// When could not save the record
+pub async fn record(
+    db: &DatabaseConnection,
+    prices: &Prices,
+    scope: &Scope,
+    usage: &Usage,
+) -> Result<usage_records::Model> {
+    Ok(usage_records::ActiveModel {
+        user_id: ActiveValue::set(scope.user_id),
+        agent_id: ActiveValue::set(scope.agent_id),
+        conversation_id: ActiveValue::set(scope.conversation_id),
+        task_id: ActiveValue::set(scope.task_id),
+        provider: ActiveValue::set(usage.provider.clone()),
+        model: ActiveValue::set(usage.model.clone()),
+        prompt_tokens: ActiveValue::set(i32::try_from(usage.prompt_tokens).unwrap_or(i32::MAX)),
+        completion_tokens: ActiveValue::set(
+            i32::try_from(usage.completion_tokens).unwrap_or(i32::MAX),
+        ),
+        cost: ActiveValue::set(prices.cost(usage)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?)
+}
+
+/// A provider that records the usage of every call it passes on. A usage
+/// record that cannot be saved is logged rather than failing the call.
+pub struct Metered<'a> {
+    inner: &'a dyn Provider,
+    db: &'a DatabaseConnection,
+    prices: &'a Prices,
+    scope: Scope,
+}
+
+impl<'a> Metered<'a> {
+    #[must_use]
+    pub const fn new(
+        inner: &'a dyn Provider,
+        db: &'a DatabaseConnection,
+        prices: &'a Prices,
+        scope: Scope,
+    ) -> Self {
+        Self {
+            inner,
+            db,
+            prices,
+            scope,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Metered<'_> {
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion> {
+        let completion = self.inner.complete(request).await?;
+        if let Some(usage) = &completion.usage {
+            if let Err(err) = record(self.db, self.prices, &self.scope, usage).await {
+                tracing::warn!(error = %err, "could not record usage");
+            }
+        }
+        Ok(completion)
+    }
+}
@@ -0,0 +1,266 @@
+//! Usage totals grouped by period and by who the calls were made for
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use loco_rs::{Error, Result};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::models::{agents, usage_records, users};
+
+/// The length of the periods usage is grouped into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    /// ISO weeks, starting on Monday
+    Week,
+    Month,
+    /// The whole range as one period
+    #[default]
+    Total,
+}
+
+impl Period {
+    /// The label of the period `at` falls in, if usage is split into
+    /// periods at all
+    #[must_use]
+    pub fn label(self, at: NaiveDateTime) -> Option<String> {
+        match self {
+            Self::Day => Some(at.format("%Y-%m-%d").to_string()),
+            Self::Week => {
+                let week = at.iso_week();
+                Some(format!("{}-W{:02}", week.year(), week.week()))
+            }
+            Self::Month => Some(at.format("%Y-%m").to_string()),
+            Self::Total => None,
+        }
+    }
+}
+
+/// What usage may be grouped by besides the period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    User,
+    Agent,
+    Conversation,
+    Model,
+}
+
+impl Dimension {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user_id",
+            Self::Agent => "agent_id",
+            Self::Conversation => "conversation_id",
+            Self::Model => "model",
+        }
+    }
+
+    /// Parses a comma separated list such as `agent,conversation`
+    ///
+    /// # Errors
+    ///
+    /// `BadRequest` naming the first unknown dimension
+    pub fn parse_list(list: &str) -> Result<Vec<Self>> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                serde_json::from_value(Value::String(name.to_string()))
+                    .map_err(|_| Error::BadRequest(format!("cannot group usage by {name}")))
+            })
+            .collect()
+    }
+
+    fn value_of(self, record: &usage_records::Model) -> Value {
+        match self {
+            Self::User => serde_json::json!(record.user_id),
+            Self::Agent => serde_json::json!(record.agent_id),
+            Self::Conversation => serde_json::json!(record.conversation_id),
+            Self::Model => Value::String(record.model.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Totals {
+    pub calls: u64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    /// In dollars
+    pub cost: f64,
+}
+
+impl Totals {
+    fn add(&mut self, record: &usage_records::Model) {
+        self.calls += 1;
+        self.prompt_tokens += i64::from(record.prompt_tokens);
+        self.completion_tokens += i64::from(record.completion_tokens);
+        self.total_tokens += record.total_tokens();
+        self.cost += record.cost;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    /// The value of every dimension grouped by, keyed by its field name
+    #[serde(flatten)]
+    pub group: Map<String, Value>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub from: NaiveDate,
+    /// The last day included
+    pub to: NaiveDate,
+    pub group_by: Period,
+    pub buckets: Vec<Bucket>,
+    pub totals: Totals,
+}
+
+/// Sums the records per period and per combination of the dimensions,
+/// ordered by period and then by group
+#[must_use]
+pub fn aggregate(
+    records: &[usage_records::Model],
+    period: Period,
+    by: &[Dimension],
+) -> Vec<Bucket> {
+    let mut buckets: BTreeMap<(Option<String>, Vec<String>), Bucket> = BTreeMap::new();
+    for record in records {
+        let label = period.label(record.created_at);
+        let group: Map<String, Value> = by
+            .iter()
+            .map(|dimension| (dimension.as_str().to_string(), dimension.value_of(record)))
+            .collect();
+        let key = (
+            label.clone(),
+            group.values().map(ToString::to_string).collect(),
+        );
+        buckets
+            .entry(key)
+            .or_insert_with(|| Bucket {
+                period: label,
+                group,
+                totals: Totals::default(),
+            })
+            .totals
+            .add(record);
+    }
+    buckets.into_values().collect()
+}
+
+/// Builds the report for the days from `from` through `to`
+#[must_use]
+pub fn build(
+    records: &[usage_records::Model],
+    from: NaiveDate,
+    to: NaiveDate,
+    period: Period,
+    by: &[Dimension],
+) -> Report {
+    let mut totals = Totals::default();
+    for record in records {
+        totals.add(record);
+    }
+    Report {
+        from,
+        to,
+        group_by: period,
+        buckets: aggregate(records, period, by),
+        totals,
+    }
+}
+
+/// The first and last day of the month `YYYY-MM`
+///
+/// # Errors
+///
+/// `BadRequest` when the month is malformed
+pub fn month_range(month: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let from = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d")
+        .map_err(|_| Error::BadRequest(format!("invalid month {month}, expected YYYY-MM")))?;
+    let to = from
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .ok_or_else(|| Error::BadRequest(format!("invalid month {month}")))?;
+    Ok((from, to))
+}
+
+/// The start of `from` and the start of the day after `to`, the bounds the
+/// records of a date range are listed with
+#[must_use]
+pub fn day_bounds(from: NaiveDate, to: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+    let until = to.succ_opt().unwrap_or(to);
+    (
+        from.and_time(NaiveTime::MIN),
+        until.and_time(NaiveTime::MIN),
+    )
+}
+
+/// The usage of a month as plain text: the overall totals, then one line
+/// per user and one per agent, costliest first
+///
+/// # Errors
+///
+/// When the month is malformed or has DB query error
+pub async fn monthly_summary(db: &DatabaseConnection, month: &str) -> Result<String> {
+    let (from, to) = month_range(month)?;
+    let (start, end) = day_bounds(from, to);
+    let records = usage_records::Model::list_between(db, start, end, None).await?;
+    let report = build(&records, from, to, Period::Total, &[]);
+
+    let emails: HashMap<i32, String> = users::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.email))
+        .collect();
+    let names: HashMap<Uuid, String> = agents::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|agent| (agent.id, agent.name))
+        .collect();
+
+    let mut out = format!("usage for {month} ({from} to {to})\n");
+    out.push_str(&line("total", &report.totals));
+    for (title, dimension) in [("users", Dimension::User), ("agents", Dimension::Agent)] {
+        out.push_str(&format!("\n{title}\n"));
+        let mut buckets = aggregate(&records, Period::Total, &[dimension]);
+        buckets.sort_by(|a, b| b.totals.cost.total_cmp(&a.totals.cost));
+        for bucket in buckets {
+            let value = &bucket.group[dimension.as_str()];
+            let name = match dimension {
+                Dimension::User => value
+                    .as_i64()
+                    .and_then(|id| i32::try_from(id).ok())
+                    .and_then(|id| emails.get(&id).cloned()),
+                _ => value
+                    .as_str()
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .and_then(|id| names.get(&id).cloned()),
+            };
+            out.push_str(&line(name.as_deref().unwrap_or("(none)"), &bucket.totals));
+        }
+    }
+    Ok(out)
+}
+
+fn line(name: &str, totals: &Totals) -> String {
+    format!(
+        "  {name:<32} {:>8} calls {:>12} prompt {:>12} completion  ${:.4}\n",
+        totals.calls, totals.prompt_tokens, totals.completion_tokens, totals.cost
+    )
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use uuid::Uuid;
+
+use crate::models::_entities::{data_exports, users};
+
+/// The current user's profile
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProfileResponse {
+    pub pid: String,
+    pub name: String,
+    pub email: String,
+    pub is_verified: bool,
+    /// The address a change was asked for, until it is confirmed
+    pub pending_email: Option<String>,
+    /// When the account is erased, if its deletion was asked for
+    pub delete_after: Option<DateTime<FixedOffset>>,
+}
+
+impl ProfileResponse {
+    #[must_use]
+    pub fn new(user: &users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            is_verified: user.email_verified_at.is_some(),
+            pending_email: user.pending_email.clone(),
+            delete_after: user.delete_after,
+        }
+    }
+}
+
+/// A data export the user asked for; the link to it is emailed once ready
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DataExportResponse {
+    pub id: Uuid,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl DataExportResponse {
+    #[must_use]
+    pub fn new(export: &data_exports::Model) -> Self {
+        Self {
+            id: export.id,
+            status: export.status.clone(),
+            created_at: export.created_at,
+            expires_at: export.expires_at,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::api_keys::{self, Scope};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// The start of the key, to tell it apart by
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    /// The key itself, only in the answer to creating or rotating it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl ApiKeyResponse {
+    #[must_use]
+    pub fn new(model: &api_keys::Model, key: Option<String>) -> Self {
+        Self {
+            id: model.id,
+            name: model.name.clone(),
+            prefix: model.prefix.clone(),
+            scopes: model.scopes(),
+            last_used_at: model.last_used_at,
+            expires_at: model.expires_at,
+            created_at: model.created_at,
+            key,
+        }
+    }
+}
@@ -1,10 +1,14 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::models::_entities::users;
+use crate::models::_entities::{sessions, users};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    /// Trades for a new access token at `/api/auth/refresh`, once
+    pub refresh_token: String,
     pub pid: String,
     pub name: String,
     pub is_verified: bool,
@@ -12,9 +16,10 @@ pub struct LoginResponse {
 
 impl LoginResponse {
     #[must_use]
-    pub fn new(user: &users::Model, token: &String) -> Self {
+    pub fn new(user: &users::Model, token: &String, refresh_token: &str) -> Self {
         Self {
             token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
             pid: user.pid.to_string(),
             name: user.name.clone(),
             is_verified: user.email_verified_at.is_some(),
@@ -22,6 +27,45 @@ impl LoginResponse {
     }
 }
 
+/// What login answers with when the user has two factor sign in on
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    /// Trades for an access token at `/api/auth/2fa/challenge`, along with
+    /// a code
+    pub challenge_token: String,
+}
+
+impl MfaChallengeResponse {
+    #[must_use]
+    pub fn new(challenge_token: String) -> Self {
+        Self {
+            mfa_required: true,
+            challenge_token,
+        }
+    }
+}
+
+/// Where to send the user to sign in with an identity provider
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OidcAuthorizationResponse {
+    pub authorization_url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpEnrollmentResponse {
+    /// Base32, for typing into an authenticator app
+    pub secret: String,
+    /// For showing as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecoveryCodesResponse {
+    /// Each works once, and this is the only time they are shown
+    pub recovery_codes: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CurrentResponse {
     pub pid: String,
@@ -39,3 +83,31 @@ impl CurrentResponse {
         }
     }
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponse {
+    /// The token family, what the session is revoked by
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub signed_in_at: NaiveDateTime,
+    pub last_refreshed_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// Whether the request was made with this session
+    pub current: bool,
+}
+
+impl SessionResponse {
+    #[must_use]
+    pub fn new(session: &sessions::Model, current: Option<Uuid>) -> Self {
+        Self {
+            id: session.family_id,
+            user_agent: session.user_agent.clone(),
+            ip_address: session.ip_address.clone(),
+            signed_in_at: session.signed_in_at,
+            last_refreshed_at: session.created_at,
+            expires_at: session.expires_at,
+            current: current == Some(session.family_id),
+        }
+    }
+}
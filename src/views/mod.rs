@@ -1 +1,4 @@
+pub mod account;
+pub mod api_keys;
 pub mod auth;
+pub mod organizations;
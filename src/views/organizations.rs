@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::_entities::{memberships, organizations, users};
+
+/// An organization as seen by one of its members
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// The role of the current user
+    pub role: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl OrganizationResponse {
+    #[must_use]
+    pub fn new(organization: &organizations::Model, membership: &memberships::Model) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name.clone(),
+            role: membership.role.clone(),
+            created_at: organization.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MemberResponse {
+    pub pid: String,
+    pub name: String,
+    pub email: String,
+    pub role: String,
+    pub joined_at: NaiveDateTime,
+}
+
+impl MemberResponse {
+    #[must_use]
+    pub fn new(membership: &memberships::Model, user: &users::Model) -> Self {
+        Self {
+            pid: user.pid.to_string(),
+            name: user.name.clone(),
+            email: user.email.clone(),
+            role: membership.role.clone(),
+            joined_at: membership.created_at,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{bus::Bus, models::bus_messages};
+
+/// Delivers a stored bus message. Failed deliveries are retried by the
+/// queue until the message runs out of attempts.
+pub struct BusWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct BusWorkerArgs {
+    pub message_id: Uuid,
+}
+
+#[async_trait]
+impl BackgroundWorker<BusWorkerArgs> for BusWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: BusWorkerArgs) -> Result<()> {
+        let message = bus_messages::Model::find_by_id(&self.ctx.db, args.message_id).await?;
+        Bus::from_context(&self.ctx)?
+            .deliver(&self.ctx.db, message)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,13 +1,70 @@
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+use crate::{
+    common::settings::Settings,
+    fetch::Fetcher,
+    knowledge::{self, Ingested},
+    models::{agents, users},
+};
+
+/// Fetches a URL on behalf of an agent, within the agent's allowlist and
+/// the configured limits, and ingests the text into a knowledge base. The
+/// document is attributed to the user who asked for it.
 pub struct DownloadWorker {
     pub ctx: AppContext,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 pub struct DownloadWorkerArgs {
+    /// The pid of the user the page is fetched for
     pub user_guid: String,
+    pub agent_id: Uuid,
+    pub url: String,
+    pub knowledge_base_id: Uuid,
+}
+
+impl DownloadWorker {
+    /// Fetches and ingests the requested URL
+    ///
+    /// # Errors
+    ///
+    /// When the user or agent does not exist, the knowledge base is not one
+    /// of the agent's organization, the fetch is refused or fails, or the
+    /// content does not fit the knowledge base owner's plan or could not be
+    /// stored
+    pub async fn run(&self, args: &DownloadWorkerArgs) -> Result<Ingested> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let user = users::Model::find_by_pid(&self.ctx.db, &args.user_guid).await?;
+        let agent = agents::Model::find_by_id(&self.ctx.db, args.agent_id).await?;
+        knowledge::knowledge_base_for(&self.ctx.db, &agent, args.knowledge_base_id).await?;
+        let document = Fetcher::for_agent(settings.fetch, &agent)
+            .fetch(&args.url)
+            .await?;
+
+        knowledge::require_room(
+            &self.ctx.db,
+            &settings.plans,
+            args.knowledge_base_id,
+            &document.text,
+        )
+        .await?;
+        knowledge::ingest(
+            &self.ctx.db,
+            args.knowledge_base_id,
+            &document.text,
+            json!({
+                "source": document.url,
+                "requested_url": args.url,
+                "content_type": document.content_type,
+                "bytes": document.bytes,
+                "agent_id": agent.id,
+                "user_pid": user.pid,
+            }),
+        )
+        .await
+    }
 }
 
 #[async_trait]
@@ -15,9 +72,14 @@ impl BackgroundWorker<DownloadWorkerArgs> for DownloadWorker {
     fn build(ctx: &AppContext) -> Self {
         Self { ctx: ctx.clone() }
     }
-    async fn perform(&self, _args: DownloadWorkerArgs) -> Result<()> {
-        // TODO: Some actual work goes here...
-
+    async fn perform(&self, args: DownloadWorkerArgs) -> Result<()> {
+        let ingested = self.run(&args).await?;
+        tracing::info!(
+            url = args.url,
+            document_id = %ingested.document.id,
+            chunks = ingested.chunks.len(),
+            "ingested fetched document"
+        );
         Ok(())
     }
 }
@@ -0,0 +1,85 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    accounts::export,
+    common::settings::Settings,
+    mailers::account::AccountMailer,
+    models::{data_exports, users},
+};
+
+/// Puts together a user's data export and emails them the link to it
+pub struct ExportWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct ExportWorkerArgs {
+    pub export_id: Uuid,
+}
+
+/// A finished export, with the only copy of the token of the link to it
+pub struct Exported {
+    pub user: users::Model,
+    pub export: data_exports::Model,
+    pub token: String,
+}
+
+impl ExportWorker {
+    /// Stores the archive of the export's user behind a download link
+    ///
+    /// # Errors
+    ///
+    /// When the export or its user does not exist, or the archive could not
+    /// be written or stored
+    pub async fn run(&self, args: &ExportWorkerArgs) -> Result<Exported> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let data_export = data_exports::Model::find_by_id(&self.ctx.db, args.export_id).await?;
+        let user = users::Entity::find_by_id(data_export.user_id)
+            .one(&self.ctx.db)
+            .await?
+            .ok_or(Error::NotFound)?;
+
+        let archive = match export::archive(&self.ctx.db, &user).await {
+            Ok(archive) => archive,
+            Err(err) => {
+                data_export.into_active_model().failed(&self.ctx.db).await?;
+                return Err(err);
+            }
+        };
+        let (data_export, token) = data_export
+            .into_active_model()
+            .ready(&self.ctx.db, archive, settings.accounts.export_ttl())
+            .await?;
+        data_export.delete_older(&self.ctx.db).await?;
+        Ok(Exported {
+            user,
+            export: data_export,
+            token,
+        })
+    }
+}
+
+#[async_trait]
+impl BackgroundWorker<ExportWorkerArgs> for ExportWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: ExportWorkerArgs) -> Result<()> {
+        let exported = self.run(&args).await?;
+        AccountMailer::send_export_ready(
+            &self.ctx,
+            &exported.user,
+            &exported.export,
+            &exported.token,
+        )
+        .await?;
+        tracing::info!(
+            pid = exported.user.pid.to_string(),
+            export_id = %exported.export.id,
+            "data export ready"
+        );
+        Ok(())
+    }
+}
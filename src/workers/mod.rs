@@ -1 +1,6 @@
+pub mod bus;
 pub mod downloader;
+pub mod exporter;
+pub mod optimizer;
+pub mod orchestrator;
+pub mod scheduler;
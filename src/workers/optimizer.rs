@@ -0,0 +1,69 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    common::settings::Settings,
+    learning::{
+        metrics,
+        optimizer::{OptimizationReport, Optimizer, OptimizerConfig},
+    },
+    llm,
+    models::learning_models,
+    usage::{Metered, Scope},
+};
+
+pub struct OptimizerWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct OptimizerWorkerArgs {
+    pub model_id: Uuid,
+    /// Name of a built-in metric, defaults to `exact_match`
+    pub metric: Option<String>,
+    #[serde(default)]
+    pub config: Option<OptimizerConfig>,
+}
+
+impl OptimizerWorker {
+    /// Optimizes the requested model and returns the report
+    ///
+    /// # Errors
+    ///
+    /// When the model or metric does not exist, or the optimizer fails
+    pub async fn run(&self, args: &OptimizerWorkerArgs) -> Result<OptimizationReport> {
+        let metric_name = args.metric.as_deref().unwrap_or("exact_match");
+        let metric = metrics::from_name(metric_name)
+            .ok_or_else(|| Error::string(&format!("unknown metric: {metric_name}")))?;
+        let settings = Settings::from_context(&self.ctx)?;
+        let provider = llm::from_config(&settings.llm)?;
+        let model = learning_models::Model::find_by_id(&self.ctx.db, args.model_id).await?;
+        let provider = Metered::new(
+            provider.as_ref(),
+            &self.ctx.db,
+            &settings.llm.prices,
+            Scope {
+                agent_id: model.agent_id,
+                ..Default::default()
+            },
+        );
+
+        Optimizer::new(&provider, metric.as_ref())
+            .with_config(args.config.clone().unwrap_or_default())
+            .with_dev_ratio(settings.learning.dev_ratio)
+            .optimize(&self.ctx.db, &model)
+            .await
+    }
+}
+
+#[async_trait]
+impl BackgroundWorker<OptimizerWorkerArgs> for OptimizerWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: OptimizerWorkerArgs) -> Result<()> {
+        self.run(&args).await?;
+        Ok(())
+    }
+}
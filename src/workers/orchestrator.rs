@@ -0,0 +1,101 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    common::settings::Settings,
+    llm,
+    models::{
+        agents, task_schedules,
+        tasks::{self, Status},
+        users,
+    },
+    orchestration::Orchestrator,
+    plans::{Entitlement, Quota},
+    policy::{self, Action},
+    scheduling,
+};
+
+pub struct OrchestratorWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct OrchestratorWorkerArgs {
+    pub task_id: Uuid,
+}
+
+impl OrchestratorWorker {
+    /// Runs the task, delegating to other agents when it is assigned to a
+    /// planner, and returns it finished. Tasks of a user who may no longer
+    /// use the agent, or who used up the tokens of their plan, fail without
+    /// calling the model; the schedule of such a run is paused.
+    ///
+    /// # Errors
+    ///
+    /// When the task does not exist or has DB query error
+    pub async fn run(&self, args: &OrchestratorWorkerArgs) -> Result<tasks::Model> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let provider = llm::from_config(&settings.llm)?;
+        let task = tasks::Model::find_by_id(&self.ctx.db, args.task_id).await?;
+        let owner = match task.user_id {
+            Some(user_id) => users::Entity::find_by_id(user_id).one(&self.ctx.db).await?,
+            None => None,
+        };
+        if let Some(owner) = owner {
+            let agent = agents::Model::find_by_id(&self.ctx.db, task.agent_id).await?;
+            if !policy::permits(&self.ctx.db, &owner, &agent, Action::Use).await? {
+                if let Some(schedule_id) = task.schedule_id {
+                    let schedule =
+                        task_schedules::Model::find_by_id(&self.ctx.db, schedule_id).await?;
+                    scheduling::suspend(&self.ctx.db, schedule).await?;
+                }
+                let output = json!({
+                    "error": "forbidden",
+                    "description": "the user may no longer use the agent",
+                });
+                return Ok(task
+                    .into_active_model()
+                    .finish(&self.ctx.db, Status::Failed, output)
+                    .await?);
+            }
+            let entitlement = Entitlement::measure(
+                &self.ctx.db,
+                &settings.plans,
+                &owner,
+                &[Quota::TokensPerMonth],
+            )
+            .await?;
+            if let Err(exceeded) = entitlement.require(&[(Quota::TokensPerMonth, 1)]) {
+                let output = json!({
+                    "error": "quota_exceeded",
+                    "description": exceeded.description(),
+                    "quota": Quota::TokensPerMonth,
+                });
+                return Ok(task
+                    .into_active_model()
+                    .finish(&self.ctx.db, Status::Failed, output)
+                    .await?);
+            }
+        }
+
+        Orchestrator::new(provider.as_ref())
+            .with_config(&settings.orchestration)
+            .with_prices(&settings.llm.prices)
+            .run(&self.ctx.db, task)
+            .await
+    }
+}
+
+#[async_trait]
+impl BackgroundWorker<OrchestratorWorkerArgs> for OrchestratorWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: OrchestratorWorkerArgs) -> Result<()> {
+        let task = self.run(&args).await?;
+        tracing::info!(task_id = %task.id, status = %task.status, "task finished");
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::Local;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::settings::Settings,
+    llm,
+    models::tasks,
+    runtime::{approvals, Runtime},
+    scheduling,
+    tools::Registry,
+    workers::orchestrator::{OrchestratorWorker, OrchestratorWorkerArgs},
+};
+
+/// Creates the tasks of every due schedule and enqueues them to run, and
+/// expires the tool call approvals past their deadline
+pub struct ScheduleWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct ScheduleWorkerArgs {}
+
+impl ScheduleWorker {
+    /// Materializes the due schedules and returns the tasks that were
+    /// enqueued
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error or a task could not be enqueued
+    pub async fn run(&self) -> Result<Vec<tasks::Model>> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let created = scheduling::tick(
+            &self.ctx.db,
+            &settings.scheduler,
+            Local::now().naive_local(),
+        )
+        .await?;
+        for task in &created {
+            OrchestratorWorker::perform_later(
+                &self.ctx,
+                OrchestratorWorkerArgs { task_id: task.id },
+            )
+            .await?;
+        }
+        Ok(created)
+    }
+
+    /// Expires the approvals nobody decided in time, letting their
+    /// conversations carry on, and returns how many expired
+    ///
+    /// # Errors
+    ///
+    /// When the provider is misconfigured or has DB query error
+    pub async fn expire_approvals(&self) -> Result<usize> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let provider = llm::from_config(&settings.llm)?;
+        let registry = Registry::builtin();
+        let runtime = Runtime::new(provider.as_ref(), &registry)
+            .with_config(&settings.runtime)
+            .with_prices(&settings.llm.prices);
+        approvals::expire_overdue(&self.ctx, &runtime).await
+    }
+}
+
+#[async_trait]
+impl BackgroundWorker<ScheduleWorkerArgs> for ScheduleWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, _args: ScheduleWorkerArgs) -> Result<()> {
+        let created = self.run().await?;
+        if !created.is_empty() {
+            tracing::info!(tasks = created.len(), "scheduled tasks created");
+        }
+        let expired = self.expire_approvals().await?;
+        if expired > 0 {
+            tracing::info!(approvals = expired, "overdue approvals expired");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use loco_rs::{bgworker::Queue, testing};
+use myapp::{
+    app::App,
+    bus::{self, local::Local, Bus, Envelope, Note, Recipient, Subscription, Transport},
+    llm::Completion,
+    models::{agent_capabilities, agents, bus_messages, organizations, users},
+    runtime::Runtime,
+    tools::Registry,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+use crate::tools::runtime::{call, create_conversation_for, Scripted};
+
+/// Stands in for a real transport: remembers what was published and fails
+/// while `failing` is set
+#[derive(Default)]
+struct Recording {
+    published: Mutex<Vec<Envelope>>,
+    failing: bool,
+}
+
+#[async_trait]
+impl Transport for Recording {
+    async fn publish(&self, envelope: &Envelope) -> loco_rs::Result<()> {
+        if self.failing {
+            return Err(loco_rs::Error::string("transport unavailable"));
+        }
+        self.published.lock().unwrap().push(envelope.clone());
+        Ok(())
+    }
+
+    async fn subscribe(&self, _recipient: &Recipient) -> loco_rs::Result<Subscription> {
+        Ok(Box::pin(tokio_stream::empty()))
+    }
+}
+
+fn note(text: &str) -> serde_json::Value {
+    json!({ "text": text, "conversation_id": null })
+}
+
+async fn next(subscription: &mut Subscription) -> Envelope {
+    tokio::time::timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("no message arrived")
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn delivers_stored_messages_once() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let transport = Arc::new(Recording::default());
+    let bus = Bus::new(transport.clone());
+
+    let message = Bus::post(
+        db,
+        None,
+        Recipient::Topic("reports".to_string()),
+        "note",
+        note("hi"),
+    )
+    .await
+    .unwrap();
+    assert!(message.is_pending());
+
+    let message = bus.deliver(db, message).await.unwrap();
+    assert_eq!(message.status, "delivered");
+    assert_eq!(message.attempts, 1);
+    assert!(message.delivered_at.is_some());
+
+    // a job that runs again does not publish again
+    bus.deliver(db, message).await.unwrap();
+    let published = transport.published.lock().unwrap().clone();
+    assert_eq!(published.len(), 1);
+    assert_eq!(
+        published[0].recipient,
+        Recipient::Topic("reports".to_string())
+    );
+    let decoded: Note = published[0].decode().unwrap();
+    assert_eq!(decoded.text, "hi");
+}
+
+#[tokio::test]
+#[serial]
+async fn failed_deliveries_stay_pending_until_attempts_run_out() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let bus = Bus::new(Arc::new(Recording {
+        failing: true,
+        ..Default::default()
+    }))
+    .with_max_attempts(2);
+
+    let message = Bus::post(
+        db,
+        None,
+        Recipient::Topic("reports".to_string()),
+        "note",
+        note("hi"),
+    )
+    .await
+    .unwrap();
+
+    assert!(bus.deliver(db, message.clone()).await.is_err());
+    let message = bus_messages::Model::find_by_id(db, message.id)
+        .await
+        .unwrap();
+    assert_eq!(message.status, "pending");
+    assert_eq!(message.attempts, 1);
+
+    assert!(bus.deliver(db, message.clone()).await.is_err());
+    let message = bus_messages::Model::find_by_id(db, message.id)
+        .await
+        .unwrap();
+    assert_eq!(message.status, "failed");
+    assert_eq!(message.attempts, 2);
+    assert!(bus_messages::Model::list_pending(db)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn pending_messages_survive_a_restart() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let ctx = &boot.app_context;
+    let recipient = Recipient::Topic("reports".to_string());
+
+    // stored, but the process stopped before the delivery job ran
+    let message = Bus::post(&ctx.db, None, recipient.clone(), "note", note("hi"))
+        .await
+        .unwrap();
+
+    // a fresh process picks it up again
+    assert_eq!(bus::recover(ctx, &Queue::None).await.unwrap(), 1);
+    let bus = Bus::new(Arc::new(Local::default()));
+    let mut subscription = bus.subscribe(&recipient).await.unwrap();
+    for pending in bus_messages::Model::list_pending(&ctx.db).await.unwrap() {
+        bus.deliver(&ctx.db, pending).await.unwrap();
+    }
+
+    let envelope = next(&mut subscription).await;
+    assert_eq!(envelope.id, message.id);
+    assert!(bus_messages::Model::list_pending(&ctx.db)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn agents_send_notes_with_the_tool() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let user = users::Model::find_by_email(&ctx.db, "user1@example.com")
+        .await
+        .unwrap();
+    let conversation = create_conversation_for(&ctx.db, user.id).await;
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(conversation.agent_id),
+        name: ActiveValue::set("send_message".to_string()),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+
+    // agents of other organizations are out of reach
+    let organization = organizations::Model::create_with_owner(&ctx.db, "acme", &user)
+        .await
+        .unwrap();
+    let outsider = agents::ActiveModel {
+        name: ActiveValue::set("outsider".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        organization_id: ActiveValue::set(Some(organization.id)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+
+    let recipient = Recipient::Topic("ops".to_string());
+    let mut subscription = Bus::from_context(ctx)
+        .unwrap()
+        .subscribe(&recipient)
+        .await
+        .unwrap();
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![
+                call(
+                    "call_1",
+                    "send_message",
+                    json!({ "topic": "ops", "text": "disk is full" }),
+                ),
+                call(
+                    "call_2",
+                    "send_message",
+                    json!({ "agent_id": Uuid::new_v4().to_string(), "text": "hello?" }),
+                ),
+                call(
+                    "call_3",
+                    "send_message",
+                    json!({ "agent_id": outsider.id, "text": "psst" }),
+                ),
+            ],
+            ..Default::default()
+        },
+        Completion::text("Told ops."),
+    ]);
+    let registry = Registry::builtin();
+    let turn = Runtime::new(&provider, &registry)
+        .reply(ctx, &conversation, "Tell ops the disk is full")
+        .await
+        .unwrap();
+    let statuses: Vec<_> = turn.messages[2..5]
+        .iter()
+        .map(|message| message.metadata.as_ref().unwrap()["status"].clone())
+        .collect();
+    assert_eq!(statuses, vec!["ok", "failed", "failed"]);
+
+    // delivered by the bus worker in the background
+    let envelope = next(&mut subscription).await;
+    assert!(envelope.is::<Note>());
+    assert_eq!(envelope.sender_id, Some(conversation.agent_id));
+    let note: Note = envelope.decode().unwrap();
+    assert_eq!(note.text, "disk is full");
+    assert_eq!(note.conversation_id, Some(conversation.id));
+}
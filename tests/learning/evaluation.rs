@@ -0,0 +1,171 @@
+use async_trait::async_trait;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    learning::{
+        evaluation::{Comparison, Evaluator},
+        metrics::{ExactMatch, Metric, F1},
+        split::split,
+    },
+    llm::{Completion, CompletionRequest, Provider},
+    models::{learning_models, model_training_data},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serde_json::{json, Value};
+use serial_test::serial;
+use uuid::Uuid;
+
+/// Reverses the word when told to, copies it otherwise. Words in `broken` are
+/// answered wrong
+struct Reverser {
+    broken: Vec<&'static str>,
+}
+
+#[async_trait]
+impl Provider for Reverser {
+    async fn complete(&self, request: &CompletionRequest) -> loco_rs::Result<Completion> {
+        let reverse = request.messages[0].content.contains("Reverse");
+        let input: Value = serde_json::from_str(&request.messages.last().unwrap().content)?;
+        let word = input["word"].as_str().unwrap();
+        let content = if self.broken.contains(&word) {
+            "???".to_string()
+        } else if reverse {
+            word.chars().rev().collect()
+        } else {
+            word.to_string()
+        };
+        Ok(Completion::text(content))
+    }
+}
+
+/// Half a point for answers of the right length
+struct SameLength;
+
+impl Metric for SameLength {
+    fn name(&self) -> &str {
+        "same_length"
+    }
+
+    fn score(&self, expected: &Value, predicted: &Value) -> f64 {
+        if expected.to_string().len() == predicted.to_string().len() {
+            0.5
+        } else {
+            0.0
+        }
+    }
+}
+
+const WORDS: [&str; 10] = [
+    "loco", "rust", "agent", "model", "train", "yaml", "queue", "task", "mailer", "view",
+];
+
+async fn create_versions(
+    db: &DatabaseConnection,
+) -> (learning_models::Model, learning_models::Model) {
+    let v1 = learning_models::ActiveModel {
+        name: ActiveValue::set("reverser".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "instructions": "Reverse the word." }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    for (n, word) in WORDS.iter().enumerate() {
+        model_training_data::ActiveModel {
+            id: ActiveValue::set(Uuid::from_u128(n as u128 + 1)),
+            model_id: ActiveValue::set(v1.id),
+            input: ActiveValue::set(json!({ "word": word })),
+            output: ActiveValue::set(json!(word.chars().rev().collect::<String>())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    let v2 = v1
+        .create_version(
+            db,
+            json!({ "instructions": "Reverse the word, carefully." }),
+            json!({}),
+        )
+        .await
+        .unwrap();
+
+    (v1, v2)
+}
+
+#[test]
+fn can_score_f1() {
+    assert!((F1.score(&json!("the cat sat"), &json!("The  cat sat")) - 1.0).abs() < 1e-9);
+    assert!((F1.score(&json!("the cat sat"), &json!("a dog")) - 0.0).abs() < 1e-9);
+    // precision 1/2, recall 1/3
+    assert!((F1.score(&json!("the cat sat"), &json!("cat ran")) - 0.4).abs() < 1e-9);
+    assert!((F1.score(&json!({ "answer": "yes" }), &json!("YES")) - 1.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_split_deterministically() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let (v1, _) = create_versions(db).await;
+
+    let first = split(v1.training_data(db).await.unwrap(), 0.2);
+    let second = split(v1.training_data(db).await.unwrap(), 0.2);
+
+    let ids = |examples: &[model_training_data::Model]| {
+        examples.iter().map(|e| e.id.as_u128()).collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&first.dev), vec![5, 6, 10]);
+    assert_eq!(ids(&first.dev), ids(&second.dev));
+    assert_eq!(first.train.len(), 7);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_evaluate_and_compare_versions() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let (v1, v2) = create_versions(db).await;
+
+    // "train" (id 5) is in the dev split and answered wrong by the first run
+    let provider = Reverser {
+        broken: vec!["train"],
+    };
+    let base = Evaluator::new(&provider, vec![&ExactMatch, &F1, &SameLength])
+        .evaluate(db, &v1)
+        .await
+        .unwrap();
+    assert_eq!(base.split, "dev");
+    assert_eq!(base.example_count, 3);
+    assert!((base.scores["exact_match"].as_f64().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+    assert!((base.scores["same_length"].as_f64().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+
+    let provider = Reverser {
+        broken: vec!["yaml"],
+    };
+    let candidate = Evaluator::new(&provider, vec![&ExactMatch, &F1, &SameLength])
+        .evaluate(db, &v2)
+        .await
+        .unwrap();
+
+    let comparison = Comparison::new(&base, &candidate, 0.5).unwrap();
+    assert_eq!(comparison.base_model_id, v1.id);
+    assert_eq!(comparison.candidate_model_id, v2.id);
+    assert!(!comparison.regresses(0.0));
+    assert!((comparison.deltas["exact_match"] - 0.0).abs() < 1e-9);
+
+    // "train" now passes while "yaml" (id 6) fails
+    let mut flipped = comparison
+        .flipped
+        .iter()
+        .filter(|flip| flip.metric == "exact_match")
+        .map(|flip| (flip.example_id.as_u128(), flip.regressed))
+        .collect::<Vec<_>>();
+    flipped.sort_unstable();
+    assert_eq!(flipped, vec![(5, false), (6, true)]);
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    learning::{
+        metrics::ExactMatch,
+        optimizer::{Optimizer, OptimizerConfig},
+        program::Program,
+    },
+    llm::{Completion, CompletionRequest, Provider},
+    models::{learning_models, model_training_data},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::{json, Value};
+use serial_test::serial;
+use uuid::Uuid;
+
+/// Reverses the requested word, but only once it has seen an example
+struct NeedsDemos;
+
+#[async_trait]
+impl Provider for NeedsDemos {
+    async fn complete(&self, request: &CompletionRequest) -> loco_rs::Result<Completion> {
+        let has_demos = request.messages.iter().any(|m| m.role == "assistant");
+        let input: Value = serde_json::from_str(&request.messages.last().unwrap().content)?;
+        let content = if has_demos {
+            input["word"].as_str().unwrap().chars().rev().collect()
+        } else {
+            "no idea".to_string()
+        };
+        Ok(Completion::text(content))
+    }
+}
+
+async fn create_model(db: &sea_orm::DatabaseConnection) -> learning_models::Model {
+    let model = learning_models::ActiveModel {
+        name: ActiveValue::set("reverser".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1.0".to_string()),
+        configuration: ActiveValue::set(Some(json!({
+            "instructions": "Reverse the word.",
+            "temperature": 0,
+        }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    for (n, word) in ["loco", "rust", "agent", "model", "train"]
+        .iter()
+        .enumerate()
+    {
+        model_training_data::ActiveModel {
+            id: ActiveValue::set(Uuid::from_u128(n as u128 + 1)),
+            model_id: ActiveValue::set(model.id),
+            input: ActiveValue::set(json!({ "word": word })),
+            output: ActiveValue::set(json!(word.chars().rev().collect::<String>())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    model
+}
+
+#[tokio::test]
+#[serial]
+async fn can_optimize_few_shot_demos() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let model = create_model(db).await;
+
+    let report = Optimizer::new(&NeedsDemos, &ExactMatch)
+        .with_config(OptimizerConfig {
+            candidates: 3,
+            max_demos: 2,
+            seed: 7,
+        })
+        .optimize(db, &model)
+        .await
+        .unwrap();
+
+    assert_eq!(report.candidates.len(), 4);
+    assert!(report.candidates[0].demo_ids.is_empty());
+    assert!((report.candidates[0].score - 0.0).abs() < f64::EPSILON);
+    assert!((report.best.score - 1.0).abs() < f64::EPSILON);
+    assert_eq!(report.best.demo_ids.len(), 2);
+
+    let saved = learning_models::Model::find_by_id(db, report.model.id)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "reverser");
+    assert_eq!(saved.version, "1.1");
+    assert_eq!(saved.parent_id, Some(model.id));
+    assert!(saved.last_trained_at.is_some());
+
+    let metrics = saved.metrics.clone().unwrap();
+    assert_eq!(metrics["metric"], "exact_match");
+    assert_eq!(metrics["score"], 1.0);
+
+    let program = Program::from_model(&saved).unwrap();
+    assert_eq!(program.instructions, "Reverse the word.");
+    assert_eq!(program.demos.len(), 2);
+    assert_eq!(saved.configuration.unwrap()["temperature"], 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn optimize_requires_training_data() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let model = learning_models::ActiveModel {
+        name: ActiveValue::set("empty".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let res = Optimizer::new(&NeedsDemos, &ExactMatch)
+        .optimize(db, &model)
+        .await;
+    assert!(res.is_err());
+}
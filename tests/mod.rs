@@ -1,4 +1,8 @@
+mod bus;
+mod learning;
 mod models;
+mod orchestration;
 mod requests;
 mod tasks;
+mod tools;
 mod workers;
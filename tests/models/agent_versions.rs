@@ -0,0 +1,230 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    llm::echo::Echo,
+    models::{agent_capabilities, agent_versions, agents, conversations, learning_models, users},
+    runtime::Runtime,
+    tools::Registry,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, IntoActiveModel, ModelTrait};
+use serde_json::json;
+use serial_test::serial;
+
+async fn create_agent(db: &DatabaseConnection) -> agents::Model {
+    agents::ActiveModel {
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "system_prompt": "Be brief." }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn versions(db: &DatabaseConnection, agent: &agents::Model) -> Vec<i32> {
+    agent_versions::Model::list_for_agent(db, agent.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|version| version.version)
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn snapshots_every_behavior_change() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let agent = create_agent(db).await;
+    assert_eq!(versions(db, &agent).await, vec![1]);
+
+    let capability = agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("current_time".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    assert_eq!(versions(db, &agent).await, vec![2, 1]);
+
+    // renaming does not change behavior
+    let mut renamed = agent.clone().into_active_model();
+    renamed.name = ActiveValue::set("helpdesk".to_string());
+    let agent = renamed.update(db).await.unwrap();
+    assert_eq!(versions(db, &agent).await, vec![2, 1]);
+
+    let mut changed = agent.clone().into_active_model();
+    changed.configuration = ActiveValue::set(Some(json!({ "system_prompt": "Be thorough." })));
+    let agent = changed.update(db).await.unwrap();
+
+    let model = learning_models::ActiveModel {
+        agent_id: ActiveValue::set(Some(agent.id)),
+        name: ActiveValue::set("helpdesk".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    assert_eq!(versions(db, &agent).await, vec![3, 2, 1]);
+    model.promote(db).await.unwrap();
+    capability.delete(db).await.unwrap();
+    assert_eq!(versions(db, &agent).await, vec![5, 4, 3, 2, 1]);
+
+    let latest = agent_versions::Model::find_version(db, agent.id, 5)
+        .await
+        .unwrap();
+    assert_eq!(latest.learning_model_id, Some(model.id));
+    assert_eq!(latest.capabilities, json!([]));
+
+    // new conversations are pinned to the current version
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let conversation = conversations::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(user.id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    assert_eq!(conversation.agent_version_id, Some(latest.id));
+}
+
+#[tokio::test]
+#[serial]
+async fn diffs_and_restores_versions() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let agent = create_agent(db).await;
+    let capability = agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("http_fetch".to_string()),
+        requires_approval: ActiveValue::set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let mut changed = agent.clone().into_active_model();
+    changed.configuration = ActiveValue::set(Some(json!({
+        "system_prompt": "Be thorough.",
+        "allowed_domains": ["example.com"],
+    })));
+    changed.update(db).await.unwrap();
+    let mut relaxed = capability.clone().into_active_model();
+    relaxed.requires_approval = ActiveValue::set(false);
+    relaxed.update(db).await.unwrap();
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("current_time".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let v2 = agent_versions::Model::find_version(db, agent.id, 2)
+        .await
+        .unwrap();
+    let v5 = agent_versions::Model::find_version(db, agent.id, 5)
+        .await
+        .unwrap();
+    let diff = serde_json::to_value(v2.diff(&v5).unwrap()).unwrap();
+    assert_eq!(
+        diff["configuration"],
+        json!([
+            { "path": "/allowed_domains", "from": null, "to": ["example.com"] },
+            { "path": "/system_prompt", "from": "Be brief.", "to": "Be thorough." },
+        ])
+    );
+    let capabilities: Vec<_> = diff["capabilities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| {
+            (
+                change["path"].clone(),
+                change["from"].is_null(),
+                change["to"].is_null(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        capabilities,
+        vec![
+            (json!("current_time"), true, false),
+            (json!("http_fetch"), false, false),
+        ]
+    );
+    assert_eq!(diff["learning_model"], json!(null));
+
+    let restored = v2.restore(db).await.unwrap();
+    assert_eq!(restored.version, 6);
+    assert!(v2.diff(&restored).unwrap().capabilities.is_empty());
+    assert!(v2.diff(&restored).unwrap().configuration.is_empty());
+
+    let agent = agents::Model::find_by_id(db, agent.id).await.unwrap();
+    assert_eq!(
+        agent.configuration,
+        Some(json!({ "system_prompt": "Be brief." }))
+    );
+    let capabilities = agent_capabilities::Model::list_for_agent(db, agent.id)
+        .await
+        .unwrap();
+    assert_eq!(capabilities.len(), 1);
+    // surviving capabilities keep their id
+    assert_eq!(capabilities[0].id, capability.id);
+    assert!(capabilities[0].requires_approval);
+}
+
+#[tokio::test]
+#[serial]
+async fn replies_name_the_version_that_wrote_them() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let db = &ctx.db;
+
+    let agent = create_agent(db).await;
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let conversation = conversations::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(user.id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let registry = Registry::new();
+    let runtime = Runtime::new(&Echo, &registry);
+
+    let turn = runtime.reply(ctx, &conversation, "hi").await.unwrap();
+    assert_eq!(turn.messages[0].agent_version_id, None);
+    assert_eq!(
+        turn.messages[1].agent_version_id,
+        conversation.agent_version_id
+    );
+
+    // the conversation keeps the version it started with, later replies
+    // name the one that wrote them
+    let mut changed = agent.into_active_model();
+    changed.configuration = ActiveValue::set(Some(json!({ "system_prompt": "Be thorough." })));
+    let agent = changed.update(db).await.unwrap();
+    let current = agent_versions::Model::find_version(db, agent.id, 2)
+        .await
+        .unwrap();
+    let turn = runtime.reply(ctx, &conversation, "again").await.unwrap();
+    assert_eq!(turn.messages[1].agent_version_id, Some(current.id));
+    assert_ne!(conversation.agent_version_id, Some(current.id));
+}
@@ -0,0 +1,52 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::learning_models::{self, next_version},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+
+#[test]
+fn can_bump_versions() {
+    assert_eq!(next_version("1"), "2");
+    assert_eq!(next_version("1.0"), "1.1");
+    assert_eq!(next_version("0.1.9"), "0.1.10");
+    assert_eq!(next_version("beta"), "beta.1");
+    assert_eq!(next_version("1.0-rc"), "1.0-rc.1");
+}
+
+#[tokio::test]
+#[serial]
+async fn can_create_version() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let first = learning_models::ActiveModel {
+        name: ActiveValue::set("classifier".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1.0".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let second = first
+        .create_version(db, json!({ "demos": [] }), json!({ "score": 0.5 }))
+        .await
+        .unwrap();
+    assert_eq!(second.version, "1.1");
+    assert_eq!(second.parent_id, Some(first.id));
+    assert!(second.last_trained_at.is_some());
+
+    // branching again from the first version skips the taken number
+    let third = first
+        .create_version(db, json!({ "demos": [] }), json!({ "score": 0.7 }))
+        .await
+        .unwrap();
+    assert_eq!(third.version, "1.2");
+
+    let versions = third.versions(db).await.unwrap();
+    assert_eq!(versions.len(), 3);
+}
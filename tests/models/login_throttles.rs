@@ -0,0 +1,47 @@
+use chrono::Duration;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::login_throttles::{self, Scope},
+};
+use serial_test::serial;
+use tokio::task::JoinSet;
+
+#[tokio::test]
+#[serial]
+async fn concurrent_failures_are_all_counted() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = boot.app_context.db.clone();
+
+    let mut failures = JoinSet::new();
+    for _ in 0..8 {
+        let db = db.clone();
+        failures.spawn(async move {
+            login_throttles::Model::record_failure(
+                &db,
+                Scope::Ip,
+                "203.0.113.9",
+                5,
+                Duration::minutes(15),
+                Duration::minutes(15),
+            )
+            .await
+            .unwrap()
+        });
+    }
+    let mut counts = Vec::new();
+    while let Some(throttle) = failures.join_next().await {
+        counts.push(throttle.unwrap().failures);
+    }
+    counts.sort_unstable();
+    assert_eq!(counts.last(), Some(&8));
+
+    let throttle = login_throttles::Model::find_for(&db, Scope::Ip, "203.0.113.9")
+        .await
+        .unwrap()
+        .expect("one row per key");
+    assert_eq!(throttle.failures, 8);
+    assert!(throttle
+        .locked_until(chrono::Local::now().naive_local())
+        .is_some());
+}
@@ -1 +1,4 @@
+mod agent_versions;
+mod learning_models;
+mod login_throttles;
 mod users;
@@ -1,8 +1,15 @@
 use insta::assert_debug_snapshot;
-use loco_rs::{model::ModelError, testing};
+use loco_rs::{
+    model::{Authenticable, ModelError},
+    testing,
+};
 use myapp::{
     app::App,
-    models::users::{self, Model, RegisterParams},
+    models::{
+        api_keys::{self, Scope},
+        previous_passwords,
+        users::{self, Model, RegisterParams},
+    },
 };
 use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 use serial_test::serial;
@@ -43,7 +50,7 @@ async fn can_create_with_password() {
 
     let params = RegisterParams {
         email: "test@framework.com".to_string(),
-        password: "1234".to_string(),
+        password: "correct-horse-battery".to_string(),
         name: "framework".to_string(),
     };
     let res = Model::create_with_password(&boot.app_context.db, &params).await;
@@ -67,7 +74,7 @@ async fn handle_create_with_password_with_duplicate() {
         &boot.app_context.db,
         &RegisterParams {
             email: "user1@example.com".to_string(),
-            password: "1234".to_string(),
+            password: "correct-horse-battery".to_string(),
             name: "framework".to_string(),
         },
     )
@@ -137,6 +144,45 @@ async fn can_verification_token() {
     assert!(user.email_verification_token.is_some());
 }
 
+#[tokio::test]
+#[serial]
+async fn verification_tokens_are_stored_hashed_and_expire() {
+    configure_insta!();
+
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+
+    let user = Model::find_by_pid(&boot.app_context.db, "11111111-1111-1111-1111-111111111111")
+        .await
+        .unwrap();
+    let (user, token) = user
+        .into_active_model()
+        .set_email_verification_sent(&boot.app_context.db)
+        .await
+        .unwrap();
+    assert_ne!(
+        user.email_verification_token.as_deref(),
+        Some(token.as_str())
+    );
+
+    let day = chrono::Duration::days(1);
+    let found = Model::find_by_verification_token(&boot.app_context.db, &token, day).await;
+    assert_eq!(found.map(|found| found.id).ok(), Some(user.id));
+    let stored = user.email_verification_token.clone().unwrap();
+    assert!(
+        Model::find_by_verification_token(&boot.app_context.db, &stored, day)
+            .await
+            .is_err()
+    );
+    assert!(Model::find_by_verification_token(
+        &boot.app_context.db,
+        &token,
+        chrono::Duration::zero()
+    )
+    .await
+    .is_err());
+}
+
 #[tokio::test]
 #[serial]
 async fn can_set_forgot_password_sent() {
@@ -210,7 +256,7 @@ async fn can_reset_password() {
     assert!(user
         .clone()
         .into_active_model()
-        .reset_password(&boot.app_context.db, "new-password")
+        .reset_password(&boot.app_context.db, "new-password", 4)
         .await
         .is_ok());
 
@@ -220,4 +266,32 @@ async fn can_reset_password() {
             .unwrap()
             .verify_password("new-password")
     );
+    assert_eq!(
+        previous_passwords::Model::recent_hashes(&boot.app_context.db, user.id, 4)
+            .await
+            .unwrap(),
+        vec![user.password]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn api_keys_are_looked_up_by_their_hash() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let user = Model::find_by_email(db, "user1@example.com").await.unwrap();
+
+    // the plaintext key of the seed is no key at all
+    assert!(<Model as Authenticable>::find_by_api_key(db, &user.api_key)
+        .await
+        .is_err());
+
+    let (_, key) = api_keys::Model::create_for(db, &user, "ci", &[Scope::AgentsRead], None)
+        .await
+        .unwrap();
+    let found = <Model as Authenticable>::find_by_api_key(db, &key)
+        .await
+        .unwrap();
+    assert_eq!(found.id, user.id);
 }
@@ -0,0 +1,354 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    llm::{Completion, CompletionRequest, Provider},
+    models::{agents, organizations, task_dependencies, tasks, users},
+    orchestration::{plan::Plan, Config, Orchestrator},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serde_json::{json, Value};
+use serial_test::serial;
+use uuid::Uuid;
+
+/// Answers by the system prompt of the request: the first route whose key
+/// the prompt contains decides the reply. `Err` routes fail the completion.
+pub struct Routed {
+    routes: Vec<(String, Result<String, String>)>,
+    pub requests: Mutex<Vec<CompletionRequest>>,
+}
+
+impl Routed {
+    pub fn new(routes: Vec<(&str, Result<String, String>)>) -> Self {
+        Self {
+            routes: routes
+                .into_iter()
+                .map(|(key, reply)| (key.to_string(), reply))
+                .collect(),
+            requests: Mutex::default(),
+        }
+    }
+
+    /// The user prompts sent with a system prompt containing `key`
+    pub fn prompts_for(&self, key: &str) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.messages[0].content.contains(key))
+            .map(|request| request.messages[1].content.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Provider for Routed {
+    async fn complete(&self, request: &CompletionRequest) -> loco_rs::Result<Completion> {
+        self.requests.lock().unwrap().push(request.clone());
+        let system = &request.messages[0].content;
+        let reply = self
+            .routes
+            .iter()
+            .find(|(key, _)| system.contains(key.as_str()))
+            .map(|(_, reply)| reply.clone())
+            .unwrap_or_else(|| Err(format!("no route for {system}")));
+        reply
+            .map(Completion::text)
+            .map_err(|err| loco_rs::Error::string(&err))
+    }
+}
+
+pub struct Team {
+    pub planner: agents::Model,
+    pub researcher: agents::Model,
+    pub writer: agents::Model,
+}
+
+async fn create_agent(
+    db: &DatabaseConnection,
+    name: &str,
+    kind: &str,
+    prompt: &str,
+) -> agents::Model {
+    agents::ActiveModel {
+        name: ActiveValue::set(name.to_string()),
+        r#type: ActiveValue::set(kind.to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "system_prompt": prompt }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+pub async fn create_team(db: &DatabaseConnection) -> Team {
+    Team {
+        planner: create_agent(db, "planner", "planner", "You plan.").await,
+        researcher: create_agent(db, "researcher", "assistant", "You research.").await,
+        writer: create_agent(db, "writer", "assistant", "You write.").await,
+    }
+}
+
+pub async fn create_task_for(
+    db: &DatabaseConnection,
+    agent: &agents::Model,
+    user_id: i32,
+) -> tasks::Model {
+    tasks::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(Some(user_id)),
+        name: ActiveValue::set("Report on otters".to_string()),
+        description: ActiveValue::set(Some("Write a short report on otters".to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn create_task(db: &DatabaseConnection, agent: &agents::Model) -> tasks::Model {
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    create_task_for(db, agent, user.id).await
+}
+
+/// The writer step comes first but depends on the research step
+pub fn plan(team: &Team) -> String {
+    json!({
+        "tasks": [
+            {
+                "key": "write",
+                "agent_id": team.writer.id,
+                "name": "Write the report",
+                "description": "Write the report from the research",
+                "depends_on": ["research"],
+            },
+            {
+                "key": "research",
+                "agent_id": team.researcher.id,
+                "name": "Research otters",
+                "description": "Collect facts about otters",
+            },
+        ]
+    })
+    .to_string()
+}
+
+#[tokio::test]
+#[serial]
+async fn delegates_in_dependency_order_and_synthesizes() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let team = create_team(db).await;
+    let task = create_task(db, &team.planner).await;
+
+    let provider = Routed::new(vec![
+        (
+            "Split the task",
+            Ok(format!("```json\n{}\n```", plan(&team))),
+        ),
+        ("subtasks are done", Ok("Otters are great.".to_string())),
+        ("You research.", Ok("Otters hold hands.".to_string())),
+        (
+            "You write.",
+            Ok("A report on hand-holding otters.".to_string()),
+        ),
+    ]);
+    let task = Orchestrator::new(&provider).run(db, task).await.unwrap();
+
+    assert_eq!(task.status, "completed");
+    assert_eq!(task.output, Some(json!({ "answer": "Otters are great." })));
+
+    let children = task.children(db).await.unwrap();
+    let names: Vec<_> = children.iter().map(|child| child.name.as_str()).collect();
+    assert_eq!(names, vec!["Research otters", "Write the report"]);
+    assert!(children.iter().all(|child| child.status == "completed"));
+    assert!(children.iter().all(|child| child.user_id == task.user_id));
+
+    let ids: Vec<Uuid> = children.iter().map(|child| child.id).collect();
+    let dependencies = task_dependencies::Model::list_for_tasks(db, &ids)
+        .await
+        .unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies[0].task_id, children[1].id);
+    assert_eq!(dependencies[0].depends_on_task_id, children[0].id);
+
+    // the planner sees who it can delegate to, the writer gets the research
+    // and the synthesis gets both outputs
+    let planning = provider.requests.lock().unwrap()[0].messages[0]
+        .content
+        .clone();
+    assert!(planning.contains(&team.researcher.id.to_string()));
+    assert!(!planning.contains(&team.planner.id.to_string()));
+    assert!(provider.prompts_for("You write.")[0].contains("Otters hold hands."));
+    let synthesis = &provider.prompts_for("subtasks are done")[0];
+    assert!(synthesis.contains("Otters hold hands."));
+    assert!(synthesis.contains("A report on hand-holding otters."));
+}
+
+#[tokio::test]
+#[serial]
+async fn failed_subtask_cancels_dependents_and_fails_the_task() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let team = create_team(db).await;
+    let task = create_task(db, &team.planner).await;
+
+    let provider = Routed::new(vec![
+        ("Split the task", Ok(plan(&team))),
+        ("You research.", Err("provider unavailable".to_string())),
+    ]);
+    let task = Orchestrator::new(&provider).run(db, task).await.unwrap();
+
+    assert_eq!(task.status, "failed");
+    let output = task.output.clone().unwrap();
+    assert_eq!(
+        output["failed"],
+        json!(["Research otters", "Write the report"])
+    );
+
+    let children = task.children(db).await.unwrap();
+    assert_eq!(children[0].status, "failed");
+    assert_eq!(children[1].status, "cancelled");
+    assert!(provider.prompts_for("You write.").is_empty());
+    assert!(provider.prompts_for("subtasks are done").is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_plans() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let team = create_team(db).await;
+
+    let step = |key: &str, agent_id: Uuid, depends_on: &[&str]| {
+        json!({
+            "key": key,
+            "agent_id": agent_id,
+            "name": key,
+            "description": key,
+            "depends_on": depends_on,
+        })
+    };
+    let cases: Vec<(Value, &str)> = vec![
+        (
+            json!({ "tasks": [
+                step("a", team.researcher.id, &["b"]),
+                step("b", team.writer.id, &["a"]),
+            ] }),
+            "cycle",
+        ),
+        (
+            json!({ "tasks": [step("a", team.planner.id, &[])] }),
+            "cannot be delegated to",
+        ),
+        (
+            json!({ "tasks": [step("a", team.writer.id, &["missing"])] }),
+            "unknown task missing",
+        ),
+        (
+            json!({ "tasks": [
+                step("a", team.writer.id, &[]),
+                step("b", team.writer.id, &[]),
+                step("c", team.writer.id, &[]),
+            ] }),
+            "at most 2",
+        ),
+        (json!("not a plan"), "invalid plan"),
+    ];
+
+    for (plan, error) in cases {
+        let task = create_task(db, &team.planner).await;
+        let provider = Routed::new(vec![("Split the task", Ok(plan.to_string()))]);
+        let task = Orchestrator::new(&provider)
+            .with_config(&Config {
+                max_subtasks: 2,
+                ..Config::default()
+            })
+            .run(db, task)
+            .await
+            .unwrap();
+
+        assert_eq!(task.status, "failed");
+        let output = task.output.clone().unwrap();
+        assert!(
+            output["error"].as_str().unwrap().contains(error),
+            "{output} should mention {error}"
+        );
+        assert!(task.children(db).await.unwrap().is_empty());
+    }
+}
+
+#[test]
+fn repeated_dependencies_are_not_a_cycle() {
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    let plan = Plan::parse(
+        &json!({ "tasks": [
+            { "key": "write", "agent_id": second, "name": "Write", "description": "Write it",
+              "depends_on": ["research", "research"] },
+            { "key": "research", "agent_id": first, "name": "Research", "description": "Look it up" },
+        ] })
+        .to_string(),
+    )
+    .unwrap();
+
+    let steps = plan.validate(&[first, second], 5).unwrap();
+    let keys: Vec<_> = steps.iter().map(|step| step.key.as_str()).collect();
+    assert_eq!(keys, vec!["research", "write"]);
+    assert_eq!(steps[1].depends_on, vec!["research"]);
+}
+
+#[tokio::test]
+#[serial]
+async fn delegates_only_within_the_organization() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let team = create_team(db).await;
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let organization = organizations::Model::create_with_owner(db, "acme", &user)
+        .await
+        .unwrap();
+    let planner = agents::ActiveModel {
+        name: ActiveValue::set("acme planner".to_string()),
+        r#type: ActiveValue::set("planner".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        organization_id: ActiveValue::set(Some(organization.id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    // the team's agents are outside the planner's organization
+    let provider = Routed::new(vec![("Split the task", Ok(plan(&team)))]);
+    let task = Orchestrator::new(&provider)
+        .run(db, create_task(db, &planner).await)
+        .await
+        .unwrap();
+    assert_eq!(task.status, "failed");
+    let error = task.output.unwrap()["error"].as_str().unwrap().to_string();
+    assert!(error.contains("no agents to delegate to"), "{error}");
+
+    // listing them does not bring them in reach
+    let mut listed = planner.into_active_model();
+    listed.configuration = ActiveValue::set(Some(json!({
+        "delegates": [team.researcher.id, team.writer.id],
+    })));
+    let planner = listed.update(db).await.unwrap();
+    let task = Orchestrator::new(&provider)
+        .run(db, create_task(db, &planner).await)
+        .await
+        .unwrap();
+    assert_eq!(task.status, "failed");
+    assert!(task.children(db).await.unwrap().is_empty());
+}
@@ -0,0 +1,311 @@
+use chrono::{Duration, Local};
+use loco_rs::{boot::run_task, task, testing};
+use myapp::{
+    accounts,
+    app::App,
+    lockout,
+    models::{
+        agents, conversations,
+        login_throttles::{self, Scope},
+        memberships, memories, organizations, users,
+    },
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, PaginatorTrait};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn updates_profile_and_password() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        // signed in elsewhere too
+        let other = prepare_data::login_as(&request, &ctx, "loco", &login.user.email).await;
+
+        let response = request
+            .patch("/api/me")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "  Loco Team " }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let profile: serde_json::Value = response.json();
+        assert_eq!(profile["name"], "Loco Team");
+        assert_eq!(profile["delete_after"], serde_json::Value::Null);
+
+        let response = request
+            .patch("/api/me")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": " x " }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post("/api/me/password")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "current_password": "wrong", "password": "a-much-longer-one" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let response = request
+            .post("/api/me/password")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "current_password": prepare_data::USER_PASSWORD, "password": "short" }))
+            .await;
+        assert_eq!(response.status_code(), 422);
+
+        let response = request
+            .post("/api/me/password")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "current_password": prepare_data::USER_PASSWORD,
+                "password": "a-much-longer-one",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // the other session is signed out, this one is kept
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": other.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": login.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": login.user.email, "password": "a-much-longer-one" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn erases_accounts_after_grace_period() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let other = prepare_data::login_as(&request, &ctx, "other", "other@loco.com").await;
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("echo".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            user_id: ActiveValue::set(Some(login.user.id)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let response = request
+            .post("/api/conversations")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "agent_id": agent.id, "title": "hello" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/me/deletion")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "password": "wrong" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let payload = json!({ "password": prepare_data::USER_PASSWORD });
+        let response = request
+            .post("/api/me/deletion")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let profile: serde_json::Value = response.json();
+        assert!(profile["delete_after"].is_string());
+
+        let response = request
+            .delete("/api/me/deletion")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let profile: serde_json::Value = response.json();
+        assert_eq!(profile["delete_after"], serde_json::Value::Null);
+
+        request
+            .post("/api/me/deletion")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&payload)
+            .await;
+        let user = users::Model::find_by_email(&ctx.db, &login.user.email)
+            .await
+            .unwrap();
+        let scheduled = user.delete_after.unwrap();
+        assert!(scheduled > Local::now() + Duration::days(29));
+
+        // still in the grace period
+        run_task::<App>(
+            &ctx,
+            Some(&"purge_accounts".to_string()),
+            &task::Vars::default(),
+        )
+        .await
+        .unwrap();
+        assert!(users::Model::find_by_email(&ctx.db, &login.user.email)
+            .await
+            .is_ok());
+
+        user.into_active_model()
+            .schedule_deletion(&ctx.db, (Local::now() - Duration::minutes(1)).into())
+            .await
+            .unwrap();
+        run_task::<App>(
+            &ctx,
+            Some(&"purge_accounts".to_string()),
+            &task::Vars::default(),
+        )
+        .await
+        .unwrap();
+        assert!(users::Model::find_by_email(&ctx.db, &login.user.email)
+            .await
+            .is_err());
+        assert_eq!(
+            conversations::Entity::find().count(&ctx.db).await.unwrap(),
+            0
+        );
+        // the organization made for them went with them, the agent stays
+        assert_eq!(
+            organizations::Entity::find().count(&ctx.db).await.unwrap(),
+            0
+        );
+        let agent = agents::Model::find_by_id(&ctx.db, agent.id).await.unwrap();
+        assert_eq!(agent.user_id, None);
+        assert!(users::Model::find_by_email(&ctx.db, &other.user.email)
+            .await
+            .is_ok());
+
+        let response = request
+            .get("/api/me")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert!(response.status_code().is_client_error());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn erasing_reaches_into_shared_organizations() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::login_as(&request, &ctx, "mixed", "Mixed.Case@Loco.com").await;
+        let other = prepare_data::login_as(&request, &ctx, "other", "other@loco.com").await;
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": " mixed.case@loco.com", "password": "wrong" }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let key = lockout::account_key(&login.user.email);
+        assert_ne!(key, login.user.email);
+        assert!(
+            login_throttles::Model::find_for(&ctx.db, Scope::Account, &key)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let organization = organizations::Model::create_with_owner(&ctx.db, "shared", &other.user)
+            .await
+            .unwrap();
+        memberships::ActiveModel {
+            organization_id: ActiveValue::set(organization.id),
+            user_id: ActiveValue::set(login.user.id),
+            role: ActiveValue::set(memberships::Role::Member.as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let agent = |name: &str, user_id: i32| agents::ActiveModel {
+            name: ActiveValue::set(name.to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            user_id: ActiveValue::set(Some(user_id)),
+            organization_id: ActiveValue::set(Some(organization.id)),
+            ..Default::default()
+        };
+        let own = agent("own", login.user.id).insert(&ctx.db).await.unwrap();
+        let theirs = agent("theirs", other.user.id)
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        let memory = |agent_id, metadata| memories::ActiveModel {
+            agent_id: ActiveValue::set(agent_id),
+            r#type: ActiveValue::set("fact".to_string()),
+            content: ActiveValue::set("likes tea".to_string()),
+            metadata: ActiveValue::set(metadata),
+            ..Default::default()
+        };
+        memory(own.id, None).insert(&ctx.db).await.unwrap();
+        memory(theirs.id, Some(json!({ "user_pid": login.user.pid })))
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        let kept = memory(theirs.id, Some(json!({ "user_pid": other.user.pid })))
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+
+        accounts::erase(&ctx.db, &login.user).await.unwrap();
+
+        // both agents stay with the organization, without what they
+        // remembered of the user
+        let remaining = memories::Entity::find().all(&ctx.db).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, kept.id);
+        let own = agents::Model::find_by_id(&ctx.db, own.id).await.unwrap();
+        assert_eq!(own.user_id, None);
+        assert!(
+            login_throttles::Model::find_for(&ctx.db, Scope::Account, &key)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn last_owners_cannot_delete_their_account() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let other = prepare_data::login_as(&request, &ctx, "other", "other@loco.com").await;
+        let organization = organizations::Model::create_with_owner(&ctx.db, "acme", &login.user)
+            .await
+            .unwrap();
+        memberships::ActiveModel {
+            organization_id: ActiveValue::set(organization.id),
+            user_id: ActiveValue::set(other.user.id),
+            role: ActiveValue::set(memberships::Role::Member.as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/api/me/deletion")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 409);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "last_owner");
+    })
+    .await;
+}
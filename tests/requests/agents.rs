@@ -0,0 +1,127 @@
+use loco_rs::testing;
+use myapp::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+use crate::tasks::agent_bundle::create_agent;
+
+#[tokio::test]
+#[serial]
+async fn can_diff_and_roll_back_versions() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let response = request
+            .post("/api/agents")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "name": "support",
+                "type": "assistant",
+                "configuration": { "system_prompt": "Be brief." },
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let agent: serde_json::Value = response.json();
+        let id = agent["id"].as_str().unwrap().to_string();
+
+        let response = request
+            .patch(&format!("/api/agents/{id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "configuration": { "system_prompt": "Be thorough." } }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .get(&format!("/api/agents/{id}/versions"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let versions: Vec<serde_json::Value> = response.json();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0]["version"], 2);
+
+        let response = request
+            .get(&format!("/api/agents/{id}/diff?from=1&to=2"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let diff: serde_json::Value = response.json();
+        assert_eq!(
+            diff["configuration"],
+            json!([{ "path": "/system_prompt", "from": "Be brief.", "to": "Be thorough." }])
+        );
+
+        let response = request
+            .post(&format!("/api/agents/{id}/versions/1/rollback"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let version: serde_json::Value = response.json();
+        assert_eq!(version["version"], 3);
+        assert_eq!(
+            version["configuration"],
+            json!({ "system_prompt": "Be brief." })
+        );
+
+        let response = request
+            .get(&format!("/api/agents/{id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let agent: serde_json::Value = response.json();
+        assert_eq!(
+            agent["configuration"],
+            json!({ "system_prompt": "Be brief." })
+        );
+
+        let response = request
+            .post(&format!("/api/agents/{id}/versions/9/rollback"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_and_import_bundle() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = create_agent(&ctx.db).await;
+
+        let response = request
+            .get(&format!("/api/agents/{}/export", agent.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.header("content-type"), "application/zip");
+        let bundle = response.as_bytes().clone();
+
+        let response = request
+            .post("/api/agents/import")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .bytes(bundle.clone())
+            .await;
+        assert_eq!(response.status_code(), 409);
+
+        let response = request
+            .post("/api/agents/import?on_conflict=rename")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .bytes(bundle)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let report: serde_json::Value = response.json();
+        assert_eq!(report["name"], "support (imported)");
+        assert_eq!(report["imported"]["training_data.jsonl"], 1);
+
+        let response = request
+            .post("/api/agents/import")
+            .add_header(auth_key, auth_value)
+            .bytes("not a bundle".into())
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
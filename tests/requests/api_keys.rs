@@ -0,0 +1,139 @@
+use chrono::{Duration, Local};
+use loco_rs::testing;
+use myapp::{app::App, models::api_keys};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn api_keys_are_limited_to_their_scopes() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let response = request
+            .post("/api/keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "ci", "scopes": ["agents:read"] }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let created: serde_json::Value = response.json();
+        let key = created["key"].as_str().unwrap().to_string();
+        assert!(key.starts_with("lo-"));
+        assert!(key.starts_with(created["prefix"].as_str().unwrap()));
+        let (key_header, key_value) = prepare_data::auth_header(&key);
+
+        let response = request
+            .get("/api/agents")
+            .add_header(key_header.clone(), key_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/agents")
+            .add_header(key_header.clone(), key_value.clone())
+            .json(&json!({ "name": "bot", "type": "assistant" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["error"], "insufficient_scope");
+
+        // keys are managed from a signed-in session only
+        let response = request
+            .post("/api/keys")
+            .add_header(key_header, key_value)
+            .json(&json!({ "name": "more", "scopes": ["agents:write"] }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .get("/api/keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let list: Vec<serde_json::Value> = response.json();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0]["scopes"], json!(["agents:read"]));
+        assert!(list[0]["last_used_at"].is_string());
+        assert!(list[0].get("key").is_none());
+
+        let response = request
+            .post("/api/keys")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "name": "ci", "scopes": ["agents:everything"] }))
+            .await;
+        assert!(response.status_code().is_client_error());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn api_keys_can_be_rotated_revoked_and_expire() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let response = request
+            .post("/api/keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "ci", "scopes": ["agents:read", "tasks:read"] }))
+            .await;
+        let created: serde_json::Value = response.json();
+        let id = created["id"].as_str().unwrap().to_string();
+        let old_key = created["key"].as_str().unwrap().to_string();
+
+        let response = request
+            .post(&format!("/api/keys/{id}/rotate"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let rotated: serde_json::Value = response.json();
+        assert_eq!(rotated["id"], created["id"]);
+        assert_eq!(rotated["scopes"], created["scopes"]);
+        let new_key = rotated["key"].as_str().unwrap().to_string();
+        assert_ne!(new_key, old_key);
+
+        let (old_header, old_value) = prepare_data::auth_header(&old_key);
+        let response = request
+            .get("/api/agents")
+            .add_header(old_header, old_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let (new_header, new_value) = prepare_data::auth_header(&new_key);
+        let response = request
+            .get("/api/agents")
+            .add_header(new_header.clone(), new_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let model = api_keys::Entity::find_by_id(uuid::Uuid::parse_str(&id).unwrap())
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut expired: api_keys::ActiveModel = model.into();
+        expired.expires_at = ActiveValue::set(Some(Local::now().naive_local() - Duration::days(1)));
+        expired.update(&ctx.db).await.unwrap();
+        let response = request
+            .get("/api/agents")
+            .add_header(new_header.clone(), new_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .delete(&format!("/api/keys/{id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get("/api/keys")
+            .add_header(auth_key, auth_value)
+            .await;
+        let list: Vec<serde_json::Value> = response.json();
+        assert!(list.is_empty());
+    })
+    .await;
+}
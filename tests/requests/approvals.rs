@@ -0,0 +1,115 @@
+use loco_rs::testing;
+use myapp::{app::App, llm::Completion, models::tool_approvals, runtime::Runtime, tools::Registry};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+use crate::tools::runtime::{call, create_conversation_for, Scripted};
+
+#[tokio::test]
+#[serial]
+async fn can_approve_tool_call() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let conversation = create_conversation_for(&ctx.db, user.user.id).await;
+
+        let provider = Scripted::new(vec![Completion {
+            content: String::new(),
+            tool_calls: vec![call("call_1", "current_time", json!({}))],
+            ..Default::default()
+        }]);
+        let registry = Registry::builtin();
+        let turn = Runtime::new(&provider, &registry)
+            .reply(&ctx, &conversation, "What time is it?")
+            .await
+            .unwrap();
+        let approval = &turn.approvals[0];
+
+        let response = request
+            .get("/api/approvals")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let pending: Vec<serde_json::Value> = response.json();
+        assert_eq!(pending.len(), 1);
+
+        let response = request
+            .post(&format!("/api/conversations/{}/messages", conversation.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "content": "hurry up" }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post(&format!("/api/approvals/{}/approve", approval.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let turn: serde_json::Value = response.json();
+        assert_eq!(turn["messages"][0]["role"], "tool");
+        assert_eq!(turn["messages"][0]["metadata"]["status"], "ok");
+        // the echo provider answers with the last user message
+        assert_eq!(turn["messages"][1]["content"], "What time is it?");
+        assert_eq!(turn["approvals"], json!([]));
+
+        let response = request
+            .post(&format!("/api/approvals/{}/reject", approval.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 409);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_decide_expired_or_foreign_approval() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let conversation = create_conversation_for(&ctx.db, user.user.id).await;
+
+        let provider = Scripted::new(vec![Completion {
+            content: String::new(),
+            tool_calls: vec![call("call_1", "current_time", json!({}))],
+            ..Default::default()
+        }]);
+        let registry = Registry::builtin();
+        let turn = Runtime::new(&provider, &registry)
+            .with_approval_ttl(chrono::Duration::seconds(-1))
+            .reply(&ctx, &conversation, "What time is it?")
+            .await
+            .unwrap();
+        let approval = &turn.approvals[0];
+
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let other = myapp::models::users::Model::find_by_email(&ctx.db, "user1@example.com")
+            .await
+            .unwrap();
+        let other_conversation = create_conversation_for(&ctx.db, other.id).await;
+        let foreign = Runtime::new(&provider, &registry)
+            .reply(&ctx, &other_conversation, "And now?")
+            .await
+            .unwrap();
+        let response = request
+            .post(&format!(
+                "/api/approvals/{}/approve",
+                foreign.approvals[0].id
+            ))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .post(&format!("/api/approvals/{}/approve", approval.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 409);
+
+        let approval = tool_approvals::Model::find_for_user(&ctx.db, approval.id, user.user.id)
+            .await
+            .unwrap();
+        assert_eq!(approval.status, "expired");
+    })
+    .await;
+}
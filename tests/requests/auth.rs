@@ -1,7 +1,8 @@
 use insta::{assert_debug_snapshot, with_settings};
 use loco_rs::testing;
-use myapp::{app::App, models::users};
+use myapp::{app::App, common::secrets, models::users};
 use rstest::rstest;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 use serial_test::serial;
 
 use super::prepare_data;
@@ -17,6 +18,13 @@ macro_rules! configure_insta {
     };
 }
 
+/// The user model filters, plus one for the random refresh token
+fn cleanup_login() -> Vec<(&'static str, &'static str)> {
+    let mut filters = testing::cleanup_user_model();
+    filters.push((r"[0-9a-f]{64}", "REFRESH_TOKEN"));
+    filters
+}
+
 #[tokio::test]
 #[serial]
 async fn can_register() {
@@ -27,14 +35,16 @@ async fn can_register() {
         let payload = serde_json::json!({
             "name": "loco",
             "email": email,
-            "password": "12341234"
+            "password": "correct-horse-battery"
         });
 
         let _response = request.post("/api/auth/register").json(&payload).await;
         let saved_user = users::Model::find_by_email(&ctx.db, email).await;
 
+        let mut filters = testing::cleanup_user_model();
+        filters.push((r"[0-9a-f]{64}", "TOKEN_HASH"));
         with_settings!({
-            filters => testing::cleanup_user_model()
+            filters => filters
         }, {
             assert_debug_snapshot!(saved_user);
         });
@@ -49,7 +59,7 @@ async fn can_register() {
 }
 
 #[rstest]
-#[case("login_with_valid_password", "12341234")]
+#[case("login_with_valid_password", "correct-horse-battery")]
 #[case("login_with_invalid_password", "invalid-password")]
 #[tokio::test]
 #[serial]
@@ -61,7 +71,7 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
         let register_payload = serde_json::json!({
             "name": "loco",
             "email": email,
-            "password": "12341234"
+            "password": "correct-horse-battery"
         });
 
         //Creating a new user
@@ -70,9 +80,15 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
             .json(&register_payload)
             .await;
 
-        let user = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+        let (_, verify_token) = users::Model::find_by_email(&ctx.db, email)
+            .await
+            .unwrap()
+            .into_active_model()
+            .set_email_verification_sent(&ctx.db)
+            .await
+            .unwrap();
         let verify_payload = serde_json::json!({
-            "token": user.email_verification_token,
+            "token": verify_token,
         });
         request.post("/api/auth/verify").json(&verify_payload).await;
 
@@ -93,7 +109,7 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
             .is_some());
 
         with_settings!({
-            filters => testing::cleanup_user_model()
+            filters => cleanup_login()
         }, {
             assert_debug_snapshot!(test_name, (response.status_code(), response.text()));
         });
@@ -103,12 +119,12 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
 
 #[tokio::test]
 #[serial]
-async fn can_login_without_verify() {
+async fn cannot_login_without_verify() {
     configure_insta!();
 
     testing::request::<App, _, _>(|request, _ctx| async move {
         let email = "test@loco.com";
-        let password = "12341234";
+        let password = "correct-horse-battery";
         let register_payload = serde_json::json!({
             "name": "loco",
             "email": email,
@@ -131,7 +147,7 @@ async fn can_login_without_verify() {
             .await;
 
         with_settings!({
-            filters => testing::cleanup_user_model()
+            filters => cleanup_login()
         }, {
             assert_debug_snapshot!((response.status_code(), response.text()));
         });
@@ -158,9 +174,17 @@ async fn can_reset_password() {
         assert!(user.reset_token.is_some());
         assert!(user.reset_sent_at.is_some());
 
+        // only the hash of the emailed token is stored, so issue one to use
+        let (user, reset_token) = user
+            .into_active_model()
+            .set_forgot_password_sent(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(user.reset_token, Some(secrets::hash(&reset_token)));
+
         let new_password = "new-password";
         let reset_payload = serde_json::json!({
-            "token": user.reset_token,
+            "token": reset_token,
             "password": new_password,
         });
 
@@ -175,6 +199,13 @@ async fn can_reset_password() {
 
         assert_debug_snapshot!((reset_response.status_code(), reset_response.text()));
 
+        // sessions from before the reset are over
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
         let response = request
             .post("/api/auth/login")
             .json(&serde_json::json!({
@@ -216,3 +247,219 @@ async fn can_get_current_user() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn verification_tokens_work_once() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let email = "test@loco.com";
+        request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "loco",
+                "email": email,
+                "password": "correct-horse-battery"
+            }))
+            .await;
+        let (_, verify_token) = users::Model::find_by_email(&ctx.db, email)
+            .await
+            .unwrap()
+            .into_active_model()
+            .set_email_verification_sent(&ctx.db)
+            .await
+            .unwrap();
+        let payload = serde_json::json!({ "token": verify_token });
+
+        let response = request.post("/api/auth/verify").json(&payload).await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.post("/api/auth/verify").json(&payload).await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn expired_reset_tokens_are_refused() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (user, reset_token) = login
+            .user
+            .into_active_model()
+            .set_forgot_password_sent(&ctx.db)
+            .await
+            .unwrap();
+
+        // sent longer ago than `reset_ttl_secs` in config/test.yaml
+        let mut user = user.into_active_model();
+        user.reset_sent_at = ActiveValue::set(Some(
+            (chrono::Local::now() - chrono::Duration::hours(2)).into(),
+        ));
+        let user = user.update(&ctx.db).await.unwrap();
+
+        request
+            .post("/api/auth/reset")
+            .json(&serde_json::json!({
+                "token": reset_token,
+                "password": "new-password",
+            }))
+            .await;
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": user.email,
+                "password": "new-password"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn resending_verification_is_limited() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let email = "test@loco.com";
+        request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "loco",
+                "email": email,
+                "password": "correct-horse-battery"
+            }))
+            .await;
+        let before = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+
+        let payload = serde_json::json!({ "email": email });
+        let response = request
+            .post("/api/auth/resend-verification")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let after = users::Model::find_by_email(&ctx.db, email).await.unwrap();
+        assert_ne!(
+            before.email_verification_token,
+            after.email_verification_token
+        );
+
+        // config/test.yaml allows two per address
+        let response = request
+            .post("/api/auth/resend-verification")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/resend-verification")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 429);
+        assert!(response.headers().contains_key("retry-after"));
+
+        // addresses nobody has are answered alike
+        let response = request
+            .post("/api/auth/resend-verification")
+            .json(&serde_json::json!({ "email": "nobody@loco.com" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn weak_passwords_are_refused() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let response = request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "loco",
+                "email": "test@loco.com",
+                "password": "password"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 422);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "invalid_password");
+        let codes: Vec<&str> = body["errors"]["password"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["code"].as_str().unwrap())
+            .collect();
+        assert_eq!(codes, ["too_short", "breached"]);
+        assert!(users::Model::find_by_email(&ctx.db, "test@loco.com")
+            .await
+            .is_err());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn common_variants_count_as_breached() {
+    testing::request::<App, _, _>(|request, _ctx| async move {
+        for password in [
+            "Baseball2019",
+            "Basketball1!",
+            "P@ssword2020",
+            "Christopher1",
+        ] {
+            let response = request
+                .post("/api/auth/register")
+                .json(&serde_json::json!({
+                    "name": "loco",
+                    "email": "test@loco.com",
+                    "password": password
+                }))
+                .await;
+            assert_eq!(response.status_code(), 422, "{password}");
+            let body: serde_json::Value = response.json();
+            assert_eq!(
+                body["errors"]["password"][0]["code"], "breached",
+                "{password}"
+            );
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn recent_passwords_are_not_taken_again() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+
+        let reset = |password: &'static str| {
+            let user = login.user.clone();
+            let request = &request;
+            let ctx = &ctx;
+            async move {
+                let user = users::Model::find_by_email(&ctx.db, &user.email)
+                    .await
+                    .unwrap();
+                let (_, token) = user
+                    .into_active_model()
+                    .set_forgot_password_sent(&ctx.db)
+                    .await
+                    .unwrap();
+                request
+                    .post("/api/auth/reset")
+                    .json(&serde_json::json!({ "token": token, "password": password }))
+                    .await
+            }
+        };
+
+        let response = reset(prepare_data::USER_PASSWORD).await;
+        assert_eq!(response.status_code(), 422);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["errors"]["password"][0]["code"], "reused");
+
+        assert_eq!(reset("second-password-here").await.status_code(), 200);
+        let response = reset(prepare_data::USER_PASSWORD).await;
+        assert_eq!(response.status_code(), 422);
+        assert_eq!(reset("third-password-here").await.status_code(), 200);
+    })
+    .await;
+}
@@ -0,0 +1,116 @@
+use loco_rs::testing;
+use myapp::{app::App, models::users};
+use sea_orm::IntoActiveModel;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn email_changes_once_the_new_address_is_confirmed() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let old_email = login.user.email.clone();
+
+        let response = request
+            .post("/api/auth/change-email")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "email": "new@loco.com", "password": "wrong" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let response = request
+            .post("/api/auth/change-email")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "email": old_email, "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 422);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["errors"]["email"][0]["code"], "unchanged");
+
+        let response = request
+            .post("/api/auth/change-email")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "email": "new@loco.com", "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // nothing moves until the link is followed
+        let user = users::Model::find_by_email(&ctx.db, &old_email)
+            .await
+            .unwrap();
+        assert_eq!(user.pending_email.as_deref(), Some("new@loco.com"));
+
+        // only the hash of the emailed token is stored, so issue one to use
+        let (_, token) = user
+            .into_active_model()
+            .set_email_change_sent(&ctx.db, "new@loco.com")
+            .await
+            .unwrap();
+        let payload = json!({ "token": token });
+        let response = request
+            .post("/api/auth/change-email/confirm")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/change-email/confirm")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let user = users::Model::find_by_email(&ctx.db, "new@loco.com")
+            .await
+            .unwrap();
+        assert!(user.pending_email.is_none());
+        assert!(users::Model::find_by_email(&ctx.db, &old_email)
+            .await
+            .is_err());
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "new@loco.com", "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn taken_emails_are_refused_on_confirmation() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let other = prepare_data::login_as(&request, &ctx, "other", "other@loco.com").await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+
+        // the address may still be free when asked for, and taken since
+        let response = request
+            .post("/api/auth/change-email")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "email": other.user.email, "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let user = users::Model::find_by_email(&ctx.db, &login.user.email)
+            .await
+            .unwrap();
+        let (_, token) = user
+            .into_active_model()
+            .set_email_change_sent(&ctx.db, &other.user.email)
+            .await
+            .unwrap();
+        let response = request
+            .post("/api/auth/change-email/confirm")
+            .json(&json!({ "token": token }))
+            .await;
+        assert_eq!(response.status_code(), 409);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "email_taken");
+        assert!(users::Model::find_by_email(&ctx.db, &login.user.email)
+            .await
+            .is_ok());
+    })
+    .await;
+}
@@ -0,0 +1,61 @@
+use loco_rs::testing;
+use myapp::{app::App, models::agents};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn can_chat_with_agent() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("echo".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/api/conversations")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "agent_id": agent.id, "title": "hello" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let conversation: serde_json::Value = response.json();
+        let id = conversation["id"].as_str().unwrap().to_string();
+
+        let response = request
+            .post(&format!("/api/conversations/{id}/messages"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "content": "ping" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let turn: serde_json::Value = response.json();
+        assert_eq!(turn["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(turn["messages"][1]["role"], "assistant");
+        assert_eq!(turn["messages"][1]["content"], "ping");
+        assert_eq!(turn["approvals"], json!([]));
+
+        let response = request
+            .get(&format!("/api/conversations/{id}/messages"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let history: Vec<serde_json::Value> = response.json();
+        assert_eq!(history.len(), 2);
+
+        let response = request
+            .post(&format!("/api/conversations/{id}/messages"))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "content": "  " }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
@@ -0,0 +1,224 @@
+use std::io::{Cursor, Read};
+
+use chrono::{Duration, Local};
+use loco_rs::{boot::run_task, task, testing};
+use myapp::{
+    app::App,
+    models::{
+        agents,
+        data_exports::{self, Status},
+        memories, users,
+    },
+    workers::exporter::{ExportWorker, ExportWorkerArgs},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, PaginatorTrait};
+use serde_json::json;
+use serial_test::serial;
+use zip::ZipArchive;
+
+use super::prepare_data;
+
+fn read_json(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> serde_json::Value {
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    serde_json::from_str(&content).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn exports_user_data_behind_an_expiring_link() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("echo".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let response = request
+            .post("/api/conversations")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "agent_id": agent.id, "title": "hello" }))
+            .await;
+        let conversation: serde_json::Value = response.json();
+        let id = conversation["id"].as_str().unwrap().to_string();
+        request
+            .post(&format!("/api/conversations/{id}/messages"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "content": "ping" }))
+            .await;
+        memories::ActiveModel {
+            agent_id: ActiveValue::set(agent.id),
+            r#type: ActiveValue::set("fact".to_string()),
+            content: ActiveValue::set("likes ping".to_string()),
+            metadata: ActiveValue::set(Some(json!({ "user_pid": login.user.pid }))),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        request
+            .post("/api/auth/login")
+            .json(&json!({ "email": login.user.email, "password": "wrong" }))
+            .await;
+
+        let response = request
+            .post("/api/me/export")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 202);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "pending");
+        let export_id = body["id"].as_str().unwrap().parse().unwrap();
+
+        // the queued export runs in the background; the emailed token is not
+        // stored, so once it is done, run it again for a token to use
+        let mut data_export = data_exports::Model::find_by_id(&ctx.db, export_id)
+            .await
+            .unwrap();
+        for _ in 0..50 {
+            if data_export.status != Status::Pending.as_str() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            data_export = data_exports::Model::find_by_id(&ctx.db, export_id)
+                .await
+                .unwrap();
+        }
+        assert_eq!(data_export.status, Status::Ready.as_str());
+        let exported = ExportWorker { ctx: ctx.clone() }
+            .run(&ExportWorkerArgs { export_id })
+            .await
+            .unwrap();
+
+        let response = request
+            .get(&format!("/api/me/export/{}", exported.token))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.headers()["content-type"].to_str().unwrap(),
+            "application/zip"
+        );
+        let mut archive = ZipArchive::new(Cursor::new(response.as_bytes().to_vec())).unwrap();
+        let account = read_json(&mut archive, "account.json");
+        assert_eq!(account["email"], login.user.email);
+        assert!(account.get("password").is_none());
+        let messages = read_json(&mut archive, "messages.json");
+        assert_eq!(messages.as_array().unwrap().len(), 2);
+        assert_eq!(messages[0]["content"], "ping");
+        let memories = read_json(&mut archive, "memories.json");
+        assert_eq!(memories[0]["content"], "likes ping");
+        assert!(memories[0].get("embedding").is_none());
+        let login_throttles = read_json(&mut archive, "login_throttles.json");
+        assert_eq!(login_throttles[0]["failures"], 1);
+        let manifest = read_json(&mut archive, "manifest.json");
+        assert_eq!(manifest["files"]["conversations.json"], 1);
+        assert_eq!(manifest["files"]["sessions.json"], 1);
+        assert_eq!(manifest["files"]["memories.json"], 1);
+        assert_eq!(manifest["files"]["tool_approvals.json"], 0);
+
+        let response = request.get("/api/me/export/not-a-token").await;
+        assert_eq!(response.status_code(), 404);
+
+        let mut expired = exported.export.into_active_model();
+        expired.expires_at =
+            ActiveValue::set(Some(Local::now().naive_local() - Duration::minutes(1)));
+        expired.update(&ctx.db).await.unwrap();
+        let response = request
+            .get(&format!("/api/me/export/{}", exported.token))
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        run_task::<App>(
+            &ctx,
+            Some(&"purge_accounts".to_string()),
+            &task::Vars::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            data_exports::Entity::find().count(&ctx.db).await.unwrap(),
+            0
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn asking_again_does_not_queue_the_pending_export_again() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let user = users::Model::find_by_pid(&ctx.db, &login.user.pid.to_string())
+            .await
+            .unwrap();
+        // started but never queued, so only a new run would finish it
+        let (pending, started) = data_exports::Model::start_for(&ctx.db, &user, Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(started);
+
+        let response = request
+            .post("/api/me/export")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 202);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["id"], pending.id.to_string());
+
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        let data_export = data_exports::Model::find_by_id(&ctx.db, pending.id)
+            .await
+            .unwrap();
+        assert_eq!(data_export.status, Status::Pending.as_str());
+        assert_eq!(
+            data_exports::Entity::find().count(&ctx.db).await.unwrap(),
+            1
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn a_stale_pending_export_does_not_block_a_new_one() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let user = users::Model::find_by_pid(&ctx.db, &login.user.pid.to_string())
+            .await
+            .unwrap();
+        // its worker died a while ago
+        let (stuck, _) = data_exports::Model::start_for(&ctx.db, &user, Duration::hours(1))
+            .await
+            .unwrap();
+        let mut stuck = stuck.into_active_model();
+        stuck.created_at = ActiveValue::set(Local::now().naive_local() - Duration::hours(2));
+        let stuck = stuck.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post("/api/me/export")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 202);
+        let body: serde_json::Value = response.json();
+        assert_ne!(body["id"], stuck.id.to_string());
+
+        // unless the new one is done already, which deletes the older ones
+        let stuck = data_exports::Entity::find_by_id(stuck.id)
+            .one(&ctx.db)
+            .await
+            .unwrap();
+        assert!(stuck.is_none_or(|stuck| stuck.status == Status::Failed.as_str()));
+    })
+    .await;
+}
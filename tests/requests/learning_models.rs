@@ -0,0 +1,163 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{learning_models, model_evaluations, model_training_data},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serde_json::json;
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data;
+
+async fn create_version(db: &DatabaseConnection, version: &str) -> learning_models::Model {
+    learning_models::ActiveModel {
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set(version.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn record_evaluation(db: &DatabaseConnection, model_id: Uuid, exact_match: f64) {
+    let passed = exact_match >= 0.5;
+    model_evaluations::ActiveModel {
+        model_id: ActiveValue::set(model_id),
+        split: ActiveValue::set("dev".to_string()),
+        example_count: ActiveValue::set(1),
+        scores: ActiveValue::set(json!({ "exact_match": exact_match })),
+        results: ActiveValue::set(json!([{
+            "example_id": Uuid::from_u128(1),
+            "expected": "yes",
+            "predicted": if passed { "yes" } else { "no" },
+            "scores": { "exact_match": exact_match },
+        }])),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn can_evaluate_version() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let model = create_version(&ctx.db, "1").await;
+        for (n, text) in ["hi", "hello", "hey"].iter().enumerate() {
+            model_training_data::ActiveModel {
+                id: ActiveValue::set(Uuid::from_u128(n as u128 + 1)),
+                model_id: ActiveValue::set(model.id),
+                input: ActiveValue::set(json!(text)),
+                output: ActiveValue::set(json!(text)),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+
+        let response = request
+            .post(&format!("/api/learning_models/{}/evaluations", model.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "metrics": ["exact_match"] }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let evaluation: serde_json::Value = response.json();
+        assert_eq!(evaluation["scores"]["exact_match"], 1.0);
+
+        let response = request
+            .get(&format!("/api/learning_models/{}/evaluations", model.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        let evaluations: Vec<serde_json::Value> = response.json();
+        assert_eq!(evaluations.len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_promote_regressing_version() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let active = create_version(&ctx.db, "1")
+            .await
+            .promote(&ctx.db)
+            .await
+            .unwrap();
+        let candidate = create_version(&ctx.db, "2").await;
+        record_evaluation(&ctx.db, active.id, 1.0).await;
+
+        // not evaluated yet
+        let response = request
+            .post(&format!("/api/learning_models/{}/promote", candidate.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        record_evaluation(&ctx.db, candidate.id, 0.0).await;
+        let response = request
+            .post(&format!("/api/learning_models/{}/promote", candidate.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 409);
+
+        let response = request
+            .get(&format!(
+                "/api/learning_models/{}/compare/{}",
+                active.id, candidate.id
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+        let comparison: serde_json::Value = response.json();
+        assert_eq!(comparison["deltas"]["exact_match"], -1.0);
+        assert_eq!(comparison["flipped"][0]["regressed"], true);
+
+        let active = learning_models::Model::find_by_id(&ctx.db, active.id)
+            .await
+            .unwrap();
+        assert_eq!(active.status, "active");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_promote_improving_version() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let active = create_version(&ctx.db, "1")
+            .await
+            .promote(&ctx.db)
+            .await
+            .unwrap();
+        let candidate = create_version(&ctx.db, "2").await;
+        record_evaluation(&ctx.db, active.id, 0.0).await;
+        record_evaluation(&ctx.db, candidate.id, 1.0).await;
+
+        let response = request
+            .post(&format!("/api/learning_models/{}/promote", candidate.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let versions = candidate.versions(&ctx.db).await.unwrap();
+        let statuses = versions
+            .iter()
+            .map(|version| (version.version.as_str(), version.status.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![("1", "archived"), ("2", "active")]);
+    })
+    .await;
+}
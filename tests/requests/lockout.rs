@@ -0,0 +1,127 @@
+use axum::http::HeaderMap;
+use loco_rs::testing;
+use myapp::{app::App, lockout};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+// config/test.yaml locks an account after 3 failures and an address after 6
+
+#[tokio::test]
+#[serial]
+async fn repeated_failures_lock_the_account() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let wrong = json!({ "email": login.user.email, "password": "wrong" });
+        let right = json!({ "email": login.user.email, "password": prepare_data::USER_PASSWORD });
+
+        // a success forgets the failures before it
+        for _ in 0..2 {
+            let response = request.post("/api/auth/login").json(&wrong).await;
+            assert_eq!(response.status_code(), 401);
+        }
+        let response = request.post("/api/auth/login").json(&right).await;
+        assert_eq!(response.status_code(), 200);
+
+        for _ in 0..3 {
+            let response = request.post("/api/auth/login").json(&wrong).await;
+            assert_eq!(response.status_code(), 401);
+        }
+        let response = request.post("/api/auth/login").json(&right).await;
+        assert_eq!(response.status_code(), 429);
+        assert!(response.headers().contains_key("retry-after"));
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "too_many_attempts");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unknown_emails_are_refused_like_wrong_passwords() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+
+        let known = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": login.user.email, "password": "wrong" }))
+            .await;
+        let unknown = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "nobody@loco.com", "password": "wrong" }))
+            .await;
+        assert_eq!(known.status_code(), unknown.status_code());
+        assert_eq!(known.text(), unknown.text());
+
+        for _ in 0..2 {
+            let response = request
+                .post("/api/auth/login")
+                .json(&json!({ "email": "Nobody@loco.com", "password": "wrong" }))
+                .await;
+            assert_eq!(response.status_code(), 401);
+        }
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "nobody@loco.com", "password": "wrong" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn repeated_failures_lock_the_address() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+
+        // the test client is no trusted proxy, so a forwarded address of its
+        // choosing does not start a new count
+        for n in 0..6 {
+            let response = request
+                .post("/api/auth/login")
+                .add_header("x-forwarded-for", format!("203.0.113.{n}"))
+                .json(&json!({ "email": format!("guess{n}@loco.com"), "password": "wrong" }))
+                .await;
+            assert_eq!(response.status_code(), 401);
+        }
+
+        let right = json!({ "email": login.user.email, "password": prepare_data::USER_PASSWORD });
+        let response = request
+            .post("/api/auth/login")
+            .add_header("x-forwarded-for", "198.51.100.2")
+            .add_header("x-real-ip", "198.51.100.3")
+            .json(&right)
+            .await;
+        assert_eq!(response.status_code(), 429);
+    })
+    .await;
+}
+
+#[test]
+fn forwarded_addresses_are_believed_only_from_trusted_proxies() {
+    let config = lockout::Config {
+        trusted_proxies: vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+        ..Default::default()
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        "198.51.100.9, 203.0.113.7, 10.0.0.2".parse().unwrap(),
+    );
+    let address = |peer: &str, headers: &HeaderMap| {
+        config
+            .client_address(peer.parse().unwrap(), headers)
+            .to_string()
+    };
+
+    assert_eq!(address("192.0.2.1", &headers), "192.0.2.1");
+    assert_eq!(address("10.0.0.1", &headers), "203.0.113.7");
+    assert_eq!(address("::ffff:10.0.0.1", &headers), "203.0.113.7");
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-real-ip", "203.0.113.8".parse().unwrap());
+    assert_eq!(address("10.0.0.1", &headers), "203.0.113.8");
+    assert_eq!(address("10.0.0.1", &HeaderMap::new()), "10.0.0.1");
+}
@@ -0,0 +1,110 @@
+use chrono::Duration;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{magic_links, users},
+};
+use sea_orm::{EntityTrait, PaginatorTrait};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn magic_links_log_in_once() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+
+        let response = request
+            .post("/api/auth/magic-link")
+            .json(&json!({ "email": login.user.email }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(magic_links::Entity::find().count(&ctx.db).await.unwrap(), 1);
+
+        // only the hash of the emailed token is stored, so issue one to use
+        let (_, token) =
+            magic_links::Model::create_for(&ctx.db, &login.user, Duration::minutes(15))
+                .await
+                .unwrap();
+        let payload = json!({ "token": token });
+        let response = request
+            .post("/api/auth/magic-link/verify")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["pid"], login.user.pid.to_string());
+        assert!(body["token"].is_string());
+        assert!(body["refresh_token"].is_string());
+
+        let response = request
+            .post("/api/auth/magic-link/verify")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let (_, expired) =
+            magic_links::Model::create_for(&ctx.db, &login.user, Duration::minutes(-1))
+                .await
+                .unwrap();
+        let response = request
+            .post("/api/auth/magic-link/verify")
+            .json(&json!({ "token": expired }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn magic_links_verify_the_email() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        request
+            .post("/api/auth/register")
+            .json(&json!({
+                "name": "loco",
+                "email": "test@loco.com",
+                "password": prepare_data::USER_PASSWORD
+            }))
+            .await;
+        let user = users::Model::find_by_email(&ctx.db, "test@loco.com")
+            .await
+            .unwrap();
+        assert!(user.email_verified_at.is_none());
+
+        let (_, token) = magic_links::Model::create_for(&ctx.db, &user, Duration::minutes(15))
+            .await
+            .unwrap();
+        let response = request
+            .post("/api/auth/magic-link/verify")
+            .json(&json!({ "token": token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let user = users::Model::find_by_email(&ctx.db, "test@loco.com")
+            .await
+            .unwrap();
+        assert!(user.email_verified_at.is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn asking_for_magic_links_is_limited() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        // config/test.yaml allows two per address, whether anyone has it
+        // or not
+        let payload = json!({ "email": "nobody@loco.com" });
+        for _ in 0..2 {
+            let response = request.post("/api/auth/magic-link").json(&payload).await;
+            assert_eq!(response.status_code(), 200);
+        }
+        let response = request.post("/api/auth/magic-link").json(&payload).await;
+        assert_eq!(response.status_code(), 429);
+        assert_eq!(magic_links::Entity::find().count(&ctx.db).await.unwrap(), 0);
+    })
+    .await;
+}
@@ -0,0 +1,214 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{agents, conversations, learning_models, messages, model_training_data},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+struct Chat {
+    model: learning_models::Model,
+    question: messages::Model,
+    answer: messages::Model,
+}
+
+async fn create_chat(db: &DatabaseConnection, user_id: i32) -> Chat {
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let model = learning_models::ActiveModel {
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        agent_id: ActiveValue::set(Some(agent.id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+    .promote(db)
+    .await
+    .unwrap();
+    let conversation = conversations::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(user_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let question = messages::ActiveModel {
+        conversation_id: ActiveValue::set(conversation.id),
+        role: ActiveValue::set("user".to_string()),
+        content: ActiveValue::set("How do I reset my password?".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let answer = messages::ActiveModel {
+        conversation_id: ActiveValue::set(conversation.id),
+        role: ActiveValue::set("assistant".to_string()),
+        content: ActiveValue::set("Use the forgot password link.".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    Chat {
+        model,
+        question,
+        answer,
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_capture_approved_turn() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let chat = create_chat(&ctx.db, user.user.id).await;
+
+        let response = request
+            .post(&format!("/api/messages/{}/feedback", chat.answer.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "rating": "up" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let outcome: serde_json::Value = response.json();
+        let sample_id = outcome["sample_id"].as_str().unwrap().to_string();
+
+        let sample = model_training_data::Model::find_by_id(&ctx.db, sample_id.parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(sample.status, "pending");
+        assert_eq!(sample.model_id, chat.model.id);
+        assert_eq!(sample.input, json!("How do I reset my password?"));
+        assert_eq!(sample.output, json!("Use the forgot password link."));
+        let metadata = sample.metadata.unwrap();
+        assert_eq!(metadata["source"], "conversation_feedback");
+        assert_eq!(metadata["message_id"], json!(chat.answer.id));
+        assert_eq!(metadata["prompt_message_id"], json!(chat.question.id));
+
+        // pending samples are not used for training
+        assert!(chat.model.training_data(&ctx.db).await.unwrap().is_empty());
+
+        let response = request
+            .get("/api/training_samples")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let queue: Vec<serde_json::Value> = response.json();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0]["id"], json!(sample_id));
+
+        let response = request
+            .post(&format!("/api/training_samples/{sample_id}/approve"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(chat.model.training_data(&ctx.db).await.unwrap().len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn correction_replaces_pending_sample() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let chat = create_chat(&ctx.db, user.user.id).await;
+
+        request
+            .post(&format!("/api/messages/{}/feedback", chat.answer.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "rating": "up" }))
+            .await;
+        let response = request
+            .post(&format!("/api/messages/{}/correction", chat.answer.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "content": "Click \"Forgot password\" on the login page." }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let pending = model_training_data::Model::list_by_status(
+            &ctx.db,
+            model_training_data::Status::Pending,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            pending[0].output,
+            json!("Click \"Forgot password\" on the login page.")
+        );
+        let metadata = pending[0].metadata.clone().unwrap();
+        assert_eq!(metadata["corrected"], true);
+        assert_eq!(metadata["original_output"], "Use the forgot password link.");
+
+        // a plain thumbs down keeps no sample
+        let response = request
+            .post(&format!("/api/messages/{}/feedback", chat.answer.id))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "rating": "down", "comment": "wrong" }))
+            .await;
+        let outcome: serde_json::Value = response.json();
+        assert!(outcome["sample_id"].is_null());
+
+        let message = messages::Model::find_by_id(&ctx.db, chat.answer.id)
+            .await
+            .unwrap();
+        assert_eq!(message.metadata.unwrap()["feedback"]["rating"], "down");
+        assert!(model_training_data::Model::list_by_status(
+            &ctx.db,
+            model_training_data::Status::Pending,
+            None,
+        )
+        .await
+        .unwrap()
+        .is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_rate_foreign_or_user_messages() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let chat = create_chat(&ctx.db, user.user.id).await;
+
+        let response = request
+            .post(&format!("/api/messages/{}/feedback", chat.question.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "rating": "up" }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let other = myapp::models::users::Model::find_by_email(&ctx.db, "user1@example.com")
+            .await
+            .unwrap();
+        let foreign = create_chat(&ctx.db, other.id).await;
+        let response = request
+            .post(&format!("/api/messages/{}/feedback", foreign.answer.id))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "rating": "up" }))
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
@@ -1,2 +1,21 @@
+mod account;
+mod agents;
+mod api_keys;
+mod approvals;
 mod auth;
+mod change_email;
+mod conversations;
+mod data_export;
+mod learning_models;
+mod lockout;
+mod magic_links;
+mod messages;
+mod oidc;
+mod organizations;
+mod plans;
 mod prepare_data;
+mod schedules;
+mod sessions;
+mod tasks;
+mod two_factor;
+mod usage;
@@ -0,0 +1,215 @@
+use axum::http::{HeaderName, HeaderValue};
+use loco_rs::testing;
+use myapp::{app::App, models::invitations, policy::ORGANIZATION_HEADER};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+fn organization_header(id: &str) -> (HeaderName, HeaderValue) {
+    (
+        HeaderName::from_static(ORGANIZATION_HEADER),
+        HeaderValue::from_str(id).unwrap(),
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn roles_decide_what_members_may_do() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+        let bob = prepare_data::login_as(&request, &ctx, "bob", "bob@example.com").await;
+        let (bob_key, bob_value) = prepare_data::auth_header(&bob.token);
+        let eve = prepare_data::login_as(&request, &ctx, "eve", "eve@example.com").await;
+        let (eve_key, eve_value) = prepare_data::auth_header(&eve.token);
+
+        let response = request
+            .post("/api/organizations")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "name": "Acme" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let organization: serde_json::Value = response.json();
+        assert_eq!(organization["role"], "owner");
+        let org_id = organization["id"].as_str().unwrap().to_string();
+        let (org_key, org_value) = organization_header(&org_id);
+
+        let response = request
+            .post(&format!("/api/organizations/{org_id}/invitations"))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "email": "bob@example.com", "role": "viewer" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let sent: serde_json::Value = response.json();
+        assert_eq!(sent["role"], "viewer");
+        assert!(sent.get("token").is_none());
+        let invitation = invitations::Entity::find()
+            .filter(invitations::invitations::Column::Email.eq("bob@example.com"))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // the invitation is only good for the address it was sent to
+        let response = request
+            .post(&format!(
+                "/api/organizations/invitations/{}/accept",
+                invitation.token
+            ))
+            .add_header(eve_key.clone(), eve_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+        let response = request
+            .post(&format!(
+                "/api/organizations/invitations/{}/accept",
+                invitation.token
+            ))
+            .add_header(bob_key.clone(), bob_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let joined: serde_json::Value = response.json();
+        assert_eq!(joined["role"], "viewer");
+
+        let response = request
+            .post("/api/agents")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .json(&json!({ "name": "shared", "type": "assistant" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let agent: serde_json::Value = response.json();
+        let agent_id = agent["id"].as_str().unwrap().to_string();
+        assert_eq!(agent["organization_id"], org_id.as_str());
+
+        // viewers read but do not change or use
+        let response = request
+            .get(&format!("/api/agents/{agent_id}"))
+            .add_header(bob_key.clone(), bob_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .patch(&format!("/api/agents/{agent_id}"))
+            .add_header(bob_key.clone(), bob_value.clone())
+            .json(&json!({ "name": "mine" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/api/conversations")
+            .add_header(bob_key.clone(), bob_value.clone())
+            .json(&json!({ "agent_id": agent_id }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/api/agents")
+            .add_header(bob_key.clone(), bob_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .json(&json!({ "name": "other", "type": "assistant" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        // outsiders do not see it at all
+        let response = request
+            .get(&format!("/api/agents/{agent_id}"))
+            .add_header(eve_key.clone(), eve_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+        let response = request
+            .get("/api/agents")
+            .add_header(eve_key, eve_value)
+            .add_header(org_key.clone(), org_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .patch(&format!(
+                "/api/organizations/{org_id}/members/{}",
+                bob.user.pid
+            ))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "role": "member" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/conversations")
+            .add_header(bob_key.clone(), bob_value.clone())
+            .json(&json!({ "agent_id": agent_id }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let conversation: serde_json::Value = response.json();
+        assert_eq!(conversation["organization_id"], org_id.as_str());
+
+        // the conversation is shared with the rest of the organization
+        let response = request
+            .get("/api/conversations")
+            .add_header(owner_key, owner_value)
+            .add_header(org_key, org_value)
+            .await;
+        let conversations: serde_json::Value = response.json();
+        assert_eq!(conversations.as_array().unwrap().len(), 1);
+        assert_eq!(conversations[0]["id"], conversation["id"]);
+
+        // members do not manage other members
+        let response = request
+            .delete(&format!(
+                "/api/organizations/{org_id}/members/{}",
+                owner.user.pid
+            ))
+            .add_header(bob_key, bob_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn organizations_keep_an_owner() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+        let response = request
+            .post("/api/organizations")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "name": "Acme" }))
+            .await;
+        let organization: serde_json::Value = response.json();
+        let org_id = organization["id"].as_str().unwrap().to_string();
+        let member_url = format!("/api/organizations/{org_id}/members/{}", owner.user.pid);
+
+        let response = request
+            .patch(&member_url)
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "role": "admin" }))
+            .await;
+        assert_eq!(response.status_code(), 409);
+        let response = request
+            .delete(&member_url)
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 409);
+
+        let (org_key, org_value) = organization_header(&org_id);
+        request
+            .post("/api/agents")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key, org_value)
+            .json(&json!({ "name": "shared", "type": "assistant" }))
+            .await;
+        let response = request
+            .delete(&format!("/api/organizations/{org_id}"))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 409);
+
+        let response = request
+            .get("/api/organizations")
+            .add_header(owner_key, owner_value)
+            .await;
+        let organizations: serde_json::Value = response.json();
+        assert_eq!(organizations.as_array().unwrap().len(), 1);
+    })
+    .await;
+}
@@ -0,0 +1,193 @@
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{agents, tasks, usage_records, users},
+    plans::Plan,
+    workers::orchestrator::{OrchestratorWorker, OrchestratorWorkerArgs},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn limits_messages_per_day() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("echo".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let response = request
+            .post("/api/conversations")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "agent_id": agent.id }))
+            .await;
+        let conversation: serde_json::Value = response.json();
+        let url = format!(
+            "/api/conversations/{}/messages",
+            conversation["id"].as_str().unwrap()
+        );
+
+        // the free plan allows 3 messages per day in the test config
+        for sent in 1..=3 {
+            let response = request
+                .post(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "content": "ping" }))
+                .await;
+            assert_eq!(response.status_code(), 200);
+            assert_eq!(response.header("x-plan"), "free");
+            assert_eq!(response.header("x-quota-messages-used"), sent.to_string());
+            assert_eq!(response.header("x-quota-messages-limit"), "3");
+            assert_eq!(
+                response.header("x-quota-messages-remaining"),
+                (3 - sent).to_string()
+            );
+            assert!(response.maybe_header("x-quota-messages-reset").is_some());
+        }
+
+        let response = request
+            .post(&url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "content": "ping" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+        assert!(response.maybe_header("retry-after").is_some());
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "quota_exceeded");
+        assert_eq!(body["quota"], "messages_per_day");
+        assert_eq!(body["plan"], "free");
+
+        let current = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap();
+        current
+            .into_active_model()
+            .set_plan(&ctx.db, Plan::Pro)
+            .await
+            .unwrap();
+        let response = request
+            .post(&url)
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "content": "ping" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.header("x-plan"), "pro");
+        assert_eq!(response.header("x-quota-messages-remaining"), "1996");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn limits_agent_count() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let create = |name: &str| {
+            request
+                .post("/api/agents")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "name": name, "type": "assistant", "status": "active" }))
+        };
+
+        // the free plan allows 3 agents in the test config
+        for count in 1..=3 {
+            let response = create(&format!("agent {count}")).await;
+            assert_eq!(response.status_code(), 200);
+            assert_eq!(response.header("x-quota-agents-used"), count.to_string());
+        }
+
+        let response = create("agent 4").await;
+        assert_eq!(response.status_code(), 402);
+        assert!(response.maybe_header("retry-after").is_none());
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["quota"], "agents");
+        assert_eq!(body["description"], "the free plan allows 3 agents");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn limits_tokens_of_tasks_and_scheduled_runs() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let response = request
+            .post("/api/agents")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "writer", "type": "assistant", "status": "active" }))
+            .await;
+        let agent: serde_json::Value = response.json();
+        let response = request
+            .post("/api/schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "agent_id": agent["id"],
+                "name": "digest",
+                "cron": "0 9 * * *",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let schedule: serde_json::Value = response.json();
+
+        // the free plan allows 200000 tokens per month in the test config
+        usage_records::ActiveModel {
+            user_id: ActiveValue::set(Some(user.user.id)),
+            provider: ActiveValue::set("echo".to_string()),
+            model: ActiveValue::set("echo".to_string()),
+            prompt_tokens: ActiveValue::set(150_000),
+            completion_tokens: ActiveValue::set(50_000),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/api/tasks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "agent_id": agent["id"], "name": "summarize" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["quota"], "tokens_per_month");
+
+        let response = request
+            .post(&format!(
+                "/api/schedules/{}/run",
+                schedule["id"].as_str().unwrap()
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 429);
+
+        // runs the scheduler created fail without calling the model
+        let task = tasks::ActiveModel {
+            agent_id: ActiveValue::set(agent["id"].as_str().unwrap().parse().unwrap()),
+            user_id: ActiveValue::set(Some(user.user.id)),
+            name: ActiveValue::set("digest".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let task = OrchestratorWorker { ctx: ctx.clone() }
+            .run(&OrchestratorWorkerArgs { task_id: task.id })
+            .await
+            .unwrap();
+        assert_eq!(task.status, "failed");
+        assert_eq!(task.output.unwrap()["error"], "quota_exceeded");
+    })
+    .await;
+}
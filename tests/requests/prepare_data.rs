@@ -1,19 +1,31 @@
 use axum::http::{HeaderName, HeaderValue};
 use loco_rs::{app::AppContext, TestServer};
 use myapp::{models::users, views::auth::LoginResponse};
+use sea_orm::IntoActiveModel;
 
 const USER_EMAIL: &str = "test@loco.com";
-const USER_PASSWORD: &str = "1234";
+pub const USER_PASSWORD: &str = "correct-horse-battery";
 
 pub struct LoggedInUser {
     pub user: users::Model,
     pub token: String,
+    pub refresh_token: String,
 }
 
 pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
+    login_as(request, ctx, "loco", USER_EMAIL).await
+}
+
+/// Registers, verifies and logs in another user
+pub async fn login_as(
+    request: &TestServer,
+    ctx: &AppContext,
+    name: &str,
+    email: &str,
+) -> LoggedInUser {
     let register_payload = serde_json::json!({
-        "name": "loco",
-        "email": USER_EMAIL,
+        "name": name,
+        "email": email,
         "password": USER_PASSWORD
     });
 
@@ -22,12 +34,17 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
         .post("/api/auth/register")
         .json(&register_payload)
         .await;
-    let user = users::Model::find_by_email(&ctx.db, USER_EMAIL)
+    // only the hash of the emailed token is stored, so issue one to use
+    let (_, verify_token) = users::Model::find_by_email(&ctx.db, email)
+        .await
+        .unwrap()
+        .into_active_model()
+        .set_email_verification_sent(&ctx.db)
         .await
         .unwrap();
 
     let verify_payload = serde_json::json!({
-        "token": user.email_verification_token,
+        "token": verify_token,
     });
 
     request.post("/api/auth/verify").json(&verify_payload).await;
@@ -35,7 +52,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let response = request
         .post("/api/auth/login")
         .json(&serde_json::json!({
-            "email": USER_EMAIL,
+            "email": email,
             "password": USER_PASSWORD
         }))
         .await;
@@ -43,10 +60,9 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
 
     LoggedInUser {
-        user: users::Model::find_by_email(&ctx.db, USER_EMAIL)
-            .await
-            .unwrap(),
+        user: users::Model::find_by_email(&ctx.db, email).await.unwrap(),
         token: login_response.token,
+        refresh_token: login_response.refresh_token,
     }
 }
 
@@ -0,0 +1,241 @@
+use axum::http::{HeaderName, HeaderValue};
+use chrono::{Duration, Local};
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    common::settings::Settings,
+    models::{
+        agents, memberships, organizations,
+        task_schedules::{self, Status},
+        tasks,
+    },
+    policy::ORGANIZATION_HEADER,
+    scheduling,
+    workers::orchestrator::{OrchestratorWorker, OrchestratorWorkerArgs},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn can_pause_resume_and_trigger_schedule() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("digest".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/api/schedules")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "agent_id": agent.id,
+                "name": "Morning digest",
+                "cron": "0 8 * * *",
+                "timezone": "Europe/Berlin",
+                "catch_up": "skip",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let schedule: serde_json::Value = response.json();
+        assert_eq!(schedule["status"], "active");
+        assert_eq!(schedule["catch_up"], "skip");
+        assert!(!schedule["next_run_at"].is_null());
+        let id = schedule["id"].as_str().unwrap().to_string();
+
+        let response = request
+            .post(&format!("/api/schedules/{id}/pause"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let paused: serde_json::Value = response.json();
+        assert_eq!(paused["status"], "paused");
+        assert!(paused["next_run_at"].is_null());
+
+        let response = request
+            .post(&format!("/api/schedules/{id}/run"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let task: serde_json::Value = response.json();
+        assert_eq!(task["schedule_id"], id.as_str());
+        assert_eq!(task["name"], "Morning digest");
+
+        let response = request
+            .post(&format!("/api/schedules/{id}/resume"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let resumed: serde_json::Value = response.json();
+        assert_eq!(resumed["status"], "active");
+        assert_eq!(resumed["next_run_at"], schedule["next_run_at"]);
+        assert!(!resumed["last_run_at"].is_null());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_cron_and_timezone() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("digest".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        for (cron, timezone) in [("every morning", "UTC"), ("0 8 * * *", "Mars/Olympus")] {
+            let response = request
+                .post("/api/schedules")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({
+                    "agent_id": agent.id,
+                    "name": "Morning digest",
+                    "cron": cron,
+                    "timezone": timezone,
+                }))
+                .await;
+            assert_eq!(response.status_code(), 400, "{cron} in {timezone}");
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn schedules_run_only_while_their_user_may_use_the_agent() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let owner = prepare_data::init_user_login(&request, &ctx).await;
+        let (owner_key, owner_value) = prepare_data::auth_header(&owner.token);
+        let bob = prepare_data::login_as(&request, &ctx, "bob", "bob@example.com").await;
+        let (bob_key, bob_value) = prepare_data::auth_header(&bob.token);
+        let organization = organizations::Model::create_with_owner(&ctx.db, "Acme", &owner.user)
+            .await
+            .unwrap();
+        memberships::ActiveModel {
+            organization_id: ActiveValue::set(organization.id),
+            user_id: ActiveValue::set(bob.user.id),
+            role: ActiveValue::set(memberships::Role::Member.as_str().to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        let org_id = organization.id.to_string();
+        let org_key = HeaderName::from_static(ORGANIZATION_HEADER);
+        let org_value = HeaderValue::from_str(&org_id).unwrap();
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("shared".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            organization_id: ActiveValue::set(Some(organization.id)),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let response = request
+            .post("/api/schedules")
+            .add_header(bob_key.clone(), bob_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .json(&json!({ "agent_id": agent.id, "name": "Hourly", "cron": "0 * * * *" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let schedule: serde_json::Value = response.json();
+        let id = schedule["id"].as_str().unwrap().to_string();
+
+        // the owner of the organization sees and manages it
+        let response = request
+            .get("/api/schedules")
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .await;
+        let listed: serde_json::Value = response.json();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["id"], id.as_str());
+        let response = request
+            .post(&format!("/api/schedules/{id}/pause"))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post(&format!("/api/schedules/{id}/resume"))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        // but does not run it as bob
+        let response = request
+            .post(&format!("/api/schedules/{id}/run"))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .add_header(org_key.clone(), org_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // a run made before bob became a viewer fails, and neither it nor
+        // the next tick makes more
+        let schedule = task_schedules::Model::find_by_id(&ctx.db, id.parse().unwrap())
+            .await
+            .unwrap();
+        let task = scheduling::trigger(&ctx.db, &schedule).await.unwrap();
+        let response = request
+            .patch(&format!(
+                "/api/organizations/{org_id}/members/{}",
+                bob.user.pid
+            ))
+            .add_header(owner_key.clone(), owner_value.clone())
+            .json(&json!({ "role": "viewer" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let task = OrchestratorWorker { ctx: ctx.clone() }
+            .run(&OrchestratorWorkerArgs { task_id: task.id })
+            .await
+            .unwrap();
+        assert_eq!(task.status, tasks::Status::Failed.as_str());
+        assert_eq!(task.output.unwrap()["error"], "forbidden");
+        let schedule = task_schedules::Model::find_by_id(&ctx.db, schedule.id)
+            .await
+            .unwrap();
+        assert_eq!(schedule.status, Status::Paused.as_str());
+
+        let mut due = schedule.into_active_model();
+        due.status = ActiveValue::set(Status::Active.as_str().to_string());
+        due.next_run_at = ActiveValue::set(Some(Local::now().naive_local() - Duration::minutes(1)));
+        let schedule = due.update(&ctx.db).await.unwrap();
+        let settings = Settings::from_context(&ctx).unwrap();
+        let created = scheduling::tick(&ctx.db, &settings.scheduler, Local::now().naive_local())
+            .await
+            .unwrap();
+        assert!(created.is_empty());
+        let schedule = task_schedules::Model::find_by_id(&ctx.db, schedule.id)
+            .await
+            .unwrap();
+        assert_eq!(schedule.status, Status::Paused.as_str());
+
+        let response = request
+            .post(&format!("/api/schedules/{id}/resume"))
+            .add_header(bob_key, bob_value)
+            .add_header(org_key, org_value)
+            .await;
+        assert_eq!(response.status_code(), 403);
+    })
+    .await;
+}
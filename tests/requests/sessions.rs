@@ -0,0 +1,128 @@
+use loco_rs::testing;
+use myapp::{app::App, models::sessions};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn refresh_tokens_rotate_and_reuse_revokes_the_session() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": login.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let refreshed: serde_json::Value = response.json();
+        let next = refreshed["refresh_token"].as_str().unwrap().to_string();
+        assert_ne!(next, login.refresh_token);
+        let (auth_key, auth_value) =
+            prepare_data::auth_header(refreshed["token"].as_str().unwrap());
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // the old token was used up; presenting it again revokes the family,
+        // so the token handed out in its place stops working too
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": login.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": next }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let rows = sessions::Entity::find()
+            .filter(sessions::sessions::Column::UserId.eq(login.user.id))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.revoked_at.is_some()));
+        assert!(rows.iter().all(|row| row.token_hash != next));
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": "not-a-token" }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn sessions_can_be_listed_and_revoked() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let first = prepare_data::init_user_login(&request, &ctx).await;
+        let response = request
+            .post("/api/auth/login")
+            .add_header(
+                axum::http::header::USER_AGENT,
+                axum::http::HeaderValue::from_static("laptop"),
+            )
+            .json(&json!({ "email": first.user.email, "password": prepare_data::USER_PASSWORD }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let second: serde_json::Value = response.json();
+        let second_refresh = second["refresh_token"].as_str().unwrap().to_string();
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&first.token);
+        let response = request
+            .get("/api/auth/sessions")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let list: Vec<serde_json::Value> = response.json();
+        assert_eq!(list.len(), 2);
+        let laptop = list
+            .iter()
+            .find(|session| session["user_agent"] == "laptop")
+            .unwrap();
+        assert_eq!(laptop["current"], false);
+        assert!(list.iter().any(|session| session["current"] == true));
+
+        let response = request
+            .delete(&format!(
+                "/api/auth/sessions/{}",
+                laptop["id"].as_str().unwrap()
+            ))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": second_refresh }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // logging out ends the session the refresh token belongs to
+        let response = request
+            .post("/api/auth/logout")
+            .json(&json!({ "refresh_token": first.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&json!({ "refresh_token": first.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .get("/api/auth/sessions")
+            .add_header(auth_key, auth_value)
+            .await;
+        let list: Vec<serde_json::Value> = response.json();
+        assert!(list.is_empty());
+    })
+    .await;
+}
@@ -0,0 +1,63 @@
+use loco_rs::testing;
+use myapp::{app::App, orchestration::Orchestrator};
+use serde_json::json;
+use serial_test::serial;
+use uuid::Uuid;
+
+use super::prepare_data;
+use crate::orchestration::orchestrator::{create_task_for, create_team, plan, Routed};
+
+#[tokio::test]
+#[serial]
+async fn can_trace_delegated_task() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let team = create_team(&ctx.db).await;
+        let task = create_task_for(&ctx.db, &team.planner, user.user.id).await;
+
+        let provider = Routed::new(vec![
+            ("Split the task", Ok(plan(&team))),
+            ("subtasks are done", Ok("done".to_string())),
+            ("You", Ok("step output".to_string())),
+        ]);
+        let task = Orchestrator::new(&provider)
+            .run(&ctx.db, task)
+            .await
+            .unwrap();
+
+        let response = request
+            .get(&format!("/api/tasks/{}/trace", task.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let trace: serde_json::Value = response.json();
+        assert_eq!(trace["status"], "completed");
+        assert_eq!(trace["agent_name"], "planner");
+        let children = trace["children"].as_array().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["agent_name"], "researcher");
+        assert_eq!(children[0]["depends_on"], json!([]));
+        assert_eq!(children[1]["agent_name"], "writer");
+        assert_eq!(children[1]["depends_on"], json!([children[0]["id"]]));
+        assert_eq!(children[1]["output"], json!({ "answer": "step output" }));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_create_task_for_unknown_agent() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let response = request
+            .post("/api/tasks")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "agent_id": Uuid::new_v4(), "name": "Do it" }))
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
@@ -0,0 +1,132 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use loco_rs::testing;
+use myapp::{app::App, mfa::totp};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[test]
+fn totp_codes_match_the_rfc_vectors() {
+    let key = b"12345678901234567890";
+    assert_eq!(totp::code_at(key, 59 / 30), "287082");
+    assert_eq!(totp::code_at(key, 1_111_111_109 / 30), "081804");
+    assert_eq!(totp::code_at(key, 1_234_567_890 / 30), "005924");
+
+    let secret = BASE32_NOPAD.encode(key);
+    assert_eq!(totp::verify(&secret, "287082", 59, None), Some(1));
+    assert_eq!(totp::verify(&secret, "287082", 59, Some(1)), None);
+    assert_eq!(totp::verify(&secret, "287082", 59 + 120, None), None);
+}
+
+#[tokio::test]
+#[serial]
+async fn two_factor_login_takes_a_code_or_recovery_code() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login.token);
+        let credentials =
+            json!({ "email": login.user.email, "password": prepare_data::USER_PASSWORD });
+
+        let response = request
+            .post("/api/auth/2fa/enroll")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let enrollment: serde_json::Value = response.json();
+        let secret = enrollment["secret"].as_str().unwrap().to_string();
+        assert!(enrollment["otpauth_uri"]
+            .as_str()
+            .unwrap()
+            .starts_with("otpauth://totp/myapp:"));
+        let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+        let step = Utc::now().timestamp() / 30;
+
+        // still off until confirmed
+        let response = request.post("/api/auth/login").json(&credentials).await;
+        let body: serde_json::Value = response.json();
+        assert!(body["token"].is_string());
+
+        let response = request
+            .post("/api/auth/2fa/verify")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": "000000" }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/2fa/verify")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": totp::code_at(&key, step) }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        let recovery_codes: Vec<String> =
+            serde_json::from_value(body["recovery_codes"].clone()).unwrap();
+        assert_eq!(recovery_codes.len(), 10);
+
+        let response = request.post("/api/auth/login").json(&credentials).await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["mfa_required"], true);
+        assert!(body.get("token").is_none());
+        let challenge_token = body["challenge_token"].as_str().unwrap().to_string();
+
+        // the challenge is no access token
+        let (challenge_key, challenge_value) = prepare_data::auth_header(&challenge_token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(challenge_key, challenge_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // the code that turned it on was used already
+        let response = request
+            .post("/api/auth/2fa/challenge")
+            .json(&json!({
+                "challenge_token": challenge_token,
+                "code": totp::code_at(&key, step),
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/2fa/challenge")
+            .json(&json!({
+                "challenge_token": challenge_token,
+                "code": totp::code_at(&key, step + 1),
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body: serde_json::Value = response.json();
+        assert!(body["token"].is_string());
+        assert!(body["refresh_token"].is_string());
+
+        let response = request
+            .post("/api/auth/2fa/challenge")
+            .json(&json!({
+                "challenge_token": challenge_token,
+                "code": recovery_codes[0].to_uppercase(),
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/2fa/challenge")
+            .json(&json!({
+                "challenge_token": challenge_token,
+                "code": recovery_codes[0],
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .post("/api/auth/2fa/disable")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": recovery_codes[1] }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.post("/api/auth/login").json(&credentials).await;
+        let body: serde_json::Value = response.json();
+        assert!(body["token"].is_string());
+    })
+    .await;
+}
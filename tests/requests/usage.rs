@@ -0,0 +1,83 @@
+use chrono::Local;
+use loco_rs::testing;
+use myapp::{app::App, models::agents};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn can_report_usage_by_conversation() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let agent = agents::ActiveModel {
+            name: ActiveValue::set("echo".to_string()),
+            r#type: ActiveValue::set("assistant".to_string()),
+            status: ActiveValue::set("active".to_string()),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        let mut conversations = Vec::new();
+        for content in ["ping", "ping pong"] {
+            let response = request
+                .post("/api/conversations")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "agent_id": agent.id, "title": content }))
+                .await;
+            let conversation: serde_json::Value = response.json();
+            let id = conversation["id"].as_str().unwrap().to_string();
+            let response = request
+                .post(&format!("/api/conversations/{id}/messages"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "content": content }))
+                .await;
+            assert_eq!(response.status_code(), 200);
+            conversations.push(id);
+        }
+
+        let today = Local::now().date_naive();
+        let response = request
+            .get(&format!(
+                "/api/usage?from={today}&to={today}&group_by=day&by=conversation"
+            ))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let report: serde_json::Value = response.json();
+        // the echo provider counts a token per word, priced at 1 and 2
+        // dollars per million prompt and completion tokens in the test config
+        assert_eq!(report["totals"]["calls"], 2);
+        assert_eq!(report["totals"]["prompt_tokens"], 3);
+        assert_eq!(report["totals"]["completion_tokens"], 3);
+        let cost = report["totals"]["cost"].as_f64().unwrap();
+        assert!((cost - 9e-6).abs() < 1e-12, "{cost}");
+
+        let buckets = report["buckets"].as_array().unwrap();
+        assert_eq!(buckets.len(), 2);
+        for bucket in buckets {
+            assert_eq!(bucket["period"], today.to_string());
+            assert!(
+                conversations.contains(&bucket["conversation_id"].as_str().unwrap().to_string())
+            );
+        }
+
+        let response = request
+            .get("/api/usage?by=user")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .get("/api/usage?from=2024-02-01&to=2024-01-01")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
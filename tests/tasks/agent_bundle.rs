@@ -0,0 +1,172 @@
+use loco_rs::{boot::run_task, task, testing};
+use myapp::{
+    app::App,
+    bundles::{self, OnConflict, Owner},
+    knowledge,
+    models::{
+        agent_capabilities, agent_versions, agents, knowledge_base, knowledge_items,
+        learning_models, model_training_data,
+    },
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serde_json::json;
+use serial_test::serial;
+
+/// An agent with a capability, a knowledge base holding one document and
+/// two learning model versions, the first one active and with a sample
+pub async fn create_agent(db: &DatabaseConnection) -> agents::Model {
+    let kb = knowledge_base::ActiveModel {
+        name: ActiveValue::set("handbook".to_string()),
+        r#type: ActiveValue::set("documents".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    knowledge::ingest(
+        db,
+        kb.id,
+        "Refunds take five days.",
+        json!({ "source": "faq" }),
+    )
+    .await
+    .unwrap();
+
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({
+            "system_prompt": "Be brief.",
+            "knowledge_base_ids": [kb.id],
+        }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("current_time".to_string()),
+        requires_approval: ActiveValue::set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let model = learning_models::ActiveModel {
+        agent_id: ActiveValue::set(Some(agent.id)),
+        name: ActiveValue::set("support".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    model_training_data::ActiveModel {
+        model_id: ActiveValue::set(model.id),
+        input: ActiveValue::set(json!("refund?")),
+        output: ActiveValue::set(json!("five days")),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let model = model.promote(db).await.unwrap();
+    model
+        .create_version(db, json!({ "demos": [] }), json!({}))
+        .await
+        .unwrap();
+
+    agent
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_and_import_agent() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let agent = create_agent(db).await;
+    let path = std::env::temp_dir().join(format!("agent-{}.zip", agent.id));
+
+    let vars = task::Vars::from_cli_args(vec![
+        ("agent".to_string(), agent.id.to_string()),
+        ("path".to_string(), path.display().to_string()),
+    ]);
+    run_task::<App>(&boot.app_context, Some(&"export_agent".to_string()), &vars)
+        .await
+        .unwrap();
+
+    let bundle = std::fs::read(&path).unwrap();
+    let manifest = bundles::manifest(&bundle).unwrap();
+    assert_eq!(manifest.format_version, bundles::FORMAT_VERSION);
+    assert_eq!(manifest.agent.id, agent.id);
+    assert_eq!(manifest.files["knowledge_items.jsonl"], 2);
+    assert_eq!(manifest.files["learning_models.jsonl"], 2);
+
+    // the names are taken by the original
+    let vars = task::Vars::from_cli_args(vec![("path".to_string(), path.display().to_string())]);
+    assert!(
+        run_task::<App>(&boot.app_context, Some(&"import_agent".to_string()), &vars)
+            .await
+            .is_err()
+    );
+
+    let report = bundles::import(db, &bundle, OnConflict::Rename, Owner::default())
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(report.name, "support (imported)");
+    assert_ne!(report.agent_id, agent.id);
+    assert_eq!(report.renamed["support"], "support (imported)");
+
+    let imported = agents::Model::find_by_id(db, report.agent_id)
+        .await
+        .unwrap();
+    let kb_ids = bundles::linked_knowledge_bases(imported.configuration.as_ref());
+    assert_eq!(kb_ids.len(), 1);
+    assert_ne!(
+        kb_ids,
+        bundles::linked_knowledge_bases(agent.configuration.as_ref())
+    );
+    let items = knowledge_items::Entity::find()
+        .filter(knowledge_items::knowledge_items::Column::KnowledgeBaseId.eq(kb_ids[0]))
+        .all(db)
+        .await
+        .unwrap();
+    let document = items.iter().find(|item| item.r#type == "document").unwrap();
+    let chunk = items.iter().find(|item| item.r#type == "chunk").unwrap();
+    assert_eq!(
+        chunk.metadata.as_ref().unwrap()["document_id"],
+        json!(document.id)
+    );
+
+    let capabilities = agent_capabilities::Model::list_for_agent(db, imported.id)
+        .await
+        .unwrap();
+    assert_eq!(capabilities.len(), 1);
+    assert!(capabilities[0].requires_approval);
+
+    let active = imported.active_learning_model(db).await.unwrap();
+    assert_eq!(active.name, "support (imported)");
+    let versions = active.versions(db).await.unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[1].parent_id, Some(active.id));
+    assert_eq!(active.training_data(db).await.unwrap().len(), 1);
+
+    let versions = agent_versions::Model::list_for_agent(db, imported.id)
+        .await
+        .unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].learning_model_id, Some(active.id));
+
+    let skipped = bundles::import(db, &bundle, OnConflict::Skip, Owner::default())
+        .await
+        .unwrap();
+    assert!(skipped.skipped);
+    assert_eq!(skipped.agent_id, agent.id);
+}
@@ -1 +1,4 @@
+pub mod agent_bundle;
+mod optimize_model;
 pub mod seed;
+mod usage_report;
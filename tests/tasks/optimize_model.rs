@@ -0,0 +1,55 @@
+use loco_rs::{boot::run_task, task, testing};
+use myapp::{
+    app::App,
+    models::{learning_models, model_training_data},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+use uuid::Uuid;
+
+#[tokio::test]
+#[serial]
+async fn test_can_optimize_model() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let model = learning_models::ActiveModel {
+        name: ActiveValue::set("echo".to_string()),
+        r#type: ActiveValue::set("few_shot".to_string()),
+        version: ActiveValue::set("1".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    for (n, text) in ["hello", "world", "again"].iter().enumerate() {
+        model_training_data::ActiveModel {
+            id: ActiveValue::set(Uuid::from_u128(n as u128 + 1)),
+            model_id: ActiveValue::set(model.id),
+            input: ActiveValue::set(json!(text)),
+            output: ActiveValue::set(json!(text)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    let vars = task::Vars::from_cli_args(vec![
+        ("model".to_string(), model.id.to_string()),
+        ("candidates".to_string(), "2".to_string()),
+    ]);
+    assert!(run_task::<App>(
+        &boot.app_context,
+        Some(&"optimize_model".to_string()),
+        &vars
+    )
+    .await
+    .is_ok());
+
+    let versions = model.versions(db).await.unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[1].version, "2");
+    assert_eq!(versions[1].metrics.as_ref().unwrap()["score"], 1.0);
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDate;
+use loco_rs::{boot::run_task, task, testing};
+use myapp::{
+    app::App,
+    llm::Usage,
+    models::{agents, usage_records, users},
+    usage::{self, report, Price, Prices, Scope},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+fn prices() -> Prices {
+    Prices(
+        [
+            (
+                "large".to_string(),
+                Price {
+                    prompt: 10.0,
+                    completion: 30.0,
+                },
+            ),
+            (
+                usage::DEFAULT_PRICE.to_string(),
+                Price {
+                    prompt: 1.0,
+                    completion: 1.0,
+                },
+            ),
+        ]
+        .into(),
+    )
+}
+
+fn call(model: &str, prompt_tokens: u32, completion_tokens: u32) -> Usage {
+    Usage {
+        provider: "open_ai".to_string(),
+        model: model.to_string(),
+        prompt_tokens,
+        completion_tokens,
+    }
+}
+
+#[test]
+fn prices_calls_per_million_tokens() {
+    let prices = prices();
+    assert!((prices.cost(&call("large", 1_000, 2_000)) - 0.07).abs() < 1e-12);
+    // unknown models fall back to the default price
+    assert!((prices.cost(&call("small", 500_000, 500_000)) - 1.0).abs() < 1e-12);
+    assert!(Prices::default().cost(&call("large", 1_000, 1_000)).abs() < f64::EPSILON);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_can_report_monthly_usage() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("digest".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let scope = Scope {
+        user_id: Some(user.id),
+        agent_id: Some(agent.id),
+        ..Default::default()
+    };
+
+    let at = |day: u32| {
+        NaiveDate::from_ymd_opt(2024, 10, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    };
+    for (day, usage) in [
+        (1, call("large", 1_000, 2_000)),
+        (31, call("large", 1_000, 2_000)),
+        // outside of the month
+        (30, call("large", 9_000, 9_000)),
+    ] {
+        let record = usage::record(db, &prices(), &scope, &usage).await.unwrap();
+        let mut record = record.into_active_model();
+        record.created_at = ActiveValue::set(if day == 30 {
+            at(day) + chrono::Duration::days(2)
+        } else {
+            at(day)
+        });
+        record.update(db).await.unwrap();
+    }
+    let records = usage_records::Model::list_between(db, at(1), at(31), None)
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 1);
+
+    let summary = report::monthly_summary(db, "2024-10").await.unwrap();
+    assert!(summary.starts_with("usage for 2024-10 (2024-10-01 to 2024-10-31)"));
+    let total = summary.lines().nth(1).unwrap();
+    assert!(total.contains("total"), "{summary}");
+    assert!(total.contains("2 calls"), "{summary}");
+    assert!(total.contains("$0.1400"), "{summary}");
+    assert!(summary.contains("user1@example.com"), "{summary}");
+    assert!(summary.contains("digest"), "{summary}");
+
+    assert!(report::monthly_summary(db, "October").await.is_err());
+
+    let vars = task::Vars::from_cli_args(vec![("month".to_string(), "2024-10".to_string())]);
+    assert!(
+        run_task::<App>(&boot.app_context, Some(&"usage_report".to_string()), &vars)
+            .await
+            .is_ok()
+    );
+}
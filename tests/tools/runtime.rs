@@ -0,0 +1,499 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    common::events,
+    llm::{Completion, CompletionRequest, Provider, ToolCall},
+    models::{
+        agent_capabilities, agents, conversations, messages,
+        tool_approvals::{self, Status},
+        users,
+    },
+    runtime::{approvals, Runtime},
+    tools::{Invocation, Registry, Tool},
+    workers::scheduler::ScheduleWorker,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+use serde_json::{json, Value};
+use serial_test::serial;
+
+/// Plays back canned completions and remembers every request. The last
+/// completion repeats once the script runs out.
+#[derive(Default)]
+pub struct Scripted {
+    replies: Mutex<VecDeque<Completion>>,
+    pub requests: Mutex<Vec<CompletionRequest>>,
+}
+
+impl Scripted {
+    pub fn new(replies: Vec<Completion>) -> Self {
+        Self {
+            replies: Mutex::new(replies.into()),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Scripted {
+    async fn complete(&self, request: &CompletionRequest) -> loco_rs::Result<Completion> {
+        self.requests.lock().unwrap().push(request.clone());
+        let mut replies = self.replies.lock().unwrap();
+        Ok(if replies.len() > 1 {
+            replies.pop_front().unwrap()
+        } else {
+            replies.front().cloned().unwrap_or_default()
+        })
+    }
+}
+
+struct Add;
+
+#[async_trait]
+impl Tool for Add {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    async fn call(
+        &self,
+        _invocation: &Invocation<'_>,
+        arguments: &Value,
+    ) -> loco_rs::Result<Value> {
+        Ok(json!({ "sum": arguments["a"].as_i64().unwrap() + arguments["b"].as_i64().unwrap() }))
+    }
+}
+
+/// Stands in for the gated `current_time` tool and counts its calls
+#[derive(Default, Clone)]
+struct Counted(Arc<AtomicUsize>);
+
+#[async_trait]
+impl Tool for Counted {
+    fn name(&self) -> &str {
+        "current_time"
+    }
+
+    async fn call(
+        &self,
+        _invocation: &Invocation<'_>,
+        _arguments: &Value,
+    ) -> loco_rs::Result<Value> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(json!({ "now": "late" }))
+    }
+}
+
+pub fn call(id: &str, name: &str, arguments: Value) -> ToolCall {
+    ToolCall {
+        id: id.to_string(),
+        name: name.to_string(),
+        arguments,
+    }
+}
+
+async fn create_conversation(db: &DatabaseConnection) -> conversations::Model {
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    create_conversation_for(db, user.id).await
+}
+
+/// An agent with an `add` capability, a `multiply` label without a tool and
+/// a `current_time` capability that requires approval
+pub async fn create_conversation_for(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> conversations::Model {
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("calculator".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "system_prompt": "You add numbers." }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("add".to_string()),
+        description: ActiveValue::set(Some("Adds two integers".to_string())),
+        parameters: ActiveValue::set(Some(json!({
+            "type": "object",
+            "properties": {
+                "a": { "type": "integer" },
+                "b": { "type": "integer" },
+            },
+            "required": ["a", "b"],
+            "additionalProperties": false,
+        }))),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    // a label without an implementation is not offered as a tool
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("multiply".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    agent_capabilities::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        name: ActiveValue::set("current_time".to_string()),
+        requires_approval: ActiveValue::set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    conversations::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(user_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn runs_tool_calls_and_feeds_results_back() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![
+                call("call_1", "add", json!({ "a": 1, "b": 2 })),
+                call("call_2", "add", json!({ "a": "one" })),
+                call("call_3", "multiply", json!({ "a": 2, "b": 3 })),
+            ],
+            ..Default::default()
+        },
+        Completion::text("1 + 2 = 3"),
+    ]);
+    let registry = Registry::builtin().register(Add);
+
+    let turn = Runtime::new(&provider, &registry)
+        .reply(ctx, &conversation, "What is 1 + 2?")
+        .await
+        .unwrap();
+    assert!(!turn.is_paused());
+    let turn = turn.messages;
+
+    let roles: Vec<_> = turn.iter().map(|message| message.role.as_str()).collect();
+    assert_eq!(
+        roles,
+        vec!["user", "assistant", "tool", "tool", "tool", "assistant"]
+    );
+    assert_eq!(turn[5].content, "1 + 2 = 3");
+
+    let statuses: Vec<_> = turn[2..5]
+        .iter()
+        .map(|message| message.metadata.as_ref().unwrap()["status"].clone())
+        .collect();
+    assert_eq!(statuses, vec!["ok", "invalid", "unknown"]);
+    assert_eq!(
+        serde_json::from_str::<Value>(&turn[2].content).unwrap(),
+        json!({ "sum": 3 })
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>(&turn[3].content).unwrap()["details"],
+        json!([
+            "/: missing required property `b`",
+            "/a: expected integer, got string"
+        ])
+    );
+
+    // every message of the turn is persisted
+    let stored = messages::Model::list_for_conversation(&ctx.db, conversation.id)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 6);
+
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let offered: Vec<_> = requests[0]
+        .tools
+        .iter()
+        .map(|tool| tool.name.as_str())
+        .collect();
+    assert_eq!(offered, vec!["add", "current_time"]);
+    assert_eq!(requests[0].messages[0].role, "system");
+
+    let followup = &requests[1].messages;
+    assert_eq!(followup[2].tool_calls.len(), 3);
+    assert_eq!(followup[3].role, "tool");
+    assert_eq!(followup[3].tool_call_id.as_deref(), Some("call_1"));
+}
+
+#[tokio::test]
+#[serial]
+async fn stops_offering_tools_after_max_rounds() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+
+    let provider = Scripted::new(vec![Completion {
+        content: "let me add".to_string(),
+        tool_calls: vec![call("call_1", "add", json!({ "a": 1, "b": 1 }))],
+        ..Default::default()
+    }]);
+    let registry = Registry::new().register(Add);
+
+    let turn = Runtime::new(&provider, &registry)
+        .with_max_tool_rounds(2)
+        .reply(ctx, &conversation, "Add forever")
+        .await
+        .unwrap()
+        .messages;
+
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(!requests[1].tools.is_empty());
+    assert!(requests[2].tools.is_empty());
+    assert_eq!(turn.last().unwrap().role, "assistant");
+    assert_eq!(
+        turn.iter().filter(|message| message.role == "tool").count(),
+        2
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn pauses_until_approved() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+    let mut stream = events::subscribe();
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![
+                call("call_1", "current_time", json!({})),
+                call("call_2", "add", json!({ "a": 2, "b": 2 })),
+            ],
+            ..Default::default()
+        },
+        Completion::text("It is late"),
+    ]);
+    let registry = Registry::builtin().register(Add);
+    let runtime = Runtime::new(&provider, &registry);
+
+    let turn = runtime
+        .reply(ctx, &conversation, "What time is it?")
+        .await
+        .unwrap();
+    assert!(turn.is_paused());
+    let roles: Vec<_> = turn
+        .messages
+        .iter()
+        .map(|message| message.role.as_str())
+        .collect();
+    // the ungated call runs right away
+    assert_eq!(roles, vec!["user", "assistant", "tool"]);
+    let approval = turn.approvals[0].clone();
+    assert_eq!(approval.name, "current_time");
+    assert_eq!(approval.status, "pending");
+    assert_eq!(approval.user_id, conversation.user_id);
+    assert_eq!(provider.requests.lock().unwrap().len(), 1);
+
+    let event = stream.try_recv().unwrap();
+    assert_eq!(event.kind, approvals::EVENT_REQUESTED);
+    assert_eq!(event.user_id, conversation.user_id);
+
+    assert!(runtime.reply(ctx, &conversation, "Hello?").await.is_err());
+
+    let turn = runtime
+        .decide(ctx, approval.clone(), Status::Approved)
+        .await
+        .unwrap();
+    assert!(!turn.is_paused());
+    let roles: Vec<_> = turn
+        .messages
+        .iter()
+        .map(|message| message.role.as_str())
+        .collect();
+    assert_eq!(roles, vec!["tool", "assistant"]);
+    assert_eq!(turn.messages[0].metadata.as_ref().unwrap()["status"], "ok");
+    assert!(serde_json::from_str::<Value>(&turn.messages[0].content).unwrap()["now"].is_string());
+    assert_eq!(turn.messages[1].content, "It is late");
+
+    let approval = tool_approvals::Entity::find_by_id(approval.id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(approval.status, "approved");
+    assert!(approval.decided_at.is_some());
+
+    let requests = provider.requests.lock().unwrap();
+    let tool_ids: Vec<_> = requests[1]
+        .messages
+        .iter()
+        .filter_map(|message| message.tool_call_id.as_deref())
+        .collect();
+    assert_eq!(tool_ids, vec!["call_2", "call_1"]);
+}
+
+#[tokio::test]
+#[serial]
+async fn rejected_and_expired_calls_are_reported_to_the_model() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![call("call_1", "current_time", json!({}))],
+            ..Default::default()
+        },
+        Completion::text("Fine"),
+    ]);
+    let registry = Registry::builtin();
+    let runtime = Runtime::new(&provider, &registry);
+
+    let turn = runtime.reply(ctx, &conversation, "Time?").await.unwrap();
+    let approval = turn.approvals[0].clone();
+    let turn = runtime
+        .decide(ctx, approval.clone(), Status::Rejected)
+        .await
+        .unwrap();
+    assert_eq!(
+        turn.messages[0].metadata.as_ref().unwrap()["status"],
+        "rejected"
+    );
+    assert_eq!(turn.messages[1].content, "Fine");
+    assert!(
+        tool_approvals::Model::list_pending_for_user(&ctx.db, conversation.user_id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![call("call_2", "current_time", json!({}))],
+            ..Default::default()
+        },
+        Completion::text("Never mind"),
+    ]);
+    let runtime = Runtime::new(&provider, &registry).with_approval_ttl(Duration::seconds(-1));
+    let turn = runtime
+        .reply(ctx, &conversation, "Time now?")
+        .await
+        .unwrap();
+    assert!(turn.is_paused());
+    assert!(runtime
+        .decide(ctx, turn.approvals[0].clone(), Status::Pending)
+        .await
+        .is_err());
+
+    assert_eq!(approvals::expire_overdue(ctx, &runtime).await.unwrap(), 1);
+    let history = messages::Model::list_for_conversation(&ctx.db, conversation.id)
+        .await
+        .unwrap();
+    let expired = &history[history.len() - 2];
+    assert_eq!(expired.metadata.as_ref().unwrap()["status"], "expired");
+    assert_eq!(history.last().unwrap().content, "Never mind");
+    assert_eq!(approvals::expire_overdue(ctx, &runtime).await.unwrap(), 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn the_scheduler_expires_overdue_approvals() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+
+    let provider = Scripted::new(vec![Completion {
+        content: String::new(),
+        tool_calls: vec![call("call_1", "current_time", json!({}))],
+        ..Default::default()
+    }]);
+    let registry = Registry::builtin();
+    let runtime = Runtime::new(&provider, &registry).with_approval_ttl(Duration::seconds(-1));
+    let turn = runtime.reply(ctx, &conversation, "Time?").await.unwrap();
+    assert!(turn.is_paused());
+    // past its deadline, it is no longer offered to decide
+    assert!(
+        tool_approvals::Model::list_pending_for_user(&ctx.db, conversation.user_id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let worker = ScheduleWorker { ctx: ctx.clone() };
+    assert_eq!(worker.expire_approvals().await.unwrap(), 1);
+    let approval = tool_approvals::Entity::find_by_id(turn.approvals[0].id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(approval.status, "expired");
+    assert!(runtime.reply(ctx, &conversation, "Again").await.is_ok());
+}
+
+#[tokio::test]
+#[serial]
+async fn approved_calls_run_once() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let conversation = create_conversation(&ctx.db).await;
+
+    let provider = Scripted::new(vec![
+        Completion {
+            content: String::new(),
+            tool_calls: vec![call("call_1", "current_time", json!({}))],
+            ..Default::default()
+        },
+        Completion::text("Done"),
+    ]);
+    let counted = Counted::default();
+    let registry = Registry::new().register(counted.clone());
+    let runtime = Runtime::new(&provider, &registry);
+
+    let turn = runtime.reply(ctx, &conversation, "Time?").await.unwrap();
+    let approval = turn.approvals[0].clone();
+    let (first, second) = tokio::join!(
+        runtime.decide(ctx, approval.clone(), Status::Approved),
+        runtime.decide(ctx, approval.clone(), Status::Approved),
+    );
+    assert_eq!(usize::from(first.is_ok()) + usize::from(second.is_ok()), 1);
+    assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+
+    // the expiry task does not get to it either
+    assert!(runtime
+        .decide(ctx, approval, Status::Expired)
+        .await
+        .is_err());
+    assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,78 @@
+use myapp::tools::schema::validate;
+use serde_json::json;
+
+#[test]
+fn accepts_matching_arguments() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string", "minLength": 1 },
+            "limit": { "type": "integer", "minimum": 1, "maximum": 50 },
+            "tags": { "type": "array", "items": { "type": "string" } },
+        },
+        "required": ["query"],
+        "additionalProperties": false,
+    });
+
+    assert_eq!(
+        validate(
+            &schema,
+            &json!({ "query": "loco", "limit": 10, "tags": ["rust"] })
+        ),
+        Ok(())
+    );
+    assert_eq!(validate(&schema, &json!({ "query": "loco" })), Ok(()));
+    assert_eq!(validate(&json!({}), &json!("anything")), Ok(()));
+}
+
+#[test]
+fn reports_every_violation() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "limit": { "type": "integer", "maximum": 50 },
+            "order": { "enum": ["asc", "desc"] },
+            "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 2 },
+        },
+        "required": ["query"],
+        "additionalProperties": false,
+    });
+
+    let errors = validate(
+        &schema,
+        &json!({
+            "limit": 100,
+            "order": "random",
+            "tags": ["a", 1, "c"],
+            "extra": true,
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            "/: missing required property `query`",
+            "/: unknown property `extra`",
+            "/limit: violates maximum 50",
+            "/order: must be one of [\"asc\",\"desc\"]",
+            "/tags: violates maxItems 2",
+            "/tags/1: expected string, got number",
+        ]
+    );
+}
+
+#[test]
+fn rejects_wrong_top_level_type() {
+    let schema = json!({ "type": "object" });
+
+    assert_eq!(
+        validate(&schema, &json!("{\"query\": ")),
+        Err(vec!["/: expected object, got string".to_string()])
+    );
+    assert_eq!(
+        validate(&json!({ "type": ["integer", "null"] }), &json!(2.0)),
+        Ok(())
+    );
+}
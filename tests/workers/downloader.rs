@@ -0,0 +1,373 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use myapp::{
+    app::App,
+    fetch::{guard, Config, Fetcher},
+    knowledge,
+    models::{agents, knowledge_base, knowledge_items, organizations, users},
+    workers::downloader::{DownloadWorker, DownloadWorkerArgs},
+};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::json;
+use serial_test::serial;
+
+const ARTICLE: &str = "<!DOCTYPE html><html><head><style>body { color: red; }</style>\
+    <script>alert('hi')</script></head><body><h1>Loco</h1><!-- hidden -->\
+    <p>Rust &amp; agents</p></body></html>";
+
+/// Serves canned responses on a random local port
+async fn stub_server() -> SocketAddr {
+    let app = Router::new()
+        .route(
+            "/article",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                    ARTICLE,
+                )
+            }),
+        )
+        .route(
+            "/data",
+            get(|| async { Response::new(Body::from(r#"{"answer": 42}"#)) }),
+        )
+        .route(
+            "/image",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "text/plain")],
+                    b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec(),
+                )
+            }),
+        )
+        .route(
+            "/redirect",
+            get(|| async { (StatusCode::FOUND, [(header::LOCATION, "/article")]) }),
+        )
+        .route(
+            "/loop",
+            get(|| async { (StatusCode::FOUND, [(header::LOCATION, "/loop")]) }),
+        )
+        .route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "late".into_response()
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+fn local_fetcher(config: Config) -> Fetcher {
+    Fetcher::new(
+        Config {
+            allow_private_networks: true,
+            ..config
+        },
+        vec!["127.0.0.1".to_string()],
+    )
+}
+
+#[tokio::test]
+async fn fetches_and_extracts_text() {
+    let addr = stub_server().await;
+    let fetcher = local_fetcher(Config::default());
+
+    let document = fetcher
+        .fetch(&format!("http://{addr}/redirect"))
+        .await
+        .unwrap();
+    assert_eq!(document.url, format!("http://{addr}/article"));
+    assert_eq!(document.content_type, "text/html");
+    assert_eq!(document.text, "Loco Rust & agents");
+
+    // no content type header, sniffed from the body
+    let document = fetcher.fetch(&format!("http://{addr}/data")).await.unwrap();
+    assert_eq!(document.content_type, "application/json");
+    assert_eq!(document.text, r#"{"answer": 42}"#);
+}
+
+#[tokio::test]
+async fn enforces_limits() {
+    let addr = stub_server().await;
+
+    let err = local_fetcher(Config::default())
+        .fetch(&format!("http://{addr}/image"))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("image/png is not allowed"),
+        "{err}"
+    );
+
+    let err = local_fetcher(Config {
+        max_bytes: 64,
+        ..Config::default()
+    })
+    .fetch(&format!("http://{addr}/article"))
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("larger than 64 bytes"), "{err}");
+
+    let err = local_fetcher(Config::default())
+        .fetch(&format!("http://{addr}/loop"))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many redirects"), "{err}");
+
+    let err = local_fetcher(Config {
+        timeout_secs: 1,
+        ..Config::default()
+    })
+    .fetch(&format!("http://{addr}/slow"))
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{err}");
+}
+
+#[tokio::test]
+async fn blocks_private_addresses_and_other_domains() {
+    let addr = stub_server().await;
+
+    // the production default refuses loopback even when allowlisted
+    let err = Fetcher::new(Config::default(), vec!["127.0.0.1".to_string()])
+        .fetch(&format!("http://{addr}/article"))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("private address"), "{err}");
+
+    let err = local_fetcher(Config::default())
+        .fetch(&format!("http://localhost:{}/article", addr.port()))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("not in the agent's allowlist"),
+        "{err}"
+    );
+
+    let err = local_fetcher(Config::default())
+        .fetch("file:///etc/passwd")
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("scheme file is not allowed"),
+        "{err}"
+    );
+
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "::ffff:127.0.0.1",
+        "fd00::1",
+        "fe80::1",
+    ] {
+        assert!(!guard::is_public(ip.parse().unwrap()), "{ip}");
+    }
+    for ip in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111"] {
+        assert!(guard::is_public(ip.parse().unwrap()), "{ip}");
+    }
+
+    let allowed = vec!["example.com".to_string(), "*.docs.rs".to_string()];
+    assert!(guard::domain_allowed("Example.com", &allowed));
+    assert!(guard::domain_allowed("loco.docs.rs", &allowed));
+    assert!(!guard::domain_allowed("docs.rs", &allowed));
+    assert!(!guard::domain_allowed("evildocs.rs", &allowed));
+    assert!(!guard::domain_allowed("www.example.com", &allowed));
+}
+
+#[test]
+fn chunks_at_whitespace() {
+    assert_eq!(
+        knowledge::chunk("one two three four", 9),
+        vec!["one two", "three", "four"]
+    );
+    assert_eq!(
+        knowledge::chunk("abcdefghij", 4),
+        vec!["abcd", "efgh", "ij"]
+    );
+    assert!(knowledge::chunk("  ", 4).is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_can_ingest_fetched_page() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let addr = stub_server().await;
+    let user = users::Model::find_by_email(&ctx.db, "user1@example.com")
+        .await
+        .unwrap();
+
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("researcher".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "allowed_domains": ["127.0.0.1"] }))),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    let knowledge_base = knowledge_base::ActiveModel {
+        name: ActiveValue::set("web".to_string()),
+        r#type: ActiveValue::set("documents".to_string()),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+
+    let worker = DownloadWorker::build(ctx);
+    let ingested = worker
+        .run(&DownloadWorkerArgs {
+            user_guid: user.pid.to_string(),
+            agent_id: agent.id,
+            url: format!("http://{addr}/article"),
+            knowledge_base_id: knowledge_base.id,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ingested.document.content, "Loco Rust & agents");
+    assert_eq!(
+        ingested.document.metadata.as_ref().unwrap()["content_type"],
+        "text/html"
+    );
+    assert_eq!(
+        ingested.document.metadata.as_ref().unwrap()["user_pid"],
+        json!(user.pid)
+    );
+    assert_eq!(ingested.chunks.len(), 1);
+
+    let chunks = knowledge_items::Model::list_by_type(
+        &ctx.db,
+        knowledge_base.id,
+        knowledge_items::TYPE_CHUNK,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        chunks[0].metadata.as_ref().unwrap()["document_id"],
+        json!(ingested.document.id)
+    );
+
+    // agents without an allowlist cannot fetch at all
+    let locked = agents::ActiveModel {
+        name: ActiveValue::set("locked".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    assert!(worker
+        .run(&DownloadWorkerArgs {
+            user_guid: user.pid.to_string(),
+            agent_id: locked.id,
+            url: format!("http://{addr}/article"),
+            knowledge_base_id: knowledge_base.id,
+        })
+        .await
+        .is_err());
+
+    // knowledge bases of other organizations are out of reach
+    let organization = organizations::Model::create_with_owner(&ctx.db, "acme", &user)
+        .await
+        .unwrap();
+    let foreign = knowledge_base::ActiveModel {
+        name: ActiveValue::set("acme".to_string()),
+        r#type: ActiveValue::set("documents".to_string()),
+        organization_id: ActiveValue::set(Some(organization.id)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    let err = worker
+        .run(&DownloadWorkerArgs {
+            user_guid: user.pid.to_string(),
+            agent_id: agent.id,
+            url: format!("http://{addr}/article"),
+            knowledge_base_id: foreign.id,
+        })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown knowledge base"), "{err}");
+}
+
+#[tokio::test]
+#[serial]
+async fn downloads_count_against_the_knowledge_quota() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let addr = stub_server().await;
+    let user = users::Model::find_by_email(&ctx.db, "user1@example.com")
+        .await
+        .unwrap();
+
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set("researcher".to_string()),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        configuration: ActiveValue::set(Some(json!({ "allowed_domains": ["127.0.0.1"] }))),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    let knowledge_base = knowledge_base::ActiveModel {
+        name: ActiveValue::set("web".to_string()),
+        r#type: ActiveValue::set("documents".to_string()),
+        user_id: ActiveValue::set(Some(user.id)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await
+    .unwrap();
+    // the free plan allows 1024 bytes of knowledge in the test config
+    knowledge::ingest(&ctx.db, knowledge_base.id, &"x".repeat(500), json!({}))
+        .await
+        .unwrap();
+
+    let err = DownloadWorker::build(ctx)
+        .run(&DownloadWorkerArgs {
+            user_guid: user.pid.to_string(),
+            agent_id: agent.id,
+            url: format!("http://{addr}/article"),
+            knowledge_base_id: knowledge_base.id,
+        })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("1024 bytes of knowledge"), "{err}");
+    assert_eq!(
+        knowledge_items::Model::list_by_type(
+            &ctx.db,
+            knowledge_base.id,
+            knowledge_items::TYPE_DOCUMENT
+        )
+        .await
+        .unwrap()
+        .len(),
+        1
+    );
+}
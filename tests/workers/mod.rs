@@ -1 +1,2 @@
-
+mod downloader;
+mod scheduler;
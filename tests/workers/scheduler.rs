@@ -0,0 +1,192 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use loco_rs::testing;
+use myapp::{
+    app::App,
+    models::{
+        agents,
+        task_schedules::{self, CatchUp, Status},
+        tasks, users,
+    },
+    scheduling::{self, Cadence, Config},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    QueryFilter,
+};
+use serde_json::json;
+use serial_test::serial;
+
+const HOURLY: &str = "0 * * * *";
+
+/// The top of an hour, so hourly schedules line up with it
+fn on_the_hour() -> NaiveDateTime {
+    Cadence::parse(HOURLY, "UTC")
+        .unwrap()
+        .next_after(Local::now().naive_local())
+        .unwrap()
+}
+
+async fn create_schedule(
+    db: &DatabaseConnection,
+    catch_up: CatchUp,
+    next_run_at: NaiveDateTime,
+) -> task_schedules::Model {
+    let user = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let agent = agents::ActiveModel {
+        name: ActiveValue::set(format!("digest-{}", catch_up.as_str())),
+        r#type: ActiveValue::set("assistant".to_string()),
+        status: ActiveValue::set("active".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    task_schedules::ActiveModel {
+        agent_id: ActiveValue::set(agent.id),
+        user_id: ActiveValue::set(user.id),
+        name: ActiveValue::set("Daily digest".to_string()),
+        input: ActiveValue::set(Some(json!({ "topic": "news" }))),
+        cron: ActiveValue::set(HOURLY.to_string()),
+        catch_up: ActiveValue::set(catch_up.as_str().to_string()),
+        next_run_at: ActiveValue::set(Some(next_run_at)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn runs_of(db: &DatabaseConnection, schedule: &task_schedules::Model) -> Vec<tasks::Model> {
+    tasks::tasks::Entity::find()
+        .filter(tasks::tasks::Column::ScheduleId.eq(schedule.id))
+        .all(db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn catches_up_on_missed_runs_by_policy() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let config = Config {
+        max_catch_up: 3,
+        ..Config::default()
+    };
+
+    // four hourly runs were missed by the time the scheduler comes back
+    let hour = on_the_hour();
+    let missed_since = hour - Duration::hours(3);
+    let skip = create_schedule(db, CatchUp::Skip, missed_since).await;
+    let latest = create_schedule(db, CatchUp::Latest, missed_since).await;
+    let all = create_schedule(db, CatchUp::All, missed_since).await;
+
+    let now = hour + Duration::minutes(1);
+    let created = scheduling::tick(db, &config, now).await.unwrap();
+    assert_eq!(created.len(), 5);
+
+    let scheduled_for = |runs: Vec<tasks::Model>| {
+        let mut times: Vec<String> = runs
+            .iter()
+            .map(|task| task.metadata.as_ref().unwrap()["scheduled_for"].to_string())
+            .collect();
+        times.sort();
+        times
+    };
+    let at = |time: NaiveDateTime| json!(time).to_string();
+    assert_eq!(scheduled_for(runs_of(db, &skip).await), vec![at(hour)]);
+    assert_eq!(scheduled_for(runs_of(db, &latest).await), vec![at(hour)]);
+    assert_eq!(
+        scheduled_for(runs_of(db, &all).await),
+        vec![
+            at(hour - Duration::hours(2)),
+            at(hour - Duration::hours(1)),
+            at(hour)
+        ]
+    );
+
+    let run = &runs_of(db, &latest).await[0];
+    assert_eq!(run.name, "Daily digest");
+    assert_eq!(run.input, Some(json!({ "topic": "news" })));
+    assert_eq!(run.user_id, Some(latest.user_id));
+
+    for schedule in [&skip, &latest, &all] {
+        let schedule = task_schedules::Model::find_by_id(db, schedule.id)
+            .await
+            .unwrap();
+        assert_eq!(schedule.next_run_at, Some(hour + Duration::hours(1)));
+        assert_eq!(schedule.last_run_at, Some(now));
+    }
+
+    // nothing is due until the next hour
+    assert!(scheduling::tick(db, &config, now).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn skips_runs_past_the_grace_period() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let hour = on_the_hour();
+    let schedule = create_schedule(db, CatchUp::Skip, hour).await;
+
+    let now = hour + Duration::minutes(10);
+    let created = scheduling::tick(db, &Config::default(), now).await.unwrap();
+    assert!(created.is_empty());
+
+    let schedule = task_schedules::Model::find_by_id(db, schedule.id)
+        .await
+        .unwrap();
+    assert_eq!(schedule.next_run_at, Some(hour + Duration::hours(1)));
+    assert_eq!(schedule.last_run_at, None);
+}
+
+#[tokio::test]
+#[serial]
+async fn runs_are_claimed_once() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let hour = on_the_hour();
+    let stale = create_schedule(db, CatchUp::Latest, hour).await;
+
+    let now = hour + Duration::minutes(1);
+    let created = scheduling::tick(db, &Config::default(), now).await.unwrap();
+    assert_eq!(created.len(), 1);
+
+    // a second scheduler holding the copy read before the tick loses
+    assert!(!stale.claim(db, None, Some(now)).await.unwrap());
+    assert_eq!(runs_of(db, &stale).await.len(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn paused_schedules_do_not_run() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let hour = on_the_hour();
+    let schedule = create_schedule(db, CatchUp::All, hour).await;
+    let schedule = schedule
+        .into_active_model()
+        .set_status(db, Status::Paused, Some(hour))
+        .await
+        .unwrap();
+
+    let now = hour + Duration::minutes(1);
+    assert!(scheduling::tick(db, &Config::default(), now)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let task = scheduling::trigger(db, &schedule).await.unwrap();
+    assert_eq!(task.schedule_id, Some(schedule.id));
+    assert_eq!(runs_of(db, &schedule).await.len(), 1);
+}